@@ -0,0 +1,39 @@
+#!/usr/bin/env python3
+# Minimal consumer for the mouse2joy wheel state feed.
+#
+#   ./telemetry_consumer.py udp 127.0.0.1:5555
+#   ./telemetry_consumer.py fifo /tmp/mouse2joy.fifo
+import json
+import socket
+import sys
+
+
+def frames(kind, target):
+    if kind == "udp":
+        host, port = target.rsplit(":", 1)
+        sock = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+        sock.bind((host, int(port)))
+        while True:
+            data, _ = sock.recvfrom(4096)
+            yield from data.decode().splitlines()
+    else:
+        while True:
+            # reopen when mouse2joy restarts and closes its end
+            with open(target) as fifo:
+                yield from fifo
+
+
+def main():
+    if len(sys.argv) != 3 or sys.argv[1] not in ("udp", "fifo"):
+        sys.exit(f"usage: {sys.argv[0]} udp HOST:PORT | fifo PATH")
+    for line in frames(sys.argv[1], sys.argv[2]):
+        frame = json.loads(line)
+        held = " (held)" if frame.get("throttle_hold") else ""
+        precision = " (precision)" if frame.get("precision") else ""
+        battery = "  LOW BATTERY" if frame.get("low_battery") else ""
+        profile = f"  [{frame['profile']}]" if frame.get("profile") else ""
+        print(f"angle {frame['angle_deg']:7.1f}{precision}  throttle {frame['throttle']:.2f}{held}  brake {frame['brake']:.2f}{battery}{profile}")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,139 @@
+use evdev::{EventType, InputEvent};
+use log::debug;
+use std::time::{Duration, Instant};
+
+use crate::configuration::{Config, InputRole};
+use crate::guard::Guard;
+use crate::spikes::SpikeFilter;
+use crate::Mouse2JoyError;
+
+// longer windows would add noticeable input lag
+const MAX_WINDOW_MS: u32 = 20;
+// how often the event rates are logged
+const RATE_INTERVAL: Duration = Duration::from_secs(10);
+
+// Sums the movement arriving within a few milliseconds of the first into one
+// update, so a mouse polling at 8 kHz doesn't cost a steering update per
+// report. No counts are lost, they are only applied up to a window later.
+pub struct InputWindow {
+    window: Duration,
+}
+
+impl InputWindow {
+    // None when input_window_ms is 0, every event is handled on arrival
+    pub fn new(conf: &Config) -> Result<Option<Self>, Mouse2JoyError> {
+        if conf.input_window_ms > MAX_WINDOW_MS {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "input_window_ms must be at most {}, got {}",
+                MAX_WINDOW_MS, conf.input_window_ms
+            )));
+        }
+        let window = Duration::from_millis(conf.input_window_ms.into());
+        Ok((!window.is_zero()).then_some(Self { window }))
+    }
+
+    // Once movement arrived keep reading until the window is over, returns
+    // how many more events were read. Keys and everything else that isn't
+    // movement are handled right away. Spikes are rejected before the sum.
+    pub fn collect(
+        &self,
+        guard: &mut Guard,
+        events: &mut Vec<(InputRole, InputEvent)>,
+        spikes: Option<&mut SpikeFilter>,
+    ) -> usize {
+        if !events.iter().any(|(_, event)| event.event_type() == EventType::RELATIVE) {
+            return 0;
+        }
+        let before = count(events);
+        let deadline = guard.now() + self.window;
+        loop {
+            let now = guard.now();
+            if now >= deadline {
+                break;
+            }
+            // the input ended, the next fetch tells the loop
+            let Some(more) = guard.fetch(Some(deadline - now)) else {
+                break;
+            };
+            events.extend(more);
+        }
+        let read = count(events) - before;
+        if let Some(spikes) = spikes {
+            spikes.filter(events);
+        }
+        *events = coalesce(std::mem::take(events));
+        read
+    }
+}
+
+// the events that aren't sync reports
+pub fn count(events: &[(InputRole, InputEvent)]) -> usize {
+    events.iter().filter(|(_, event)| event.event_type() != EventType::SYNCHRONIZATION).count()
+}
+
+// Merge the relative events of a device and axis between two other events
+// into one, the sync reports in between go too. Keys keep their order.
+fn coalesce(events: Vec<(InputRole, InputEvent)>) -> Vec<(InputRole, InputEvent)> {
+    let mut merged: Vec<(InputRole, InputEvent)> = Vec::new();
+    // where the run of movement since the last other event starts
+    let mut run = 0;
+    for (role, event) in events {
+        match event.event_type() {
+            EventType::SYNCHRONIZATION => continue,
+            EventType::RELATIVE => {
+                let same = merged[run..]
+                    .iter_mut()
+                    .find(|(other_role, other)| *other_role == role && other.code() == event.code());
+                match same {
+                    Some((_, other)) => {
+                        *other = InputEvent::new(EventType::RELATIVE, event.code(), other.value() + event.value())
+                    }
+                    None => merged.push((role, event)),
+                }
+            }
+            _ => {
+                merged.push((role, event));
+                run = merged.len();
+            }
+        }
+    }
+    merged
+}
+
+// The input events read, the updates they were handled in and the events
+// sent to the wheel, logged per second every RATE_INTERVAL while there is
+// input.
+pub struct Rates {
+    since: Instant,
+    read: u64,
+    updates: u64,
+    emitted: u64,
+}
+
+impl Rates {
+    pub fn new(now: Instant) -> Self {
+        Self { since: now, read: 0, updates: 0, emitted: 0 }
+    }
+
+    pub fn record(&mut self, read: usize, emitted: usize, now: Instant) {
+        if read > 0 {
+            self.read += read as u64;
+            self.updates += 1;
+        }
+        self.emitted += emitted as u64;
+        let elapsed = now.duration_since(self.since);
+        if elapsed < RATE_INTERVAL {
+            return;
+        }
+        if self.read > 0 {
+            let per_second = |count: u64| count as f64 / elapsed.as_secs_f64();
+            debug!(
+                "Input {:.0} events/s in {:.0} updates/s, output {:.0} events/s",
+                per_second(self.read),
+                per_second(self.updates),
+                per_second(self.emitted)
+            );
+        }
+        *self = Self::new(now);
+    }
+}
@@ -0,0 +1,105 @@
+use evdev::{EventType, InputEvent, RelativeAxisType};
+use std::time::{Duration, Instant};
+
+use crate::configuration::{AutoPauseConfig, InputRole};
+use crate::steering::STEERING_MAX;
+use crate::Mouse2JoyError;
+
+// a break this long between two moves ends a resume burst
+const BURST_GAP: Duration = Duration::from_millis(100);
+const MAX_BURST_MS: u32 = 10_000;
+
+// [auto_pause]: without input for idle_seconds, with the wheel near center
+// and the pedals up, the loop pauses like on the pause hotkey, releasing the
+// mice for the desktop, but leaves the wheel where it is. Moving the mouse
+// on the desktop must not wake it, only the pause hotkey (SIGUSR1, mouse2joy
+// resume) does, or with resume_burst_ms moving the steering mouse that long
+// without a break.
+pub struct AutoPause {
+    idle: Duration,
+    band: i32,
+    burst: Option<Duration>,
+    last_input: Instant,
+    // while paused, the start and the last move of the movement going on
+    moving: Option<(Instant, Instant)>,
+}
+
+impl AutoPause {
+    pub fn new(conf: &AutoPauseConfig, now: Instant) -> Result<Option<Self>, Mouse2JoyError> {
+        if conf.idle_seconds == 0 {
+            return Ok(None);
+        }
+        if conf.center_band > STEERING_MAX as u32 {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "auto_pause.center_band must be at most {}, got {}",
+                STEERING_MAX, conf.center_band
+            )));
+        }
+        if conf.resume_burst_ms > MAX_BURST_MS {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "auto_pause.resume_burst_ms must be at most {}, got {}",
+                MAX_BURST_MS, conf.resume_burst_ms
+            )));
+        }
+        Ok(Some(Self {
+            idle: Duration::from_secs(conf.idle_seconds.into()),
+            band: conf.center_band as i32,
+            burst: (conf.resume_burst_ms > 0).then(|| Duration::from_millis(conf.resume_burst_ms.into())),
+            last_input: now,
+            moving: None,
+        }))
+    }
+
+    pub fn seconds(&self) -> u64 {
+        self.idle.as_secs()
+    }
+
+    pub fn burst(&self) -> Option<Duration> {
+        self.burst
+    }
+
+    // input, or resuming, starts the idle time over
+    pub fn input(&mut self, now: Instant) {
+        self.last_input = now;
+        self.moving = None;
+    }
+
+    // whether to pause now, after counting the input read now: idle for long
+    // enough with the wheel in the band and the pedals `neutral`
+    pub fn due(&mut self, input: bool, steering: i32, neutral: bool, now: Instant) -> bool {
+        if input {
+            self.input(now);
+            return false;
+        }
+        now.saturating_duration_since(self.last_input) >= self.idle && steering.abs() <= self.band && neutral
+    }
+
+    // until the idle time is up, None once it is
+    pub fn wakeup(&self, now: Instant) -> Option<Duration> {
+        let left = (self.last_input + self.idle).saturating_duration_since(now);
+        (!left.is_zero()).then_some(left)
+    }
+
+    // Paused by it, whether `events` finish the resume burst. Only the
+    // motion of the steering mouse counts, not its wheel or buttons.
+    pub fn resumes(&mut self, events: &[(InputRole, InputEvent)], now: Instant) -> bool {
+        let Some(burst) = self.burst else {
+            return false;
+        };
+        let moved = events.iter().any(|(role, ev)| {
+            *role == InputRole::Steering
+                && ev.event_type() == EventType::RELATIVE
+                && (ev.code() == RelativeAxisType::REL_X.0 || ev.code() == RelativeAxisType::REL_Y.0)
+                && ev.value() != 0
+        });
+        if !moved {
+            return false;
+        }
+        let start = match self.moving {
+            Some((start, last)) if now.saturating_duration_since(last) < BURST_GAP => start,
+            _ => now,
+        };
+        self.moving = Some((start, now));
+        now.saturating_duration_since(start) >= burst
+    }
+}
@@ -0,0 +1,102 @@
+use evdev::Device;
+use log::{info, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+const INPUT_CLASS_DIR: &str = "/sys/class/input";
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Battery of a wireless mouse, looked up in sysfs. A dying battery makes the
+// sensor erratic, so a warning is logged once its capacity drops below the
+// threshold. Mice without battery information are ignored without a word.
+pub struct Battery {
+    path: PathBuf,
+    device: String,
+    threshold: u32,
+    low: bool,
+    next_check: Instant,
+}
+
+impl Battery {
+    // the power supply of the mouse opened from `node`, if it reports one
+    pub fn find(device: &Device, node: &Path, threshold: u32, now: Instant) -> Option<Self> {
+        if threshold == 0 {
+            return None;
+        }
+        let path = power_supply(device, node)?;
+        let mut battery = Self {
+            path,
+            device: device.name().unwrap_or("Unknown Device").to_string(),
+            threshold,
+            low: false,
+            next_check: now,
+        };
+        battery.check(now);
+        Some(battery)
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.low
+    }
+
+    pub fn wakeup(&self, now: Instant) -> Duration {
+        self.next_check.saturating_duration_since(now)
+    }
+
+    // re-read the capacity once the check interval passed
+    pub fn check(&mut self, now: Instant) {
+        if now < self.next_check {
+            return;
+        }
+        self.next_check = now + CHECK_INTERVAL;
+
+        let capacity = read(&self.path, "capacity").and_then(|capacity| capacity.parse::<u32>().ok());
+        let low = match capacity {
+            Some(capacity) => capacity < self.threshold,
+            // some drivers only report a coarse level
+            None => read(&self.path, "capacity_level").is_some_and(|level| level == "Low" || level == "Critical"),
+        };
+        let level = capacity.map(|capacity| format!("{}%", capacity)).unwrap_or_else(|| String::from("low"));
+        if low && !self.low {
+            warn!(device = self.device.as_str(); "Battery of \"{}\" is at {}, expect erratic steering", self.device, level);
+        } else if !low && self.low {
+            info!(device = self.device.as_str(); "Battery of \"{}\" is back at {}", self.device, level);
+        }
+        self.low = low;
+    }
+}
+
+fn read(dir: &Path, attribute: &str) -> Option<String> {
+    fs::read_to_string(dir.join(attribute)).ok().map(|value| value.trim().to_string())
+}
+
+// A power supply belongs to the mouse when its device is the HID device the
+// input node hangs off, or when its name carries the mouse's unique id (e.g.
+// hid-aa:bb:cc:dd:ee:ff-battery for bluetooth mice).
+fn power_supply(device: &Device, node: &Path) -> Option<PathBuf> {
+    let event = fs::canonicalize(node).ok()?.file_name()?.to_owned();
+    let input = fs::canonicalize(Path::new(INPUT_CLASS_DIR).join(event)).ok();
+    let uniq = device
+        .unique_name()
+        .filter(|uniq| !uniq.is_empty())
+        .map(str::to_lowercase);
+
+    fs::read_dir(POWER_SUPPLY_DIR)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        // laptop and UPS batteries power the system, not the mouse
+        .filter(|supply| read(supply, "scope").is_none_or(|scope| scope == "Device"))
+        .find(|supply| {
+            let parent = fs::canonicalize(supply.join("device")).ok();
+            let by_device = input.as_ref().zip(parent).is_some_and(|(input, parent)| input.starts_with(parent));
+            let by_uniq = uniq.as_ref().is_some_and(|uniq| {
+                supply
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().to_lowercase().contains(uniq.as_str()))
+            });
+            by_device || by_uniq
+        })
+}
@@ -0,0 +1,192 @@
+use evdev::{EventType, InputEvent, Key, RelativeAxisType, Synchronization};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::input::Script;
+
+// version of the report, bumped when a line changes meaning
+const REPORT_FORMAT: u32 = 1;
+// the synthetic input repeats every CYCLE_MS, starting with a burst
+const CYCLE_MS: u64 = 2000;
+const BURST_MS: u64 = 150;
+// reports per millisecond of a burst, an 8 kHz mouse
+const BURST_RATE: u32 = 8;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+// The system allocator, counting allocations for the --bench report. One
+// relaxed add each, next to nothing beside the allocation itself.
+struct Counting;
+
+// SAFETY: everything is passed on to the system allocator unchanged
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// Synthetic input for --bench, `events` of them besides the sync reports,
+// the same every run. A mouse at 1 kHz making small corrections, every two
+// seconds a flick at 8 kHz with large deltas and twice a second a click
+// whose button chatters.
+pub fn script(events: u64) -> Script {
+    let mut generated = Vec::new();
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+    let mut count = 0;
+    let mut last = Duration::ZERO;
+    // a report cut short once there are enough events
+    let mut add = |at: Duration, report: &[InputEvent], count: &mut u64| {
+        if *count == events {
+            return;
+        }
+        for (index, ev) in report.iter().take((events - *count) as usize).enumerate() {
+            let sleep = if index == 0 { at - last } else { Duration::ZERO };
+            generated.push((sleep, *ev));
+            *count += 1;
+        }
+        let sync = InputEvent::new(EventType::SYNCHRONIZATION, Synchronization::SYN_REPORT.0, 0);
+        generated.push((Duration::ZERO, sync));
+        last = at;
+    };
+    let mut ms = 0;
+    while count < events {
+        let at = Duration::from_millis(ms);
+        let phase = ms % CYCLE_MS;
+        if phase < BURST_MS {
+            // each flick the other way
+            let direction = if (ms / CYCLE_MS).is_multiple_of(2) { 1 } else { -1 };
+            for report in 0..BURST_RATE {
+                let moved = [
+                    rel(RelativeAxisType::REL_X, direction * random.range(40, 160)),
+                    rel(RelativeAxisType::REL_Y, random.range(-20, 20)),
+                ];
+                let offset = Duration::from_micros(1000 / u64::from(BURST_RATE) * u64::from(report));
+                add(at + offset, &moved, &mut count);
+            }
+        } else {
+            let delta = match random.range(-3, 3) {
+                0 => 1,
+                delta => delta,
+            };
+            add(at, &[rel(RelativeAxisType::REL_X, delta)], &mut count);
+        }
+        // pressed, bouncing once, released again
+        match ms % 500 {
+            250 | 253 => add(at, &[key(Key::BTN_LEFT, 1)], &mut count),
+            252 | 330 => add(at, &[key(Key::BTN_LEFT, 0)], &mut count),
+            _ => {}
+        }
+        ms += 1;
+    }
+    Script::generated(generated)
+}
+
+fn rel(axis: RelativeAxisType, value: i32) -> InputEvent {
+    InputEvent::new(EventType::RELATIVE, axis.0, value)
+}
+
+fn key(key: Key, value: i32) -> InputEvent {
+    InputEvent::new(EventType::KEY, key.code(), value)
+}
+
+// xorshift, good enough to vary the deltas and the same on every run
+struct Random(u64);
+
+impl Random {
+    fn range(&mut self, min: i32, max: i32) -> i32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        min + (self.0 % (max - min + 1) as u64) as i32
+    }
+}
+
+// the parts of one pass through the loop, in order
+#[derive(Clone, Copy)]
+pub enum Stage {
+    // fetching the input
+    Read,
+    // spikes, the input window and chords
+    Filter,
+    // handling every event and hotkey action
+    Map,
+    // the ramps, macros, scripts, failsafe and published state
+    Tick,
+    Emit,
+}
+
+const STAGE_NAMES: [&str; 5] = ["read", "filter", "map", "tick", "emit"];
+
+// Where the time of a --bench run goes, measured on the real clock unlike
+// everything else the input script drives. Doesn't measure anything
+// otherwise.
+pub struct Stages {
+    enabled: bool,
+    last: Instant,
+    allocations: u64,
+    spent: [Duration; 5],
+    allocated: [u64; 5],
+}
+
+impl Stages {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            last: Instant::now(),
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            spent: [Duration::ZERO; 5],
+            allocated: [0; 5],
+        }
+    }
+
+    // the time since the previous lap went to `stage`
+    pub fn lap(&mut self, stage: Stage) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        self.spent[stage as usize] += now - self.last;
+        self.allocated[stage as usize] += allocations - self.allocations;
+        self.last = now;
+        self.allocations = allocations;
+    }
+
+    // One "key value" line each, in an order and with names that stay put
+    // between releases so runs can be compared by a script.
+    pub fn report(&self, events: u64, emitted: u64, output: &str) {
+        let total: Duration = self.spent.iter().sum();
+        let per_event = |value: f64| if events == 0 { 0.0 } else { value / events as f64 };
+        println!("format {}", REPORT_FORMAT);
+        println!("version {}", env!("CARGO_PKG_VERSION"));
+        println!("output {}", output);
+        println!("events {}", events);
+        println!("emitted {}", emitted);
+        println!("seconds {:.6}", total.as_secs_f64());
+        println!("events_per_second {:.0}", events as f64 / total.as_secs_f64().max(f64::MIN_POSITIVE));
+        println!("allocations_per_event {:.3}", per_event(self.allocated.iter().sum::<u64>() as f64));
+        for (index, name) in STAGE_NAMES.iter().enumerate() {
+            println!("{}_ns_per_event {:.1}", name, per_event(self.spent[index].as_nanos() as f64));
+            println!("{}_allocations_per_event {:.3}", name, per_event(self.allocated[index] as f64));
+        }
+    }
+}
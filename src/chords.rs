@@ -0,0 +1,149 @@
+use evdev::{EventType, InputEvent, Key};
+use std::time::{Duration, Instant};
+
+// Chords: two sources pressed together press a button of their own, e.g.
+// BTN_SIDE+BTN_EXTRA for the handbrake while each alone shifts. The press of
+// a member is withheld for the chord window. When the other member comes in
+// time the chord is pressed and neither goes on, otherwise the press goes on
+// late and the source does what it does alone. A withheld press always ends
+// up either passed on or taken by a chord, and the release of a taken one
+// is swallowed.
+pub struct Chords<R> {
+    chords: Vec<([Key; 2], Key)>,
+    window: Duration,
+    // member presses waiting for their chord, oldest first
+    pending: Vec<(Key, Instant, R)>,
+    // the chords down, by index
+    active: Vec<usize>,
+    // members whose press went to a chord
+    consumed: Vec<Key>,
+}
+
+impl<R: Copy> Chords<R> {
+    pub fn new(chords: Vec<([Key; 2], Key)>, window: Duration) -> Option<Self> {
+        if chords.is_empty() {
+            return None;
+        }
+        Some(Self { chords, window, pending: Vec::new(), active: Vec::new(), consumed: Vec::new() })
+    }
+
+    pub fn members(&self) -> Vec<Key> {
+        let mut members: Vec<Key> = self.chords.iter().flat_map(|(members, _)| *members).collect();
+        members.sort_by_key(|key| key.code());
+        members.dedup();
+        members
+    }
+
+    // when the oldest withheld press has to go on
+    pub fn wakeup(&self, now: Instant) -> Option<Duration> {
+        self.pending.iter().map(|(_, at, _)| (*at + self.window).saturating_duration_since(now)).min()
+    }
+
+    // The events read with the members taken out: presses whose window ran
+    // out come first, then everything else in order. The chord buttons go
+    // to `outgoing`.
+    pub fn filter(
+        &mut self,
+        events: Vec<(R, InputEvent)>,
+        now: Instant,
+        outgoing: &mut Vec<InputEvent>,
+    ) -> Vec<(R, InputEvent)> {
+        let mut passed = Vec::new();
+        let window = self.window;
+        self.pending.retain(|(key, at, role)| {
+            let expired = now.saturating_duration_since(*at) >= window;
+            if expired {
+                passed.push((*role, key_event(*key, 1)));
+            }
+            !expired
+        });
+        for (role, ev) in events {
+            let key = Key::new(ev.code());
+            if ev.event_type() != EventType::KEY || !self.is_member(key) {
+                passed.push((role, ev));
+                continue;
+            }
+            match ev.value() {
+                1 => self.press(key, role, now, outgoing),
+                0 => self.release(key, role, &mut passed, outgoing),
+                // repeats of a withheld or taken press go nowhere either
+                _ if self.is_withheld(key) || self.consumed.contains(&key) => {}
+                _ => passed.push((role, ev)),
+            }
+        }
+        passed
+    }
+
+    // Paused or the failsafe tripped: the withheld presses are dropped, as their
+    // release may never be seen, and the chords down are released.
+    pub fn reset(&mut self) -> Vec<InputEvent> {
+        self.pending.clear();
+        self.consumed.clear();
+        let mut buttons: Vec<Key> = self.active.drain(..).map(|index| self.chords[index].1).collect();
+        buttons.sort_by_key(|key| key.code());
+        buttons.dedup();
+        buttons.into_iter().map(|button| key_event(button, 0)).collect()
+    }
+
+    fn press(&mut self, key: Key, role: R, now: Instant, outgoing: &mut Vec<InputEvent>) {
+        if self.is_withheld(key) || self.consumed.contains(&key) {
+            return;
+        }
+        let partner = self.pending.iter().enumerate().find_map(|(position, (other, _, _))| {
+            let chord = self.chords.iter().position(|(members, _)| {
+                (members[0] == key && members[1] == *other) || (members[1] == key && members[0] == *other)
+            })?;
+            Some((position, chord))
+        });
+        let Some((position, chord)) = partner else {
+            self.pending.push((key, now, role));
+            return;
+        };
+        let (other, _, _) = self.pending.remove(position);
+        self.consumed.extend([key, other]);
+        let button = self.chords[chord].1;
+        if !self.is_down(button) {
+            outgoing.push(key_event(button, 1));
+        }
+        self.active.push(chord);
+    }
+
+    fn release(&mut self, key: Key, role: R, passed: &mut Vec<(R, InputEvent)>, outgoing: &mut Vec<InputEvent>) {
+        if let Some(position) = self.pending.iter().position(|(pending, _, _)| *pending == key) {
+            // a tap, too short for the chord
+            self.pending.remove(position);
+            passed.push((role, key_event(key, 1)));
+            passed.push((role, key_event(key, 0)));
+            return;
+        }
+        let Some(taken) = self.consumed.iter().position(|consumed| *consumed == key) else {
+            passed.push((role, key_event(key, 0)));
+            return;
+        };
+        self.consumed.remove(taken);
+        // the first member up releases the chord, the other one's release
+        // is swallowed too
+        if let Some(position) = self.active.iter().position(|index| self.chords[*index].0.contains(&key)) {
+            let button = self.chords[self.active.remove(position)].1;
+            if !self.is_down(button) {
+                outgoing.push(key_event(button, 0));
+            }
+        }
+    }
+
+    fn is_member(&self, key: Key) -> bool {
+        self.chords.iter().any(|(members, _)| members.contains(&key))
+    }
+
+    fn is_withheld(&self, key: Key) -> bool {
+        self.pending.iter().any(|(pending, _, _)| *pending == key)
+    }
+
+    fn is_down(&self, button: Key) -> bool {
+        self.active.iter().any(|index| self.chords[*index].1 == button)
+    }
+}
+
+fn key_event(key: Key, value: i32) -> InputEvent {
+    InputEvent::new(EventType::KEY, key.code(), value)
+}
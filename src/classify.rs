@@ -0,0 +1,124 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_RX: u16 = 0x03;
+const ABS_PRESSURE: u16 = 0x18;
+const BTN_MOUSE: u16 = 0x110;
+const BTN_JOYSTICK: u16 = 0x120;
+const BTN_DIGI: u16 = 0x140;
+const BTN_TOOL_PEN: u16 = 0x140;
+const BTN_TOOL_FINGER: u16 = 0x145;
+const BTN_TOUCH: u16 = 0x14a;
+const BTN_STYLUS: u16 = 0x14b;
+const BTN_DPAD_UP: u16 = 0x220;
+const BTN_DPAD_RIGHT: u16 = 0x223;
+const BTN_TRIGGER_HAPPY1: u16 = 0x2c0;
+const BTN_TRIGGER_HAPPY40: u16 = 0x2e7;
+const INPUT_PROP_DIRECT: u16 = 0x01;
+
+// where udev keeps the properties of a device node
+const UDEV_DATA: &str = "/run/udev/data";
+
+// what udev's input_id makes of a device, the pointer kinds are the ones
+// libinput picks up and moves the cursor with
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Class {
+    Joystick,
+    Mouse,
+    Tablet,
+    Touchpad,
+    Touchscreen,
+    Unclassified,
+}
+
+impl Class {
+    pub fn property(self) -> &'static str {
+        match self {
+            Class::Joystick => "ID_INPUT_JOYSTICK",
+            Class::Mouse => "ID_INPUT_MOUSE",
+            Class::Tablet => "ID_INPUT_TABLET",
+            Class::Touchpad => "ID_INPUT_TOUCHPAD",
+            Class::Touchscreen => "ID_INPUT_TOUCHSCREEN",
+            Class::Unclassified => "no ID_INPUT class",
+        }
+    }
+
+    pub fn is_pointer(self) -> bool {
+        matches!(self, Class::Mouse | Class::Tablet | Class::Touchpad | Class::Touchscreen)
+    }
+}
+
+// The part of udev's input_id heuristics that applies to a device without
+// relative axes, from the absolute axes, keys and input properties it
+// advertises. A virtual device needs to come out as a joystick: games look
+// for ID_INPUT_JOYSTICK and libinput leaves joysticks alone.
+pub fn classify(abs: &[u16], keys: &[u16], props: &[u16]) -> Class {
+    let key = |code: u16| keys.contains(&code);
+    let any_key = |from: u16, to: u16| keys.iter().any(|code| (from..=to).contains(code));
+    let coordinates = abs.contains(&ABS_X) && abs.contains(&ABS_Y);
+    let mouse_button = any_key(BTN_MOUSE, BTN_JOYSTICK - 1);
+    // a mouse with more than 16 buttons runs into the joystick range
+    let joystick_buttons = !key(BTN_JOYSTICK - 1)
+        && (any_key(BTN_JOYSTICK, BTN_DIGI - 1)
+            || any_key(BTN_TRIGGER_HAPPY1, BTN_TRIGGER_HAPPY40)
+            || any_key(BTN_DPAD_UP, BTN_DPAD_RIGHT));
+    let joystick = joystick_buttons || abs.iter().any(|axis| (ABS_RX..ABS_PRESSURE).contains(axis));
+    let direct = props.contains(&INPUT_PROP_DIRECT);
+
+    if coordinates {
+        if key(BTN_STYLUS) || key(BTN_TOOL_PEN) {
+            return Class::Tablet;
+        }
+        if key(BTN_TOOL_FINGER) && !direct {
+            return Class::Touchpad;
+        }
+        if mouse_button {
+            return Class::Mouse;
+        }
+        if key(BTN_TOUCH) || direct {
+            return Class::Touchscreen;
+        }
+    }
+    if joystick {
+        return Class::Joystick;
+    }
+    if mouse_button {
+        return Class::Mouse;
+    }
+    Class::Unclassified
+}
+
+// the ID_INPUT_* classes set in a udev database entry, e.g. "E:ID_INPUT_JOYSTICK=1"
+pub fn udev_classes(data: &str) -> Vec<Class> {
+    [Class::Joystick, Class::Mouse, Class::Tablet, Class::Touchpad, Class::Touchscreen]
+        .into_iter()
+        .filter(|class| data.lines().any(|line| line == format!("E:{}=1", class.property())))
+        .collect()
+}
+
+// How udev classified the event node, waiting up to `timeout` for it to
+// process a node that was just created. None without a udev database.
+pub fn udev_classified(node: &Path, timeout: Duration) -> Option<Vec<Class>> {
+    if !Path::new(UDEV_DATA).is_dir() {
+        return None;
+    }
+    let rdev = nix::sys::stat::stat(node).ok()?.st_rdev;
+    let entry = Path::new(UDEV_DATA).join(format!(
+        "c{}:{}",
+        nix::sys::stat::major(rdev),
+        nix::sys::stat::minor(rdev)
+    ));
+    let deadline = Instant::now() + timeout;
+    loop {
+        // the entry is written once udev is through with the node
+        if let Ok(data) = std::fs::read_to_string(&entry) {
+            return Some(udev_classes(&data));
+        }
+        if Instant::now() > deadline {
+            return Some(Vec::new());
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
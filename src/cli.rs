@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+
+use crate::control::Request;
+use crate::Mouse2JoyError;
+
+pub const USAGE: &str = "\
+Usage: mouse2joy [OPTIONS]
+       mouse2joy [--config FILE] COMMAND
+
+Commands, sent to the running mouse2joy:
+  status [--json]             Print its input, steering position, pedals,
+                              sensitivity, profile, pause state and counters
+  pause, resume               Pause or resume it, like SIGUSR1
+  set sensitivity N           Change the sensitivity until it exits
+
+Options:
+  --config FILE               Use FILE instead of the default configuration
+  --profile NAME              Apply the overrides of profile NAME from the
+                              configuration
+  --export-profile NAME [FILE]
+                              Write every setting of profile NAME to FILE (or
+                              stdout) for sharing, then exit
+  --import-profile FILE       Add an exported profile to the configuration,
+                              then exit
+  --force                     Let --import-profile replace an existing profile
+  --migrate-config            Update the configuration to the current format,
+                              keeping the old file as FILE.bak, then exit
+  --input-dir DIR             Scan DIR instead of /dev/input for input devices
+  --list-devices              List the mice found, with the ones ignore_devices
+                              or only_devices leave out, then exit
+  --max-events N              Exit after processing N input events
+  --input-script FILE         Read synthetic input events from FILE (\"-\" for
+                              stdin) instead of a mouse, see README. With
+                              [[pipelines]] once for each, in their order
+  --dry-run                   Print the events instead of creating a virtual
+                              steering wheel
+  --bench [N]                 Run N (default 200000) synthetic input events
+                              through the pipeline as fast as possible and
+                              print the throughput, then exit. With --dry-run
+                              or without uinput access nothing is emitted
+  --trace-input [FILE]        Write every event read from the input devices
+                              and sent to the virtual device to stderr (or
+                              FILE), whatever the log level
+  --trace-seconds N           Stop --trace-input after N seconds (default 60)
+  --log-csv FILE              Write the raw steering input, the steering,
+                              throttle and brake values and the profile to FILE
+                              as CSV, for tuning
+  --log-csv-rate HZ           Rows per second of --log-csv (default 50)
+  --show-curve                Plot the mouse travel against the steering
+                              output with every shaping setting applied, with
+                              sample points, the dead zone edge and full lock,
+                              then exit. Fails if the output ever turns back
+  --print-sdl-mapping [FILE]  Print an SDL game controller mapping for the
+                              virtual device (and write it to FILE) then exit
+  --self-test                 Create the virtual steering wheel, read every
+                              axis and button back and report, then exit
+  --learn                     Press an input for each wheel function in turn
+                              and save them as [buttons] mappings, then exit
+  --calibrate-rotation        Measure the direction the steering mouse moves
+                              in while steering right and save it as the
+                              [rotation] angle, then exit
+  --doctor                    Check uinput, permissions, mice and the
+                              configuration and report, then exit
+  --daemon                    Go to the background once the devices are set
+                              up, with a pidfile in $XDG_RUNTIME_DIR. Needs a
+                              mouse that can be picked without asking
+  --stop                      Stop the mouse2joy started with --daemon, then
+                              exit
+  --exit-with-launched        Exit once the command the profile launched
+                              exits, for wrapper scripts
+  --keep-root                 Keep running as root, started with sudo or with
+                              run_as configured it switches to that user once
+                              the devices are set up
+  --install-udev-rule [GROUP] Let GROUP (default input) use uinput through a
+                              udev rule and reload udev, then exit. With
+                              --dry-run or without root the rule is printed
+  --tag-joystick              Make --install-udev-rule also mark the virtual
+                              devices as joysticks, for Steam
+  -h, --help                  Print this help
+
+Emergency release:
+  Ctrl+Alt+End ungrabs the mice, removes the virtual devices and exits at
+  once, even when mouse2joy hangs with the mouse grabbed. It reads the
+  keyboards on its own, set another combination with emergency_release in
+  the configuration
+
+Signals:
+  SIGUSR1  pause (center the outputs, release the mice, ignore input) or resume
+  SIGUSR2  log the current state
+  SIGINT, SIGTERM
+           center the outputs, release the devices and exit
+
+Exit codes:
+  0    success, also when stopped with SIGINT or SIGTERM
+  2    invalid arguments
+  3    invalid configuration
+  4    no mouse found
+  5    no keyboard able to send the configured keys found
+  6    failed to read input
+  7    failed to create the virtual steering wheel (uinput)
+  8    failed to write a file
+  9    no mouse selected before the prompt timed out
+  10   the selected mouse kept failing to open or grab
+  11   the virtual steering wheel stopped working and couldn't be rebuilt
+  12   --self-test found capabilities that didn't read back
+  13   --doctor found a problem that keeps mouse2joy from working
+  14   stopped by the emergency release
+  15   no running mouse2joy answered a command
+  16   failed to switch from root to the run_as or sudo user
+  101  crashed
+";
+
+// synthetic input events of a --bench run
+const DEFAULT_BENCH_EVENTS: u64 = 200_000;
+
+#[derive(Default)]
+pub struct Args {
+    pub help: bool,
+    pub print_sdl_mapping: bool,
+    pub sdl_mapping_file: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub profile: Option<String>,
+    pub export_profile: Option<String>,
+    pub export_file: Option<PathBuf>,
+    pub import_profile: Option<PathBuf>,
+    pub force: bool,
+    pub migrate_config: bool,
+    pub input_dir: Option<PathBuf>,
+    pub list_devices: bool,
+    pub max_events: Option<u64>,
+    pub input_scripts: Vec<PathBuf>,
+    pub dry_run: bool,
+    pub bench: Option<u64>,
+    pub trace_input: bool,
+    pub trace_file: Option<PathBuf>,
+    pub trace_seconds: Option<u64>,
+    pub log_csv: Option<PathBuf>,
+    pub log_csv_rate: Option<u32>,
+    pub show_curve: bool,
+    pub self_test: bool,
+    pub doctor: bool,
+    pub learn: bool,
+    pub calibrate_rotation: bool,
+    pub udev_group: Option<String>,
+    pub tag_joystick: bool,
+    pub daemon: bool,
+    pub stop: bool,
+    pub keep_root: bool,
+    pub exit_with_launched: bool,
+    pub request: Option<Request>,
+}
+
+impl Args {
+    pub fn parse() -> Result<Args, Mouse2JoyError> {
+        let mut parsed = Args::default();
+        let mut args = std::env::args().skip(1).peekable();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => parsed.help = true,
+                "--config" => parsed.config = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--profile" => parsed.profile = Some(value(&arg, args.next())?),
+                "--export-profile" => {
+                    parsed.export_profile = Some(value(&arg, args.next())?);
+                    parsed.export_file = args.next_if(|next| !next.starts_with('-')).map(PathBuf::from);
+                }
+                "--import-profile" => parsed.import_profile = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--force" => parsed.force = true,
+                "--migrate-config" => parsed.migrate_config = true,
+                "--input-dir" => parsed.input_dir = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--list-devices" => parsed.list_devices = true,
+                "--max-events" => {
+                    let count = value(&arg, args.next())?;
+                    parsed.max_events = Some(count.parse().map_err(|_| {
+                        Mouse2JoyError::InvalidArguments(format!("invalid event count \"{}\"", count))
+                    })?);
+                }
+                "--input-script" => parsed.input_scripts.push(PathBuf::from(value(&arg, args.next())?)),
+                "--dry-run" => parsed.dry_run = true,
+                "--bench" => {
+                    let count = args.next_if(|next| !next.starts_with('-'));
+                    parsed.bench = Some(match count {
+                        Some(count) => count.parse().ok().filter(|count| *count > 0).ok_or_else(|| {
+                            Mouse2JoyError::InvalidArguments(format!("invalid event count \"{}\"", count))
+                        })?,
+                        None => DEFAULT_BENCH_EVENTS,
+                    });
+                }
+                "--trace-input" => {
+                    parsed.trace_input = true;
+                    parsed.trace_file = args.next_if(|next| !next.starts_with('-')).map(PathBuf::from);
+                }
+                "--trace-seconds" => {
+                    let seconds = value(&arg, args.next())?;
+                    parsed.trace_seconds = Some(seconds.parse().ok().filter(|seconds| *seconds > 0).ok_or_else(|| {
+                        Mouse2JoyError::InvalidArguments(format!("invalid number of seconds \"{}\"", seconds))
+                    })?);
+                }
+                "--log-csv" => parsed.log_csv = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--log-csv-rate" => {
+                    let rate = value(&arg, args.next())?;
+                    parsed.log_csv_rate =
+                        Some(rate.parse().ok().filter(|rate| (1..=1000).contains(rate)).ok_or_else(|| {
+                            Mouse2JoyError::InvalidArguments(format!("invalid rate \"{}\", 1 to 1000 Hz", rate))
+                        })?);
+                }
+                "--show-curve" => parsed.show_curve = true,
+                "--self-test" => parsed.self_test = true,
+                "--doctor" => parsed.doctor = true,
+                "--learn" => parsed.learn = true,
+                "--calibrate-rotation" => parsed.calibrate_rotation = true,
+                "--install-udev-rule" => {
+                    let group = args.next_if(|next| !next.starts_with('-'));
+                    parsed.udev_group = Some(group.unwrap_or_else(|| String::from("input")));
+                }
+                "--tag-joystick" => parsed.tag_joystick = true,
+                "--daemon" => parsed.daemon = true,
+                "--stop" => parsed.stop = true,
+                "--keep-root" => parsed.keep_root = true,
+                "--exit-with-launched" => parsed.exit_with_launched = true,
+                "status" if parsed.request.is_none() => parsed.request = Some(Request::Status { json: false }),
+                "--json" if matches!(parsed.request, Some(Request::Status { .. })) => {
+                    parsed.request = Some(Request::Status { json: true });
+                }
+                "pause" if parsed.request.is_none() => parsed.request = Some(Request::Pause(true)),
+                "resume" if parsed.request.is_none() => parsed.request = Some(Request::Pause(false)),
+                "set" if parsed.request.is_none() => {
+                    let setting = value(&arg, args.next())?;
+                    parsed.request = Some(Request::Set(setting, value(&arg, args.next())?));
+                }
+                "--print-sdl-mapping" => {
+                    parsed.print_sdl_mapping = true;
+                    parsed.sdl_mapping_file = args.next_if(|next| !next.starts_with('-')).map(PathBuf::from);
+                }
+                _ => return Err(Mouse2JoyError::InvalidArguments(format!("unknown argument \"{}\"", arg))),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn value(flag: &str, value: Option<String>) -> Result<String, Mouse2JoyError> {
+    value.ok_or_else(|| Mouse2JoyError::InvalidArguments(format!("{} expects a value", flag)))
+}
@@ -1,91 +1,1047 @@
+use log::warn;
+use nix::unistd::User;
 use serde::{Serialize, Deserialize};
-use toml;
-use toml::de::Error;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
 
-const BASE_RANGE: i32 = 1024;
-const BASE_DEFAULT_POSITION: i32 = 0;
-// WARN: can crash program
+use crate::Mouse2JoyError;
+
+mod lookup;
+pub use lookup::{Lookup, Source as ConfigSource};
+use lookup::Places;
+
+// the machine-wide defaults, for users without a file of their own
+const SYSTEM_CONFIG: &str = "/etc/mouse2joy/config.toml";
+
+// format of the config file, bump it and add a step to MIGRATIONS whenever a
+// key is renamed or dropped
+pub const CONFIG_VERSION: u32 = 1;
+
+// step i upgrades a table from version i to i + 1, returning what it changed
+const MIGRATIONS: [fn(&mut toml_edit::Table, &str) -> Vec<String>; CONFIG_VERSION as usize] = [drop_gui];
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
+  pub config_version: u32,
   pub sensitivity: i32,
   pub dead_zone: i32,
   pub flat: i32,
-  pub gui: bool,
+  pub tick_rate: u32,
+  // milliseconds of mouse movement summed into one update, 0 handles every
+  // event on arrival
+  pub input_window_ms: u32,
+  pub spike_rejection: SpikeRejectionConfig,
+  pub rotation: RotationConfig,
+  // turn the wheel lock to lock once the virtual device exists, for games
+  // that learn the range from the first motion
+  pub startup_sweep: bool,
+  pub pedals: PedalsConfig,
+  pub buttons: BTreeMap<String, ButtonMapping>,
+  // how long the press of a chord member waits for the other one
+  pub chord_window_ms: u32,
+  // milliseconds a release of the source waits for the press of its
+  // chatter, by source
+  pub debounce: BTreeMap<String, u32>,
+  // timed step lists a [buttons] mapping plays
+  pub macros: BTreeMap<String, Vec<String>>,
+  // sources sent on as keyboard keys, by a virtual keyboard of their own
+  pub keys: BTreeMap<String, String>,
+  pub hat: HatConfig,
+  pub extra_buttons: u16,
+  pub joystick_buttons: bool,
+  // no EV_KEY capability at all, for games taking any buttons for a gamepad
+  pub no_buttons: bool,
+  pub layout: OutputLayout,
+  // the steering sent on this axis as well, e.g. "ABS_RX" for a game that
+  // looks for the wheel there
+  pub mirror_axis: Option<String>,
+  pub publish: PublishConfig,
+  pub mouse_steering: bool,
+  // exclusive access, the desktop pointer stops following the mouse
+  pub grab_mouse: bool,
+  // ungrabs, removes the virtual devices and exits from a thread of its own,
+  // "" disables it
+  pub emergency_release: String,
+  // warn when the mouse battery drops below this percentage, 0 disables
+  pub low_battery: u32,
+  pub steer_while_held: Option<String>,
+  pub keyboard_steering: KeyboardSteeringConfig,
+  pub precision: PrecisionConfig,
+  pub acceleration: AccelerationConfig,
+  pub center_detent: CenterDetentConfig,
+  pub axis_steering: AxisSteeringConfig,
+  pub scroll: ScrollConfig,
+  pub mouse_look: MouseLookConfig,
+  pub flick: FlickConfig,
+  pub steering: SteeringConfig,
+  pub log_backend: LogBackend,
+  // seconds to wait at the device prompt, 0 waits forever
+  pub prompt_timeout: u32,
+  pub prompt_default: PromptDefault,
+  pub full_lock_cue: CueConfig,
+  pub lock_led: LockLedConfig,
+  pub overlay: OverlayConfig,
+  pub notifications: NotificationsConfig,
+  pub idle_inhibit: IdleInhibitConfig,
+  pub telemetry: TelemetryConfig,
+  pub profile_keys: ProfileKeysConfig,
+  // of a profile, the command run when it gets selected, e.g. the game
+  pub launch: Vec<String>,
+  // log what the launched command prints
+  pub launch_output: bool,
+  // of the base settings, without it no profile launches anything
+  pub allow_launch: bool,
+  // action name to the key combination doing it, e.g. pause = "KEY_LEFTCTRL+KEY_F8"
+  pub hotkeys: BTreeMap<String, String>,
+  pub input_backend: InputBackend,
+  // mice with a role, without any the one mouse steers
+  pub inputs: Vec<InputConfig>,
+  // mice left out of the scan, and if any are listed the only ones scanned
+  pub ignore_devices: Vec<String>,
+  pub only_devices: Vec<String>,
+  // events a device queued before it was (re)opened are dropped when older
+  // than this, 0 drops them all
+  pub stale_input_ms: u32,
+  // Rhai script with a tick(input) function adjusting the emitted values
+  pub script: Option<String>,
+  pub failsafe: FailsafeConfig,
+  // exits like on SIGTERM after this long without any input, 0 never does
+  pub exit_after_idle_minutes: u32,
+  pub auto_pause: AutoPauseConfig,
+  // SCHED_FIFO and niceness of the input loop, set once the devices exist
+  pub realtime: bool,
+  pub nice: Option<i32>,
+  // started as root, the user (name or uid) to become once the devices are
+  // set up, without it the one sudo names
+  pub run_as: Option<String>,
+  // virtual wheels run side by side from their own mice, e.g. for
+  // splitscreen, without any the file drives the one wheel
+  pub pipelines: Vec<PipelineConfig>,
 }
 
+// Pausing by itself when the wheel is left alone, without centering it. Only
+// a deliberate gesture resumes.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AutoPauseConfig {
+  // without input for this long, 0 never pauses
+  pub idle_seconds: u32,
+  // steering units around center the wheel has to be within
+  pub center_band: u32,
+  // moving the steering mouse this long without a break resumes, 0 leaves
+  // it to the pause hotkey
+  pub resume_burst_ms: u32,
+}
 
+// Outputs going neutral when the input is lost or goes quiet. The throttle
+// always drops, steering and brake can hold instead.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct FailsafeConfig {
+  // without any input for this long while an axis is off neutral, 0 only
+  // reacts to a lost device
+  pub timeout_ms: u32,
+  pub steering: FailsafeAction,
+  pub brake: FailsafeAction,
+}
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FailsafeAction {
+  // centered or released
+  #[default]
+  Neutral,
+  Hold,
+}
 
+// one of several wheels, its settings are the file with the profile applied
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PipelineConfig {
+  // in the virtual device name, "mouse2joy P1", and the log
+  pub name: String,
+  pub profile: Option<String>,
+}
 
-impl Config {
-  pub fn default() -> Self {
+// keys switching profiles while running, e.g. "KEY_LEFTCTRL+KEY_F1"
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ProfileKeysConfig {
+  // cycles through the base settings and the profiles in name order
+  pub next: Option<String>,
+  // profile name to the key jumping straight to it
+  pub select: BTreeMap<String, String>,
+}
+
+// vehicle speed from the game scaling the steering sensitivity
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TelemetryConfig {
+  pub format: TelemetryFormat,
+  pub listen: String,
+  // [km/h, gain] points, interpolated in between
+  pub curve: Vec<[f32; 2]>,
+  // without packets for this long the gain fades back to 1.0 over decay_ms
+  pub timeout_ms: u32,
+  pub decay_ms: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TelemetryFormat {
+  #[default]
+  None,
+  // DiRT, GRID and F1 with the legacy UDP format
+  Codemasters,
+  // Forza Motorsport and Horizon "Data Out"
+  Forza,
+}
+
+// on-screen window showing the wheel state, needs the overlay feature
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OverlayConfig {
+  pub enabled: bool,
+  pub x: i16,
+  pub y: i16,
+  pub width: u16,
+  pub height: u16,
+  pub opacity: f32,
+  // key showing and hiding the overlay
+  pub toggle: Option<String>,
+}
+
+// desktop notifications about profile switches, lost devices and the
+// failsafe
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct NotificationsConfig {
+  pub enabled: bool,
+  // shortest time between two notifications of the same kind
+  pub min_interval_ms: u32,
+}
+
+// keeping the screensaver and idle suspend off while steering, needs the
+// inhibit feature
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct IdleInhibitConfig {
+  pub enabled: bool,
+  // held on for this long after the last input
+  pub input_seconds: u32,
+}
+
+// sound played when the wheel reaches full lock
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CueMode {
+  #[default]
+  Off,
+  // terminal bell
+  Bell,
+  // generated tone played through `player`
+  Beep,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CueConfig {
+  pub mode: CueMode,
+  pub volume: f32,
+  // steering units the wheel has to back off before the cue fires again
+  pub hysteresis: i32,
+  pub player: Vec<String>,
+}
+
+// a keyboard LED lit at full lock, with the hysteresis of the full lock cue
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LockLedConfig {
+  pub enabled: bool,
+  // LED_SCROLLL, LED_CAPSL, LED_NUML, ...
+  pub led: String,
+  // the name of the keyboard, without it the first one having the LED
+  pub device: Option<String>,
+  pub blink_on_failsafe: bool,
+}
+
+// what the device prompt picks when nobody answers in time
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptDefault {
+  #[default]
+  First,
+  // give up with an error instead
+  None,
+}
+
+// where log records go, auto picks journald when started by systemd
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogBackend {
+  #[default]
+  Auto,
+  Stderr,
+  Journald,
+  Syslog,
+}
+
+// digital (key driven) pedal turned into an analog axis through a ramp
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PedalConfig {
+  pub keys: Vec<String>,
+  pub rise_ms: u32,
+  pub fall_ms: u32,
+  pub curve: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PedalsConfig {
+  pub throttle: PedalConfig,
+  pub brake: PedalConfig,
+  pub brake_overrides_throttle: bool,
+  // pedal units per count of a pedals mouse, forward is throttle
+  pub mouse_sensitivity: u32,
+  // sent as PEDAL_MAX at rest and 0 fully pressed
+  pub invert_throttle: bool,
+  pub invert_brake: bool,
+}
+
+// how the axes and buttons are spread over virtual devices
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputLayout {
+  // one device with everything
+  #[default]
+  Combined,
+  // the wheel with its buttons and the pedals on their own device
+  Split,
+  // like split, with the extra buttons on a shifter device
+  #[serde(rename = "split_shifter")]
+  SplitShifter,
+}
+
+// where the input comes from
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InputBackend {
+  // the event nodes in /dev/input
+  #[default]
+  Evdev,
+  // XInput2 raw events of the seat, needs the x11-input feature
+  X11,
+  // experimental, the event nodes with the steering mouse's X movement
+  // from its raw HID reports
+  Hidraw,
+}
+
+// what the movement of a mouse listed in [[inputs]] drives
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InputRole {
+  #[default]
+  Steering,
+  Pedals,
+  // the device with the axis_steering axis, not a mouse
+  Axis,
+}
+
+// steering from the absolute axis of a gamepad or a small wheel, its range
+// (read from the device) mapped lock to lock onto the virtual wheel
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AxisSteeringConfig {
+  pub enabled: bool,
+  pub axis: String,
+  // source units around the center that read as centered
+  pub deadzone: i32,
+  pub invert: bool,
+}
+
+// a mouse picked by path or name, neither asks at startup
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct InputConfig {
+  pub path: Option<String>,
+  pub name: Option<String>,
+  pub role: InputRole,
+}
+
+// how mouse movement turns into steering, one mode for the whole axis
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SteeringMode {
+  // movement accumulates into a wheel position
+  #[default]
+  Position,
+  // mouse speed sets the deflection, which returns to center when it stops
+  Velocity,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SteeringConfig {
+  pub mode: SteeringMode,
+  // velocity mode: steering units per mouse count per second
+  pub gain: f32,
+  pub window_ms: u32,
+  pub return_ms: u32,
+  pub inertia: InertiaConfig,
+}
+
+// the wheel follows the mouse like a heavy wheel on a damped spring
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct InertiaConfig {
+  pub enabled: bool,
+  // about how long it takes to catch up with the mouse
+  pub response_ms: u32,
+  // 1.0 settles without overshooting, less swings past the mouse position
+  // and back, more creeps up to it
+  pub damping: f32,
+}
+
+// steering from two keys, ramping towards full lock while held
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct KeyboardSteeringConfig {
+  pub enabled: bool,
+  pub left: String,
+  pub right: String,
+  pub lock_ms: u32,
+  pub return_ms: u32,
+}
+
+// mouse steering scaled down while a key is held, for small corrections
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PrecisionConfig {
+  pub enabled: bool,
+  pub key: String,
+  // factor on the movement while held
+  pub scale: f32,
+}
+
+// Mouse acceleration: the sensitivity multiplied by a gain that depends on
+// how fast the mouse moves, not on where the wheel is or how fast the car
+// goes
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AccelerationConfig {
+  pub enabled: bool,
+  // the speed is the counts of this long, either direction, per ms
+  pub window_ms: u32,
+  // two or three [counts per ms, gain] points, interpolated in between
+  pub curve: Vec<[f32; 2]>,
+}
+
+// the mouse steering latched at center, in steering units, 0 is off
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CenterDetentConfig {
+  // how close to center slow movement latches it
+  pub band: u32,
+  // how far the mouse has to push, from center, to get out
+  pub breakout: u32,
+}
+
+// what the scroll wheel does, it can only have one role at a time
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollMode {
+  #[default]
+  None,
+  // every detent changes a held throttle level
+  Throttle,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ScrollConfig {
+  pub mode: ScrollMode,
+  pub step: u32,
+  pub axis: String,
+  pub reset: String,
+}
+
+// what happens to a steering delta taken for a sensor glitch
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SpikeAction {
+  // cut down to the largest plausible delta
+  #[default]
+  Clamp,
+  Discard,
+}
+
+// single implausible REL_X deltas of the steering mouse, e.g. from lifting
+// it off the desk
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SpikeRejectionConfig {
+  pub enabled: bool,
+  // counts a single event may move, 0 doesn't cap them
+  pub max_delta: u32,
+  // how many times the recent average a delta may be
+  pub factor: f32,
+  // deltas up to this are never taken for spikes
+  pub min_delta: u32,
+  pub action: SpikeAction,
+}
+
+// what the movement across the steering direction of a rotated mouse does
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossMovement {
+  // goes on as REL_Y, to whatever reads that of the steering mouse
+  #[default]
+  Keep,
+  Discard,
+}
+
+// the direction the steering mouse moves in while steering right, in degrees
+// from its X axis towards its Y axis
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RotationConfig {
+  pub angle: f32,
+  pub cross: CrossMovement,
+}
+
+// axes of the steering mouse passed on to a virtual pointer for looking
+// around, REL_X keeps steering
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct MouseLookConfig {
+  pub enabled: bool,
+  pub forward: Vec<String>,
+  // only while this key or button is held, empty always
+  pub hold: String,
+}
+
+// a short sharp sideways flick of the steering mouse taps a button, e.g. to
+// glance left or right
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct FlickConfig {
+  pub enabled: bool,
+  // the buttons of a flick to the left and to the right, empty for none
+  pub left: String,
+  pub right: String,
+  // counts per second the flick has to average
+  pub speed: u32,
+  // counts it has to cover in one direction
+  pub distance: u32,
+  // it has to be over within this
+  pub max_ms: u32,
+  // and followed by this long without movement
+  pub stop_ms: u32,
+  // steer back to where the wheel was before the flick
+  pub rollback: bool,
+  // how long the button is pressed
+  pub hold_ms: u32,
+}
+
+// d-pad driven by four keys or buttons
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HatConfig {
+  pub enabled: bool,
+  pub up: String,
+  pub down: String,
+  pub left: String,
+  pub right: String,
+}
+
+// where to publish the wheel state for overlays and dashboards
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PublishConfig {
+  pub udp: Option<String>,
+  pub fifo: Option<String>,
+  pub rate: u32,
+}
+
+// destination of a key/button, either a virtual button name or an axis
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ButtonMapping {
+  Button(String),
+  Axis {
+    axis: String,
+    #[serde(default)]
+    mode: AxisMode,
+  },
+  Action {
+    action: MappingAction,
+    #[serde(default)]
+    release: SnapRelease,
+  },
+  // one of [macros]
+  Macro {
+    #[serde(rename = "macro")]
+    name: String,
+  },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AxisMode {
+  // the axis follows the ramp of the pedal living on it
+  #[default]
+  Ramp,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingAction {
+  // steering at full lock while held
+  SnapLeft,
+  SnapRight,
+  // cruise control, the throttle stays where it is until braking
+  ThrottleHoldToggle,
+}
+
+// what the wheel does when a snap button is released
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapRelease {
+  // back to where the mouse put it
+  #[default]
+  Return,
+  // the mouse position moves to full lock as well
+  Stay,
+}
+
+impl Default for Config {
+  fn default() -> Self {
     Self {
+      config_version: CONFIG_VERSION,
       sensitivity: 3,
       dead_zone: 0,
       flat: 0,
-      gui: true,
+      tick_rate: 250,
+      input_window_ms: 0,
+      spike_rejection: SpikeRejectionConfig::default(),
+      rotation: RotationConfig::default(),
+      startup_sweep: false,
+      pedals: PedalsConfig::default(),
+      buttons: BTreeMap::new(),
+      chord_window_ms: 50,
+      debounce: BTreeMap::new(),
+      macros: BTreeMap::new(),
+      keys: BTreeMap::new(),
+      hat: HatConfig::default(),
+      extra_buttons: 0,
+      joystick_buttons: true,
+      no_buttons: false,
+      layout: OutputLayout::Combined,
+      mirror_axis: None,
+      publish: PublishConfig::default(),
+      mouse_steering: true,
+      grab_mouse: false,
+      emergency_release: String::from("KEY_LEFTCTRL+KEY_LEFTALT+KEY_END"),
+      low_battery: 15,
+      steer_while_held: None,
+      keyboard_steering: KeyboardSteeringConfig::default(),
+      precision: PrecisionConfig::default(),
+      acceleration: AccelerationConfig::default(),
+      center_detent: CenterDetentConfig::default(),
+      axis_steering: AxisSteeringConfig::default(),
+      scroll: ScrollConfig::default(),
+      mouse_look: MouseLookConfig::default(),
+      flick: FlickConfig::default(),
+      steering: SteeringConfig::default(),
+      log_backend: LogBackend::Auto,
+      prompt_timeout: 0,
+      prompt_default: PromptDefault::First,
+      full_lock_cue: CueConfig::default(),
+      lock_led: LockLedConfig::default(),
+      overlay: OverlayConfig::default(),
+      notifications: NotificationsConfig::default(),
+      idle_inhibit: IdleInhibitConfig::default(),
+      telemetry: TelemetryConfig::default(),
+      profile_keys: ProfileKeysConfig::default(),
+      launch: Vec::new(),
+      launch_output: false,
+      allow_launch: false,
+      hotkeys: BTreeMap::new(),
+      input_backend: InputBackend::Evdev,
+      inputs: Vec::new(),
+      ignore_devices: Vec::new(),
+      only_devices: Vec::new(),
+      stale_input_ms: 0,
+      script: None,
+      failsafe: FailsafeConfig::default(),
+      exit_after_idle_minutes: 0,
+      auto_pause: AutoPauseConfig::default(),
+      realtime: false,
+      nice: None,
+      run_as: None,
+      pipelines: Vec::new(),
     }
   }
+}
 
-  pub fn exists() -> bool {
-    match dirs::config_dir() {
-      Some(_) => {
-        return std::path::Path::new(&Config::path()).exists()
-      }
-      None => {
-        return false
-      }
+impl Default for TelemetryConfig {
+  fn default() -> Self {
+    Self {
+      format: TelemetryFormat::None,
+      listen: String::from("127.0.0.1:20777"),
+      curve: vec![[0.0, 1.0], [60.0, 1.0], [200.0, 0.4]],
+      timeout_ms: 1000,
+      decay_ms: 2000,
+    }
+  }
+}
+
+impl Default for NotificationsConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      min_interval_ms: 5000,
+    }
+  }
+}
+
+impl Default for IdleInhibitConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      input_seconds: 60,
+    }
+  }
+}
+
+impl Default for OverlayConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      x: 20,
+      y: 20,
+      width: 240,
+      height: 40,
+      opacity: 0.8,
+      toggle: None,
+    }
+  }
+}
+
+impl Default for CueConfig {
+  fn default() -> Self {
+    Self {
+      mode: CueMode::Off,
+      volume: 0.5,
+      hysteresis: 200,
+      player: vec![String::from("aplay"), String::from("-q"), String::from("-")],
+    }
+  }
+}
+
+impl Default for SteeringConfig {
+  fn default() -> Self {
+    Self {
+      mode: SteeringMode::Position,
+      gain: 5.0,
+      window_ms: 50,
+      return_ms: 300,
+      inertia: InertiaConfig::default(),
+    }
+  }
+}
+
+impl Default for InertiaConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      response_ms: 80,
+      damping: 0.8,
+    }
+  }
+}
+
+impl Default for KeyboardSteeringConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      left: String::from("KEY_LEFT"),
+      right: String::from("KEY_RIGHT"),
+      lock_ms: 600,
+      return_ms: 400,
+    }
+  }
+}
+
+impl Default for PrecisionConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      key: String::from("KEY_LEFTSHIFT"),
+      scale: 0.25,
     }
   }
+}
 
-  pub fn path() -> String {
+impl Default for AccelerationConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      window_ms: 20,
+      curve: vec![[0.5, 0.5], [2.0, 1.0], [8.0, 2.0]],
+    }
+  }
+}
 
-    // Get the SUDO_USER environment variable to find the actual user
-    let user = std::env::var("SUDO_USER").unwrap_or_else(|_| String::from("root"));
-    
-    // If running as sudo, construct path using the real user's home
-    if user != "root" {
-        format!("/home/{}/.config/mouse2joy/config.toml", user)
-    } else {
-        // Fallback to root's config if not running through sudo
-        dirs::config_dir()
-            .unwrap_or_default()
-            .join("mouse2joy")
-            .join("config.toml")
-            .to_string_lossy()
-            .into_owned()
+impl Default for LockLedConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      led: String::from("LED_SCROLLL"),
+      device: None,
+      blink_on_failsafe: false,
     }
   }
+}
 
-  // WARN: Can make program crash
-  pub fn load() -> Result<Config,Error> {
-    let file = Config::path();
-    let contents = std::fs::read_to_string(file).unwrap();
-    toml::from_str(&contents)
+impl Default for AutoPauseConfig {
+  fn default() -> Self {
+    Self {
+      idle_seconds: 0,
+      center_band: 300,
+      resume_burst_ms: 0,
+    }
   }
+}
 
-  pub fn range_min(&self) -> i32 {
-    -(self.sensitivity*BASE_RANGE)/2
+impl Default for AxisSteeringConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      axis: String::from("ABS_X"),
+      deadzone: 0,
+      invert: false,
+    }
   }
+}
 
-  pub fn range_max(&self) -> i32 {
-    (self.sensitivity*BASE_RANGE)/2
+impl Default for FlickConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      left: String::new(),
+      right: String::new(),
+      speed: 10000,
+      distance: 500,
+      max_ms: 100,
+      stop_ms: 80,
+      rollback: true,
+      hold_ms: 100,
+    }
   }
+}
 
-  pub fn fuzz(&self) -> i32 {
-    self.dead_zone
+impl Default for MouseLookConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      forward: vec![String::from("REL_Y")],
+      hold: String::new(),
+    }
   }
+}
 
-  pub fn value(&self) -> i32 {
-    BASE_DEFAULT_POSITION
+impl Default for SpikeRejectionConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      max_delta: 0,
+      factor: 8.0,
+      min_delta: 100,
+      action: SpikeAction::Clamp,
+    }
   }
+}
 
-  pub fn resolution(&self) -> i32 {
-    BASE_RANGE
+impl Default for RotationConfig {
+  fn default() -> Self {
+    Self {
+      angle: 0.0,
+      cross: CrossMovement::Keep,
+    }
   }
+}
+
+impl Default for ScrollConfig {
+  fn default() -> Self {
+    Self {
+      mode: ScrollMode::None,
+      step: 10,
+      axis: String::from("ABS_THROTTLE"),
+      reset: String::from("BTN_MIDDLE"),
+    }
+  }
+}
+
+impl Default for PublishConfig {
+  fn default() -> Self {
+    Self {
+      udp: None,
+      fifo: None,
+      rate: 30,
+    }
+  }
+}
+
+impl Default for HatConfig {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      up: String::from("KEY_UP"),
+      down: String::from("KEY_DOWN"),
+      left: String::from("KEY_LEFT"),
+      right: String::from("KEY_RIGHT"),
+    }
+  }
+}
+
+impl Default for PedalConfig {
+  fn default() -> Self {
+    Self {
+      keys: Vec::new(),
+      rise_ms: 300,
+      fall_ms: 200,
+      curve: 1.0,
+    }
+  }
+}
+
+impl Default for PedalsConfig {
+  fn default() -> Self {
+    Self {
+      throttle: PedalConfig::default(),
+      brake: PedalConfig::default(),
+      brake_overrides_throttle: true,
+      mouse_sensitivity: 2,
+      invert_throttle: false,
+      invert_brake: false,
+    }
+  }
+}
+
+
+impl Config {
+  // Where the configuration is looked for when --config isn't given, see
+  // lookup::Places::find. A legacy file is moved on the way.
+  pub fn path() -> Lookup {
+    // under sudo the configuration of the user who ran it, not root's
+    let sudo_home = std::env::var("SUDO_USER").ok().filter(|user| user != "root").map(|user| {
+      User::from_name(&user)
+        .ok()
+        .flatten()
+        .map_or_else(|| PathBuf::from("/home").join(&user), |user| user.dir)
+    });
+    let home = sudo_home.or_else(dirs::home_dir).unwrap_or_default();
+    let mut legacy = vec![home.join(".config").join("mouse2joy").join("config.toml")];
+    if let Ok(user) = std::env::var("SUDO_USER") {
+      legacy.push(PathBuf::from("/home").join(user).join(".config").join("mouse2joy").join("config.toml"));
+    }
+    let places = Places {
+      config_home: std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+      home,
+      legacy,
+      system: PathBuf::from(SYSTEM_CONFIG),
+    };
+    places.find()
+  }
+
+  pub fn load(file: &Path) -> Result<Config, Mouse2JoyError> {
+    let (document, migrated) = Config::document(file)?;
+    if migrated {
+      warn!("Run mouse2joy --migrate-config to update {}", file.display());
+    }
+    toml::from_str(&document.to_string())
+      .map_err(|e| Mouse2JoyError::InvalidConfig(format!("{}: {}", file.display(), e)))
+  }
+
+  // the file brought up to the current format in memory, and whether that
+  // changed anything
+  pub fn document(file: &Path) -> Result<(DocumentMut, bool), Mouse2JoyError> {
+    let contents = std::fs::read_to_string(file)
+      .map_err(|e| Mouse2JoyError::InvalidConfig(format!("can't read {}: {}", file.display(), e)))?;
+    let mut document: DocumentMut = contents
+      .parse()
+      .map_err(|e| Mouse2JoyError::InvalidConfig(format!("{}: {}", file.display(), e)))?;
+    let changes = migrate(&mut document)
+      .map_err(|e| Mouse2JoyError::InvalidConfig(format!("{}: {}", file.display(), e)))?;
+    for change in &changes {
+      warn!("{} is in an older format: {}", file.display(), change);
+    }
+    Ok((document, !changes.is_empty()))
+  }
+
+  // write a document back to its file, keeping the old contents in
+  // FILE.bak first if asked to
+  pub fn save(file: &Path, document: &DocumentMut, backup: bool) -> Result<(), Mouse2JoyError> {
+    if backup {
+      let mut bak = file.as_os_str().to_owned();
+      bak.push(".bak");
+      std::fs::copy(file, &bak)
+        .map_err(|e| Mouse2JoyError::FailedToWrite(Path::new(&bak).display().to_string(), e))?;
+    }
+    std::fs::write(file, document.to_string())
+      .map_err(|e| Mouse2JoyError::FailedToWrite(file.display().to_string(), e))
+  }
+}
+
+// Upgrade a config written for an older mouse2joy, including the profiles in
+// it. Files without a config_version predate versioning and count as 0.
+pub fn migrate(table: &mut toml_edit::Table) -> Result<Vec<String>, String> {
+  let version = match table.get("config_version") {
+    None => 0,
+    Some(version) => version
+      .as_integer()
+      .and_then(|version| u32::try_from(version).ok())
+      .ok_or_else(|| String::from("config_version must be a positive integer"))?,
+  };
+  if version > CONFIG_VERSION {
+    return Err(format!(
+      "config_version {} is newer than this mouse2joy understands (up to {}), update mouse2joy",
+      version, CONFIG_VERSION
+    ));
+  }
+
+  let mut changes = Vec::new();
+  for step in &MIGRATIONS[version as usize..] {
+    changes.extend(step(table, ""));
+    if let Some(profiles) = table.get_mut("profiles").and_then(toml_edit::Item::as_table_like_mut) {
+      for (name, profile) in profiles.iter_mut() {
+        if let Some(profile) = profile.as_table_mut() {
+          changes.extend(step(profile, &format!("profiles.{}.", name)));
+        }
+      }
+    }
+    if let Some(devices) = table.get_mut("device").and_then(toml_edit::Item::as_table_like_mut) {
+      for (name, device) in devices.iter_mut() {
+        if let Some(device) = device.as_table_mut() {
+          changes.extend(step(device, &format!("device.\"{}\".", name)));
+        }
+      }
+    }
+  }
+  if version < CONFIG_VERSION {
+    table.insert("config_version", toml_edit::value(i64::from(CONFIG_VERSION)));
+  }
+  Ok(changes)
+}
 
-  pub fn flat(&self) -> i32 {
-    self.flat
+// 0 -> 1: the gui setting never had an effect
+fn drop_gui(table: &mut toml_edit::Table, prefix: &str) -> Vec<String> {
+  match table.remove("gui") {
+    Some(_) => vec![format!("dropped {}gui, it never had an effect", prefix)],
+    None => Vec::new(),
   }
 }
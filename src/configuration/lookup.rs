@@ -0,0 +1,103 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// What the lookup goes by, read from the environment by the caller.
+pub struct Places {
+    // $XDG_CONFIG_HOME, the spec says to ignore it unless it is absolute
+    pub config_home: Option<PathBuf>,
+    // the home of the user the configuration belongs to, under sudo the
+    // one who ran it
+    pub home: PathBuf,
+    // where earlier versions looked, moved to the XDG location when found
+    pub legacy: Vec<PathBuf>,
+    pub system: PathBuf,
+}
+
+// why the file was chosen
+#[derive(PartialEq, Debug)]
+pub enum Source {
+    Given,
+    ConfigHome,
+    Home,
+    Migrated(PathBuf),
+    // a legacy file that couldn't be moved, used where it is
+    Legacy(String),
+    System,
+    Missing,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Lookup {
+    pub path: PathBuf,
+    // where the user's own file goes, which imports write to
+    pub user: PathBuf,
+    pub source: Source,
+}
+
+impl Lookup {
+    // --config, no lookup at all
+    pub fn given(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), user: path.to_path_buf(), source: Source::Given }
+    }
+}
+
+impl Places {
+    // the XDG location: $XDG_CONFIG_HOME/mouse2joy/config.toml, or
+    // ~/.config/mouse2joy/config.toml
+    pub fn user(&self) -> (PathBuf, Source) {
+        match self.config_home.as_ref().filter(|dir| dir.is_absolute()) {
+            Some(dir) => (dir.join("mouse2joy").join("config.toml"), Source::ConfigHome),
+            None => (self.home.join(".config").join("mouse2joy").join("config.toml"), Source::Home),
+        }
+    }
+
+    // The user's file, else a legacy one moved there, else the system-wide
+    // one. Without any of them the user's file is where one would go.
+    pub fn find(&self) -> Lookup {
+        let (user, source) = self.user();
+        if user.exists() {
+            return Lookup { path: user.clone(), user, source };
+        }
+        if let Some(legacy) = self.legacy.iter().find(|legacy| **legacy != user && legacy.exists()) {
+            let moved = user
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| std::fs::rename(legacy, &user));
+            return match moved {
+                Ok(()) => Lookup { path: user.clone(), user, source: Source::Migrated(legacy.clone()) },
+                Err(e) => Lookup { path: legacy.clone(), user, source: Source::Legacy(e.to_string()) },
+            };
+        }
+        if self.system.exists() {
+            return Lookup { path: self.system.clone(), user, source: Source::System };
+        }
+        Lookup { path: user.clone(), user, source: Source::Missing }
+    }
+}
+
+impl fmt::Display for Lookup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.source {
+            Source::Given => write!(f, "Configuration file {} given with --config", self.path.display()),
+            Source::ConfigHome => write!(f, "Configuration file {} found in $XDG_CONFIG_HOME", self.path.display()),
+            Source::Home => write!(f, "Configuration file {} found in ~/.config", self.path.display()),
+            Source::Migrated(from) => {
+                write!(f, "Moved the configuration file {} to {}", from.display(), self.path.display())
+            }
+            Source::Legacy(e) => write!(
+                f,
+                "Failed to move the configuration file {} to {}, using it where it is:  {}",
+                self.path.display(),
+                self.user.display(),
+                e
+            ),
+            Source::System => write!(
+                f,
+                "No configuration file {}, using the system-wide {}",
+                self.user.display(),
+                self.path.display()
+            ),
+            Source::Missing => write!(f, "No configuration file {}", self.path.display()),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use log::{debug, info, warn};
+use nix::sys::pthread::{pthread_kill, pthread_self, Pthread};
+use nix::unistd::{chown, Uid};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::publisher::json_string;
+use crate::steering::{Steering, STEERING_MAX};
+use crate::{daemon, pipeline, Mouse2JoyError};
+
+// how long the socket waits for a loop to answer
+const TIMEOUT: Duration = Duration::from_secs(2);
+// and the client for the socket, the loops answer one after another
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+// a loop that hasn't answered yet is woken again, the first wakeup may have
+// come just before its poll
+const REWAKE: Duration = Duration::from_millis(50);
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+// the loops that answer, in the order of the pipelines
+static WHEELS: Mutex<Vec<Wheel>> = Mutex::new(Vec::new());
+// opened by this process, removed on the way out
+static SOCKET: Mutex<Option<PathBuf>> = Mutex::new(None);
+// held while a client is answered, the exit waits for the answer to go out
+static ANSWERING: Mutex<()> = Mutex::new(());
+
+// what a client asks of the running mouse2joy, one line on the socket
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    Status { json: bool },
+    // true pauses, false resumes
+    Pause(bool),
+    Set(String, String),
+}
+
+impl Request {
+    fn line(&self) -> String {
+        match self {
+            Request::Status { json: false } => String::from("status"),
+            Request::Status { json: true } => String::from("status json"),
+            Request::Pause(true) => String::from("pause"),
+            Request::Pause(false) => String::from("resume"),
+            Request::Set(setting, value) => format!("set {} {}", setting, value),
+        }
+    }
+
+    fn parse(line: &str) -> Option<Request> {
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["status"] => Some(Request::Status { json: false }),
+            ["status", "json"] => Some(Request::Status { json: true }),
+            ["pause"] => Some(Request::Pause(true)),
+            ["resume"] => Some(Request::Pause(false)),
+            ["set", setting, value] => Some(Request::Set(setting.to_string(), value.to_string())),
+            _ => None,
+        }
+    }
+}
+
+pub enum Reply {
+    Status(Status),
+    Done(String),
+    Rejected(String),
+}
+
+// what `mouse2joy status` shows of a loop
+pub struct Status {
+    pub paused: bool,
+    pub input: String,
+    pub steering: i32,
+    pub throttle: f32,
+    pub brake: f32,
+    pub sensitivity: i32,
+    pub precision: bool,
+    // None on the base settings
+    pub profile: Option<String>,
+    pub input_events: u64,
+    pub output_events: u64,
+}
+
+impl Status {
+    fn to_text(&self, pipeline: Option<&str>) -> String {
+        let mut text = String::new();
+        if let Some(name) = pipeline {
+            text.push_str(&format!("pipeline       {}\n", name));
+        }
+        text.push_str(&format!(
+            "state          {}\ninput          {}\nsteering       {} of {}\nthrottle       {:.0}%\n\
+             brake          {:.0}%\nsensitivity    {}{}\nprofile        {}\ninput events   {}\noutput events  {}\n",
+            if self.paused { "paused" } else { "running" },
+            self.input,
+            self.steering,
+            STEERING_MAX,
+            self.throttle * 100.0,
+            self.brake * 100.0,
+            self.sensitivity,
+            if self.precision { " (precision held)" } else { "" },
+            self.profile.as_deref().map_or(String::from("base"), |name| format!("\"{}\"", name)),
+            self.input_events,
+            self.output_events
+        ));
+        text
+    }
+
+    fn to_json(&self, pipeline: Option<&str>) -> String {
+        let optional = |value: Option<&str>| value.map_or(String::from("null"), json_string);
+        format!(
+            "{{\"pipeline\": {}, \"paused\": {}, \"input\": {}, \"steering\": {}, \"steering_max\": {}, \
+             \"throttle\": {:.3}, \"brake\": {:.3}, \"sensitivity\": {}, \"precision\": {}, \"profile\": {}, \
+             \"input_events\": {}, \"output_events\": {}}}\n",
+            optional(pipeline),
+            self.paused,
+            json_string(&self.input),
+            self.steering,
+            STEERING_MAX,
+            self.throttle,
+            self.brake,
+            self.sensitivity,
+            self.precision,
+            optional(self.profile.as_deref()),
+            self.input_events,
+            self.output_events
+        )
+    }
+}
+
+#[derive(Clone)]
+struct Wheel {
+    index: usize,
+    name: Option<String>,
+    thread: Pthread,
+    requests: Sender<(Request, Sender<Reply>)>,
+}
+
+// The requests of the socket for the loop on this thread, which gets woken
+// by pipeline::WAKE for each and answers on the reply sender.
+pub struct Control {
+    index: usize,
+    requests: Receiver<(Request, Sender<Reply>)>,
+}
+
+impl Control {
+    // the requests that came in since the last call
+    pub fn take(&self) -> Vec<(Request, Sender<Reply>)> {
+        self.requests.try_iter().collect()
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        lock(&WHEELS).retain(|wheel| wheel.index != self.index);
+    }
+}
+
+// $XDG_RUNTIME_DIR/mouse2joy.sock, or for a --config file one named after
+// its path, so the client finds the instance of the same file
+pub fn socket(config: Option<&Path>) -> PathBuf {
+    let name = match config {
+        Some(config) => {
+            let config = std::fs::canonicalize(config)
+                .or_else(|_| std::path::absolute(config))
+                .unwrap_or_else(|_| config.to_path_buf());
+            format!("mouse2joy-{:016x}.sock", fnv1a(config.as_os_str().as_bytes()))
+        }
+        None => String::from("mouse2joy.sock"),
+    };
+    daemon::runtime_dir().join(name)
+}
+
+// stable across builds, unlike the std hasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3))
+}
+
+// Make the loop on this thread answer on the socket, which the first loop
+// opens. A socket that fails to open is only logged, the wheel works
+// without it.
+pub fn register(socket: &Path, index: usize, name: Option<&str>) -> Control {
+    if !STARTED.swap(true, Ordering::Relaxed) {
+        match listen(socket) {
+            Ok(listener) => {
+                if let Err(e) = pipeline::install_wake() {
+                    warn!("Failed to install a {} handler, mouse2joy status may answer late:  {}", pipeline::WAKE, e);
+                }
+                info!("Answering mouse2joy status and set on {}", socket.display());
+                *lock(&SOCKET) = Some(socket.to_path_buf());
+                std::thread::spawn(move || serve(listener));
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                debug!("No runtime directory for the control socket {}:  {}", socket.display(), e);
+            }
+            Err(e) => warn!(
+                "Failed to open the control socket {}, mouse2joy status won't reach this one:  {}",
+                socket.display(),
+                e
+            ),
+        }
+    }
+    let (requests, received) = mpsc::channel();
+    let wheel = Wheel { index, name: name.map(String::from), thread: pthread_self(), requests };
+    let mut wheels = lock(&WHEELS);
+    wheels.push(wheel);
+    wheels.sort_by_key(|wheel| wheel.index);
+    Control { index, requests: received }
+}
+
+fn listen(socket: &Path) -> std::io::Result<UnixListener> {
+    let listener = match UnixListener::bind(socket) {
+        Err(e) if e.kind() == ErrorKind::AddrInUse => {
+            if UnixStream::connect(socket).is_ok() {
+                return Err(std::io::Error::new(ErrorKind::AddrInUse, "another mouse2joy answers on it"));
+            }
+            // left behind by a mouse2joy that didn't get to clean up
+            std::fs::remove_file(socket)?;
+            UnixListener::bind(socket)?
+        }
+        bound => bound?,
+    };
+    // under sudo the user who ran it asks without sudo
+    if let Some(uid) = std::env::var("SUDO_UID").ok().and_then(|uid| uid.parse().ok()) {
+        let _ = chown(socket, Some(Uid::from_raw(uid)), None);
+    }
+    Ok(listener)
+}
+
+// the socket goes with the process, after the answer to a pause or set that
+// ended the last loop
+pub fn finish() {
+    let _answering = lock(&ANSWERING);
+    if let Some(socket) = lock(&SOCKET).take() {
+        let _ = std::fs::remove_file(socket);
+    }
+}
+
+// one client at a time, each asks one thing
+fn serve(listener: UnixListener) {
+    for stream in listener.incoming() {
+        let answered = stream.and_then(|stream| {
+            let _answering = lock(&ANSWERING);
+            stream.set_read_timeout(Some(TIMEOUT))?;
+            let mut line = String::new();
+            BufReader::new(&stream).take(1024).read_line(&mut line)?;
+            let answer = match Request::parse(&line) {
+                Some(request) => ask(&request),
+                None => Answer::Rejected(format!("unknown request \"{}\"", line.trim())),
+            };
+            let answer = match answer {
+                Answer::Done(body) => format!("ok\n{}", body),
+                Answer::Rejected(message) => format!("error\n{}\n", message),
+                Answer::Failed(message) => format!("failed\n{}\n", message),
+            };
+            (&stream).write_all(answer.as_bytes())
+        });
+        if let Err(e) = answered {
+            debug!("A control client went away:  {}", e);
+        }
+    }
+}
+
+enum Answer {
+    Done(String),
+    // the request itself, mapped to invalid arguments
+    Rejected(String),
+    // the loops didn't answer
+    Failed(String),
+}
+
+// Every loop in turn, with the name of its pipeline on each reply. A set or
+// pause goes to all of them, the first rejection is the answer.
+fn ask(request: &Request) -> Answer {
+    let wheels = lock(&WHEELS).clone();
+    if wheels.is_empty() {
+        return Answer::Failed(String::from("mouse2joy isn't running a wheel (yet)"));
+    }
+    let mut body = String::new();
+    for (i, wheel) in wheels.iter().enumerate() {
+        let (reply, replied) = mpsc::channel();
+        if wheel.requests.send((request.clone(), reply)).is_err() {
+            continue;
+        }
+        let prefix = wheel.name.as_ref().map(|name| format!("{}: ", name)).unwrap_or_default();
+        match wait(wheel.thread, &replied) {
+            Ok(Reply::Status(status)) => match request {
+                Request::Status { json: true } => body.push_str(&status.to_json(wheel.name.as_deref())),
+                _ => {
+                    if i > 0 {
+                        body.push('\n');
+                    }
+                    body.push_str(&status.to_text(wheel.name.as_deref()));
+                }
+            },
+            Ok(Reply::Done(message)) => body.push_str(&format!("{}{}\n", prefix, message)),
+            Ok(Reply::Rejected(message)) => return Answer::Rejected(format!("{}{}", prefix, message)),
+            Err(message) => return Answer::Failed(format!("{}{}", prefix, message)),
+        }
+    }
+    Answer::Done(body)
+}
+
+fn wait(thread: Pthread, replied: &Receiver<Reply>) -> Result<Reply, String> {
+    let start = Instant::now();
+    loop {
+        let _ = pthread_kill(thread, pipeline::WAKE);
+        match replied.recv_timeout(REWAKE) {
+            Ok(reply) => return Ok(reply),
+            Err(RecvTimeoutError::Timeout) if start.elapsed() < TIMEOUT => {}
+            Err(RecvTimeoutError::Timeout) => return Err(String::from("the wheel didn't answer in time")),
+            Err(RecvTimeoutError::Disconnected) => return Err(String::from("the wheel stopped before answering")),
+        }
+    }
+}
+
+// `mouse2joy set SETTING VALUE` on the loop, only for this run
+pub fn set(steering: &mut Steering, setting: &str, value: &str) -> Reply {
+    match setting {
+        "sensitivity" => {
+            let Ok(sensitivity) = value.parse() else {
+                return Reply::Rejected(format!("invalid sensitivity \"{}\", a whole number", value));
+            };
+            match steering.set_sensitivity(sensitivity) {
+                Ok(()) => {
+                    info!("Sensitivity set to {} by mouse2joy set", sensitivity);
+                    Reply::Done(format!("Sensitivity set to {}", sensitivity))
+                }
+                Err(Mouse2JoyError::InvalidConfig(message)) => Reply::Rejected(message),
+                Err(e) => Reply::Rejected(e.to_string()),
+            }
+        }
+        _ => Reply::Rejected(format!("unknown setting \"{}\", only sensitivity can be set", setting)),
+    }
+}
+
+// The client side: send the request to the mouse2joy answering on the
+// socket and print what it answers.
+pub fn send(socket: &Path, request: &Request) -> Result<(), Mouse2JoyError> {
+    let mut stream = UnixStream::connect(socket).map_err(|e| {
+        Mouse2JoyError::NotRunning(format!("nothing answers on {} ({})", socket.display(), e))
+    })?;
+    let failed = |e: std::io::Error| Mouse2JoyError::NotRunning(format!("no answer on {} ({})", socket.display(), e));
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT)).map_err(failed)?;
+    stream.write_all(format!("{}\n", request.line()).as_bytes()).map_err(failed)?;
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer).map_err(failed)?;
+    match answer.split_once('\n') {
+        Some(("ok", body)) => {
+            print!("{}", body);
+            Ok(())
+        }
+        Some(("error", message)) => Err(Mouse2JoyError::InvalidArguments(message.trim_end().to_string())),
+        Some(("failed", message)) => Err(Mouse2JoyError::NotRunning(message.trim_end().to_string())),
+        _ => Err(Mouse2JoyError::NotRunning(format!("no answer on {}", socket.display()))),
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,146 @@
+use log::{debug, warn};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{sync_channel, SyncSender};
+
+use crate::configuration::{CueConfig, CueMode};
+use crate::steering::STEERING_MAX;
+use crate::Mouse2JoyError;
+
+const SAMPLE_RATE: u32 = 44100;
+const BEEP_HZ: f32 = 880.0;
+const BEEP_MS: u32 = 80;
+
+// Audible cue standing in for the missing steering stop: fires once when the
+// wheel reaches full lock and again only after it left the hysteresis band
+// (or reached the opposite lock).
+// Sounds are made on a helper thread so they never delay an emit.
+pub struct LockCue {
+    hysteresis: i32,
+    // side of the lock the cue last fired for, until the wheel backs off
+    locked: Option<i32>,
+    sender: SyncSender<()>,
+}
+
+impl LockCue {
+    pub fn new(conf: &CueConfig) -> Result<Option<Self>, Mouse2JoyError> {
+        if conf.mode == CueMode::Off {
+            return Ok(None);
+        }
+        if !(0.0..=1.0).contains(&conf.volume) {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "cue volume must be between 0.0 and 1.0, got {}",
+                conf.volume
+            )));
+        }
+        check_hysteresis(conf.hysteresis)?;
+        if conf.mode == CueMode::Beep && conf.player.is_empty() {
+            return Err(Mouse2JoyError::InvalidConfig(String::from("beep cue needs a player command")));
+        }
+
+        // a cue still playing swallows the next one instead of queueing up
+        let (sender, receiver) = sync_channel::<()>(1);
+        let mode = conf.mode;
+        let sound = beep(conf.volume);
+        let player = conf.player.clone();
+        std::thread::spawn(move || {
+            for () in receiver {
+                match mode {
+                    CueMode::Bell => bell(),
+                    CueMode::Beep => play(&player, &sound),
+                    CueMode::Off => {}
+                }
+            }
+        });
+        Ok(Some(Self { hysteresis: conf.hysteresis, locked: None, sender }))
+    }
+
+    pub fn update(&mut self, position: i32) {
+        let side = locked_side(self.locked, position, self.hysteresis);
+        if side.is_some() && side != self.locked {
+            debug!("Full lock cue");
+            let _ = self.sender.try_send(());
+        }
+        self.locked = side;
+    }
+}
+
+pub fn check_hysteresis(hysteresis: i32) -> Result<(), Mouse2JoyError> {
+    if !(0..=STEERING_MAX).contains(&hysteresis) {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "cue hysteresis must be between 0 and {}, got {}",
+            STEERING_MAX, hysteresis
+        )));
+    }
+    Ok(())
+}
+
+// The side of the lock the wheel counts as held at: from reaching it until it
+// backs off further than `hysteresis`, or reaches the other one.
+pub fn locked_side(locked: Option<i32>, position: i32, hysteresis: i32) -> Option<i32> {
+    let distance = STEERING_MAX - position.abs();
+    if distance <= 0 {
+        Some(position.signum())
+    } else if distance > hysteresis {
+        None
+    } else {
+        locked
+    }
+}
+
+// terminal bell, the zero dependency fallback. Written to the controlling
+// terminal so it doesn't end up in redirected output.
+fn bell() {
+    if let Ok(mut tty) = OpenOptions::new().write(true).open("/dev/tty") {
+        let _ = tty.write_all(b"\x07");
+    }
+}
+
+// pipe a wav file into the player command, e.g. aplay
+fn play(player: &[String], sound: &[u8]) {
+    let child = Command::new(&player[0])
+        .args(&player[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                let _ = stdin.write_all(sound);
+            }
+            let _ = child.wait();
+        }
+        Err(e) => warn!("Failed to run {}:  {}", player[0], e),
+    }
+}
+
+// short sine beep as a 16 bit mono wav file, faded in and out to avoid clicks
+fn beep(volume: f32) -> Vec<u8> {
+    let samples = SAMPLE_RATE * BEEP_MS / 1000;
+    let fade = samples / 10;
+    let data_len = samples * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, mono
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for i in 0..samples {
+        let envelope = (i.min(samples - i) as f32 / fade as f32).min(1.0);
+        let phase = i as f32 / SAMPLE_RATE as f32 * BEEP_HZ * std::f32::consts::TAU;
+        let sample = (phase.sin() * envelope * volume * i16::MAX as f32) as i16;
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
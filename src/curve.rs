@@ -0,0 +1,156 @@
+use log::LevelFilter;
+use std::time::{Duration, Instant};
+
+use crate::configuration::{Config, SteeringMode};
+use crate::mapping::{parse_key, Mapping};
+use crate::steering::{Steering, STEERING_MAX};
+use crate::Mouse2JoyError;
+
+// one count per event at this pace, what the acceleration and the center
+// detent see of the mouse
+const PACE: Duration = Duration::from_millis(1);
+// the sweep gives up on full lock after this many counts each way
+const MAX_TRAVEL: usize = 100_000;
+// of the plot, both odd so center has a column and a row
+const WIDTH: usize = 61;
+const HEIGHT: usize = 21;
+const SAMPLES: usize = 12;
+
+// The steering response for --show-curve: a real Steering fed the mouse
+// moving steadily from center to either side, the position it heads for
+// after each count. Inertia only delays that, it is left out. A response
+// turning back is a configuration mistake.
+pub fn show(conf: &Config, mapping: &Mapping) -> Result<(), Mouse2JoyError> {
+    if !conf.mouse_steering {
+        return Err(Mouse2JoyError::InvalidArguments(String::from(
+            "--show-curve plots the mouse steering, mouse_steering is off",
+        )));
+    }
+    if conf.steering.mode == SteeringMode::Velocity {
+        return Err(Mouse2JoyError::InvalidArguments(String::from(
+            "--show-curve plots the position mode, velocity steering follows the speed instead of the travel",
+        )));
+    }
+    // every count would log the position
+    let level = log::max_level();
+    log::set_max_level(LevelFilter::Warn);
+    let sides = sweep(conf, mapping, -1).and_then(|left| Ok([left, sweep(conf, mapping, 1)?]));
+    log::set_max_level(level);
+    let [mut left, mut right] = sides?;
+
+    // a quarter past the later full lock, to show it
+    let knee = |positions: &[i32]| positions.iter().position(|position| position.abs() == STEERING_MAX);
+    let range = match (knee(&left), knee(&right)) {
+        (Some(left), Some(right)) => (left.max(right) + left.max(right) / 4).clamp(1, MAX_TRAVEL),
+        _ => MAX_TRAVEL,
+    };
+    left.truncate(range + 1);
+    right.truncate(range + 1);
+    let value = |travel: i64| match travel {
+        ..0 => left[travel.unsigned_abs() as usize],
+        _ => right[travel as usize],
+    };
+
+    let mut stages = vec![format!("sensitivity {}", conf.sensitivity)];
+    if conf.acceleration.enabled {
+        stages.push(String::from("acceleration"));
+    }
+    if conf.center_detent.band > 0 {
+        let detent = &conf.center_detent;
+        stages.push(format!("the center detent, band {} and breakout {}", detent.band, detent.breakout));
+    }
+    println!("Mouse travel in counts, one per {} ms, against ABS_X with {}", PACE.as_millis(), stages.join(", "));
+    println!();
+    plot(range as i64, &value);
+    println!();
+    println!("{:>10}  {:>6}  {:>6}", "travel", "left", "right");
+    for sample in 0..SAMPLES {
+        let travel = sample * range / (SAMPLES - 1);
+        println!("{:>10}  {:>6}  {:>6}", travel, left[travel], right[travel]);
+    }
+    println!();
+    let edge = |positions: &[i32]| match positions.iter().position(|position| *position != 0) {
+        None => String::from("never moves"),
+        Some(1) => format!("none, 1 count at {}", positions[1]),
+        Some(moves) => format!("{} counts at 0, {} at {}", moves - 1, moves, positions[moves]),
+    };
+    println!("{:<18}left {}; right {}", "dead zone edge", edge(&left), edge(&right));
+    let lock = |positions: &[i32]| match knee(positions) {
+        Some(travel) => format!("{} counts at {}", travel, positions[travel]),
+        None => format!("not within {} counts", MAX_TRAVEL),
+    };
+    println!("{:<18}left {}; right {}", "saturation knee", lock(&left), lock(&right));
+
+    // either way round, a negative sensitivity turns it over
+    let rising = value(range as i64) >= value(-(range as i64));
+    let turn = (-(range as i64)..range as i64).find(|travel| {
+        let (from, to) = (value(*travel), value(travel + 1));
+        if rising { to < from } else { to > from }
+    });
+    if let Some(travel) = turn {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "the steering response turns back between {} and {} counts, from {} to {}",
+            travel,
+            travel + 1,
+            value(travel),
+            value(travel + 1)
+        )));
+    }
+    Ok(())
+}
+
+// the positions after 0 to MAX_TRAVEL counts in `direction`, the ones past
+// full lock stay there
+fn sweep(conf: &Config, mapping: &Mapping, direction: i32) -> Result<Vec<i32>, Mouse2JoyError> {
+    let mut now = Instant::now();
+    let mut steering = Steering::new(conf, mapping, now)?;
+    if let Some(gate) = conf.steer_while_held.as_deref() {
+        steering.handle_key(parse_key(gate)?, 1, now);
+    }
+    let mut positions = vec![steering.target()];
+    while positions.len() <= MAX_TRAVEL && steering.target().abs() < STEERING_MAX {
+        now += PACE;
+        steering.handle_rel_x(direction, now);
+        positions.push(steering.target());
+    }
+    positions.resize(MAX_TRAVEL + 1, steering.target());
+    Ok(positions)
+}
+
+// the response from -range to range counts, full lock at the top and bottom
+fn plot(range: i64, value: &dyn Fn(i64) -> i32) {
+    let (width, height) = (WIDTH as i64, HEIGHT as i64);
+    let mut grid = vec![vec![' '; WIDTH]; HEIGHT];
+    for (row, line) in grid.iter_mut().enumerate() {
+        line[WIDTH / 2] = if row == HEIGHT / 2 { '+' } else { '|' };
+    }
+    for (column, cell) in grid[HEIGHT / 2].iter_mut().enumerate() {
+        *cell = if column == WIDTH / 2 { '+' } else { '-' };
+    }
+    let max = i64::from(STEERING_MAX);
+    for column in 0..width {
+        let travel = ((2 * column - (width - 1)) * range) / (width - 1);
+        let row = ((max - i64::from(value(travel))) * (height - 1) + max) / (2 * max);
+        grid[row as usize][column as usize] = '*';
+    }
+    for (row, line) in grid.iter().enumerate() {
+        let label = match row {
+            0 => STEERING_MAX.to_string(),
+            _ if row == HEIGHT / 2 => String::from("0"),
+            _ if row == HEIGHT - 1 => (-STEERING_MAX).to_string(),
+            _ => String::new(),
+        };
+        println!("{:>6} {}", label, line.iter().collect::<String>());
+    }
+    let (left, right) = (format!("-{}", range), range.to_string());
+    let middle = WIDTH / 2 - left.len();
+    println!(
+        "{:>6} {}{:>middle$}{:>rest$}",
+        "",
+        left,
+        "0",
+        right,
+        middle = middle + 1,
+        rest = WIDTH - WIDTH / 2 - 1
+    );
+}
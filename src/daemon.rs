@@ -0,0 +1,209 @@
+use log::{info, warn};
+use nix::sys::signal::{kill, Signal};
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::{dup2, fork, getuid, setsid, ForkResult, Pid};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{logging, Mouse2JoyError};
+
+// how long --stop waits for the daemon to exit
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+// the process is the background one, it can't ask anything
+static DAEMON: AtomicBool = AtomicBool::new(false);
+// the wheels still being set up before the terminal is let go
+static SETUP: Mutex<Option<Setup>> = Mutex::new(None);
+// written once detached, removed on the way out
+static PIDFILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+struct Setup {
+    // the launching process, waiting to hear that the wheels are up
+    parent: UnixStream,
+    pending: Vec<usize>,
+    ready: bool,
+}
+
+// $XDG_RUNTIME_DIR, under sudo the runtime directory of the user who ran it
+pub fn runtime_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).unwrap_or_else(|| {
+        let uid = std::env::var("SUDO_UID").unwrap_or_else(|_| getuid().to_string());
+        PathBuf::from("/run/user").join(uid)
+    })
+}
+
+pub fn pidfile() -> PathBuf {
+    runtime_dir().join("mouse2joy.pid")
+}
+
+// the mouse2joy a pidfile names, if it still runs
+fn running(pidfile: &Path) -> Option<Pid> {
+    let pid = std::fs::read_to_string(pidfile).ok()?.trim().parse().ok().map(Pid::from_raw)?;
+    // the pid may have been reused since
+    let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    (kill(pid, None).is_ok() && comm.trim() == "mouse2joy").then_some(pid)
+}
+
+pub fn is_daemon() -> bool {
+    DAEMON.load(Ordering::Relaxed)
+}
+
+// Fork into the background. The launching process waits until the wheels are
+// set up, errors until then still reach its terminal, and exits with the
+// code of the daemon if that fails first.
+pub fn start() -> Result<(), Mouse2JoyError> {
+    let pidfile = pidfile();
+    if let Some(pid) = running(&pidfile) {
+        return Err(Mouse2JoyError::InvalidArguments(format!(
+            "mouse2joy already runs in the background (pid {}), stop it with --stop first",
+            pid
+        )));
+    }
+    let (mut waiting, parent) = UnixStream::pair()
+        .map_err(|e| Mouse2JoyError::InvalidArguments(format!("--daemon can't talk to its child:  {}", e)))?;
+    // SAFETY: no thread has been started yet
+    match unsafe { fork() } {
+        Ok(ForkResult::Parent { child }) => {
+            drop(parent);
+            let mut ready = [0];
+            if waiting.read(&mut ready).is_ok_and(|read| read == 1) {
+                info!("Running in the background as pid {}, stop it with mouse2joy --stop", child);
+                std::process::exit(0);
+            }
+            // it failed before it was up, and logged why
+            let code = match waitpid(child, None) {
+                Ok(WaitStatus::Exited(_, code)) => code,
+                _ => 101,
+            };
+            std::process::exit(code);
+        }
+        Ok(ForkResult::Child) => {
+            drop(waiting);
+            // no controlling terminal, closing it sends no SIGHUP
+            let _ = setsid();
+            DAEMON.store(true, Ordering::Relaxed);
+            *lock(&SETUP) = Some(Setup { parent, pending: vec![0], ready: false });
+            *lock(&PIDFILE) = Some(pidfile);
+            Ok(())
+        }
+        Err(e) => Err(Mouse2JoyError::InvalidArguments(format!("--daemon failed to fork:  {}", e))),
+    }
+}
+
+// the [[pipelines]] to wait for instead of the one wheel
+pub fn expect(wheels: usize) {
+    if let Some(setup) = lock(&SETUP).as_mut() {
+        setup.pending = (0..wheels).collect();
+    }
+}
+
+// A wheel is up. Once every one is up, or failed with at least one up, the
+// pidfile is written and the terminal let go.
+pub fn ready(wheel: usize) {
+    settle(wheel, true);
+}
+
+pub fn failed(wheel: usize) {
+    settle(wheel, false);
+}
+
+fn settle(wheel: usize, ready: bool) {
+    let mut setup = lock(&SETUP);
+    let Some(state) = setup.as_mut() else {
+        return;
+    };
+    state.pending.retain(|pending| *pending != wheel);
+    state.ready |= ready;
+    if !state.pending.is_empty() || !state.ready {
+        return;
+    }
+    let Some(Setup { mut parent, .. }) = setup.take() else {
+        return;
+    };
+    if let Some(pidfile) = lock(&PIDFILE).as_ref() {
+        if let Err(e) = std::fs::write(pidfile, format!("{}\n", std::process::id())) {
+            warn!("Failed to write the pidfile {}, --stop won't find mouse2joy:  {}", pidfile.display(), e);
+        }
+    }
+    // stderr goes away with the terminal
+    logging::detach();
+    match File::options().read(true).write(true).open("/dev/null") {
+        Ok(null) => {
+            for fd in 0..=2 {
+                let _ = dup2(null.as_raw_fd(), fd);
+            }
+        }
+        Err(e) => warn!("Failed to open /dev/null, keeping the terminal:  {}", e),
+    }
+    let _ = parent.write_all(&[1]);
+    info!("Running in the background");
+}
+
+// the pidfile goes with the daemon
+pub fn finish() {
+    if let Some(pidfile) = lock(&PIDFILE).take() {
+        if running(&pidfile).is_some_and(|pid| pid == Pid::this()) {
+            let _ = std::fs::remove_file(pidfile);
+        }
+    }
+}
+
+// The status line systemctl shows for the unit, sent to $NOTIFY_SOCKET. It
+// takes NotifyAccess=main (or Type=notify) in the unit, without the socket
+// this does nothing.
+pub fn status(status: &str) {
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return;
+    };
+    let message = format!("STATUS={}", status);
+    // a leading @ is the abstract namespace
+    let sent = match path.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name).and_then(|addr| socket.send_to_addr(message.as_bytes(), &addr)),
+        None => socket.send_to(message.as_bytes(), &path),
+    };
+    if let Err(e) = sent {
+        warn!("Failed to tell systemd the status:  {}", e);
+    }
+}
+
+// --stop: SIGTERM to the mouse2joy of the pidfile, waiting for it to exit
+pub fn stop() -> Result<(), Mouse2JoyError> {
+    let pidfile = pidfile();
+    let Some(pid) = running(&pidfile) else {
+        if pidfile.exists() {
+            let _ = std::fs::remove_file(&pidfile);
+        }
+        return Err(Mouse2JoyError::InvalidArguments(format!(
+            "no mouse2joy runs in the background, {} names none",
+            pidfile.display()
+        )));
+    };
+    kill(pid, Signal::SIGTERM)
+        .map_err(|e| Mouse2JoyError::InvalidArguments(format!("can't stop mouse2joy (pid {}):  {}", pid, e)))?;
+    let start = Instant::now();
+    // until it removed its pidfile
+    while running(&pidfile) == Some(pid) {
+        if start.elapsed() > STOP_TIMEOUT {
+            warn!("mouse2joy (pid {}) is still shutting down", pid);
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    info!("Stopped mouse2joy (pid {})", pid);
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
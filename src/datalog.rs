@@ -0,0 +1,150 @@
+use log::{info, warn};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::Mouse2JoyError;
+
+// header of the file, the columns never change order
+const HEADER: &str = "seconds,raw_delta,steering,throttle,brake,profile";
+// how often the writer flushes what it has buffered
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Row {
+    seconds: f64,
+    raw_delta: i64,
+    steering: i32,
+    throttle: f32,
+    brake: f32,
+    profile: Option<String>,
+}
+
+// A CSV file of the steering and pedal values for tuning in a spreadsheet,
+// one row per sample at `rate` rather than per event:
+//
+//   seconds,raw_delta,steering,throttle,brake,profile
+//
+// The seconds count from the start of the log on mouse2joy's clock, the raw
+// delta is the REL_X read since the previous row and the steering the value
+// sent after the curve. The profile is empty on the base settings. A thread
+// of its own writes the rows, the ones it can't keep up with are dropped.
+pub struct DataLog {
+    sender: Option<SyncSender<Row>>,
+    writer: Option<JoinHandle<()>>,
+    start: Instant,
+    interval: Duration,
+    due: Instant,
+    raw_delta: i64,
+    dropped: u64,
+}
+
+impl DataLog {
+    pub fn new(path: &Path, rate: u32, now: Instant) -> Result<Self, Mouse2JoyError> {
+        let failed = |e| Mouse2JoyError::FailedToWrite(path.display().to_string(), e);
+        let mut out = BufWriter::new(File::create(path).map_err(failed)?);
+        writeln!(out, "{}", HEADER).map_err(failed)?;
+        info!("Logging the steering and pedals to {} at {} Hz", path.display(), rate);
+
+        let (sender, receiver) = sync_channel(256);
+        let display = path.display().to_string();
+        let writer = std::thread::spawn(move || write_rows(receiver, out, display));
+        Ok(Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            start: now,
+            interval: Duration::from_secs(1) / rate.max(1),
+            due: now,
+            raw_delta: 0,
+            dropped: 0,
+        })
+    }
+
+    pub fn add_delta(&mut self, delta: i32) {
+        self.raw_delta += delta as i64;
+    }
+
+    // a row once the next sample is due
+    pub fn sample(&mut self, steering: i32, throttle: f32, brake: f32, profile: Option<&str>, now: Instant) {
+        if now < self.due {
+            return;
+        }
+        // on the grid of the rate, skipping the samples missed in a stall
+        self.due += self.interval;
+        if self.due <= now {
+            self.due = now + self.interval;
+        }
+        let row = Row {
+            seconds: now.saturating_duration_since(self.start).as_secs_f64(),
+            raw_delta: std::mem::take(&mut self.raw_delta),
+            steering,
+            throttle,
+            brake,
+            profile: profile.map(String::from),
+        };
+        // a full channel means the writer is behind
+        if let Some(sender) = &self.sender {
+            if sender.try_send(row).is_err() {
+                self.dropped += 1;
+            }
+        }
+    }
+
+    // when the next row is due
+    pub fn wakeup(&self, now: Instant) -> Duration {
+        self.due.saturating_duration_since(now)
+    }
+}
+
+// the rows queued are written and the file closed before exiting
+impl Drop for DataLog {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+        if self.dropped > 0 {
+            warn!("Dropped {} rows of the CSV log, the disk didn't keep up", self.dropped);
+        }
+    }
+}
+
+fn write_rows(receiver: Receiver<Row>, mut out: BufWriter<File>, path: String) {
+    let mut flushed = Instant::now();
+    loop {
+        match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(row) => {
+                let profile = row.profile.as_deref().map(csv_field).unwrap_or_default();
+                let written = writeln!(
+                    out,
+                    "{:.3},{},{},{:.3},{:.3},{}",
+                    row.seconds, row.raw_delta, row.steering, row.throttle, row.brake, profile
+                );
+                if let Err(e) = written {
+                    warn!("Failed to write {}, stopped the CSV log:  {}", path, e);
+                    return;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if flushed.elapsed() >= FLUSH_INTERVAL {
+            let _ = out.flush();
+            flushed = Instant::now();
+        }
+    }
+    if let Err(e) = out.flush() {
+        warn!("Failed to write {}:  {}", path, e);
+    }
+}
+
+// quoted when the name has a comma, quote or line break in it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        String::from(value)
+    }
+}
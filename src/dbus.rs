@@ -0,0 +1,185 @@
+use nix::libc;
+use std::env;
+use std::io::Write;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixStream};
+use std::time::Duration;
+
+pub mod wire;
+
+use wire::{Call, ERROR, METHOD_RETURN};
+
+// a reply taking longer means the service is stuck
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+// read at once
+const CHUNK: usize = 4096;
+
+// A connection to the session or the system bus, which only ever makes
+// method calls and waits for their replies.
+pub struct Bus {
+    stream: UnixStream,
+    // read and not taken yet, and the descriptors that came with it
+    buffer: Vec<u8>,
+    fds: Vec<OwnedFd>,
+    serial: u32,
+}
+
+// the message replied with and the descriptors sent along
+#[cfg_attr(not(feature = "inhibit"), allow(dead_code))]
+pub struct Reply {
+    pub message: Vec<u8>,
+    pub fds: Vec<OwnedFd>,
+}
+
+impl Bus {
+    // DBUS_SESSION_BUS_ADDRESS, or the usual socket of the user's session
+    pub fn session() -> Result<Self, String> {
+        let address = env::var("DBUS_SESSION_BUS_ADDRESS")
+            .unwrap_or_else(|_| format!("unix:path=/run/user/{}/bus", nix::unistd::getuid()));
+        let stream = connect(&address).ok_or("no session bus reachable")?;
+        Self::open(stream, false).map_err(|e| format!("the session bus refused the connection ({})", e))
+    }
+
+    // descriptors can be passed over it, logind replies with one
+    #[cfg_attr(not(feature = "inhibit"), allow(dead_code))]
+    pub fn system() -> Result<Self, String> {
+        let address = env::var("DBUS_SYSTEM_BUS_ADDRESS")
+            .unwrap_or_else(|_| String::from("unix:path=/run/dbus/system_bus_socket"));
+        let stream = connect(&address).ok_or("no system bus reachable")?;
+        Self::open(stream, true).map_err(|e| format!("the system bus refused the connection ({})", e))
+    }
+
+    fn open(stream: UnixStream, unix_fds: bool) -> Result<Self, String> {
+        stream.set_read_timeout(Some(REPLY_TIMEOUT)).map_err(|e| e.to_string())?;
+        let mut bus = Self { stream, buffer: Vec::new(), fds: Vec::new(), serial: 0 };
+        bus.authenticate(unix_fds)?;
+        bus.call(&Call {
+            destination: "org.freedesktop.DBus",
+            path: "/org/freedesktop/DBus",
+            interface: "org.freedesktop.DBus",
+            member: "Hello",
+            signature: "",
+            body: Vec::new(),
+        })?;
+        Ok(bus)
+    }
+
+    // the EXTERNAL mechanism, the server checks the uid of the socket
+    fn authenticate(&mut self, unix_fds: bool) -> Result<(), String> {
+        let uid = nix::unistd::getuid().to_string();
+        let hex: String = uid.bytes().map(|byte| format!("{:02x}", byte)).collect();
+        self.send(format!("\0AUTH EXTERNAL {}\r\n", hex).as_bytes())?;
+        let line = self.read_line()?;
+        if !line.starts_with("OK ") {
+            return Err(line);
+        }
+        if unix_fds {
+            self.send(b"NEGOTIATE_UNIX_FD\r\n")?;
+            let line = self.read_line()?;
+            if line != "AGREE_UNIX_FD" {
+                return Err(format!("no file descriptors passed ({})", line));
+            }
+        }
+        self.send(b"BEGIN\r\n")
+    }
+
+    // send a method call and wait for its reply, skipping the signals
+    // and anything else arriving meanwhile
+    pub fn call(&mut self, call: &Call) -> Result<Reply, String> {
+        self.serial += 1;
+        self.send(&wire::method_call(self.serial, call))?;
+        loop {
+            let fixed: [u8; 16] = self.read(16)?.try_into().unwrap_or([0; 16]);
+            let len = wire::message_len(&fixed).ok_or("the bus sent a broken message")?;
+            let mut message = fixed.to_vec();
+            message.extend(self.read(len - 16)?);
+            let header = wire::header(&message).ok_or("the bus sent a broken message")?;
+            let sent = (header.unix_fds as usize).min(self.fds.len());
+            let fds: Vec<OwnedFd> = self.fds.drain(..sent).collect();
+            if header.reply_serial != Some(self.serial) {
+                continue;
+            }
+            return match header.kind {
+                METHOD_RETURN => Ok(Reply { message, fds }),
+                ERROR => Err(header.error_name.unwrap_or_else(|| String::from("an error without a name"))),
+                _ => Err(String::from("an unexpected reply")),
+            };
+        }
+    }
+
+    fn send(&mut self, data: &[u8]) -> Result<(), String> {
+        self.stream.write_all(data).map_err(|e| e.to_string())
+    }
+
+    fn read(&mut self, len: usize) -> Result<Vec<u8>, String> {
+        while self.buffer.len() < len {
+            self.receive()?;
+        }
+        Ok(self.buffer.drain(..len).collect())
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        loop {
+            if let Some(end) = self.buffer.windows(2).position(|window| window == b"\r\n") {
+                let line: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                return Ok(String::from_utf8_lossy(&line[..end]).into_owned());
+            }
+            self.receive()?;
+        }
+    }
+
+    // Reads what arrived, keeping the descriptors sent along. A plain read
+    // would close them.
+    fn receive(&mut self) -> Result<(), String> {
+        let mut data = [0u8; CHUNK];
+        // room for a few descriptors, u64 for the alignment of the control
+        // messages
+        let mut control = [0u64; 8];
+        let mut iov = libc::iovec { iov_base: data.as_mut_ptr().cast(), iov_len: CHUNK };
+        // all zero is an empty one
+        let mut header: libc::msghdr = unsafe { std::mem::zeroed() };
+        header.msg_iov = &mut iov;
+        header.msg_iovlen = 1;
+        header.msg_control = control.as_mut_ptr().cast();
+        header.msg_controllen = std::mem::size_of_val(&control) as _;
+        // the buffers outlive the call
+        let read = unsafe { libc::recvmsg(self.stream.as_raw_fd(), &mut header, libc::MSG_CMSG_CLOEXEC) };
+        if read < 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        if read == 0 {
+            return Err(String::from("the bus closed the connection"));
+        }
+        // the kernel filled in the control messages
+        unsafe {
+            let mut message = libc::CMSG_FIRSTHDR(&header);
+            while !message.is_null() {
+                if (*message).cmsg_level == libc::SOL_SOCKET && (*message).cmsg_type == libc::SCM_RIGHTS {
+                    let count = ((*message).cmsg_len as usize - libc::CMSG_LEN(0) as usize) / size_of::<RawFd>();
+                    let fds = libc::CMSG_DATA(message).cast::<RawFd>();
+                    for i in 0..count {
+                        self.fds.push(OwnedFd::from_raw_fd(fds.add(i).read_unaligned()));
+                    }
+                }
+                message = libc::CMSG_NXTHDR(&header, message);
+            }
+        }
+        self.buffer.extend(&data[..read as usize]);
+        Ok(())
+    }
+}
+
+fn connect(address: &str) -> Option<UnixStream> {
+    address.split(';').find_map(|address| {
+        let params = address.strip_prefix("unix:")?;
+        params.split(',').find_map(|param| match param.split_once('=')? {
+            ("path", path) => UnixStream::connect(path).ok(),
+            ("abstract", name) => {
+                let address = SocketAddr::from_abstract_name(name.as_bytes()).ok()?;
+                UnixStream::connect_addr(&address).ok()
+            }
+            _ => None,
+        })
+    })
+}
@@ -0,0 +1,260 @@
+// The part of the D-Bus wire protocol notifications and idle inhibitors take:
+// marshalling method calls, little endian, and reading back the header and
+// a number replied with, in either byte order.
+
+pub const METHOD_RETURN: u8 = 2;
+pub const ERROR: u8 = 3;
+
+const METHOD_CALL: u8 = 1;
+// header field codes
+const PATH: u8 = 1;
+const INTERFACE: u8 = 2;
+const MEMBER: u8 = 3;
+const ERROR_NAME: u8 = 4;
+const REPLY_SERIAL: u8 = 5;
+const DESTINATION: u8 = 6;
+const SIGNATURE: u8 = 8;
+const UNIX_FDS: u8 = 9;
+
+pub struct Call<'a> {
+    pub destination: &'a str,
+    pub path: &'a str,
+    pub interface: &'a str,
+    pub member: &'a str,
+    // empty without a body
+    pub signature: &'a str,
+    pub body: Vec<u8>,
+}
+
+// Appends values at their alignment. Offsets count from the start of the
+// buffer, which works for bodies too since they start 8 byte aligned.
+#[derive(Default)]
+pub struct Writer {
+    pub buf: Vec<u8>,
+}
+
+impl Writer {
+    fn align(&mut self, alignment: usize) {
+        self.buf.resize(self.buf.len().next_multiple_of(alignment), 0);
+    }
+
+    pub fn byte(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend(value.to_le_bytes());
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.align(4);
+        self.buf.extend(value.to_le_bytes());
+    }
+
+    // also object paths
+    pub fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buf.extend(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    pub fn signature(&mut self, value: &str) {
+        self.buf.push(value.len() as u8);
+        self.buf.extend(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    // an array of elements aligned to `alignment`, the length counts from
+    // the first element, after the padding even for an empty array
+    pub fn array(&mut self, alignment: usize, elements: impl FnOnce(&mut Self)) {
+        self.u32(0);
+        let length_at = self.buf.len() - 4;
+        self.align(alignment);
+        let start = self.buf.len();
+        elements(self);
+        let length = (self.buf.len() - start) as u32;
+        self.buf[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+    }
+}
+
+pub fn method_call(serial: u32, call: &Call) -> Vec<u8> {
+    let mut message = Writer::default();
+    message.buf.extend([b'l', METHOD_CALL, 0, 1]);
+    message.u32(call.body.len() as u32);
+    message.u32(serial);
+    message.array(8, |fields| {
+        let string_fields = [
+            (PATH, "o", call.path),
+            (INTERFACE, "s", call.interface),
+            (MEMBER, "s", call.member),
+            (DESTINATION, "s", call.destination),
+        ];
+        for (code, signature, value) in string_fields {
+            fields.align(8);
+            fields.byte(code);
+            fields.signature(signature);
+            fields.string(value);
+        }
+        if !call.signature.is_empty() {
+            fields.align(8);
+            fields.byte(SIGNATURE);
+            fields.signature("g");
+            fields.signature(call.signature);
+        }
+    });
+    message.align(8);
+    message.buf.extend(&call.body);
+    message.buf
+}
+
+#[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+pub const NOTIFY_SIGNATURE: &str = "susssasa{sv}i";
+
+// the arguments of org.freedesktop.Notifications.Notify, urgency 0 to 2
+// for low, normal and critical
+#[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+pub fn notify_body(app: &str, icon: &str, summary: &str, body: &str, urgency: u8) -> Vec<u8> {
+    let mut args = Writer::default();
+    args.string(app);
+    // replaces_id, always a new notification
+    args.u32(0);
+    args.string(icon);
+    args.string(summary);
+    args.string(body);
+    // no actions
+    args.array(4, |_| {});
+    args.array(8, |hints| {
+        hints.align(8);
+        hints.string("urgency");
+        hints.signature("y");
+        hints.byte(urgency);
+    });
+    // the server's default expiry
+    args.i32(-1);
+    args.buf
+}
+
+// a body of strings only, e.g. the arguments of an Inhibit
+#[cfg_attr(not(feature = "inhibit"), allow(dead_code))]
+pub fn strings_body(values: &[&str]) -> Vec<u8> {
+    let mut args = Writer::default();
+    for value in values {
+        args.string(value);
+    }
+    args.buf
+}
+
+// what matters about an incoming message
+#[derive(Debug, PartialEq)]
+pub struct Header {
+    pub kind: u8,
+    pub reply_serial: Option<u32>,
+    pub error_name: Option<String>,
+    // file descriptors sent along
+    pub unix_fds: u32,
+}
+
+// the length of a whole message from its first 16 bytes
+pub fn message_len(fixed: &[u8; 16]) -> Option<usize> {
+    let reader = Reader::new(fixed)?;
+    let body = reader.u32_at(4)? as usize;
+    let fields = reader.u32_at(12)? as usize;
+    Some((16 + fields).next_multiple_of(8) + body)
+}
+
+pub fn header(message: &[u8]) -> Option<Header> {
+    let mut reader = Reader::new(message)?;
+    let fields_end = 16 + reader.u32_at(12)? as usize;
+    reader.pos = 16;
+    let mut header = Header { kind: *message.get(1)?, reply_serial: None, error_name: None, unix_fds: 0 };
+    while reader.pos < fields_end {
+        reader.align(8);
+        let code = reader.byte()?;
+        let signature = reader.signature()?;
+        match signature.as_str() {
+            "u" => {
+                let value = reader.u32()?;
+                match code {
+                    REPLY_SERIAL => header.reply_serial = Some(value),
+                    UNIX_FDS => header.unix_fds = value,
+                    _ => {}
+                }
+            }
+            "s" | "o" => {
+                let value = reader.string()?;
+                if code == ERROR_NAME {
+                    header.error_name = Some(value);
+                }
+            }
+            "g" => {
+                reader.signature()?;
+            }
+            // nothing else is defined for the header
+            _ => return None,
+        }
+    }
+    Some(header)
+}
+
+// The first value of the body, when it is a u32 or a file descriptor,
+// which goes as its index among the ones sent along.
+#[cfg_attr(not(feature = "inhibit"), allow(dead_code))]
+pub fn body_u32(message: &[u8]) -> Option<u32> {
+    let reader = Reader::new(message)?;
+    let body = (16 + reader.u32_at(12)? as usize).next_multiple_of(8);
+    reader.u32_at(body)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.first()? {
+            b'l' => false,
+            b'B' => true,
+            _ => return None,
+        };
+        Some(Self { data, pos: 0, big_endian })
+    }
+
+    fn align(&mut self, alignment: usize) {
+        self.pos = self.pos.next_multiple_of(alignment);
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let value = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(value)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.align(4);
+        let value = self.u32_at(self.pos)?;
+        self.pos += 4;
+        Some(value)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        let value = self.data.get(self.pos..self.pos + len)?;
+        self.pos += len + 1;
+        String::from_utf8(value.to_vec()).ok()
+    }
+
+    fn signature(&mut self) -> Option<String> {
+        let len = self.byte()? as usize;
+        let value = self.data.get(self.pos..self.pos + len)?;
+        self.pos += len + 1;
+        String::from_utf8(value.to_vec()).ok()
+    }
+}
@@ -0,0 +1,84 @@
+use evdev::{EventType, InputEvent, Key};
+use std::time::{Duration, Instant};
+
+// Debouncing: a worn switch chatters, releasing and pressing again within a
+// few milliseconds of the real press. The press of a debounced source goes
+// on at once, its release is withheld for the source's interval and a press
+// within it continues the hold, so neither goes on. Presses of a source
+// already down are dropped. Only releases are ever late.
+pub struct Debounce<R> {
+    intervals: Vec<(Key, Duration)>,
+    // the sources down as far as the mapping knows
+    down: Vec<Key>,
+    // withheld releases and when they go on, oldest first
+    pending: Vec<(Key, Instant, R)>,
+}
+
+impl<R: Copy> Debounce<R> {
+    // the sources with an interval of 0 are left alone
+    pub fn new(mut intervals: Vec<(Key, Duration)>) -> Option<Self> {
+        intervals.retain(|(_, interval)| !interval.is_zero());
+        if intervals.is_empty() {
+            return None;
+        }
+        Some(Self { intervals, down: Vec::new(), pending: Vec::new() })
+    }
+
+    // when the oldest withheld release has to go on
+    pub fn wakeup(&self, now: Instant) -> Option<Duration> {
+        self.pending.iter().map(|(_, due, _)| due.saturating_duration_since(now)).min()
+    }
+
+    // The events read with the chatter taken out: releases whose interval
+    // ran out come first, then everything else in order.
+    pub fn filter(&mut self, events: Vec<(R, InputEvent)>, now: Instant) -> Vec<(R, InputEvent)> {
+        let mut passed = Vec::new();
+        let down = &mut self.down;
+        self.pending.retain(|(key, due, role)| {
+            let expired = now >= *due;
+            if expired {
+                down.retain(|other| other != key);
+                passed.push((*role, key_event(*key, 0)));
+            }
+            !expired
+        });
+        for (role, ev) in events {
+            let key = Key::new(ev.code());
+            let interval = self.intervals.iter().find(|(source, _)| *source == key).map(|(_, interval)| *interval);
+            let Some(interval) = interval.filter(|_| ev.event_type() == EventType::KEY) else {
+                passed.push((role, ev));
+                continue;
+            };
+            let withheld = self.pending.iter().position(|(pending, _, _)| *pending == key);
+            let down = self.down.contains(&key);
+            match (ev.value(), withheld) {
+                // the hold goes on
+                (1, Some(position)) => {
+                    self.pending.remove(position);
+                }
+                (1, None) if !down => {
+                    self.down.push(key);
+                    passed.push((role, ev));
+                }
+                (0, None) if down => self.pending.push((key, now + interval, role)),
+                // from before it was seen pressed
+                (0, None) => passed.push((role, ev)),
+                (2, None) if down => passed.push((role, ev)),
+                // a second press or release, or a repeat of a withheld one
+                _ => {}
+            }
+        }
+        passed
+    }
+
+    // Paused or the failsafe tripped: the withheld releases are dropped with
+    // everything else held, the next press starts over.
+    pub fn reset(&mut self) {
+        self.down.clear();
+        self.pending.clear();
+    }
+}
+
+fn key_event(key: Key, value: i32) -> InputEvent {
+    InputEvent::new(EventType::KEY, key.code(), value)
+}
@@ -0,0 +1,289 @@
+use evdev::{uinput::VirtualDeviceBuilder, AbsInfo, AbsoluteAxisType, AttributeSet, Device, Key, UinputAbsSetup};
+use nix::unistd::{access, getegid, getgroups, AccessFlags, Gid, Group};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::classify::{self, Class};
+use crate::cli::Args;
+use crate::configuration::{Config, InputBackend};
+use crate::filter::DeviceFilter;
+use crate::seat::SeatInput;
+use crate::Mouse2JoyError;
+
+const UINPUT: &str = "/dev/uinput";
+// how long joydev may take to attach to the probe device
+const JS_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Status {
+    Ok,
+    // worth knowing, mouse2joy still works
+    Warn,
+    // keeps mouse2joy from working
+    Fail,
+    Skip,
+}
+
+// the printed lines, counting the blocking problems
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn line(&mut self, status: Status, check: &str, message: &str) {
+        let label = match status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+            Status::Skip => "skip",
+        };
+        if status == Status::Fail {
+            self.problems += 1;
+        }
+        println!("{:<5} {}: {}", label, check, message);
+    }
+
+    fn detail(&self, message: &str) {
+        println!("        {}", message);
+    }
+}
+
+// Checks the environment mouse2joy needs, one line per check, meant to be
+// pasted into bug reports. Only the checks marked FAIL make it exit non-zero.
+pub fn run(args: &Args, config_path: &Path) -> Result<(), Mouse2JoyError> {
+    let input_dir = args.input_dir.clone().unwrap_or_else(|| PathBuf::from(crate::INPUT_DIR));
+    let mut report = Report { problems: 0 };
+
+    // a config that fails to load is reported with the config check, until
+    // then its backend and filters are the defaults
+    let conf = Config::load(config_path).ok();
+    let backend = conf.as_ref().map(|conf| conf.input_backend).unwrap_or_default();
+    let uinput = check_uinput(&mut report, backend);
+    match backend {
+        InputBackend::Evdev | InputBackend::Hidraw => {
+            check_permissions(&mut report, &input_dir);
+            let filter = conf.as_ref().and_then(|conf| DeviceFilter::new(conf).ok()).unwrap_or_default();
+            check_mice(&mut report, &input_dir, &filter);
+        }
+        InputBackend::X11 => {
+            report.line(Status::Skip, "input", "input_backend = \"x11\" doesn't read the event devices");
+            check_seat(&mut report);
+        }
+    }
+    check_leftovers(&mut report, &input_dir);
+    match crate::start(args, config_path) {
+        Ok(()) if !config_path.exists() => {
+            report.line(Status::Ok, "config", &format!("no {}, the defaults are valid", config_path.display()))
+        }
+        Ok(()) => report.line(Status::Ok, "config", &format!("{} is valid", config_path.display())),
+        Err(e) => report.line(Status::Fail, "config", &e.to_string()),
+    }
+    if uinput {
+        check_probe(&mut report);
+    } else {
+        report.line(Status::Skip, "js", "needs uinput");
+        report.line(Status::Skip, "class", "needs uinput");
+    }
+
+    if report.problems > 0 {
+        return Err(Mouse2JoyError::DoctorFoundProblems(report.problems));
+    }
+    println!("No blocking problems found");
+    Ok(())
+}
+
+// returns whether virtual devices can be created, the wheel always needs
+// uinput whatever the input is read from
+fn check_uinput(report: &mut Report, backend: InputBackend) -> bool {
+    let path = Path::new(UINPUT);
+    let problem = if !path.exists() {
+        if Path::new("/sys/module/uinput").exists() {
+            format!("the module is loaded but {} is missing", UINPUT)
+        } else {
+            format!("{} is missing, load the module with `sudo modprobe uinput`", UINPUT)
+        }
+    } else if let Err(e) = access(path, AccessFlags::W_OK) {
+        format!("{} isn't writable ({}), {}", UINPUT, e, group_hint(path))
+    } else {
+        report.line(Status::Ok, "uinput", &format!("{} is writable", UINPUT));
+        return true;
+    };
+    // the seat is readable without the input group, the wheel still isn't
+    let suffix = match backend {
+        InputBackend::Evdev | InputBackend::Hidraw => "",
+        InputBackend::X11 => ", the virtual wheel needs it even with input_backend = \"x11\"",
+    };
+    report.line(Status::Fail, "uinput", &format!("{}{}", problem, suffix));
+    false
+}
+
+fn check_seat(report: &mut Report) {
+    match SeatInput::connect() {
+        Ok(seat) => report.line(Status::Ok, "x11", &format!("reading the seat through {}", seat.version())),
+        Err(e) => report.line(Status::Fail, "x11", &e),
+    }
+}
+
+fn check_permissions(report: &mut Report, input_dir: &Path) {
+    let nodes = event_nodes(input_dir);
+    let unreadable: Vec<&PathBuf> = nodes
+        .iter()
+        .filter(|node| access(node.as_path(), AccessFlags::R_OK).is_err())
+        .collect();
+    let readable = nodes.len() - unreadable.len();
+    match unreadable.first() {
+        _ if nodes.is_empty() => {
+            report.line(Status::Fail, "input", &format!("no event devices in {}", input_dir.display()))
+        }
+        None => report.line(Status::Ok, "input", &format!("all {} event devices are readable", nodes.len())),
+        Some(node) => report.line(
+            if readable == 0 { Status::Fail } else { Status::Warn },
+            "input",
+            &format!("{} of {} event devices are readable, {}", readable, nodes.len(), group_hint(node)),
+        ),
+    }
+}
+
+fn check_mice(report: &mut Report, input_dir: &Path, filter: &DeviceFilter) {
+    let (mut mice, excluded) = crate::scan_mice(input_dir);
+    let mut filtered = Vec::new();
+    mice.retain(|(path, mouse)| match filter.excluded_by(mouse) {
+        Some(rule) => {
+            let name = mouse.name().unwrap_or("Unknown Device");
+            filtered.push(format!("{} \"{}\" left out, {}", path.display(), name, rule));
+            false
+        }
+        None => true,
+    });
+    let names: Vec<String> = mice
+        .iter()
+        .map(|(_, mouse)| format!("\"{}\"", mouse.name().unwrap_or("Unknown Device")))
+        .collect();
+    match mice.len() {
+        0 => report.line(Status::Fail, "mice", &format!("none found in {}", input_dir.display())),
+        count => report.line(Status::Ok, "mice", &format!("{} found: {}", count, names.join(", "))),
+    }
+    // js nodes, the legacy mice node and the by-id links are no candidates
+    for (path, reason) in excluded.iter().filter(|(path, _)| is_event_node(path)) {
+        report.detail(&format!("{} excluded, {}", path.display(), reason));
+    }
+    for line in &filtered {
+        report.detail(line);
+    }
+}
+
+// a wheel from an earlier run that was never removed confuses games
+fn check_leftovers(report: &mut Report, input_dir: &Path) {
+    let id = crate::vjoystick_id();
+    let found: Vec<PathBuf> = event_nodes(input_dir)
+        .into_iter()
+        .filter(|node| {
+            Device::open(node).is_ok_and(|device| {
+                let other = device.input_id();
+                other.vendor() == id.vendor() && (id.product()..id.product() + 3).contains(&other.product())
+            })
+        })
+        .collect();
+    match found.first() {
+        None => report.line(Status::Ok, "leftover", "no mouse2joy device present"),
+        Some(node) => report.line(
+            Status::Warn,
+            "leftover",
+            &format!(
+                "a mouse2joy device exists at {}, another mouse2joy is running or one was left behind",
+                node.display()
+            ),
+        ),
+    }
+}
+
+// A short lived probe device with a button and ABS_X like the wheel. Joydev
+// attaching to it means the real wheel gets a js node too, and udev has to
+// take it for a joystick rather than some pointer.
+fn check_probe(report: &mut Report) {
+    let mut keys = AttributeSet::new();
+    keys.insert(Key::BTN_TRIGGER);
+    let probe = VirtualDeviceBuilder::new()
+        .and_then(|builder| builder.name("mouse2joy doctor").with_keys(&keys))
+        .and_then(|builder| {
+            builder.with_absolute_axis(&UinputAbsSetup::new(
+                AbsoluteAxisType::ABS_X,
+                AbsInfo::new(0, -1, 1, 0, 0, 0),
+            ))
+        })
+        .and_then(|builder| builder.build());
+    let mut probe = match probe {
+        Ok(probe) => probe,
+        Err(e) => return report.line(Status::Fail, "js", &format!("can't create a virtual device: {}", e)),
+    };
+    let node = probe.enumerate_dev_nodes_blocking().ok().and_then(|mut nodes| nodes.next()).and_then(Result::ok);
+    match node.and_then(|node| classify::udev_classified(&node, JS_TIMEOUT)).as_deref() {
+        None => report.line(Status::Skip, "class", "no udev database to tell how virtual devices are classified"),
+        Some([Class::Joystick]) => report.line(Status::Ok, "class", "udev classifies virtual devices as joysticks"),
+        Some(classes) if classes.iter().any(|class| class.is_pointer()) => {
+            let names: Vec<&str> = classes.iter().map(|class| class.property()).collect();
+            report.line(
+                Status::Fail,
+                "class",
+                &format!("udev takes virtual devices for {}, the cursor may follow the wheel", names.join(" ")),
+            )
+        }
+        Some(_) => report.line(
+            Status::Warn,
+            "class",
+            "udev doesn't mark virtual devices as joysticks (ID_INPUT_JOYSTICK), games may not see them",
+        ),
+    }
+    let deadline = Instant::now() + JS_TIMEOUT;
+    loop {
+        if let Some(node) = crate::find_js_node(&mut probe) {
+            return report.line(Status::Ok, "js", &format!("virtual devices get a js node, e.g. {}", node.display()));
+        }
+        if Instant::now() > deadline {
+            return report.line(
+                Status::Warn,
+                "js",
+                "no js node appeared, legacy games need the joydev module (`sudo modprobe joydev`)",
+            );
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn event_nodes(input_dir: &Path) -> Vec<PathBuf> {
+    let mut nodes: Vec<PathBuf> = fs::read_dir(input_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| is_event_node(path))
+        .collect();
+    nodes.sort();
+    nodes
+}
+
+fn is_event_node(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("event"))
+}
+
+// who may open the node and whether the user is among them
+fn group_hint(path: &Path) -> String {
+    let Ok(metadata) = fs::metadata(path) else {
+        return String::from("run as root");
+    };
+    let gid = Gid::from_raw(metadata.gid());
+    let group = Group::from_gid(gid)
+        .ok()
+        .flatten()
+        .map(|group| group.name)
+        .unwrap_or_else(|| gid.to_string());
+    let mut groups = getgroups().unwrap_or_default();
+    groups.push(getegid());
+    if groups.contains(&gid) {
+        format!("the user is in its group \"{}\" but the group may not use it", group)
+    } else {
+        format!("it belongs to group \"{}\", add the user with `sudo usermod -aG {} $USER` or run as root", group, group)
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+use crate::configuration::{FailsafeAction, FailsafeConfig};
+
+// Notices the input going away: a lost device or, with a timeout, no events
+// at all for that long. It trips once per outage and only fresh input arms it
+// again, so nothing is restored to what it was before the failure.
+pub struct Failsafe {
+    timeout: Option<Duration>,
+    center_steering: bool,
+    release_brake: bool,
+    last_input: Instant,
+    tripped: bool,
+}
+
+impl Failsafe {
+    pub fn new(conf: &FailsafeConfig, now: Instant) -> Self {
+        Self {
+            timeout: (conf.timeout_ms > 0).then(|| Duration::from_millis(conf.timeout_ms.into())),
+            center_steering: conf.steering == FailsafeAction::Neutral,
+            release_brake: conf.brake == FailsafeAction::Neutral,
+            last_input: now,
+            tripped: false,
+        }
+    }
+
+    pub fn center_steering(&self) -> bool {
+        self.center_steering
+    }
+
+    pub fn release_brake(&self) -> bool {
+        self.release_brake
+    }
+
+    pub fn input(&mut self, now: Instant) {
+        self.last_input = now;
+        self.tripped = false;
+    }
+
+    // time left until the timeout, None without one or once tripped
+    pub fn wakeup(&self, now: Instant) -> Option<Duration> {
+        if self.tripped {
+            return None;
+        }
+        self.timeout
+            .map(|timeout| (self.last_input + timeout).saturating_duration_since(now))
+    }
+
+    // why the outputs have to go neutral now, if they do
+    pub fn check(&mut self, lost: bool, now: Instant) -> Option<String> {
+        if self.tripped {
+            return None;
+        }
+        let reason = if lost {
+            String::from("Lost an input device")
+        } else {
+            let timeout = self.timeout?;
+            if now.saturating_duration_since(self.last_input) < timeout {
+                return None;
+            }
+            format!("No input for {}ms", timeout.as_millis())
+        };
+        self.tripped = true;
+        Some(reason)
+    }
+}
@@ -0,0 +1,57 @@
+use evdev::Device;
+
+use crate::configuration::Config;
+use crate::Mouse2JoyError;
+
+// Which scanned mice are candidates, from ignore_devices and only_devices.
+// A pattern with * or ? is a glob on the whole name or phys path, anything
+// else matches a part of either.
+#[derive(Default)]
+pub struct DeviceFilter {
+    ignore: Vec<String>,
+    only: Vec<String>,
+}
+
+impl DeviceFilter {
+    pub fn new(conf: &Config) -> Result<Self, Mouse2JoyError> {
+        for (setting, patterns) in [("ignore_devices", &conf.ignore_devices), ("only_devices", &conf.only_devices)] {
+            if patterns.iter().any(|pattern| pattern.is_empty()) {
+                return Err(Mouse2JoyError::InvalidConfig(format!("{} holds an empty pattern", setting)));
+            }
+        }
+        Ok(Self {
+            ignore: conf.ignore_devices.clone(),
+            only: conf.only_devices.clone(),
+        })
+    }
+
+    // the rule that leaves the mouse out, None for a candidate
+    pub fn excluded_by(&self, device: &Device) -> Option<String> {
+        let fields = [device.name(), device.physical_path()];
+        let matches = |pattern: &str| fields.iter().flatten().any(|field| pattern_matches(pattern, field));
+        if let Some(pattern) = self.ignore.iter().find(|pattern| matches(pattern)) {
+            return Some(format!("ignored by ignore_devices \"{}\"", pattern));
+        }
+        if !self.only.is_empty() && !self.only.iter().any(|pattern| matches(pattern)) {
+            return Some(String::from("not matched by only_devices"));
+        }
+        None
+    }
+}
+
+fn pattern_matches(pattern: &str, text: &str) -> bool {
+    if !pattern.contains(['*', '?']) {
+        return text.contains(pattern);
+    }
+    let (pattern, text): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    glob(&pattern, &text)
+}
+
+fn glob(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && glob(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && glob(rest, &text[1..]),
+    }
+}
@@ -0,0 +1,111 @@
+use evdev::{EventType, InputEvent, Key};
+use log::{debug, info};
+use std::time::{Duration, Instant};
+
+use crate::configuration::FlickConfig;
+use crate::mapping::parse_button;
+use crate::Mouse2JoyError;
+
+mod detector;
+use detector::{Detector, Direction, Thresholds};
+
+// Flick gestures: a short sharp sideways flick of the steering mouse taps the
+// button of its direction, e.g. a glance left or right in a cockpit game.
+// The flick steers like any movement while it is made, there is no telling
+// it apart before it stopped. With rollback the wheel then goes back to
+// where it was when the flick started.
+pub struct Flick {
+    detector: Detector,
+    left: Option<Key>,
+    right: Option<Key>,
+    rollback: bool,
+    hold: Duration,
+    // the mouse part of the steering when the stroke started
+    before: i32,
+    // the button tapped and when it goes up
+    pressed: Option<(Key, Instant)>,
+}
+
+impl Flick {
+    pub fn new(conf: &FlickConfig, buttons: &[Key]) -> Result<Option<Self>, Mouse2JoyError> {
+        if !conf.enabled {
+            return Ok(None);
+        }
+        let button = |name: &str| (!name.is_empty()).then(|| parse_button(name, buttons)).transpose();
+        let (left, right) = (button(&conf.left)?, button(&conf.right)?);
+        if left.is_none() && right.is_none() {
+            return Err(Mouse2JoyError::InvalidConfig(String::from("[flick] has neither a left nor a right button")));
+        }
+        for (value, name) in [(conf.max_ms, "max_ms"), (conf.stop_ms, "stop_ms"), (conf.hold_ms, "hold_ms")] {
+            if value == 0 {
+                return Err(Mouse2JoyError::InvalidConfig(format!("[flick] {} must be above 0", name)));
+            }
+        }
+        let thresholds = Thresholds {
+            speed: conf.speed as f32,
+            distance: conf.distance,
+            max: Duration::from_millis(conf.max_ms.into()),
+            stop: Duration::from_millis(conf.stop_ms.into()),
+        };
+        Ok(Some(Self {
+            detector: Detector::new(thresholds),
+            left,
+            right,
+            rollback: conf.rollback,
+            hold: Duration::from_millis(conf.hold_ms.into()),
+            before: 0,
+            pressed: None,
+        }))
+    }
+
+    // a REL_X delta, before `mouse` (the mouse part of the steering) takes
+    // it in
+    pub fn handle_rel_x(&mut self, delta: i32, mouse: i32, now: Instant) {
+        if self.detector.push(delta, now) {
+            self.before = mouse;
+        }
+    }
+
+    // The button events due and, for a flick to roll back, where the mouse
+    // part of the steering goes back to.
+    pub fn tick(&mut self, now: Instant) -> (Vec<InputEvent>, Option<i32>) {
+        let mut events = Vec::new();
+        if let Some((button, _)) = self.pressed.filter(|(_, until)| *until <= now) {
+            events.push(InputEvent::new(EventType::KEY, button.code(), 0));
+            self.pressed = None;
+        }
+        let Some(direction) = self.detector.poll(now) else {
+            return (events, None);
+        };
+        let (button, side) = match direction {
+            Direction::Left => (self.left, "left"),
+            Direction::Right => (self.right, "right"),
+        };
+        let Some(button) = button else {
+            debug!("Flick to the {} without a button, steering as is", side);
+            return (events, None);
+        };
+        info!("Flick to the {}, tapping {:?}", side, button);
+        // a flick during the last tap ends it
+        if let Some((pressed, _)) = self.pressed.take() {
+            events.push(InputEvent::new(EventType::KEY, pressed.code(), 0));
+        }
+        events.push(InputEvent::new(EventType::KEY, button.code(), 1));
+        self.pressed = Some((button, now + self.hold));
+        (events, self.rollback.then_some(self.before))
+    }
+
+    // when the stroke will have ended or the tapped button goes up
+    pub fn wakeup(&self, now: Instant) -> Option<Duration> {
+        let release = self.pressed.map(|(_, until)| until.saturating_duration_since(now));
+        [self.detector.wakeup(now), release].into_iter().flatten().min()
+    }
+
+    // paused or the failsafe tripped, the stroke is forgotten and the button
+    // goes up
+    pub fn release(&mut self) -> Option<InputEvent> {
+        self.detector.reset();
+        let (button, _) = self.pressed.take()?;
+        Some(InputEvent::new(EventType::KEY, button.code(), 0))
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::{Duration, Instant};
+
+// deltas up to this still count as the mouse standing still, sensors jitter
+const STILL: u32 = 2;
+// how much of the movement of a flick has to go its way
+const STRAIGHTNESS: f32 = 0.9;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+// what a stroke needs to be taken for a flick
+pub struct Thresholds {
+    // counts per second, averaged over the stroke
+    pub speed: f32,
+    pub distance: u32,
+    pub max: Duration,
+    pub stop: Duration,
+}
+
+// Movement of the mouse from standing still to standing still again, the
+// stop being `stop` without a delta above STILL.
+struct Stroke {
+    start: Instant,
+    // of the last delta above STILL
+    last: Instant,
+    sum: i64,
+    travel: u64,
+}
+
+// Tells flicks from steering. A stroke is a flick once it ended: fast, far
+// enough, over quickly, nearly all one way and followed by a stop. Steering
+// corrections are slower, longer or turn around without stopping.
+pub struct Detector {
+    thresholds: Thresholds,
+    stroke: Option<Stroke>,
+}
+
+impl Detector {
+    pub fn new(thresholds: Thresholds) -> Self {
+        Self { thresholds, stroke: None }
+    }
+
+    // true when the delta starts a stroke
+    pub fn push(&mut self, delta: i32, now: Instant) -> bool {
+        let moving = delta.unsigned_abs() > STILL;
+        match self.stroke.as_mut() {
+            Some(stroke) if now.saturating_duration_since(stroke.last) < self.thresholds.stop => {
+                if moving {
+                    stroke.last = now;
+                }
+                stroke.sum += i64::from(delta);
+                stroke.travel += u64::from(delta.unsigned_abs());
+                false
+            }
+            // a stroke left unchecked by poll is over anyway
+            _ if moving => {
+                let travel = u64::from(delta.unsigned_abs());
+                self.stroke = Some(Stroke { start: now, last: now, sum: i64::from(delta), travel });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // once the stroke stopped, whether it was a flick
+    pub fn poll(&mut self, now: Instant) -> Option<Direction> {
+        let stroke = self.stroke.as_ref()?;
+        if now.saturating_duration_since(stroke.last) < self.thresholds.stop {
+            return None;
+        }
+        let stroke = self.stroke.take()?;
+        let duration = stroke.last - stroke.start;
+        // a single report still took a millisecond or so
+        let speed = stroke.sum.unsigned_abs() as f32 / duration.max(Duration::from_millis(1)).as_secs_f32();
+        let flick = duration <= self.thresholds.max
+            && stroke.sum.unsigned_abs() >= u64::from(self.thresholds.distance)
+            && stroke.sum.unsigned_abs() as f32 >= STRAIGHTNESS * stroke.travel as f32
+            && speed >= self.thresholds.speed;
+        match flick {
+            true if stroke.sum < 0 => Some(Direction::Left),
+            true => Some(Direction::Right),
+            false => None,
+        }
+    }
+
+    // when the stroke being made will have stopped
+    pub fn wakeup(&self, now: Instant) -> Option<Duration> {
+        let stroke = self.stroke.as_ref()?;
+        Some((stroke.last + self.thresholds.stop).saturating_duration_since(now))
+    }
+
+    // paused, the stroke is forgotten
+    pub fn reset(&mut self) {
+        self.stroke = None;
+    }
+}
@@ -0,0 +1,305 @@
+use evdev::{AbsoluteAxisType, EventType, InputEvent};
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::os::fd::AsRawFd;
+use std::time::{Duration, Instant};
+
+use crate::configuration::InputRole;
+use crate::input::Inputs;
+use crate::output::{Output, VirtualDevices};
+use crate::pedals::PEDAL_MAX;
+use crate::rotation::Rotation;
+use crate::trace::Tracer;
+use crate::Mouse2JoyError;
+
+// UI_DEV_DESTROY, evdev only closes the uinput fd on drop
+nix::ioctl_none!(ui_dev_destroy, b'U', 2);
+
+// consecutive failed writes before the virtual device gets rebuilt
+const EMIT_FAILURES: u32 = 5;
+// rebuilds without a successful write in between before giving up
+const MAX_REBUILDS: u32 = 5;
+
+// builds the virtual devices again with the same capabilities
+pub type CreateDevice = Box<dyn Fn() -> std::io::Result<VirtualDevices>>;
+
+// Owns the input devices and the output for the whole run. Dropping it, also
+// while unwinding from a panic, centers the virtual wheel, releases the input
+// devices and destroys the uinput node, so a crash never leaves the mouse or
+// a stuck wheel behind.
+pub struct Guard {
+    inputs: Inputs,
+    output: Option<Output>,
+    create: Option<CreateDevice>,
+    // last value sent on each axis, the ones not at rest get centered. Of
+    // an inverted axis the value before the inversion.
+    axes: HashMap<u16, i32>,
+    now: Instant,
+    failures: u32,
+    rebuilds: u32,
+    next_rebuild: Option<Instant>,
+    // the virtual device was replaced since the last take_recreated
+    recreated: bool,
+    // --trace-input, sees everything read and sent
+    tracer: Option<Tracer>,
+    // mirror_axis, gets a copy of every ABS_X value
+    mirror: Option<u16>,
+    // the pedal axes sent as PEDAL_MAX minus their value
+    inverted: Vec<u16>,
+    // [rotation], turns the steering mouse's movement after the tracer saw it
+    rotation: Option<Rotation>,
+}
+
+impl Guard {
+    // take over the inputs right after opening them, before anything else
+    // can go wrong
+    pub fn new(inputs: Inputs) -> Self {
+        let now = inputs.now();
+        Self {
+            inputs,
+            output: None,
+            create: None,
+            axes: HashMap::new(),
+            now,
+            failures: 0,
+            rebuilds: 0,
+            next_rebuild: None,
+            recreated: false,
+            tracer: None,
+            mirror: None,
+            inverted: Vec::new(),
+            rotation: None,
+        }
+    }
+
+    // `create` is used to rebuild a virtual device that stopped working
+    pub fn set_output(&mut self, output: Output, create: Option<CreateDevice>) {
+        self.output = Some(output);
+        self.create = create;
+    }
+
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = Some(rotation);
+    }
+
+    pub fn set_mirror(&mut self, code: u16) {
+        self.mirror = Some(code);
+    }
+
+    // Everything before the output works with 0 at rest, the inversion is
+    // the last thing done to the events. Returns the events putting the
+    // inverted axes at rest, for right after creating the virtual device.
+    pub fn set_inverted(&mut self, codes: Vec<u16>) -> Vec<InputEvent> {
+        self.inverted = codes;
+        self.inverted.iter().map(|code| InputEvent::new(EventType::ABSOLUTE, *code, 0)).collect()
+    }
+
+    pub fn now(&self) -> Instant {
+        self.inputs.now()
+    }
+
+    // the last value sent on an axis
+    pub fn axis(&self, code: u16) -> i32 {
+        self.axes.get(&code).copied().unwrap_or(0)
+    }
+
+    pub fn fetch(&mut self, timeout: Option<Duration>) -> Option<Vec<(InputRole, InputEvent)>> {
+        let events = self.inputs.fetch(timeout)?;
+        if let Some(tracer) = self.tracer.as_mut() {
+            let now = self.inputs.now();
+            for (role, event) in &events {
+                tracer.input(*role, event, now);
+            }
+        }
+        Some(match self.rotation.as_mut() {
+            Some(rotation) => rotation.apply(events),
+            None => events,
+        })
+    }
+
+    // whether an input device was unplugged since the last call
+    pub fn take_lost(&mut self) -> bool {
+        self.inputs.take_lost()
+    }
+
+    // hand the input devices to the desktop while paused, and take them back
+    pub fn release_inputs(&mut self) {
+        self.inputs.release();
+    }
+
+    pub fn grab_inputs(&mut self) {
+        self.inputs.grab();
+    }
+
+    pub fn describe_inputs(&self) -> String {
+        self.inputs.describe()
+    }
+
+    // A failing write is only logged. When writes keep failing the virtual
+    // device is rebuilt, with a growing backoff, and only once the rebuilds
+    // don't help either this gives up with an error.
+    pub fn emit(&mut self, events: &[InputEvent], now: Instant) -> Result<(), Mouse2JoyError> {
+        self.now = now;
+        let mirrored;
+        let events = match self.mirror {
+            Some(mirror) if events.iter().any(is_steering) => {
+                mirrored = mirror_steering(events, mirror);
+                &mirrored[..]
+            }
+            _ => events,
+        };
+        for ev in events.iter().filter(|ev| ev.event_type() == EventType::ABSOLUTE) {
+            self.axes.insert(ev.code(), ev.value());
+        }
+        let inverted;
+        let events = if self.inverted.is_empty() {
+            events
+        } else {
+            inverted = invert(events, &self.inverted);
+            &inverted[..]
+        };
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.output(events, now);
+        }
+        let Some(output) = self.output.as_mut() else {
+            return Ok(());
+        };
+        let e = match output.emit(events, now) {
+            Ok(()) => {
+                self.failures = 0;
+                self.rebuilds = 0;
+                return Ok(());
+            }
+            Err(e) => e,
+        };
+        warn!("Failed to emit steering wheel event: {}", e);
+        self.failures += 1;
+        if self.failures < EMIT_FAILURES || self.next_rebuild.is_some_and(|next| now < next) {
+            return Ok(());
+        }
+
+        if self.rebuilds == MAX_REBUILDS {
+            let e = Mouse2JoyError::FailedToRecreateDevice(e);
+            error!("{}", e);
+            return Err(e);
+        }
+        self.rebuilds += 1;
+        self.next_rebuild = Some(now + Duration::from_secs(1 << (self.rebuilds - 1)));
+        if self.recreate() {
+            info!("Recreated the virtual steering wheel after {} failed writes", self.failures);
+            self.failures = 0;
+        }
+        Ok(())
+    }
+
+    // once after every recreation of the virtual device
+    pub fn take_recreated(&mut self) -> bool {
+        std::mem::take(&mut self.recreated)
+    }
+
+    // After a resume: open the inputs again and recreate the virtual device
+    // if it is gone. Returns how many inputs were reopened and whether the
+    // device was recreated.
+    pub fn reestablish(&mut self) -> (usize, bool) {
+        let reopened = self.inputs.reopen();
+        let alive = match &mut self.output {
+            Some(Output::Device(devices)) => devices.is_alive(),
+            _ => true,
+        };
+        (reopened, !alive && self.recreate())
+    }
+
+    // replace the virtual device and restore the last axis values on it
+    fn recreate(&mut self) -> bool {
+        let (Some(Output::Device(device)), Some(create)) = (self.output.as_mut(), self.create.as_ref()) else {
+            return false;
+        };
+        match create() {
+            Ok(new_device) => {
+                *device = new_device;
+                self.recreated = true;
+                let mut state: Vec<InputEvent> = self
+                    .axes
+                    .iter()
+                    .map(|(code, value)| InputEvent::new(EventType::ABSOLUTE, *code, *value))
+                    .collect();
+                state.sort_by_key(InputEvent::code);
+                if let Err(e) = device.emit(&invert(&state, &self.inverted)) {
+                    warn!("Failed to restore the virtual steering wheel: {}", e);
+                }
+                true
+            }
+            Err(e) => {
+                warn!("Failed to recreate the virtual steering wheel: {}", e);
+                false
+            }
+        }
+    }
+}
+
+fn is_steering(ev: &InputEvent) -> bool {
+    ev.event_type() == EventType::ABSOLUTE && ev.code() == AbsoluteAxisType::ABS_X.0
+}
+
+// every steering value followed by the same on the mirror axis, so both
+// change in the same frame
+fn mirror_steering(events: &[InputEvent], mirror: u16) -> Vec<InputEvent> {
+    let mut mirrored = Vec::with_capacity(events.len() + 1);
+    for ev in events {
+        mirrored.push(*ev);
+        if is_steering(ev) {
+            mirrored.push(InputEvent::new(EventType::ABSOLUTE, mirror, ev.value()));
+        }
+    }
+    mirrored
+}
+
+fn invert(events: &[InputEvent], inverted: &[u16]) -> Vec<InputEvent> {
+    events
+        .iter()
+        .map(|ev| {
+            if ev.event_type() == EventType::ABSOLUTE && inverted.contains(&ev.code()) {
+                InputEvent::new(EventType::ABSOLUTE, ev.code(), PEDAL_MAX - ev.value())
+            } else {
+                *ev
+            }
+        })
+        .collect()
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if let Some(mut output) = self.output.take() {
+            // steering centered, pedals released and the hat at rest are all
+            // 0, before inverting
+            let mut center: Vec<InputEvent> = self
+                .axes
+                .iter()
+                .filter(|(_, value)| **value != 0)
+                .map(|(code, _)| InputEvent::new(EventType::ABSOLUTE, *code, 0))
+                .collect();
+            center.sort_by_key(InputEvent::code);
+            let center = invert(&center, &self.inverted);
+            if let Some(tracer) = self.tracer.as_mut() {
+                tracer.output(&center, self.now);
+            }
+            if let Err(e) = output.emit(&center, self.now) {
+                warn!("Failed to center the virtual steering wheel: {}", e);
+            }
+            if let Output::Device(devices) = &mut output {
+                for (device, _) in devices.parts_mut() {
+                    // SAFETY: the fd belongs to the device, which is still open
+                    if let Err(e) = unsafe { ui_dev_destroy(device.as_raw_fd()) } {
+                        warn!("Failed to destroy the virtual steering wheel: {}", e);
+                    }
+                }
+            }
+        }
+        self.inputs.release();
+        info!("Released the input devices and the virtual steering wheel");
+    }
+}
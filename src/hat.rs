@@ -0,0 +1,80 @@
+use evdev::{AbsInfo, AbsoluteAxisType, EventType, InputEvent, Key, UinputAbsSetup};
+
+use crate::configuration::HatConfig;
+use crate::mapping::parse_key;
+use crate::Mouse2JoyError;
+
+// D-pad emitted on ABS_HAT0X/ABS_HAT0Y, driven by four keys or buttons.
+// Opposing directions held together cancel out to 0.
+pub struct Hat {
+    // up, down, left, right
+    keys: [Key; 4],
+    pressed: [bool; 4],
+    enabled: bool,
+}
+
+impl Hat {
+    pub fn new(conf: &HatConfig) -> Result<Self, Mouse2JoyError> {
+        Ok(Self {
+            keys: [
+                parse_key(&conf.up)?,
+                parse_key(&conf.down)?,
+                parse_key(&conf.left)?,
+                parse_key(&conf.right)?,
+            ],
+            pressed: [false; 4],
+            enabled: conf.enabled,
+        })
+    }
+
+    pub fn keys(&self) -> Vec<Key> {
+        if self.enabled {
+            self.keys.to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+
+    // axes to advertise on the virtual device
+    pub fn axes(&self) -> Vec<UinputAbsSetup> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let abs_info = AbsInfo::new(0, -1, 1, 0, 0, 0);
+        vec![
+            UinputAbsSetup::new(AbsoluteAxisType::ABS_HAT0X, abs_info),
+            UinputAbsSetup::new(AbsoluteAxisType::ABS_HAT0Y, abs_info),
+        ]
+    }
+
+    // Events for a key driving the hat, None if the key isn't a hat source.
+    // Both axes are re-emitted on every change, even when their value stays
+    // the same, so a dropped event can't leave the hat stuck.
+    pub fn handle_key(&mut self, key: Key, value: i32) -> Option<Vec<InputEvent>> {
+        if !self.enabled {
+            return None;
+        }
+        let direction = self.keys.iter().position(|k| *k == key)?;
+        if value == 2 {
+            return Some(Vec::new());
+        }
+        self.pressed[direction] = value != 0;
+
+        let [up, down, left, right] = self.pressed.map(i32::from);
+        Some(vec![
+            InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_HAT0X.0, right - left),
+            InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_HAT0Y.0, down - up),
+        ])
+    }
+    // paused or the failsafe tripped, back to center if a direction was held
+    pub fn reset(&mut self) -> Vec<InputEvent> {
+        if !self.pressed.contains(&true) {
+            return Vec::new();
+        }
+        self.pressed = [false; 4];
+        vec![
+            InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_HAT0X.0, 0),
+            InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_HAT0Y.0, 0),
+        ]
+    }
+}
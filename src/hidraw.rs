@@ -0,0 +1,69 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+mod descriptor;
+use descriptor::Field;
+
+// one read returns one report, mice send far fewer bytes
+const REPORT_BUFFER: usize = 256;
+
+// The raw HID reports of a mouse, read from its /dev/hidraw node for the X
+// displacement alone. Buttons and everything else still come from its event
+// node.
+pub struct Hidraw {
+    pub path: PathBuf,
+    file: File,
+    field: Field,
+}
+
+impl Hidraw {
+    // the hidraw node of the HID device behind an event node, Err tells why
+    // there is none that can be used
+    pub fn open(event: &Path) -> Result<Self, String> {
+        let event = fs::canonicalize(event).map_err(|e| e.to_string())?;
+        let name = event.file_name().ok_or("not an event node")?;
+        let hid = Path::new("/sys/class/input").join(name).join("device/device");
+        let node = fs::read_dir(hid.join("hidraw"))
+            .map_err(|_| String::from("it isn't a HID device"))?
+            .filter_map(Result::ok)
+            .map(|entry| Path::new("/dev").join(entry.file_name()))
+            .next()
+            .ok_or("it has no hidraw node")?;
+        let descriptor = fs::read(hid.join("report_descriptor")).map_err(|e| e.to_string())?;
+        let field = descriptor::x_field(&descriptor)?;
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(nix::libc::O_NONBLOCK)
+            .open(&node)
+            .map_err(|e| format!("can't open {} ({})", node.display(), e))?;
+        Ok(Self { path: node, file, field })
+    }
+
+    // the X displacement of every report queued, Err once the node broke
+    pub fn read(&mut self) -> io::Result<Vec<i32>> {
+        let mut deltas = Vec::new();
+        let mut report = [0; REPORT_BUFFER];
+        loop {
+            match self.file.read(&mut report) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => deltas.extend(self.field.value(&report[..len]).filter(|delta| *delta != 0)),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(deltas),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // reports carry no timestamps, so everything queued goes
+    pub fn drain(&mut self) -> usize {
+        self.read().map_or(0, |deltas| deltas.len())
+    }
+}
+
+impl AsFd for Hidraw {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
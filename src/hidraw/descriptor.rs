@@ -0,0 +1,172 @@
+// Just enough of a HID report descriptor parser to find a mouse's X
+// displacement: the first relative Generic Desktop X input inside a Mouse
+// or Pointer application collection. Everything else in the descriptor only
+// matters for the bit offsets it takes up.
+
+const USAGE_MOUSE: u32 = 0x0001_0002;
+const USAGE_POINTER: u32 = 0x0001_0001;
+const USAGE_X: u32 = 0x0001_0030;
+
+// where X sits in an input report
+#[derive(Debug, PartialEq)]
+pub struct Field {
+    // the first byte of every report when the descriptor uses report IDs
+    pub report_id: Option<u8>,
+    // bits from the start of the report data, after the ID
+    pub offset: usize,
+    pub size: usize,
+    pub signed: bool,
+}
+
+impl Field {
+    // the X displacement a report carries, None for the reports of other
+    // IDs and ones too short to hold it
+    pub fn value(&self, report: &[u8]) -> Option<i32> {
+        let data = match self.report_id {
+            Some(id) => report.strip_prefix(&[id])?,
+            None => report,
+        };
+        if (self.offset + self.size).div_ceil(8) > data.len() {
+            return None;
+        }
+        let mut value: u32 = 0;
+        for bit in 0..self.size {
+            let at = self.offset + bit;
+            value |= u32::from(data[at / 8] >> (at % 8) & 1) << bit;
+        }
+        if self.signed && self.size < 32 && value & (1 << (self.size - 1)) != 0 {
+            value |= u32::MAX << self.size;
+        }
+        Some(value as i32)
+    }
+}
+
+// the global items, kept across main items and saved by Push
+#[derive(Clone, Copy, Default)]
+struct Globals {
+    usage_page: u32,
+    logical_min: i32,
+    report_size: usize,
+    report_count: usize,
+    report_id: Option<u8>,
+}
+
+pub fn x_field(descriptor: &[u8]) -> Result<Field, String> {
+    let mut globals = Globals::default();
+    let mut stack = Vec::new();
+    // usages are resolved against the usage page current when they appear
+    let mut usages = Vec::new();
+    let mut usage_range: (Option<u32>, Option<u32>) = (None, None);
+    // the application flag and usage of each open collection
+    let mut collections: Vec<(bool, u32)> = Vec::new();
+    // bits taken so far in the input report of each ID
+    let mut offsets: Vec<(Option<u8>, usize)> = Vec::new();
+
+    let mut rest = descriptor;
+    while let Some((&prefix, after)) = rest.split_first() {
+        // long items carry their size in the next byte, none of them matter
+        if prefix == 0xfe {
+            let size = *after.first().ok_or("the descriptor is truncated")? as usize;
+            rest = after.get(2 + size..).ok_or("the descriptor is truncated")?;
+            continue;
+        }
+        let size = match prefix & 0x03 {
+            3 => 4,
+            size => size as usize,
+        };
+        let data = after.get(..size).ok_or("the descriptor is truncated")?;
+        rest = &after[size..];
+        let unsigned = data.iter().rev().fold(0u32, |value, byte| value << 8 | u32::from(*byte));
+        let signed = match size {
+            1 => i32::from(data[0] as i8),
+            2 => i32::from(i16::from_le_bytes([data[0], data[1]])),
+            _ => unsigned as i32,
+        };
+
+        match prefix & 0xfc {
+            // Input
+            0x80 => {
+                let offset = match offsets.iter_mut().find(|(id, _)| *id == globals.report_id) {
+                    Some((_, offset)) => offset,
+                    None => {
+                        offsets.push((globals.report_id, 0));
+                        &mut offsets.last_mut().unwrap().1
+                    }
+                };
+                let constant = unsigned & 0x01 != 0;
+                let variable = unsigned & 0x02 != 0;
+                let relative = unsigned & 0x04 != 0;
+                let in_mouse = collections
+                    .iter()
+                    .any(|(application, usage)| *application && matches!(*usage, USAGE_MOUSE | USAGE_POINTER));
+                if !constant && variable && in_mouse {
+                    for i in 0..globals.report_count {
+                        let usage = match usage_range {
+                            (Some(min), Some(max)) => Some((min + i as u32).min(max)),
+                            // the last usage stands for the remaining fields
+                            _ => usages.get(i).or(usages.last()).copied(),
+                        };
+                        if usage != Some(USAGE_X) {
+                            continue;
+                        }
+                        if !relative {
+                            return Err(String::from("its X axis is absolute, like a tablet's"));
+                        }
+                        if !(1..=32).contains(&globals.report_size) {
+                            return Err(format!("its X field is {} bits wide", globals.report_size));
+                        }
+                        return Ok(Field {
+                            report_id: globals.report_id,
+                            offset: *offset + i * globals.report_size,
+                            size: globals.report_size,
+                            signed: globals.logical_min < 0,
+                        });
+                    }
+                }
+                *offset += globals.report_size * globals.report_count;
+            }
+            // Collection
+            0xa0 => {
+                let usage = usages.first().copied().or(usage_range.0).unwrap_or(0);
+                collections.push((unsigned == 0x01, usage));
+            }
+            // End Collection
+            0xc0 => {
+                collections.pop();
+            }
+            // Output, Feature: other reports, no input bits taken
+            0x90 | 0xb0 => {}
+            // Usage Page
+            0x04 => globals.usage_page = unsigned,
+            // Logical Minimum
+            0x14 => globals.logical_min = signed,
+            // Report Size
+            0x74 => globals.report_size = unsigned as usize,
+            // Report ID
+            0x84 => globals.report_id = Some(unsigned as u8),
+            // Report Count
+            0x94 => globals.report_count = unsigned as usize,
+            // Push, Pop
+            0xa4 => stack.push(globals),
+            0xb4 => globals = stack.pop().ok_or("the descriptor pops more than it pushed")?,
+            // Usage, Usage Minimum, Usage Maximum, with the page unless
+            // given in full
+            0x08 | 0x18 | 0x28 => {
+                let usage = if size == 4 { unsigned } else { globals.usage_page << 16 | unsigned };
+                match prefix & 0xfc {
+                    0x08 => usages.push(usage),
+                    0x18 => usage_range.0 = Some(usage),
+                    _ => usage_range.1 = Some(usage),
+                }
+                continue;
+            }
+            _ => continue,
+        }
+        // the local items only last until the next main item
+        if matches!(prefix & 0xfc, 0x80 | 0x90 | 0xa0 | 0xb0 | 0xc0) {
+            usages.clear();
+            usage_range = (None, None);
+        }
+    }
+    Err(String::from("its report descriptor has no relative X axis in a mouse collection"))
+}
@@ -0,0 +1,133 @@
+use evdev::{InputEvent, InputEventKind, Key};
+use std::collections::BTreeMap;
+
+use crate::configuration::InputRole;
+use crate::Mouse2JoyError;
+
+mod combo;
+pub use combo::Combo;
+
+// what a hotkey does
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    // like SIGUSR1
+    Pause,
+    // the wheel back to the center, the mouse steers on from there
+    Recenter,
+    // like the [profile_keys] next key
+    NextProfile,
+    ToggleOverlay,
+    // like SIGUSR2
+    LogState,
+}
+
+// the action names of [hotkeys]
+const ACTIONS: [(&str, Action); 5] = [
+    ("pause", Action::Pause),
+    ("recenter", Action::Recenter),
+    ("next_profile", Action::NextProfile),
+    ("toggle_overlay", Action::ToggleOverlay),
+    ("log_state", Action::LogState),
+];
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        ACTIONS.iter().find(|(_, action)| *action == self).map_or("", |(name, _)| name)
+    }
+}
+
+// a key combination of the configuration
+pub fn parse(spec: &str) -> Result<Combo, Mouse2JoyError> {
+    Combo::parse(spec).map_err(Mouse2JoyError::InvalidConfig)
+}
+
+// The [hotkeys] table, action names to key combinations, as one dispatch
+// table. The loop hands it every key first, a bound key does nothing else
+// whether or not its modifiers are held.
+pub struct Hotkeys {
+    bindings: Vec<(Combo, Action)>,
+    held: Vec<Key>,
+}
+
+impl Hotkeys {
+    // None without any binding
+    pub fn new(conf: &BTreeMap<String, String>) -> Result<Option<Self>, Mouse2JoyError> {
+        if conf.is_empty() {
+            return Ok(None);
+        }
+        let mut bindings: Vec<(Combo, Action)> = Vec::new();
+        for (name, spec) in conf {
+            let Some(&(_, action)) = ACTIONS.iter().find(|(known, _)| known == name) else {
+                let known: Vec<&str> = ACTIONS.iter().map(|(name, _)| *name).collect();
+                return Err(Mouse2JoyError::InvalidConfig(format!(
+                    "unknown hotkey action \"{}\", expected one of {}",
+                    name,
+                    known.join(", ")
+                )));
+            };
+            let combo = parse(spec)?;
+            let same = |other: &Combo| {
+                other.key == combo.key
+                    && other.modifiers.len() == combo.modifiers.len()
+                    && other.modifiers.iter().all(|modifier| combo.modifiers.contains(modifier))
+            };
+            if let Some((_, other)) = bindings.iter().find(|(bound, _)| same(bound)) {
+                return Err(Mouse2JoyError::InvalidConfig(format!(
+                    "{} is bound to both {} and {}",
+                    combo,
+                    other.name(),
+                    name
+                )));
+            }
+            bindings.push((combo, action));
+        }
+        Ok(Some(Self { bindings, held: Vec::new() }))
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (&Combo, Action)> {
+        self.bindings.iter().map(|(combo, action)| (combo, *action))
+    }
+
+    // the bound keys, for the conflict check
+    pub fn keys(&self) -> Vec<Key> {
+        self.bindings.iter().map(|(combo, _)| combo.key).collect()
+    }
+
+    // every key to read, modifiers included
+    pub fn source_keys(&self) -> Vec<Key> {
+        self.bindings.iter().flat_map(|(combo, _)| combo.keys()).collect()
+    }
+
+    pub fn binds(&self, key: Key) -> bool {
+        self.bindings.iter().any(|(combo, _)| combo.key == key)
+    }
+
+    // The actions pressed in a batch of events, in order. Of the bindings of
+    // a key the one with the most modifiers held wins, so "KEY_F8" and
+    // "KEY_LEFTCTRL+KEY_F8" can do different things.
+    pub fn scan(&mut self, events: &[(InputRole, InputEvent)]) -> Vec<Action> {
+        let mut pressed = Vec::new();
+        for (_, ev) in events {
+            let InputEventKind::Key(key) = ev.kind() else {
+                continue;
+            };
+            if self.bindings.iter().any(|(combo, _)| combo.modifiers.contains(&key)) {
+                match ev.value() {
+                    0 => self.held.retain(|held| *held != key),
+                    1 if !self.held.contains(&key) => self.held.push(key),
+                    _ => {}
+                }
+            }
+            if ev.value() != 1 {
+                continue;
+            }
+            let binding = self
+                .bindings
+                .iter()
+                .filter(|(combo, _)| combo.is_pressed(key, &self.held))
+                .max_by_key(|(combo, _)| combo.modifiers.len());
+            pressed.extend(binding.map(|(_, action)| *action));
+        }
+        pressed
+    }
+}
@@ -0,0 +1,48 @@
+use evdev::Key;
+use std::fmt;
+
+// A key pressed while the modifiers are held, written as the evdev key names
+// joined by "+" with the key last: "KEY_F8", "BTN_MIDDLE" or
+// "KEY_LEFTCTRL+KEY_LEFTSHIFT+KEY_F8".
+#[derive(Clone, PartialEq, Debug)]
+pub struct Combo {
+    pub key: Key,
+    pub modifiers: Vec<Key>,
+}
+
+impl Combo {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        for name in spec.split('+').map(str::trim) {
+            if name.is_empty() {
+                return Err(format!("\"{}\" has an empty key name", spec));
+            }
+            let key = name.parse::<Key>().map_err(|_| format!("unknown key \"{}\" in \"{}\"", name, spec))?;
+            if keys.contains(&key) {
+                return Err(format!("\"{}\" lists {} twice", spec, name));
+            }
+            keys.push(key);
+        }
+        let key = keys.pop().expect("split always yields a part");
+        Ok(Self { key, modifiers: keys })
+    }
+
+    // the key goes down while every modifier is held
+    pub fn is_pressed(&self, key: Key, held: &[Key]) -> bool {
+        self.key == key && self.modifiers.iter().all(|modifier| held.contains(modifier))
+    }
+
+    // modifiers first
+    pub fn keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.modifiers.iter().copied().chain([self.key])
+    }
+}
+
+impl fmt::Display for Combo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{:?}+", modifier)?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+use crate::daemon;
+
+// how long before the exit the systemd status counts down
+const COUNTDOWN: Duration = Duration::from_secs(5 * 60);
+const MINUTE: Duration = Duration::from_secs(60);
+
+// exit_after_idle_minutes: without any input for that long the loop ends
+// like on SIGTERM, so a forgotten mouse2joy doesn't keep a wireless mouse
+// awake and grabbed all night. Only the events of the configured inputs
+// count, the keyboards deliver nothing but their configured keys.
+pub struct IdleExit {
+    timeout: Duration,
+    last_input: Instant,
+    // minutes left in the systemd status, while counting down
+    shown: Option<u64>,
+}
+
+impl IdleExit {
+    // None when 0
+    pub fn new(minutes: u32, now: Instant) -> Option<Self> {
+        (minutes > 0).then(|| Self { timeout: MINUTE * minutes, last_input: now, shown: None })
+    }
+
+    pub fn minutes(&self) -> u64 {
+        self.timeout.as_secs() / 60
+    }
+
+    // whether the time is up, after counting the input read now
+    pub fn expired(&mut self, input: bool, now: Instant) -> bool {
+        if input {
+            self.last_input = now;
+            if self.shown.take().is_some() {
+                daemon::status("Running");
+            }
+            return false;
+        }
+        let left = self.left(now);
+        if left.is_zero() {
+            return true;
+        }
+        if left <= COUNTDOWN {
+            let minutes = left.as_secs().div_ceil(60);
+            if self.shown != Some(minutes) {
+                daemon::status(&format!("No input, exiting in {} min", minutes));
+                self.shown = Some(minutes);
+            }
+        }
+        false
+    }
+
+    // until the exit or, counting down, the next minute of the status
+    pub fn wakeup(&self, now: Instant) -> Duration {
+        let left = self.left(now);
+        if left > COUNTDOWN {
+            return left - COUNTDOWN;
+        }
+        let minutes = left.as_secs().div_ceil(60);
+        left.saturating_sub(MINUTE * minutes.saturating_sub(1) as u32)
+    }
+
+    fn left(&self, now: Instant) -> Duration {
+        (self.last_input + self.timeout).saturating_duration_since(now)
+    }
+}
@@ -0,0 +1,227 @@
+use log::warn;
+use std::sync::mpsc::{self, Sender};
+use std::time::{Duration, Instant};
+
+use crate::configuration::IdleInhibitConfig;
+use crate::Mouse2JoyError;
+
+const MAX_INPUT_SECONDS: u32 = 3600;
+
+// [idle_inhibit]: with the mice grabbed the desktop never sees any input, so
+// it blanks the screen or suspends in the middle of a race. While input comes
+// in and is translated, an org.freedesktop.ScreenSaver inhibit on the session
+// bus keeps the screensaver off and a logind "idle" inhibitor on the system
+// bus idle suspend, taken by a thread of their own. They are taken once for
+// the whole stretch of input and released input_seconds after its end, on a
+// pause or the auto-pause and when the failsafe trips. The inhibit goes with
+// the connection and the inhibitor is a descriptor, so mouse2joy exiting or
+// crashing never leaves either behind.
+pub struct IdleInhibit {
+    linger: Duration,
+    // until when it is held, None while released
+    held: Option<Instant>,
+    sender: Sender<bool>,
+}
+
+impl IdleInhibit {
+    pub fn new(conf: &IdleInhibitConfig) -> Result<Option<Self>, Mouse2JoyError> {
+        if !conf.enabled {
+            return Ok(None);
+        }
+        if !(1..=MAX_INPUT_SECONDS).contains(&conf.input_seconds) {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "idle_inhibit.input_seconds must be between 1 and {}, got {}",
+                MAX_INPUT_SECONDS, conf.input_seconds
+            )));
+        }
+        if !cfg!(feature = "inhibit") {
+            warn!("The idle inhibit is enabled but mouse2joy was built without the inhibit feature");
+            return Ok(None);
+        }
+        let (sender, receiver) = mpsc::channel::<bool>();
+        #[cfg(feature = "inhibit")]
+        std::thread::spawn(move || bus::run(receiver));
+        #[cfg(not(feature = "inhibit"))]
+        drop(receiver);
+        Ok(Some(Self { linger: Duration::from_secs(conf.input_seconds.into()), held: None, sender }))
+    }
+
+    // translating, after the input read now or the lack of it
+    pub fn update(&mut self, input: bool, now: Instant) {
+        if input {
+            if self.held.is_none() {
+                self.send(true);
+            }
+            self.held = Some(now + self.linger);
+        } else if self.held.is_some_and(|until| now >= until) {
+            self.release();
+        }
+    }
+
+    // paused or the failsafe tripped, the next input takes it again
+    pub fn release(&mut self) {
+        if self.held.take().is_some() {
+            self.send(false);
+        }
+    }
+
+    // until it lapses
+    pub fn wakeup(&self, now: Instant) -> Option<Duration> {
+        self.held.map(|until| until.saturating_duration_since(now))
+    }
+
+    fn send(&self, hold: bool) {
+        // the thread only ends when neither bus inhibits anything
+        let _ = self.sender.send(hold);
+    }
+}
+
+#[cfg(feature = "inhibit")]
+mod bus {
+    use log::{debug, info};
+    use std::os::fd::OwnedFd;
+    use std::sync::mpsc::Receiver;
+
+    use crate::dbus::wire::{self, Call, Writer};
+    use crate::dbus::Bus;
+
+    const SCREENSAVER: &str = "org.freedesktop.ScreenSaver";
+    const REASON: &str = "Steering with mouse2joy";
+
+    // Connects to both buses on the first hold. Whatever fails is logged in
+    // a single debug line and not tried again, without either the thread
+    // ends and the rest is dropped.
+    pub fn run(receiver: Receiver<bool>) {
+        let mut inhibitors: Option<Inhibitors> = None;
+        for hold in receiver {
+            let Some(inhibitors) = inhibitors.as_mut() else {
+                // holding comes first
+                let (mut connected, mut failed) = Inhibitors::connect();
+                failed.extend(connected.hold());
+                if !failed.is_empty() {
+                    debug!("No idle inhibit:  {}", failed.join(", "));
+                }
+                if !connected.any() {
+                    return;
+                }
+                info!("Keeping {} off while steering", connected.describe());
+                inhibitors = Some(connected);
+                continue;
+            };
+            if hold {
+                for e in inhibitors.hold() {
+                    debug!("Stopped inhibiting:  {}", e);
+                }
+            } else {
+                inhibitors.release();
+            }
+            if !inhibitors.any() {
+                return;
+            }
+        }
+    }
+
+    struct Inhibitors {
+        session: Option<Bus>,
+        system: Option<Bus>,
+        // of the screensaver inhibit, and the logind inhibitor, while held
+        cookie: Option<u32>,
+        inhibitor: Option<OwnedFd>,
+    }
+
+    impl Inhibitors {
+        fn connect() -> (Self, Vec<String>) {
+            let mut failed = Vec::new();
+            let session = Bus::session().map_err(|e| failed.push(e)).ok();
+            let system = Bus::system().map_err(|e| failed.push(e)).ok();
+            (Self { session, system, cookie: None, inhibitor: None }, failed)
+        }
+
+        fn any(&self) -> bool {
+            self.session.is_some() || self.system.is_some()
+        }
+
+        fn describe(&self) -> &'static str {
+            match (&self.session, &self.system) {
+                (Some(_), Some(_)) => "the screensaver and idle suspend",
+                (Some(_), None) => "the screensaver",
+                _ => "idle suspend",
+            }
+        }
+
+        // a bus failing is given up on, what it held goes with the connection
+        fn hold(&mut self) -> Vec<String> {
+            let mut failed = Vec::new();
+            if let Some(bus) = self.session.as_mut() {
+                match inhibit_screensaver(bus) {
+                    Ok(cookie) => self.cookie = Some(cookie),
+                    Err(e) => {
+                        failed.push(format!("no screensaver inhibit ({})", e));
+                        self.session = None;
+                    }
+                }
+            }
+            if let Some(bus) = self.system.as_mut() {
+                match inhibit_idle(bus) {
+                    Ok(inhibitor) => self.inhibitor = Some(inhibitor),
+                    Err(e) => {
+                        failed.push(format!("no logind inhibitor ({})", e));
+                        self.system = None;
+                    }
+                }
+            }
+            failed
+        }
+
+        fn release(&mut self) {
+            if let (Some(bus), Some(cookie)) = (self.session.as_mut(), self.cookie.take()) {
+                if let Err(e) = uninhibit_screensaver(bus, cookie) {
+                    debug!("Stopped inhibiting the screensaver:  {}", e);
+                    self.session = None;
+                }
+            }
+            // closing it releases it
+            self.inhibitor = None;
+        }
+    }
+
+    fn inhibit_screensaver(bus: &mut Bus) -> Result<u32, String> {
+        let reply = bus.call(&Call {
+            destination: SCREENSAVER,
+            path: "/org/freedesktop/ScreenSaver",
+            interface: SCREENSAVER,
+            member: "Inhibit",
+            signature: "ss",
+            body: wire::strings_body(&["mouse2joy", REASON]),
+        })?;
+        wire::body_u32(&reply.message).ok_or_else(|| String::from("a reply without a cookie"))
+    }
+
+    fn uninhibit_screensaver(bus: &mut Bus, cookie: u32) -> Result<(), String> {
+        let mut body = Writer::default();
+        body.u32(cookie);
+        bus.call(&Call {
+            destination: SCREENSAVER,
+            path: "/org/freedesktop/ScreenSaver",
+            interface: SCREENSAVER,
+            member: "UnInhibit",
+            signature: "u",
+            body: body.buf,
+        })
+        .map(drop)
+    }
+
+    // blocking only what the idle time would do, not a suspend asked for
+    fn inhibit_idle(bus: &mut Bus) -> Result<OwnedFd, String> {
+        let reply = bus.call(&Call {
+            destination: "org.freedesktop.login1",
+            path: "/org/freedesktop/login1",
+            interface: "org.freedesktop.login1.Manager",
+            member: "Inhibit",
+            signature: "ssss",
+            body: wire::strings_body(&["idle", "mouse2joy", REASON, "block"]),
+        })?;
+        let index = wire::body_u32(&reply.message).ok_or("a reply without a descriptor")?;
+        reply.fds.into_iter().nth(index as usize).ok_or_else(|| String::from("the descriptor didn't come along"))
+    }
+}
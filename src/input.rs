@@ -0,0 +1,535 @@
+use evdev::{AbsoluteAxisType, Device, EventType, InputEvent, InputEventKind, Key, RelativeAxisType};
+use log::{debug, info, warn};
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::configuration::InputRole;
+use crate::hidraw::Hidraw;
+use crate::seat::SeatInput;
+use crate::Mouse2JoyError;
+
+mod keyset;
+pub use keyset::KeySet;
+
+// reads of a device while draining it, a device that never runs dry stops it
+const DRAIN_READS: usize = 64;
+
+// One step of an input script: wait `sleep` after the previous step, then
+// deliver `event`, or take control of the device for an `open` step. A
+// `wait` step really waits instead, a `lose` step loses a device.
+struct Step {
+    sleep: Duration,
+    event: Option<InputEvent>,
+    wait: bool,
+    lose: bool,
+    // queued before an `open` step, while mouse2joy wasn't reading
+    queued: bool,
+}
+
+// Synthetic input read from a file (or stdin for "-"), one event per line:
+//
+//   sleep_ms type code value
+//
+// e.g. `16 EV_REL REL_X 5` or `0 1 272 1`. Empty lines and lines starting
+// with '#' are ignored. A `sleep_ms open` line stands for (re)opening the
+// device, the events since the previous one were queued in the meantime. A
+// `sleep_ms wait` line waits for real, up to sleep_ms or until a signal
+// arrives, so a test can signal mouse2joy at that point. A `sleep_ms lose`
+// line stands for a device being unplugged, the script goes on as the
+// devices left.
+// EV_ABS events come from the steering axis device, whose range is given by
+// a `range AXIS min max` line.
+pub struct Script {
+    steps: VecDeque<Step>,
+    ranges: Vec<(AbsoluteAxisType, i32, i32)>,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Self, Mouse2JoyError> {
+        let invalid = |e: std::io::Error| {
+            Mouse2JoyError::InvalidArguments(format!("can't read {}: {}", path.display(), e))
+        };
+        let lines: Vec<String> = if path == Path::new("-") {
+            std::io::stdin().lock().lines().collect::<Result<_, _>>().map_err(invalid)?
+        } else {
+            std::fs::read_to_string(path)
+                .map_err(invalid)?
+                .lines()
+                .map(String::from)
+                .collect()
+        };
+
+        let mut ranges = Vec::new();
+        let mut steps = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .filter(|(_, line)| match parse_range(line) {
+                Some(range) => {
+                    ranges.push(range);
+                    false
+                }
+                None => true,
+            })
+            .map(|(number, line)| {
+                parse_step(line).ok_or_else(|| {
+                    Mouse2JoyError::InvalidArguments(format!(
+                        "{}:{}: expected \"sleep_ms type code value\", got \"{}\"",
+                        path.display(),
+                        number + 1,
+                        line
+                    ))
+                })
+            })
+            .collect::<Result<VecDeque<Step>, Mouse2JoyError>>()?;
+        // everything before an open step was queued for it
+        let mut queued = false;
+        for step in steps.iter_mut().rev() {
+            queued |= step.event.is_none() && !step.wait && !step.lose;
+            step.queued = queued && step.event.is_some();
+        }
+        Ok(Self { steps, ranges })
+    }
+
+    // a script of events made up rather than read, each after its sleep
+    pub fn generated(events: impl IntoIterator<Item = (Duration, InputEvent)>) -> Self {
+        let steps = events
+            .into_iter()
+            .map(|(sleep, event)| Step { sleep, event: Some(event), wait: false, lose: false, queued: false })
+            .collect();
+        Self { steps, ranges: Vec::new() }
+    }
+}
+
+fn parse_range(line: &str) -> Option<(AbsoluteAxisType, i32, i32)> {
+    let ["range", axis, min, max] = line.split_whitespace().collect::<Vec<&str>>()[..] else {
+        return None;
+    };
+    Some((axis.parse().ok()?, min.parse().ok()?, max.parse().ok()?))
+}
+
+fn parse_step(line: &str) -> Option<Step> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if let [sleep, kind @ ("open" | "wait" | "lose")] = fields[..] {
+        let sleep = Duration::from_millis(sleep.parse().ok()?);
+        return Some(Step { sleep, event: None, wait: kind == "wait", lose: kind == "lose", queued: false });
+    }
+    let [sleep, event_type, code, value] = fields[..] else {
+        return None;
+    };
+    let event_type = match event_type {
+        "EV_SYN" => EventType::SYNCHRONIZATION,
+        "EV_KEY" => EventType::KEY,
+        "EV_REL" => EventType::RELATIVE,
+        "EV_ABS" => EventType::ABSOLUTE,
+        number => EventType(number.parse().ok()?),
+    };
+    let code = match code.parse::<u16>() {
+        Ok(code) => code,
+        Err(_) => match event_type {
+            EventType::KEY => code.parse::<Key>().ok()?.code(),
+            EventType::RELATIVE => code.parse::<RelativeAxisType>().ok()?.0,
+            EventType::ABSOLUTE => code.parse::<AbsoluteAxisType>().ok()?.0,
+            _ => return None,
+        },
+    };
+    Some(Step {
+        sleep: Duration::from_millis(sleep.parse().ok()?),
+        event: Some(InputEvent::new(event_type, code, value.parse().ok()?)),
+        wait: false,
+        lose: false,
+        queued: false,
+    })
+}
+
+// an opened input device and how to open it again
+pub struct InputDevice {
+    pub path: PathBuf,
+    pub device: Device,
+    pub grab: bool,
+    // keyboards only send keys, their role doesn't matter
+    pub role: InputRole,
+    // unplugged, no longer polled until it is reopened
+    lost: bool,
+    // the X movement is read from the hidraw node instead, `raw` asks for it
+    // again on reopen after a fallback
+    raw: bool,
+    hidraw: Option<Hidraw>,
+    // a keyboard only hands on these keys, everything else typed on it is
+    // dropped as it is read
+    keys: Option<KeySet>,
+}
+
+impl InputDevice {
+    pub fn new(path: PathBuf, device: Device, grab: bool, role: InputRole) -> Self {
+        Self { path, device, grab, role, lost: false, raw: false, hidraw: None, keys: None }
+    }
+
+    pub fn only_keys(&mut self, keys: KeySet) {
+        self.keys = Some(keys);
+    }
+
+    // Take the X movement from the mouse's hidraw node, or keep reading it
+    // from the event node when that can't be done.
+    pub fn use_hidraw(&mut self) {
+        self.raw = true;
+        match Hidraw::open(&self.path) {
+            Ok(hidraw) => {
+                info!("Reading the X movement of {} from {}", self.path.display(), hidraw.path.display());
+                self.hidraw = Some(hidraw);
+            }
+            Err(e) => warn!("Can't read {} through hidraw, {}, using evdev", self.path.display(), e),
+        }
+    }
+}
+
+enum Source {
+    Devices(Vec<InputDevice>),
+    // scripts run on a simulated clock, waiting just advances it
+    Script { script: Script, clock: Instant, due: Instant, queue: Vec<(Instant, InputEvent)> },
+    // boxed, the X connection is far larger than the rest
+    Seat(Box<SeatInput>),
+}
+
+// Where input events come from: the selected mouse plus any keyboards, or a
+// script standing in for them.
+pub struct Inputs {
+    source: Source,
+    // a device was lost since the last take_lost
+    lost: bool,
+    // events queued before a device was (re)opened are dropped once older
+    // than this, 0 drops them all
+    stale: Duration,
+    // recent enough queued events, delivered by the next fetch
+    kept: Vec<(InputRole, InputEvent)>,
+}
+
+impl Inputs {
+    // the devices were just opened and grabbed, whatever they queued before
+    // is dropped
+    pub fn devices(devices: Vec<InputDevice>, stale: Duration) -> Self {
+        let mut inputs = Self { source: Source::Devices(devices), lost: false, stale, kept: Vec::new() };
+        if let Source::Devices(devices) = &mut inputs.source {
+            for input in devices {
+                drain(input, stale, &mut inputs.kept);
+            }
+        }
+        inputs
+    }
+
+    pub fn seat(seat: SeatInput) -> Self {
+        Self { source: Source::Seat(Box::new(seat)), lost: false, stale: Duration::ZERO, kept: Vec::new() }
+    }
+
+    pub fn script(script: Script, stale: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            source: Source::Script { script, clock: now, due: now, queue: Vec::new() },
+            lost: false,
+            stale,
+            kept: Vec::new(),
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        match &self.source {
+            Source::Devices(_) | Source::Seat(_) => Instant::now(),
+            Source::Script { clock, .. } => *clock,
+        }
+    }
+
+    // give up any exclusive grab on the devices, a device that was never
+    // grabbed refuses which is fine
+    pub fn release(&mut self) {
+        if let Source::Devices(devices) = &mut self.source {
+            for input in devices {
+                let _ = input.device.ungrab();
+            }
+        }
+    }
+
+    // take the grabs given up by release again
+    pub fn grab(&mut self) {
+        if let Source::Devices(devices) = &mut self.source {
+            for input in devices.iter_mut().filter(|input| input.grab && !input.lost) {
+                if let Err(e) = input.device.grab() {
+                    warn!("Failed to grab {} again:  {}", input.path.display(), e);
+                }
+            }
+        }
+    }
+
+    // what the input is read from, for the logs
+    pub fn describe(&self) -> String {
+        match &self.source {
+            Source::Devices(devices) => devices
+                .iter()
+                .map(|input| format!("{}{}", input.path.display(), if input.lost { " (lost)" } else { "" }))
+                .collect::<Vec<String>>()
+                .join(", "),
+            Source::Script { .. } => String::from("the input script"),
+            Source::Seat(_) => String::from("the X seat"),
+        }
+    }
+
+    // Open (and grab) all devices again, e.g. after a resume left their fds
+    // broken. Returns how many were re-established, a device failing to open
+    // keeps its old fd.
+    pub fn reopen(&mut self) -> usize {
+        let Source::Devices(devices) = &mut self.source else {
+            return 0;
+        };
+        let mut reopened = 0;
+        for input in devices {
+            let device = Device::open(&input.path).and_then(|mut device| {
+                if input.grab {
+                    device.grab()?;
+                }
+                Ok(device)
+            });
+            match device {
+                Ok(device) => {
+                    input.device = device;
+                    input.lost = false;
+                    if input.raw {
+                        input.hidraw = None;
+                        input.use_hidraw();
+                    }
+                    drain(input, self.stale, &mut self.kept);
+                    reopened += 1;
+                }
+                Err(e) => warn!("Failed to reopen {}:  {}", input.path.display(), e),
+            }
+        }
+        reopened
+    }
+
+    // the range the steering axis device reports for `axis`
+    pub fn axis_range(&self, axis: AbsoluteAxisType) -> Option<(i32, i32)> {
+        match &self.source {
+            Source::Devices(devices) => {
+                let input = devices.iter().find(|input| input.role == InputRole::Axis)?;
+                let info = input.device.get_abs_state().ok()?[usize::from(axis.0)];
+                Some((info.minimum, info.maximum))
+            }
+            Source::Script { script, .. } => script
+                .ranges
+                .iter()
+                .find(|(range, _, _)| *range == axis)
+                .map(|(_, min, max)| (*min, *max)),
+            Source::Seat(_) => None,
+        }
+    }
+
+    pub fn take_lost(&mut self) -> bool {
+        std::mem::take(&mut self.lost)
+    }
+
+    // Wait until input is available or the timeout expires and return the
+    // events read with the role of their device, None once the input has
+    // ended. Script events come from the steering mouse, EV_ABS ones from
+    // the steering axis device.
+    pub fn fetch(&mut self, timeout: Option<Duration>) -> Option<Vec<(InputRole, InputEvent)>> {
+        if !self.kept.is_empty() {
+            return Some(std::mem::take(&mut self.kept));
+        }
+        match &mut self.source {
+            Source::Devices(devices) if devices.iter().all(|input| input.lost) => None,
+            Source::Devices(devices) => {
+                let lost = devices.iter().filter(|input| input.lost).count();
+                let events = fetch_devices(devices, timeout);
+                self.lost |= devices.iter().filter(|input| input.lost).count() > lost;
+                Some(events)
+            }
+            Source::Seat(seat) => {
+                let events = seat.fetch(timeout)?;
+                Some(events.into_iter().map(|event| (InputRole::Steering, event)).collect())
+            }
+            Source::Script { script, clock, due, queue } => loop {
+                let step = script.steps.front()?;
+                if step.wait {
+                    let sleep = script.steps.pop_front()?.sleep;
+                    let start = Instant::now();
+                    // no fds, only the timeout or a signal ends it, one that
+                    // came in before is handled first
+                    debug!("Waiting up to {}ms for a signal", sleep.as_millis());
+                    if !crate::signal_pending() {
+                        let wait = PollTimeout::try_from(sleep.as_millis()).unwrap_or(PollTimeout::MAX);
+                        let _ = poll(&mut [] as &mut [PollFd], wait);
+                    }
+                    *clock += start.elapsed().min(sleep);
+                    *due = *clock;
+                    return Some(Vec::new());
+                }
+                let next = *due + step.sleep;
+                if let Some(timeout) = timeout.filter(|timeout| *clock + *timeout < next) {
+                    *clock += timeout;
+                    return Some(Vec::new());
+                }
+                let step = script.steps.pop_front()?;
+                *clock = next.max(*clock);
+                *due = next;
+                match step.event {
+                    None if step.lose => {
+                        self.lost = true;
+                        return Some(Vec::new());
+                    }
+                    Some(event) if step.queued => queue.push((*clock, event)),
+                    Some(event) => return Some(vec![(script_role(&event), event)]),
+                    None => {
+                        let now = *clock;
+                        let queued = std::mem::take(queue);
+                        let (stale, kept): (Vec<_>, Vec<_>) =
+                            queued.into_iter().partition(|(at, _)| now.duration_since(*at) >= self.stale);
+                        log_dropped(stale.iter().map(|(_, event)| event), "the script");
+                        return Some(kept.into_iter().map(|(_, event)| (script_role(&event), event)).collect());
+                    }
+                }
+            },
+        }
+    }
+}
+
+// scripts stand in for the steering mouse and the steering axis device
+fn script_role(event: &InputEvent) -> InputRole {
+    match event.event_type() {
+        EventType::ABSOLUTE => InputRole::Axis,
+        _ => InputRole::Steering,
+    }
+}
+
+// Read everything a device queued up before mouse2joy took control, keeping
+// only what is younger than `stale`, so a backlog doesn't yank the wheel.
+fn drain(input: &mut InputDevice, stale: Duration, kept: &mut Vec<(InputRole, InputEvent)>) {
+    let cutoff = SystemTime::now().checked_sub(stale).unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut dropped = Vec::new();
+    for _ in 0..DRAIN_READS {
+        let ready = wait_for_input(&[&mut *input], Some(Duration::ZERO));
+        if !ready.is_ok_and(|ready| ready[0].0) {
+            break;
+        }
+        let Ok(events) = input.device.fetch_events() else {
+            break;
+        };
+        for event in events {
+            if !wanted(input.keys.as_ref(), &event) {
+                continue;
+            }
+            if stale.is_zero() || event.timestamp() < cutoff {
+                dropped.push(event);
+            } else {
+                kept.push((input.role, event));
+            }
+        }
+    }
+    log_dropped(dropped.iter(), &input.path.display().to_string());
+    if let Some(hidraw) = &mut input.hidraw {
+        // reports carry no timestamps, `stale` can't keep any
+        let dropped = hidraw.drain();
+        if dropped > 0 {
+            info!("Dropped {} stale report(s) queued on {}", dropped, hidraw.path.display());
+        }
+    }
+}
+
+// of a keyboard only the allowed keys
+fn wanted(keys: Option<&KeySet>, event: &InputEvent) -> bool {
+    keys.is_none_or(|keys| event.event_type() == EventType::KEY && keys.contains(event.code()))
+}
+
+fn log_dropped<'a>(events: impl Iterator<Item = &'a InputEvent>, source: &str) {
+    let dropped = events.filter(|event| event.event_type() != EventType::SYNCHRONIZATION).count();
+    if dropped > 0 {
+        info!("Dropped {} stale event(s) queued on {}", dropped, source);
+    }
+}
+
+// An unplugged device is dropped from the poll, the others carry on.
+fn fetch_devices(devices: &mut [InputDevice], timeout: Option<Duration>) -> Vec<(InputRole, InputEvent)> {
+    let mut active: Vec<&mut InputDevice> = devices.iter_mut().filter(|input| !input.lost).collect();
+    let ready = match wait_for_input(&active, timeout) {
+        Ok(ready) => ready,
+        Err(e) => {
+            warn!("Failed to poll input devices:  {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut events = Vec::new();
+    for (input, (events_ready, reports_ready)) in active.iter_mut().zip(ready) {
+        if events_ready {
+            // with a hidraw node the X movement comes from there
+            let raw_x = input.hidraw.is_some();
+            let (role, keys) = (input.role, input.keys.as_ref());
+            match input.device.fetch_events() {
+                Ok(fetched) => events.extend(
+                    fetched
+                        .filter(|event| !(raw_x && event.kind() == InputEventKind::RelAxis(RelativeAxisType::REL_X)))
+                        .filter(|event| wanted(keys, event))
+                        .map(|event| (role, event)),
+                ),
+                Err(e) if e.raw_os_error() == Some(nix::libc::ENODEV) => {
+                    warn!("Lost {}, reading the other devices on", input.path.display());
+                    input.lost = true;
+                }
+                Err(e) => warn!("Failed to fetch input events:  {}", e),
+            }
+        }
+        let Some(hidraw) = input.hidraw.as_mut().filter(|_| reports_ready) else {
+            continue;
+        };
+        match hidraw.read() {
+            Ok(deltas) => {
+                for delta in deltas {
+                    events.push((input.role, InputEvent::new(EventType::RELATIVE, RelativeAxisType::REL_X.0, delta)));
+                    events.push((input.role, InputEvent::new(EventType::SYNCHRONIZATION, 0, 0)));
+                }
+            }
+            // unplugged, the event node tells
+            Err(e) if e.raw_os_error() == Some(nix::libc::ENODEV) => input.hidraw = None,
+            Err(e) => {
+                let (node, path) = (hidraw.path.display(), input.path.display());
+                warn!("Failed to read {}:  {}, reading the X movement from {} again", node, e, path);
+                input.hidraw = None;
+            }
+        }
+    }
+    events
+}
+
+// block until at least one device has events or hidraw reports to read or
+// the timeout expires, returns which of them are ready for every device
+fn wait_for_input(devices: &[&mut InputDevice], timeout: Option<Duration>) -> nix::Result<Vec<(bool, bool)>> {
+    let mut fds = Vec::new();
+    for input in devices {
+        // SAFETY: the devices outlive the poll call, so their fds stay open
+        fds.push(PollFd::new(unsafe { BorrowedFd::borrow_raw(input.device.as_raw_fd()) }, PollFlags::POLLIN));
+        if let Some(hidraw) = &input.hidraw {
+            fds.push(PollFd::new(hidraw.as_fd(), PollFlags::POLLIN));
+        }
+    }
+    // round up so a sub-millisecond wait doesn't turn into a busy loop
+    let timeout = timeout
+        .map(|timeout| PollTimeout::try_from(timeout.as_micros().div_ceil(1000)).unwrap_or(PollTimeout::MAX))
+        .unwrap_or(PollTimeout::NONE);
+
+    match poll(&mut fds, timeout) {
+        Ok(_) => {}
+        Err(nix::errno::Errno::EINTR) => return Ok(vec![(false, false); devices.len()]),
+        Err(e) => return Err(e),
+    }
+
+    let mut ready = fds.iter().map(|fd| fd.revents().is_some_and(|revents| !revents.is_empty()));
+    Ok(devices
+        .iter()
+        .map(|input| {
+            let events = ready.next().unwrap_or(false);
+            let reports = input.hidraw.is_some() && ready.next().unwrap_or(false);
+            (events, reports)
+        })
+        .collect())
+}
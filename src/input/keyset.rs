@@ -0,0 +1,33 @@
+use evdev::Key;
+
+// key codes end at KEY_MAX, 0x2ff
+const WORDS: usize = 0x300 / 64;
+
+// A set of key codes as a bitset, looking one up is a shift and a mask so
+// the keys nobody asked for cost next to nothing.
+#[derive(Clone, Default)]
+pub struct KeySet([u64; WORDS]);
+
+impl KeySet {
+    pub fn insert(&mut self, key: Key) {
+        let code = usize::from(key.code());
+        if code < WORDS * 64 {
+            self.0[code / 64] |= 1 << (code % 64);
+        }
+    }
+
+    pub fn contains(&self, code: u16) -> bool {
+        let code = usize::from(code);
+        code < WORDS * 64 && self.0[code / 64] & (1 << (code % 64)) != 0
+    }
+}
+
+impl FromIterator<Key> for KeySet {
+    fn from_iter<I: IntoIterator<Item = Key>>(keys: I) -> Self {
+        let mut set = Self::default();
+        for key in keys {
+            set.insert(key);
+        }
+        set
+    }
+}
@@ -0,0 +1,86 @@
+use evdev::{EventType, InputEvent, InputId, Key};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::mapping::parse_key;
+use crate::output::DeviceSpec;
+use crate::Mouse2JoyError;
+
+// Sources mapped to keyboard keys, for game functions that can't be bound to
+// a joystick button. The keys are sent by a virtual keyboard of their own,
+// held while the source is held like a mapped button.
+pub struct KeyMapping {
+    table: HashMap<Key, Key>,
+    // sources currently down
+    held: Vec<Key>,
+}
+
+impl KeyMapping {
+    pub fn new(conf: &BTreeMap<String, String>) -> Result<Option<Self>, Mouse2JoyError> {
+        if conf.is_empty() {
+            return Ok(None);
+        }
+        let mut table = HashMap::new();
+        for (source, name) in conf {
+            let source = parse_key(source)?;
+            let key = parse_key(name)?;
+            if !is_keyboard_key(key) {
+                return Err(Mouse2JoyError::InvalidConfig(format!(
+                    "{:?} is not a keyboard key, map it in [buttons]",
+                    key
+                )));
+            }
+            table.insert(source, key);
+        }
+        Ok(Some(Self { table, held: Vec::new() }))
+    }
+
+    pub fn sources(&self) -> Vec<Key> {
+        self.table.keys().copied().collect()
+    }
+
+    // The virtual keyboard, with the mapped keys only. Its product ID comes
+    // after the [mouse_look] pointer.
+    pub fn spec(&self, name: &str, id: InputId) -> DeviceSpec {
+        let mut buttons: Vec<Key> = self.table.values().copied().collect();
+        buttons.sort_by_key(|key| key.code());
+        buttons.dedup();
+        DeviceSpec {
+            name: format!("{} keys", name),
+            id: InputId::new(id.bus_type(), id.vendor(), id.product() + 4, id.version()),
+            axes: Vec::new(),
+            rel: Vec::new(),
+            buttons,
+        }
+    }
+
+    // None if the key isn't mapped here. Repeats are dropped like for the
+    // buttons, and with two sources on one key it is held until both are up.
+    pub fn handle_key(&mut self, source: Key, value: i32) -> Option<Option<InputEvent>> {
+        let key = *self.table.get(&source)?;
+        let before = self.is_down(key);
+        match value {
+            0 => self.held.retain(|held| *held != source),
+            1 if !self.held.contains(&source) => self.held.push(source),
+            _ => {}
+        }
+        let after = self.is_down(key);
+        Some((before != after).then(|| InputEvent::new(EventType::KEY, key.code(), after as i32)))
+    }
+
+    // paused, nothing may stay held in the game
+    pub fn release(&mut self) -> Vec<InputEvent> {
+        let mut keys: Vec<Key> = self.held.drain(..).map(|source| self.table[&source]).collect();
+        keys.sort_by_key(|key| key.code());
+        keys.dedup();
+        keys.into_iter().map(|key| InputEvent::new(EventType::KEY, key.code(), 0)).collect()
+    }
+
+    fn is_down(&self, key: Key) -> bool {
+        self.held.iter().any(|source| self.table[source] == key)
+    }
+}
+
+// KEY_* rather than BTN_*, the names evdev gives them tell them apart
+pub fn is_keyboard_key(key: Key) -> bool {
+    format!("{:?}", key).starts_with("KEY_")
+}
@@ -0,0 +1,115 @@
+use log::{info, warn};
+use nix::sys::pthread::{pthread_kill, pthread_self, Pthread};
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::configuration::Config;
+use crate::pipeline;
+
+// the loop is woken this often once the command exited, until it saw that
+const REWAKE: Duration = Duration::from_millis(50);
+
+// The launch command of a profile, run when it gets selected with --profile
+// or a switch, e.g. the game. It gets a process group of its own and nothing
+// on stdin, so neither Ctrl-C nor mouse2joy exiting ends it, and a thread
+// waits for it so it never lingers as a zombie. Without allow_launch in the
+// base settings, which no profile can set, nothing is run: an imported
+// profile can't start anything by itself.
+pub struct Launched {
+    program: String,
+    exited: Arc<AtomicBool>,
+    // the loop to wake once it exited, None when it doesn't wait for that
+    // or is done with it
+    waker: Arc<Mutex<Option<Pthread>>>,
+}
+
+impl Launched {
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn exited(&self) -> bool {
+        self.exited.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Launched {
+    fn drop(&mut self) {
+        *self.waker.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+// None without a command, when not allowed or when it can't be started.
+// With `wake` the exit wakes the loop calling this.
+pub fn launch(conf: &Config, profile: &str, wake: bool) -> Option<Launched> {
+    let [program, args @ ..] = &conf.launch[..] else {
+        return None;
+    };
+    if !conf.allow_launch {
+        warn!(
+            "Profile \"{}\" launches {:?}, not run without allow_launch = true in the base settings",
+            profile, conf.launch
+        );
+        return None;
+    }
+    info!("Launching {:?} for profile \"{}\"", conf.launch, profile);
+    let output = || if conf.launch_output { Stdio::piped() } else { Stdio::null() };
+    let spawned = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(output())
+        .stderr(output())
+        .process_group(0)
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to launch {}:  {}", program, e);
+            return None;
+        }
+    };
+    let pid = child.id();
+    info!("Launched {} as pid {}", program, pid);
+    let streams: [Option<Box<dyn Read + Send>>; 2] =
+        [child.stdout.take().map(|out| Box::new(out) as _), child.stderr.take().map(|err| Box::new(err) as _)];
+    for stream in streams.into_iter().flatten() {
+        let program = program.clone();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                info!("{}: {}", program, line);
+            }
+        });
+    }
+
+    if wake {
+        if let Err(e) = pipeline::install_wake() {
+            warn!("Failed to install a {} handler, the exit of {} is seen late:  {}", pipeline::WAKE, program, e);
+        }
+    }
+    let exited = Arc::new(AtomicBool::new(false));
+    let waker = Arc::new(Mutex::new(wake.then(pthread_self)));
+    let (waiting, woken, name) = (exited.clone(), waker.clone(), program.clone());
+    std::thread::spawn(move || {
+        match child.wait() {
+            Ok(status) => info!("{} (pid {}) exited, {}", name, pid, status),
+            Err(e) => warn!("Lost track of {} (pid {}):  {}", name, pid, e),
+        }
+        waiting.store(true, Ordering::Relaxed);
+        // a wakeup just before the loop polls again goes unnoticed
+        loop {
+            {
+                let thread = woken.lock().unwrap_or_else(|e| e.into_inner());
+                let Some(thread) = *thread else {
+                    break;
+                };
+                let _ = pthread_kill(thread, pipeline::WAKE);
+            }
+            std::thread::sleep(REWAKE);
+        }
+    });
+    Some(Launched { program: program.clone(), exited, waker })
+}
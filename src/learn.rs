@@ -0,0 +1,196 @@
+use evdev::{AbsoluteAxisType, InputEventKind, Key};
+use log::{info, warn};
+use std::io::Write;
+use std::path::Path;
+
+use crate::configuration::{self, Config, MappingAction, SnapRelease};
+use crate::input::Inputs;
+use crate::mapping::{parse_key, Destination, Mapping};
+use crate::pedals::PEDAL_AXES;
+use crate::Mouse2JoyError;
+
+// what gets learned without a [buttons] table, the paddles first
+const DEFAULT_FUNCTIONS: [Destination; 8] = [
+    Destination::Axis(AbsoluteAxisType::ABS_GAS),
+    Destination::Axis(AbsoluteAxisType::ABS_BRAKE),
+    Destination::Button(Key::BTN_TR),
+    Destination::Button(Key::BTN_TL),
+    Destination::Button(Key::BTN_START),
+    Destination::Button(Key::BTN_SELECT),
+    Destination::Button(Key::BTN_TR2),
+    Destination::Button(Key::BTN_TL2),
+];
+
+// Walk through the virtual functions, the ones [buttons] already maps or the
+// defaults, capture the next pressed key or button for each and write the
+// result into the [buttons] table of the config after asking. Esc skips a
+// function, a skipped one keeps its old mapping.
+pub fn run(
+    mut inputs: Inputs,
+    conf: &Config,
+    mapping: &Mapping,
+    buttons: &[Key],
+    path: &Path,
+) -> Result<(), Mouse2JoyError> {
+    let learned = learn(&mut inputs, &functions(conf, mapping, buttons));
+    inputs.release();
+    if learned.is_empty() {
+        println!("Nothing learned, {} is unchanged", path.display());
+        return Ok(());
+    }
+
+    println!("Learned mappings:");
+    for (source, destination) in &learned {
+        println!("  {:<16} {}", format!("{:?}", source), label(*destination));
+    }
+    print!("Save them to {}? [y/N] ", path.display());
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() || !answer.trim().eq_ignore_ascii_case("y") {
+        println!("Not saved");
+        return Ok(());
+    }
+
+    let (mut document, migrated) = if path.exists() {
+        Config::document(path)?
+    } else {
+        let mut document = toml_edit::DocumentMut::new();
+        document.insert("config_version", toml_edit::value(i64::from(configuration::CONFIG_VERSION)));
+        (document, false)
+    };
+    if !document.contains_table("buttons") {
+        document.insert("buttons", toml_edit::Item::Table(toml_edit::Table::new()));
+    }
+    let table = document["buttons"]
+        .as_table_mut()
+        .ok_or_else(|| Mouse2JoyError::InvalidConfig(format!("{}: buttons is not a table", path.display())))?;
+    // the learned sources and functions replace whatever mapped them before
+    let replaced: Vec<String> = table
+        .iter()
+        .filter_map(|(source, _)| {
+            let key = parse_key(source).ok()?;
+            let old = mapping.destination(key);
+            learned
+                .iter()
+                .any(|(learned_source, destination)| *learned_source == key || old == Some(*destination))
+                .then(|| source.to_string())
+        })
+        .collect();
+    for source in replaced {
+        table.remove(&source);
+    }
+    for (source, destination) in &learned {
+        table.insert(&format!("{:?}", source), toml_edit::value(mapping_value(*destination)));
+    }
+
+    // a mapping the next start would refuse is never written
+    let updated: Config = toml::from_str(&document.to_string())
+        .map_err(|e| Mouse2JoyError::InvalidConfig(format!("{}: {}", path.display(), e)))?;
+    Mapping::new(&updated.buttons, buttons, &PEDAL_AXES)?;
+    Config::save(path, &document, migrated)?;
+    info!("Saved {} learned mapping(s) to {}", learned.len(), path.display());
+    Ok(())
+}
+
+fn functions(conf: &Config, mapping: &Mapping, buttons: &[Key]) -> Vec<Destination> {
+    let mut functions: Vec<Destination> = Vec::new();
+    for source in conf.buttons.keys() {
+        let destination = parse_key(source).ok().and_then(|key| mapping.destination(key));
+        if let Some(destination) = destination.filter(|destination| !functions.contains(destination)) {
+            functions.push(destination);
+        }
+    }
+    if functions.is_empty() {
+        functions = DEFAULT_FUNCTIONS
+            .into_iter()
+            .filter(|destination| match destination {
+                Destination::Button(button) => buttons.contains(button),
+                _ => true,
+            })
+            .collect();
+    }
+    functions
+}
+
+// the source picked for each function, in order, until the input ends
+fn learn(inputs: &mut Inputs, functions: &[Destination]) -> Vec<(Key, Destination)> {
+    let mut learned: Vec<(Key, Destination)> = Vec::new();
+    for destination in functions {
+        println!("Press the input you want for {} (Esc skips)", label(*destination));
+        let Some(key) = next_press(inputs, &learned) else {
+            println!("Input ended, stopping");
+            break;
+        };
+        if key == Key::KEY_ESC {
+            println!("  skipped");
+            continue;
+        }
+        println!("  {:?}", key);
+        learned.push((key, *destination));
+    }
+    learned
+}
+
+// the next pressed key or button that can be written down and isn't taken
+fn next_press(inputs: &mut Inputs, learned: &[(Key, Destination)]) -> Option<Key> {
+    loop {
+        for (_, ev) in inputs.fetch(None)? {
+            let InputEventKind::Key(key) = ev.kind() else {
+                continue;
+            };
+            if ev.value() != 1 {
+                continue;
+            }
+            // configs name keys, a code without a name can't go in there
+            if format!("{:?}", key).parse::<Key>() != Ok(key) {
+                warn!("Key code {} has no name, press another input", key.code());
+                continue;
+            }
+            if let Some((_, taken)) = learned.iter().find(|(source, _)| *source == key) {
+                warn!("{:?} is already used for {}, press another input or Esc", key, label(*taken));
+                continue;
+            }
+            return Some(key);
+        }
+    }
+}
+
+fn label(destination: Destination) -> String {
+    match destination {
+        Destination::Axis(AbsoluteAxisType::ABS_GAS) => String::from("THROTTLE"),
+        Destination::Axis(AbsoluteAxisType::ABS_BRAKE) => String::from("BRAKE"),
+        Destination::Axis(axis) => format!("{:?}", axis),
+        Destination::Button(Key::BTN_TR) => String::from("SHIFT UP (BTN_TR)"),
+        Destination::Button(Key::BTN_TL) => String::from("SHIFT DOWN (BTN_TL)"),
+        Destination::Button(button) => format!("{:?}", button),
+        Destination::Action(MappingAction::SnapLeft, _) => String::from("SNAP LEFT"),
+        Destination::Action(MappingAction::SnapRight, _) => String::from("SNAP RIGHT"),
+        Destination::Action(MappingAction::ThrottleHoldToggle, _) => String::from("THROTTLE HOLD"),
+    }
+}
+
+// the [buttons] value for a function, like a hand written one
+fn mapping_value(destination: Destination) -> toml_edit::Value {
+    match destination {
+        Destination::Button(button) => format!("{:?}", button).into(),
+        Destination::Axis(axis) => {
+            let mut table = toml_edit::InlineTable::new();
+            table.insert("axis", format!("{:?}", axis).into());
+            table.insert("mode", "ramp".into());
+            table.into()
+        }
+        Destination::Action(action, release) => {
+            let mut table = toml_edit::InlineTable::new();
+            let action = match action {
+                MappingAction::SnapLeft => "snap_left",
+                MappingAction::SnapRight => "snap_right",
+                MappingAction::ThrottleHoldToggle => "throttle_hold_toggle",
+            };
+            table.insert("action", action.into());
+            if release == SnapRelease::Stay {
+                table.insert("release", "stay".into());
+            }
+            table.into()
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use evdev::{Device, EventType, InputEvent, LedType};
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::configuration::LockLedConfig;
+use crate::cue::{check_hysteresis, locked_side};
+use crate::Mouse2JoyError;
+
+// a failsafe blinks the LED this often, this long on and off
+const BLINKS: u32 = 3;
+const BLINK: Duration = Duration::from_millis(150);
+
+enum Command {
+    Lit(bool),
+    Blink,
+    // back to how it was before mouse2joy, until the next Lit
+    Restore,
+}
+
+// A keyboard LED, Scroll Lock by default, lit while the wheel is held at full
+// lock, with the hysteresis of the full lock cue. A thread of its own opens
+// the keyboard and writes to it, the loop only sends it the changes. Paused
+// and on the way out the LED goes back to how it was. Without a keyboard
+// having the LED it turns itself off.
+pub struct LockLed {
+    hysteresis: i32,
+    blink_on_failsafe: bool,
+    locked: Option<i32>,
+    // what the LED was last told, None when restored
+    shown: Option<bool>,
+    sender: Option<Sender<Command>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LockLed {
+    pub fn new(conf: &LockLedConfig, hysteresis: i32, input_dir: &Path) -> Result<Option<Self>, Mouse2JoyError> {
+        if !conf.enabled {
+            return Ok(None);
+        }
+        check_hysteresis(hysteresis)?;
+        let led = conf
+            .led
+            .parse::<LedType>()
+            .map_err(|_| Mouse2JoyError::InvalidConfig(format!("lock_led: unknown LED \"{}\"", conf.led)))?;
+        let (sender, receiver) = channel();
+        let device = conf.device.clone();
+        let input_dir = input_dir.to_path_buf();
+        let thread = std::thread::spawn(move || match open(led, device.as_deref(), &input_dir) {
+            Some((path, device)) => drive(led, &path, device, receiver),
+            None => debug!(
+                "No keyboard with {:?}{} found, no full lock LED",
+                led,
+                device.map_or(String::new(), |name| format!(" named \"{}\"", name))
+            ),
+        });
+        Ok(Some(Self {
+            hysteresis,
+            blink_on_failsafe: conf.blink_on_failsafe,
+            locked: None,
+            shown: None,
+            sender: Some(sender),
+            thread: Some(thread),
+        }))
+    }
+
+    pub fn update(&mut self, position: i32) {
+        self.locked = locked_side(self.locked, position, self.hysteresis);
+        let lit = self.locked.is_some();
+        if self.shown != Some(lit) {
+            self.send(Command::Lit(lit));
+            self.shown = Some(lit);
+        }
+    }
+
+    pub fn failsafe(&mut self) {
+        if self.blink_on_failsafe {
+            self.send(Command::Blink);
+        }
+    }
+
+    // paused, the next update lights it up again
+    pub fn restore(&mut self) {
+        if self.shown.take().is_some() {
+            self.send(Command::Restore);
+        }
+    }
+
+    fn send(&self, command: Command) {
+        // gone without a keyboard
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(command);
+        }
+    }
+}
+
+// the thread restores the LED once the commands end, waited for so it
+// happens before the exit
+impl Drop for LockLed {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// the keyboard named `name`, or the first one with the LED
+fn open(led: LedType, name: Option<&str>, input_dir: &Path) -> Option<(PathBuf, Device)> {
+    let mut paths: Vec<PathBuf> =
+        std::fs::read_dir(input_dir).into_iter().flatten().filter_map(Result::ok).map(|entry| entry.path()).collect();
+    paths.sort();
+    paths.into_iter().find_map(|path| {
+        let device = Device::open(&path).ok()?;
+        let named = name.is_none_or(|name| device.name() == Some(name));
+        let has_led = device.supported_leds().is_some_and(|leds| leds.contains(led));
+        (named && has_led).then_some((path, device))
+    })
+}
+
+fn drive(led: LedType, path: &Path, mut device: Device, receiver: Receiver<Command>) {
+    let original = device.get_led_state().is_ok_and(|leds| leds.contains(led));
+    info!("Lighting {:?} of {} at full lock", led, path.display());
+    // warned about once, a keyboard unplugged fails every time
+    let mut failed = false;
+    let mut set = |on: bool| {
+        if let Err(e) = device.send_events(&[InputEvent::new(EventType::LED, led.0, on.into())]) {
+            if !std::mem::replace(&mut failed, true) {
+                warn!("Failed to set {:?} of {}:  {}", led, path.display(), e);
+            }
+        }
+    };
+    let mut lit = original;
+    for command in receiver {
+        match command {
+            Command::Lit(on) => lit = on,
+            Command::Blink => {
+                for _ in 0..BLINKS {
+                    set(!lit);
+                    std::thread::sleep(BLINK);
+                    set(lit);
+                    std::thread::sleep(BLINK);
+                }
+                continue;
+            }
+            Command::Restore => lit = original,
+        }
+        set(lit);
+    }
+    set(original);
+}
@@ -0,0 +1,218 @@
+use env_logger::filter::{Builder as FilterBuilder, Filter};
+use log::kv::{Error as KvError, Key, Value, VisitSource};
+use log::{warn, Level, LevelFilter, Log, Metadata, Record};
+use std::os::unix::net::UnixDatagram;
+use std::cell::RefCell;
+use std::sync::{Mutex, OnceLock};
+
+use crate::configuration::LogBackend;
+
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_SOCKET: &str = "/dev/log";
+const IDENTIFIER: &str = "mouse2joy";
+
+static LOGGER: OnceLock<&'static Logger> = OnceLock::new();
+
+thread_local! {
+    // the pipeline the thread runs, its records start with "[NAME] "
+    static PIPELINE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Where log records end up. The level filter is applied before a record gets
+// here, so all sinks see exactly the same records.
+enum Sink {
+    Stderr(env_logger::Logger),
+    Journald(UnixDatagram),
+    Syslog(UnixDatagram),
+}
+
+impl Sink {
+    // falls back from journald to syslog to stderr when a socket is missing
+    fn open(backend: LogBackend) -> Self {
+        let journal = || connect(JOURNALD_SOCKET).map(Sink::Journald);
+        let syslog = || connect(SYSLOG_SOCKET).map(Sink::Syslog);
+        let sink = match backend {
+            LogBackend::Auto if std::env::var_os("JOURNAL_STREAM").is_some() => journal().or_else(syslog),
+            LogBackend::Auto | LogBackend::Stderr => None,
+            LogBackend::Journald => journal().or_else(syslog),
+            LogBackend::Syslog => syslog(),
+        };
+        sink.unwrap_or_else(|| {
+            // the filter in front of it decides what gets printed
+            Sink::Stderr(env_logger::Builder::new().filter_level(LevelFilter::Trace).build())
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Sink::Stderr(_) => "stderr",
+            Sink::Journald(_) => "journald",
+            Sink::Syslog(_) => "syslog",
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        // a failing log sink has nowhere to report to
+        let _ = match self {
+            Sink::Stderr(logger) => {
+                logger.log(record);
+                Ok(0)
+            }
+            Sink::Journald(socket) => socket.send(&journald_entry(record)),
+            Sink::Syslog(socket) => socket.send(syslog_entry(record).as_bytes()),
+        };
+    }
+}
+
+fn connect(path: &str) -> Option<UnixDatagram> {
+    let socket = UnixDatagram::unbound().ok()?;
+    socket.connect(path).ok()?;
+    Some(socket)
+}
+
+struct Logger {
+    filter: Filter,
+    sink: Mutex<Sink>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+        let sink = self.sink.lock().unwrap_or_else(|e| e.into_inner());
+        PIPELINE.with_borrow(|pipeline| match pipeline {
+            Some(name) => sink.log(&record.to_builder().args(format_args!("[{}] {}", name, record.args())).build()),
+            None => sink.log(record),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+// Install the logger with the automatically detected backend: journald when
+// started by systemd, stderr otherwise. Everything is logged unless RUST_LOG
+// says otherwise.
+pub fn init() {
+    let mut filter = FilterBuilder::new();
+    filter.filter_level(LevelFilter::Trace);
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        filter.parse(&filters);
+    }
+    let logger: &'static Logger = Box::leak(Box::new(Logger {
+        filter: filter.build(),
+        sink: Mutex::new(Sink::open(LogBackend::Auto)),
+    }));
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(logger.filter.filter());
+        let _ = LOGGER.set(logger);
+    }
+}
+
+// switch to the configured backend once the configuration is loaded
+pub fn set_backend(backend: LogBackend) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let sink = Sink::open(backend);
+    let fell_back = match backend {
+        LogBackend::Journald => !matches!(sink, Sink::Journald(_)),
+        LogBackend::Syslog => !matches!(sink, Sink::Syslog(_)),
+        LogBackend::Auto | LogBackend::Stderr => false,
+    };
+    let name = sink.name();
+    *logger.sink.lock().unwrap_or_else(|e| e.into_inner()) = sink;
+    if fell_back {
+        warn!("Requested log backend is not available, logging to {}", name);
+    }
+}
+
+// stderr goes away with the terminal of a daemon, journald (or syslog) takes
+// over unless one of them is used already
+pub fn detach() {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    let stderr = matches!(*logger.sink.lock().unwrap_or_else(|e| e.into_inner()), Sink::Stderr(_));
+    if stderr {
+        set_backend(LogBackend::Journald);
+    }
+}
+
+// prefix what this thread logs with the pipeline name, None stops
+pub fn set_pipeline(name: Option<&str>) {
+    PIPELINE.set(name.map(String::from));
+}
+
+// syslog severity of a log level
+fn priority(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+// journald native protocol: one datagram of FIELD=value lines, values with
+// newlines use the length prefixed binary form
+fn journald_entry(record: &Record) -> Vec<u8> {
+    let mut entry = Vec::new();
+    field(&mut entry, "PRIORITY", &priority(record.level()).to_string());
+    field(&mut entry, "MESSAGE", &record.args().to_string());
+    field(&mut entry, "SYSLOG_IDENTIFIER", IDENTIFIER);
+    field(&mut entry, "SYSLOG_PID", &std::process::id().to_string());
+    field(&mut entry, "TARGET", record.target());
+    if let Some(file) = record.file() {
+        field(&mut entry, "CODE_FILE", file);
+    }
+    if let Some(line) = record.line() {
+        field(&mut entry, "CODE_LINE", &line.to_string());
+    }
+
+    // key-values of the record become fields of their own, e.g. STEERING
+    struct Fields<'a>(&'a mut Vec<u8>);
+    impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), KvError> {
+            let name: String = key
+                .as_str()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect();
+            field(self.0, name.trim_start_matches('_'), &value.to_string());
+            Ok(())
+        }
+    }
+    let _ = record.key_values().visit(&mut Fields(&mut entry));
+    entry
+}
+
+fn field(entry: &mut Vec<u8>, name: &str, value: &str) {
+    if name.is_empty() {
+        return;
+    }
+    entry.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
+
+// "<14>mouse2joy[1234]: message" with the user facility
+fn syslog_entry(record: &Record) -> String {
+    format!(
+        "<{}>{}[{}]: {}",
+        8 + priority(record.level()),
+        IDENTIFIER,
+        std::process::id(),
+        record.args()
+    )
+}
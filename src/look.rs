@@ -0,0 +1,115 @@
+use evdev::{InputEvent, InputId, Key, RelativeAxisType};
+use log::info;
+
+use crate::configuration::{MouseLookConfig, ScrollMode};
+use crate::mapping::parse_key;
+use crate::output::DeviceSpec;
+use crate::Mouse2JoyError;
+
+// what becomes of a movement axis of the steering mouse
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Route {
+    // handled by mouse2joy, REL_X steers and the wheel scrolls
+    Wheel,
+    // passed on to the virtual pointer as read
+    Pointer,
+}
+
+// Mouse-look: REL_X keeps steering while the other axes named in [mouse_look]
+// go to a virtual pointer of their own, so the game gets real mouse movement
+// on them. The axes of one report are split, every count ends up in exactly
+// one place. With a hold key they only do so while it is held, and are
+// dropped otherwise.
+pub struct MouseLook {
+    forward: Vec<RelativeAxisType>,
+    hold: Option<Key>,
+    held: bool,
+}
+
+impl MouseLook {
+    pub fn new(conf: &MouseLookConfig, scroll: ScrollMode) -> Result<Option<Self>, Mouse2JoyError> {
+        if !conf.enabled {
+            return Ok(None);
+        }
+        let mut forward = Vec::new();
+        for name in &conf.forward {
+            let axis = name
+                .parse::<RelativeAxisType>()
+                .map_err(|_| Mouse2JoyError::InvalidConfig(format!("unknown axis \"{}\" in [mouse_look]", name)))?;
+            if axis == RelativeAxisType::REL_X {
+                return Err(Mouse2JoyError::InvalidConfig(String::from(
+                    "[mouse_look] can't forward REL_X, it steers",
+                )));
+            }
+            let wheel = [RelativeAxisType::REL_WHEEL, RelativeAxisType::REL_WHEEL_HI_RES];
+            if scroll != ScrollMode::None && wheel.contains(&axis) {
+                return Err(Mouse2JoyError::InvalidConfig(format!(
+                    "[mouse_look] can't forward {}, the scroll throttle reads the wheel",
+                    name
+                )));
+            }
+            if !forward.contains(&axis) {
+                forward.push(axis);
+            }
+        }
+        if forward.is_empty() {
+            return Err(Mouse2JoyError::InvalidConfig(String::from("[mouse_look] forwards no axis")));
+        }
+        let hold = (!conf.hold.is_empty()).then(|| parse_key(&conf.hold)).transpose()?;
+        let axes: Vec<String> = forward.iter().map(|axis| format!("{:?}", axis)).collect();
+        match hold {
+            Some(hold) => info!("Forwarding {} to the virtual pointer while {:?} is held", axes.join(", "), hold),
+            None => info!("Forwarding {} to the virtual pointer", axes.join(", ")),
+        }
+        Ok(Some(Self { forward, hold, held: false }))
+    }
+
+    pub fn keys(&self) -> Vec<Key> {
+        self.hold.into_iter().collect()
+    }
+
+    // The virtual pointer, with what udev needs to take it for a mouse. Its
+    // product ID is past the ones --tag-joystick marks as joysticks.
+    pub fn spec(&self, name: &str, id: InputId) -> DeviceSpec {
+        let mut rel = vec![RelativeAxisType::REL_X, RelativeAxisType::REL_Y];
+        for axis in &self.forward {
+            if !rel.contains(axis) {
+                rel.push(*axis);
+            }
+        }
+        DeviceSpec {
+            name: format!("{} look", name),
+            id: InputId::new(id.bus_type(), id.vendor(), id.product() + 3, id.version()),
+            axes: Vec::new(),
+            rel,
+            buttons: vec![Key::BTN_LEFT],
+        }
+    }
+
+    pub fn route(&self, axis: RelativeAxisType) -> Route {
+        if self.forward.contains(&axis) {
+            Route::Pointer
+        } else {
+            Route::Wheel
+        }
+    }
+
+    // the event for the pointer, None while the hold key is up
+    pub fn forward(&self, event: &InputEvent) -> Option<InputEvent> {
+        (self.hold.is_none() || self.held).then_some(*event)
+    }
+
+    // true if the key is the hold key
+    pub fn handle_key(&mut self, key: Key, value: i32) -> bool {
+        if self.hold != Some(key) {
+            return false;
+        }
+        self.held = value != 0;
+        true
+    }
+
+    // paused or the failsafe tripped, the hold key may come up unseen
+    pub fn release(&mut self) {
+        self.held = false;
+    }
+}
@@ -0,0 +1,172 @@
+use evdev::{EventType, InputEvent, Key, UinputAbsSetup};
+use log::{info, warn};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::configuration::ButtonMapping;
+use crate::mapping::{parse_button, parse_key};
+use crate::Mouse2JoyError;
+
+mod step;
+pub use step::Step;
+
+// Macros: a [buttons] source mapped to { macro = "NAME" } plays the steps of
+// [macros] NAME, e.g. clutch, ignition held for a second, release. The steps
+// run from the loop's timer like the pedal ramps, never sleeping in it.
+// Pressing a trigger of a running macro cancels it, so each runs at most
+// once at a time. When it ends or is cancelled its buttons are released and
+// its axes go back to the value they had before it first set them, unless
+// the step says "keep".
+pub struct Macros {
+    macros: Vec<(String, Vec<Step>)>,
+    triggers: HashMap<Key, usize>,
+    running: Vec<Run>,
+}
+
+struct Run {
+    index: usize,
+    next: usize,
+    due: Instant,
+    pressed: Vec<Key>,
+    restore: Vec<(u16, i32)>,
+}
+
+impl Macros {
+    pub fn new(
+        conf: &BTreeMap<String, Vec<String>>,
+        mappings: &BTreeMap<String, ButtonMapping>,
+        buttons: &[Key],
+        axes: &[UinputAbsSetup],
+    ) -> Result<Option<Self>, Mouse2JoyError> {
+        let mut macros = Vec::new();
+        for (name, specs) in conf {
+            if specs.is_empty() {
+                return Err(Mouse2JoyError::InvalidConfig(format!("[macros] {} has no steps", name)));
+            }
+            let mut steps = Vec::new();
+            for (number, spec) in specs.iter().enumerate() {
+                let invalid = |e: String| {
+                    Mouse2JoyError::InvalidConfig(format!("[macros] {}, step {}: {}", name, number + 1, e))
+                };
+                let step = Step::parse(spec).map_err(invalid)?;
+                match step {
+                    Step::Press(button) | Step::Release(button) => {
+                        parse_button(&format!("{:?}", button), buttons).map_err(|e| invalid(e.to_string()))?;
+                    }
+                    Step::Axis { axis, value, .. } => {
+                        let Some(setup) = axes.iter().find(|setup| setup.code() == axis.0) else {
+                            return Err(invalid(format!("{:?} is not an axis of the virtual device", axis)));
+                        };
+                        let (min, max) = (setup.absinfo().minimum(), setup.absinfo().maximum());
+                        if !(min..=max).contains(&value) {
+                            return Err(invalid(format!("{} is outside {:?}'s range {} to {}", value, axis, min, max)));
+                        }
+                    }
+                    Step::Wait(_) => {}
+                }
+                steps.push(step);
+            }
+            macros.push((name.clone(), steps));
+        }
+        let mut triggers = HashMap::new();
+        for (source, mapping) in mappings {
+            let ButtonMapping::Macro { name } = mapping else {
+                continue;
+            };
+            let Some(index) = macros.iter().position(|(known, _)| known == name) else {
+                return Err(Mouse2JoyError::InvalidConfig(format!(
+                    "[buttons] maps {} to the unknown macro \"{}\"",
+                    source, name
+                )));
+            };
+            triggers.insert(parse_key(source)?, index);
+        }
+        let mapped: HashSet<usize> = triggers.values().copied().collect();
+        for (_, (name, _)) in macros.iter().enumerate().filter(|(index, _)| !mapped.contains(index)) {
+            warn!("[macros] {} isn't mapped to any source", name);
+        }
+        if triggers.is_empty() {
+            return Ok(None);
+        }
+        info!("{} macro(s) mapped", mapped.len());
+        Ok(Some(Self { macros, triggers, running: Vec::new() }))
+    }
+
+    pub fn keys(&self) -> Vec<Key> {
+        self.triggers.keys().copied().collect()
+    }
+
+    // Starts the macro of a trigger or, if it is running, cancels it. None
+    // if the key isn't a trigger, repeats and releases do nothing.
+    pub fn handle_key(&mut self, key: Key, value: i32, now: Instant) -> Option<Vec<InputEvent>> {
+        let index = *self.triggers.get(&key)?;
+        if value != 1 {
+            return Some(Vec::new());
+        }
+        if let Some(position) = self.running.iter().position(|run| run.index == index) {
+            info!("Cancelled the macro {}", self.macros[index].0);
+            return Some(undo(self.running.remove(position)));
+        }
+        self.running.push(Run { index, next: 0, due: now, pressed: Vec::new(), restore: Vec::new() });
+        Some(Vec::new())
+    }
+
+    // the steps that are due, `current` gives the value of an axis before a
+    // macro first sets it
+    pub fn tick(&mut self, now: Instant, current: impl Fn(u16) -> i32) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        let mut finished = Vec::new();
+        for (position, run) in self.running.iter_mut().enumerate() {
+            let steps = &self.macros[run.index].1;
+            while run.due <= now {
+                let Some(step) = steps.get(run.next) else {
+                    finished.push(position);
+                    break;
+                };
+                run.next += 1;
+                match *step {
+                    Step::Press(button) => {
+                        events.push(InputEvent::new(EventType::KEY, button.code(), 1));
+                        if !run.pressed.contains(&button) {
+                            run.pressed.push(button);
+                        }
+                    }
+                    Step::Release(button) => {
+                        events.push(InputEvent::new(EventType::KEY, button.code(), 0));
+                        run.pressed.retain(|pressed| *pressed != button);
+                    }
+                    // from when it was due, so the waits don't add up lag
+                    Step::Wait(wait) => run.due += wait,
+                    Step::Axis { axis, value, keep } => {
+                        let saved = run.restore.iter().any(|(code, _)| *code == axis.0);
+                        if !keep && !saved {
+                            run.restore.push((axis.0, current(axis.0)));
+                        }
+                        events.push(InputEvent::new(EventType::ABSOLUTE, axis.0, value));
+                    }
+                }
+            }
+        }
+        for position in finished.into_iter().rev() {
+            events.extend(undo(self.running.remove(position)));
+        }
+        events
+    }
+
+    // when the next step of a running macro is due
+    pub fn wakeup(&self, now: Instant) -> Option<Duration> {
+        self.running.iter().map(|run| run.due.saturating_duration_since(now)).min()
+    }
+
+    // paused or the failsafe tripped, every running macro is cancelled
+    pub fn cancel(&mut self) -> Vec<InputEvent> {
+        self.running.drain(..).flat_map(undo).collect()
+    }
+}
+
+// the buttons a macro still holds up and its axes back
+fn undo(run: Run) -> Vec<InputEvent> {
+    let released = run.pressed.iter().map(|button| InputEvent::new(EventType::KEY, button.code(), 0));
+    let restored = run.restore.iter().map(|(code, value)| InputEvent::new(EventType::ABSOLUTE, *code, *value));
+    released.chain(restored).collect()
+}
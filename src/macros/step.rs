@@ -0,0 +1,38 @@
+use evdev::{AbsoluteAxisType, Key};
+use std::time::Duration;
+
+// One step of a macro, written like "press BTN_TL2", "wait 1000",
+// "release BTN_TL2" or "axis ABS_GAS 32767". An axis step with a trailing
+// "keep" leaves the axis where it put it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Step {
+    Press(Key),
+    Release(Key),
+    Wait(Duration),
+    Axis { axis: AbsoluteAxisType, value: i32, keep: bool },
+}
+
+impl Step {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let words: Vec<&str> = spec.split_whitespace().collect();
+        let key = |name: &str| name.parse::<Key>().map_err(|_| format!("unknown button \"{}\"", name));
+        match words[..] {
+            ["press", name] => key(name).map(Step::Press),
+            ["release", name] => key(name).map(Step::Release),
+            ["wait", ms] => {
+                let ms = ms.parse().map_err(|_| format!("invalid number of milliseconds \"{}\"", ms))?;
+                Ok(Step::Wait(Duration::from_millis(ms)))
+            }
+            ["axis", axis, value] | ["axis", axis, value, "keep"] => {
+                let axis = axis.parse().map_err(|_| format!("unknown axis \"{}\"", axis))?;
+                let value = value.parse().map_err(|_| format!("invalid axis value \"{}\"", value))?;
+                Ok(Step::Axis { axis, value, keep: words.len() == 4 })
+            }
+            [] => Err(String::from("empty step")),
+            _ => Err(format!(
+                "\"{}\" is none of \"press BUTTON\", \"release BUTTON\", \"wait MS\" and \"axis AXIS VALUE [keep]\"",
+                spec
+            )),
+        }
+    }
+}
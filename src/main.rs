@@ -1,16 +1,138 @@
 use evdev::{
-    uinput::VirtualDevice, uinput::VirtualDeviceBuilder, AbsInfo, AbsoluteAxisType, Device,
-    EventType, InputEvent, InputEventKind, Key, RelativeAxisType, UinputAbsSetup,
+    uinput::VirtualDevice, AbsInfo, AbsoluteAxisType, BusType,
+    Device, EventType, InputEventKind, InputId, Key, RelativeAxisType, UinputAbsSetup,
 };
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
 use std::fs;
+use std::io::IsTerminal;
+use std::os::fd::AsFd;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread::LocalKey;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
 use thiserror::Error;
-use log::{info, warn, error, LevelFilter};
-use env_logger::Builder;
+use log::{debug, info, warn, error};
 
+mod aggregate;
+use aggregate::{InputWindow, Rates};
+mod autopause;
+use autopause::AutoPause;
+mod battery;
+mod bench;
+use bench::{Stage, Stages};
+use battery::Battery;
+mod chords;
+use chords::Chords;
+mod classify;
+use classify::Class;
+mod cli;
+use cli::Args;
 mod configuration;
-use configuration::Config;
+mod control;
+use control::{Reply, Request, Status};
+mod cue;
+mod curve;
+mod daemon;
+mod debounce;
+use debounce::Debounce;
+mod datalog;
+use datalog::DataLog;
+#[cfg(any(feature = "notifications", feature = "inhibit"))]
+mod dbus;
+mod doctor;
+use cue::LockCue;
+use configuration::{
+    ButtonMapping, Config, ConfigSource, InputBackend, InputConfig, InputRole, Lookup, OutputLayout, PromptDefault,
+    ScrollMode,
+};
+mod failsafe;
+use failsafe::Failsafe;
+mod filter;
+mod flick;
+use flick::Flick;
+use filter::DeviceFilter;
+mod guard;
+use guard::{CreateDevice, Guard};
+mod hat;
+use hat::Hat;
+mod hidraw;
+mod hotkey;
+mod idle;
+use idle::IdleExit;
+mod inhibit;
+use inhibit::IdleInhibit;
+use hotkey::{Action, Hotkeys};
+mod input;
+mod keys;
+mod launch;
+mod learn;
+mod led;
+use led::LockLed;
+use input::{InputDevice, Inputs, KeySet, Script};
+use keys::KeyMapping;
+mod logging;
+mod macros;
+use macros::Macros;
+mod look;
+use look::{MouseLook, Route};
+mod mapping;
+use mapping::{is_trigger_happy, parse_key, Mapping};
+mod notify;
+use notify::{Notice, Notifier};
+mod output;
+use output::{DeviceSpec, Output, VirtualDevices};
+mod overlay;
+use overlay::Overlay;
+mod pipeline;
+use pipeline::Pipeline;
+use profile::ProfileSwitch;
+mod pedals;
+use pedals::{Pedals, PEDAL_AXES};
+mod profile;
+mod publisher;
+use publisher::{Frame, Publisher};
+mod scroll;
+use scroll::{ScrollThrottle, Wheel};
+mod scripting;
+use scripting::{ScriptHook, TickInput};
+mod privileges;
+mod realtime;
+mod resume;
+mod rotation;
+use rotation::Rotation;
+use resume::ResumeDetector;
+mod sdl;
+mod seat;
+use seat::SeatInput;
+mod selftest;
+mod spikes;
+use spikes::SpikeFilter;
+mod steering;
+use steering::{Steering, STEERING_MAX};
+mod sweep;
+use sweep::Sweep;
+mod telemetry;
+use telemetry::Telemetry;
+mod trace;
+use trace::Tracer;
+mod udev;
+mod watchdog;
 
 const VJOYSTICK_NAME: &str = "mouse2joy";
+const INPUT_DIR: &str = "/dev/input";
+
+// bus, vendor, product and version of the virtual device
+fn vjoystick_id() -> InputId {
+    InputId::new(BusType::BUS_USB, 0x1234, 0x5678, 0x111)
+}
+
+// lock to lock rotation represented by the steering axis range
+const WHEEL_ROTATION: f32 = 900.0;
 
 // virtual steering wheel buttons (relevant to steering wheels)
 static KEYS: [Key; 6] = [
@@ -22,153 +144,1825 @@ static KEYS: [Key; 6] = [
     Key::BTN_TR2,
 ];
 
+// joystick-class buttons (BTN_JOYSTICK range), the legacy js interface and
+// older games only consider a device a joystick when it has these
+static JOYSTICK_KEYS: [Key; 12] = [
+    Key::BTN_TRIGGER,
+    Key::BTN_THUMB,
+    Key::BTN_THUMB2,
+    Key::BTN_TOP,
+    Key::BTN_TOP2,
+    Key::BTN_PINKIE,
+    Key::BTN_BASE,
+    Key::BTN_BASE2,
+    Key::BTN_BASE3,
+    Key::BTN_BASE4,
+    Key::BTN_BASE5,
+    Key::BTN_BASE6,
+];
+
+// scans of the mouse devices before giving up on a failing selection
+const MOUSE_ATTEMPTS: u32 = 3;
+
+// the kernel defines BTN_TRIGGER_HAPPY1 up to BTN_TRIGGER_HAPPY40
+const MAX_EXTRA_BUTTONS: u16 = 40;
+// a chord member held back longer than this feels like lag
+const MAX_CHORD_WINDOW_MS: u32 = 500;
+// chatter comes within a few milliseconds, a release held back longer
+// than this feels like a stuck button
+const MAX_DEBOUNCE_MS: u32 = 200;
+
 #[derive(Error, Debug)]
 pub enum Mouse2JoyError {
     #[error("Failed to find a mouse device.  Make sure you are running the application with root priviledges.")]
     NoMouseError,
 
+    #[error("Failed to find a keyboard able to send the configured keys.  Make sure you are running the application with root priviledges.")]
+    NoKeyboardError,
+
     #[error("Failed to read a mouse input")]
     FailedToReadInput,
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
+
+    #[error("Failed to write {0}: {1}")]
+    FailedToWrite(String, std::io::Error),
+
+    #[error("Failed to recreate the virtual steering wheel after repeated write failures: {0}")]
+    FailedToRecreateDevice(std::io::Error),
+
+    #[error("No mouse was selected before the prompt timed out")]
+    NoSelectionError,
+
+    #[error("Failed to use the mouse \"{0}\": {1}")]
+    FailedToUseMouse(String, std::io::Error),
+
+    #[error("Failed to create the virtual steering wheel: {0}.  Make sure uinput is loaded and you are running the application with root priviledges.")]
+    FailedToCreateDevice(std::io::Error),
+
+    #[error("Self test failed: {0}")]
+    SelfTestFailed(String),
+
+    #[error("--doctor found {0} blocking problem(s)")]
+    DoctorFoundProblems(usize),
+
+    #[error("No running mouse2joy answered: {0}")]
+    NotRunning(String),
+
+    #[error("Failed to stop running as root: {0}")]
+    FailedToDropPrivileges(String),
+}
+
+impl Mouse2JoyError {
+    // Process exit code for each failure class. These are documented in the
+    // README and --help, never renumber them. Panics exit with Rust's 101.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Mouse2JoyError::InvalidArguments(_) => 2,
+            Mouse2JoyError::InvalidConfig(_) => 3,
+            Mouse2JoyError::NoMouseError => 4,
+            Mouse2JoyError::NoKeyboardError => 5,
+            Mouse2JoyError::FailedToReadInput => 6,
+            Mouse2JoyError::FailedToCreateDevice(_) => 7,
+            Mouse2JoyError::FailedToWrite(_, _) => 8,
+            Mouse2JoyError::NoSelectionError => 9,
+            Mouse2JoyError::FailedToUseMouse(_, _) => 10,
+            Mouse2JoyError::FailedToRecreateDevice(_) => 11,
+            Mouse2JoyError::SelfTestFailed(_) => 12,
+            Mouse2JoyError::DoctorFoundProblems(_) => 13,
+            Mouse2JoyError::NotRunning(_) => 15,
+            Mouse2JoyError::FailedToDropPrivileges(_) => 16,
+        }
+    }
 }
 
-fn main() -> Result<(), Mouse2JoyError> {
+// set by SIGINT/SIGTERM, the main loop then shuts down cleanly
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+// counted up by SIGUSR1 and SIGUSR2, the loop of every pipeline acts on the
+// ones it hasn't seen yet
+static PAUSE_TOGGLES: AtomicUsize = AtomicUsize::new(0);
+static STATE_DUMPS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static PAUSES_SEEN: Cell<usize> = const { Cell::new(0) };
+    static DUMPS_SEEN: Cell<usize> = const { Cell::new(0) };
+}
+
+// whether the count moved since this thread last looked
+fn take_signal(count: &AtomicUsize, seen: &'static LocalKey<Cell<usize>>) -> bool {
+    let count = count.load(Ordering::Relaxed);
+    seen.with(|seen| seen.replace(count) != count)
+}
+
+// a signal arrived that the loop on this thread hasn't acted on yet
+fn signal_pending() -> bool {
+    SHUTDOWN.load(Ordering::Relaxed)
+        || PAUSES_SEEN.with(Cell::get) != PAUSE_TOGGLES.load(Ordering::Relaxed)
+        || DUMPS_SEEN.with(Cell::get) != STATE_DUMPS.load(Ordering::Relaxed)
+}
+
+fn main() -> ExitCode {
 
     // initialize logger
-    Builder::new()
-        .filter_level(LevelFilter::Trace)
-        .init();
+    logging::init();
+    // log panics, unwinding then drops the guard which releases the devices
+    std::panic::set_hook(Box::new(|info| error!("{}", info)));
 
-    let conf = load_config();
-    info!("sensitivity: {}", conf.sensitivity);
-    
-    // find all input devices that can be used as a mouse
-    let mut mouse_devices:  Vec<Device> = fs::read_dir("/dev/input")
-        .unwrap()
-        .filter_map(Result::ok)
-        .filter_map(|entry| entry.path().into_os_string().to_str().map(String::from))
-        .filter_map(|path| {
-            Device::open(&path)
-                .ok()
-                .filter(|device| device.supported_events().contains(EventType::RELATIVE))
-        })
-        .collect();
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => ExitCode::from(e.exit_code()),
+    }
+}
 
-    if mouse_devices.is_empty() {
-        error!("{}", Mouse2JoyError::NoMouseError);
-        return Err(Mouse2JoyError::NoMouseError);
+fn run() -> Result<(), Mouse2JoyError> {
+    let args = Args::parse().inspect_err(|e| error!("{}", e))?;
+    if args.help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if args.stop {
+        return daemon::stop().inspect_err(|e| error!("{}", e));
+    }
+    if let Some(request) = &args.request {
+        return control::send(&control::socket(args.config.as_deref()), request).inspect_err(|e| error!("{}", e));
+    }
+    if let Some(group) = &args.udev_group {
+        return udev::install(group, args.tag_joystick, args.dry_run).inspect_err(|e| error!("{}", e));
     }
 
-    // ask user which mouse to use
-    if !(mouse_devices.len() == 1) {
-        println!("Several mouses detected, please select one:");
-        for (i, mouse) in mouse_devices.iter().enumerate() {
-            println!("{}: {}", i + 1, mouse.name().unwrap_or("Unknown Device"));
+    let lookup = args.config.as_deref().map_or_else(Config::path, Lookup::given);
+    match lookup.source {
+        ConfigSource::Legacy(_) => warn!("{}", lookup),
+        ConfigSource::Given => debug!("{}", lookup),
+        _ => info!("{}", lookup),
+    }
+    let config_path = lookup.path;
+    if let Some(name) = &args.export_profile {
+        return profile::export(&config_path, name, args.export_file.as_deref()).inspect_err(|e| error!("{}", e));
+    }
+    // into the user's own file, not the system-wide one
+    if let Some(file) = &args.import_profile {
+        return profile::import(&lookup.user, file, args.force).inspect_err(|e| error!("{}", e));
+    }
+    if args.migrate_config {
+        return migrate_config(&config_path).inspect_err(|e| error!("{}", e));
+    }
+    if args.doctor {
+        return doctor::run(&args, &config_path).inspect_err(|e| error!("{}", e));
+    }
+    if args.learn && args.profile.is_some() {
+        let e = Mouse2JoyError::InvalidArguments(String::from("--learn writes the base [buttons] table, not a profile"));
+        error!("{}", e);
+        return Err(e);
+    }
+    if args.calibrate_rotation && (args.profile.is_some() || args.learn) {
+        let e = Mouse2JoyError::InvalidArguments(String::from(
+            "--calibrate-rotation writes the base [rotation] angle, it goes without --profile and --learn",
+        ));
+        error!("{}", e);
+        return Err(e);
+    }
+    if args.bench.is_some() && !args.input_scripts.is_empty() {
+        let e = Mouse2JoyError::InvalidArguments(String::from(
+            "--bench makes up its input, it doesn't go with --input-script",
+        ));
+        error!("{}", e);
+        return Err(e);
+    }
+    if args.daemon {
+        check_daemon(&args).and_then(|()| daemon::start()).inspect_err(|e| error!("{}", e))?;
+    }
+    let result = start(&args, &config_path);
+    control::finish();
+    daemon::finish();
+    result
+}
+
+// --daemon only goes with running the wheel, and needs no terminal for it
+fn check_daemon(args: &Args) -> Result<(), Mouse2JoyError> {
+    let exits = [
+        (args.list_devices, "--list-devices"),
+        (args.print_sdl_mapping, "--print-sdl-mapping"),
+        (args.self_test, "--self-test"),
+        (args.learn, "--learn"),
+        (args.calibrate_rotation, "--calibrate-rotation"),
+        (args.bench.is_some(), "--bench"),
+        (args.show_curve, "--show-curve"),
+    ];
+    if let Some((_, flag)) = exits.iter().find(|(given, _)| *given) {
+        return Err(Mouse2JoyError::InvalidArguments(format!("--daemon doesn't go with {}", flag)));
+    }
+    if args.input_scripts.iter().any(|script| script.as_os_str() == "-") {
+        return Err(Mouse2JoyError::InvalidArguments(String::from(
+            "--daemon lets go of the terminal, it can't read --input-script from stdin",
+        )));
+    }
+    Ok(())
+}
+
+// Load and check the configuration, set everything up and run the main loop.
+// With --doctor it stops once the configuration is known to be valid.
+fn start(args: &Args, config_path: &Path) -> Result<(), Mouse2JoyError> {
+    start_pipeline(args, config_path, None)
+}
+
+// The one wheel, or one of the [[pipelines]] with its profile applied and its
+// name on the virtual devices.
+fn start_pipeline(args: &Args, config_path: &Path, pipeline: Option<&Pipeline>) -> Result<(), Mouse2JoyError> {
+    let profile = match pipeline {
+        Some(pipeline) => pipeline.profile,
+        None => args.profile.as_deref(),
+    };
+    let conf = match profile {
+        Some(name) => {
+            let conf = profile::load(config_path, name).inspect_err(|e| error!("{}", e))?;
+            info!("Using profile \"{}\" from {}", name, config_path.display());
+            conf
         }
+        None => load_config(config_path)
+            .and_then(|conf| {
+                if conf.launch.is_empty() {
+                    Ok(conf)
+                } else {
+                    Err(profile::launch_outside_profile(config_path))
+                }
+            })
+            .inspect_err(|e| error!("{}", e))?,
+    };
+    logging::set_backend(conf.log_backend);
+    let input_dir = args.input_dir.clone().unwrap_or_else(|| PathBuf::from(INPUT_DIR));
+    let filter = DeviceFilter::new(&conf).inspect_err(|e| error!("{}", e))?;
+    if args.list_devices {
+        list_devices(&input_dir, &filter);
+        return Ok(());
+    }
+    // --learn, --calibrate-rotation, --self-test, --bench and --show-curve stay
+    // with the base settings
+    let base_only =
+        args.learn || args.calibrate_rotation || args.self_test || args.bench.is_some() || args.show_curve;
+    if pipeline.is_none() && !conf.pipelines.is_empty() && !base_only {
+        return pipeline::run(args, config_path, &conf.pipelines);
     }
+    if pipeline.is_some() && conf.input_backend == InputBackend::X11 {
+        let e = Mouse2JoyError::InvalidConfig(String::from(
+            "input_backend = \"x11\" reads the whole seat, it can't feed one of several [[pipelines]]",
+        ));
+        error!("{}", e);
+        return Err(e);
+    }
+    info!("sensitivity: {}", conf.sensitivity);
 
-    let index = input_in_range(1, mouse_devices.len());
-    let mut mouse = mouse_devices. remove(index - 1);
-    info!("Using \"{}\" as input device", mouse.name().unwrap_or("Unknown Device"));
+    let buttons = virtual_buttons(&conf).inspect_err(|e| error!("{}", e))?;
+    let mut mapping = Mapping::new(&conf.buttons, &buttons, &PEDAL_AXES).inspect_err(|e| error!("{}", e))?;
+    if conf.chord_window_ms > MAX_CHORD_WINDOW_MS {
+        let e = Mouse2JoyError::InvalidConfig(format!(
+            "chord_window_ms must be at most {}, got {}",
+            MAX_CHORD_WINDOW_MS, conf.chord_window_ms
+        ));
+        error!("{}", e);
+        return Err(e);
+    }
+    let mut chords = Chords::new(mapping.chords().to_vec(), Duration::from_millis(conf.chord_window_ms.into()));
+    let mut debounce = Debounce::new(debounce_intervals(&conf.debounce).inspect_err(|e| error!("{}", e))?);
+    let mut key_mapping = KeyMapping::new(&conf.keys).inspect_err(|e| error!("{}", e))?;
+    let mut pedals = Pedals::new(&conf.pedals, &mapping, Instant::now()).inspect_err(|e| error!("{}", e))?;
+    let mut hat = Hat::new(&conf.hat).inspect_err(|e| error!("{}", e))?;
+    let mut steering = Steering::new(&conf, &mapping, Instant::now()).inspect_err(|e| error!("{}", e))?;
+    check_inputs(&conf, steering.mouse_enabled()).inspect_err(|e| error!("{}", e))?;
+    if args.show_curve {
+        return curve::show(&conf, &mapping).inspect_err(|e| error!("{}", e));
+    }
+    realtime::check(&conf).inspect_err(|e| error!("{}", e))?;
+    let run_as = privileges::target(&conf, args.keep_root).inspect_err(|e| error!("{}", e))?;
+    let input_window = InputWindow::new(&conf).inspect_err(|e| error!("{}", e))?;
+    let mut spikes = SpikeFilter::new(&conf.spike_rejection).inspect_err(|e| error!("{}", e))?;
+    let rotation = Rotation::new(&conf.rotation).inspect_err(|e| error!("{}", e))?;
+    if input_for(&conf, InputRole::Pedals).is_some() {
+        pedals.enable_mouse();
+    }
+    let mut scroll_throttle = match conf.scroll.mode {
+        ScrollMode::Throttle => Some(ScrollThrottle::new(&conf.scroll).inspect_err(|e| error!("{}", e))?),
+        ScrollMode::None => None,
+    };
+    let mut mouse_look = MouseLook::new(&conf.mouse_look, conf.scroll.mode).inspect_err(|e| error!("{}", e))?;
+    if mouse_look.is_some() {
+        check_mouse_look(&conf, steering.mouse_enabled()).inspect_err(|e| error!("{}", e))?;
+    }
+    let mut flick = Flick::new(&conf.flick, &buttons).inspect_err(|e| error!("{}", e))?;
+    if flick.is_some() && !steering.mouse_enabled() {
+        let e = Mouse2JoyError::InvalidConfig(String::from(
+            "[flick] looks at the steering mouse, mouse_steering is off",
+        ));
+        error!("{}", e);
+        return Err(e);
+    }
+    let emergency = watchdog::combo(&conf.emergency_release).inspect_err(|e| error!("{}", e))?;
+    let mut auto_pause = AutoPause::new(&conf.auto_pause, Instant::now()).inspect_err(|e| error!("{}", e))?;
+    let mut lock_cue = LockCue::new(&conf.full_lock_cue).inspect_err(|e| error!("{}", e))?;
+    let mut lock_led =
+        LockLed::new(&conf.lock_led, conf.full_lock_cue.hysteresis, &input_dir).inspect_err(|e| error!("{}", e))?;
+    let mut publisher = Publisher::new(&conf.publish).inspect_err(|e| error!("{}", e))?;
+    let overlay = Overlay::new(&conf.overlay).inspect_err(|e| error!("{}", e))?;
+    let mut notifier = Notifier::new(&conf.notifications);
+    let mut idle_inhibit = IdleInhibit::new(&conf.idle_inhibit).inspect_err(|e| error!("{}", e))?;
+    let telemetry = Telemetry::new(&conf.telemetry).inspect_err(|e| error!("{}", e))?;
+    let mut hotkeys = Hotkeys::new(&conf.hotkeys).inspect_err(|e| error!("{}", e))?;
+    let hotkey_bound =
+        |action| hotkeys.as_ref().is_some_and(|hotkeys| hotkeys.bindings().any(|(_, bound)| bound == action));
+    let mut profile_switch =
+        ProfileSwitch::new(&conf.profile_keys, config_path, profile, hotkey_bound(Action::NextProfile))
+            .inspect_err(|e| error!("{}", e))?;
+    let device_configs = profile::check_devices(config_path, profile).inspect_err(|e| error!("{}", e))?;
+    // a profile or device section that can't be applied fails now rather
+    // than mid-game
+    for profile in profile_switch.iter().flat_map(ProfileSwitch::configs).chain(&device_configs) {
+        Steering::new(profile, &mapping, Instant::now())
+            .and_then(|_| Pedals::new(&profile.pedals, &mapping, Instant::now()))
+            .and_then(|_| match scroll_throttle {
+                Some(_) => ScrollThrottle::new(&profile.scroll).map(|_| ()),
+                None => Ok(()),
+            })
+            .inspect_err(|e| error!("{}", e))?;
+    }
+    // a hotkey beats a key mapped to a button, anything else is a conflict
+    let hotkey_keys = hotkeys.as_ref().map(Hotkeys::keys).unwrap_or_default();
+    if let Some(hotkeys) = &hotkeys {
+        for (combo, action) in hotkeys.bindings().filter(|(combo, _)| mapping.button_sources().contains(&combo.key)) {
+            warn!("{} is mapped in [buttons] and bound to the {} hotkey, the hotkey wins", combo, action.name());
+        }
+    }
+    // the scan sees the keys before a chord could hold them back
+    if let Some(key) = chords.iter().flat_map(Chords::members).find(|key| hotkey_keys.contains(key)) {
+        let e = Mouse2JoyError::InvalidConfig(format!("{:?} is part of a chord and bound to a hotkey", key));
+        error!("{}", e);
+        return Err(e);
+    }
 
-    // ungrab unwanted mouse devices
-    for mut device in mouse_devices {
-        device
-            .ungrab()
-            .unwrap_or_else(|e| warn!("Failed to ungrab device:  {}", e));
+    let mut axes = pedals.axes();
+    axes.extend(hat.axes());
+    if let Some(scroll_throttle) = &scroll_throttle {
+        let axis = scroll_throttle.axis();
+        if axis.code() == AbsoluteAxisType::ABS_X.0 || axes.iter().any(|other| other.code() == axis.code()) {
+            let e = Mouse2JoyError::InvalidConfig(format!(
+                "the scroll throttle axis {:?} is already in use",
+                AbsoluteAxisType(axis.code())
+            ));
+            error!("{}", e);
+            return Err(e);
+        }
+        axes.push(axis);
     }
+    let mirror = match &conf.mirror_axis {
+        Some(name) => {
+            let axis = name
+                .parse::<AbsoluteAxisType>()
+                .map_err(|_| Mouse2JoyError::InvalidConfig(format!("mirror_axis: unknown axis \"{}\"", name)))
+                .inspect_err(|e| error!("{}", e))?;
+            if axis == AbsoluteAxisType::ABS_X || axes.iter().any(|other| other.code() == axis.0) {
+                let e = Mouse2JoyError::InvalidConfig(format!("the mirror axis {:?} is already in use", axis));
+                error!("{}", e);
+                return Err(e);
+            }
+            Some(axis)
+        }
+        None => None,
+    };
 
     // set up virtual steering wheel with 900 degree rotation
     // Range: -4500 to 4500 (representing -900 to +900 degrees)
     // fuzz=0 and flat=0 for smooth input without deadzone
     let axis_info = AbsInfo::new(
         0,              // value (center)
-        -4500,          // range_min (left extreme)
-        4500,           // range_max (right extreme)
+        -STEERING_MAX,  // range_min (left extreme)
+        STEERING_MAX,   // range_max (right extreme)
         0,              // fuzz:  0 for no deadzone
         0,              // flat: 0 for no deadzone
         0               // resolution: 0 for raw values
     );
-    let mut steering_wheel = create_steering_wheel(axis_info, VJOYSTICK_NAME).unwrap();
-    info!("Virtual steering wheel created (900 degree rotation - smooth, no deadzone)");
+    let name = match pipeline {
+        Some(pipeline) => format!("{} {}", VJOYSTICK_NAME, pipeline.name),
+        None => String::from(VJOYSTICK_NAME),
+    };
+    let mut steering_axes = vec![UinputAbsSetup::new(AbsoluteAxisType::ABS_X, axis_info)];
+    steering_axes.extend(mirror.map(|axis| UinputAbsSetup::new(axis, axis_info)));
+    let specs =
+        device_specs(&name, conf.layout, &steering_axes, &axes, &buttons).inspect_err(|e| error!("{}", e))?;
+    let all_axes: Vec<UinputAbsSetup> = specs.iter().flat_map(|spec| spec.axes.iter().copied()).collect();
+    let mut macros =
+        Macros::new(&conf.macros, &conf.buttons, &buttons, &all_axes).inspect_err(|e| error!("{}", e))?;
+    let mut button_sources = mapping.button_sources();
+    button_sources.retain(|key| !hotkey_keys.contains(key));
+    check_key_conflicts(&[
+        ("hotkeys", hotkey_keys),
+        ("profile_keys", profile_switch.as_ref().map(ProfileSwitch::keys).unwrap_or_default()),
+        ("overlay", overlay.as_ref().map(Overlay::keys).unwrap_or_default()),
+        ("scroll", scroll_throttle.as_ref().map(ScrollThrottle::keys).unwrap_or_default()),
+        ("mouse_look", mouse_look.as_ref().map(MouseLook::keys).unwrap_or_default()),
+        ("pedals", pedals.keys()),
+        ("hat", hat.keys()),
+        ("steering", steering.keys()),
+        ("buttons", button_sources),
+        ("keys", key_mapping.as_ref().map(KeyMapping::sources).unwrap_or_default()),
+        ("macros", macros.as_ref().map(Macros::keys).unwrap_or_default()),
+    ])
+    .inspect_err(|e| error!("{}", e))?;
+    // a mouse on purpose, it is left out of the checks, the mapping and the
+    // self test
+    let pointer = mouse_look.as_ref().map(|look| look.spec(&name, vjoystick_id()));
+    let keyboard = key_mapping.as_ref().map(|keys| keys.spec(&name, vjoystick_id()));
+    // games only list joysticks, and a pointer would move the cursor
+    for spec in specs.iter().filter(|spec| spec.class() != Class::Joystick) {
+        warn!(
+            "udev won't take \"{}\" for a joystick ({}), games may not see it",
+            spec.name,
+            spec.class().property()
+        );
+    }
 
-    // fetch events and send them through to virtual steering wheel
-    let min:  i32 = -4500;
-    let max: i32 = 4500;
-    let mut steering_position: i32 = 0;
+    if args.doctor {
+        return Ok(());
+    }
+    if args.print_sdl_mapping {
+        // the pipelines after the first add theirs to the file
+        return print_sdl_mapping(args, &specs, pipeline.is_some_and(|pipeline| pipeline.index > 0));
+    }
+    if args.self_test {
+        let devices = VirtualDevices::create(&specs)
+            .map_err(Mouse2JoyError::FailedToCreateDevice)
+            .inspect_err(|e| error!("{}", e))?;
+        return selftest::run(devices).inspect_err(|e| error!("{}", e));
+    }
     
+    // keyboards are only read (never grabbed) when they drive a pedal or mapping
+    let mut source_keys = pedals.keys();
+    source_keys.extend(hat.keys());
+    source_keys.extend(scroll_throttle.as_ref().map(ScrollThrottle::keys).unwrap_or_default());
+    source_keys.extend(steering.keys());
+    source_keys.extend(mapping.sources());
+    source_keys.extend(profile_switch.as_ref().map(ProfileSwitch::source_keys).unwrap_or_default());
+    source_keys.extend(hotkeys.as_ref().map(Hotkeys::source_keys).unwrap_or_default());
+    source_keys.extend(overlay.as_ref().map(Overlay::keys).unwrap_or_default());
+    source_keys.extend(mouse_look.as_ref().map(MouseLook::keys).unwrap_or_default());
+    source_keys.extend(key_mapping.as_ref().map(KeyMapping::sources).unwrap_or_default());
+    source_keys.extend(macros.as_ref().map(Macros::keys).unwrap_or_default());
+
+    // any keyboard, to skip a function with Esc
+    if args.learn {
+        source_keys.push(Key::KEY_ESC);
+    }
+
+    // only known for a real mouse that reports its battery
+    let mut battery = None;
+    let mut wheel_hi_res = false;
+    let stale = Duration::from_millis(conf.stale_input_ms.into());
+    // the [device."NAME"] sections of the selected mice
+    let mut sections = Vec::new();
+    let input_script = match pipeline {
+        Some(pipeline) => args.input_scripts.get(pipeline.index),
+        None if args.input_scripts.len() > 1 => {
+            let e = Mouse2JoyError::InvalidArguments(String::from(
+                "--input-script is given once for each of the [[pipelines]], there are none",
+            ));
+            error!("{}", e);
+            return Err(e);
+        }
+        None => args.input_scripts.first(),
+    };
+    let script = match (args.bench, input_script) {
+        (Some(events), _) => Some(bench::script(events)),
+        (None, Some(path)) => Some(Script::load(path).inspect_err(|e| error!("{}", e))?),
+        (None, None) => None,
+    };
+    let inputs = match script {
+        Some(script) => Inputs::script(script, stale),
+        None if conf.input_backend == InputBackend::X11 => {
+            let seat = SeatInput::connect().map_err(|e| {
+                error!("Can't read the seat input from X:  {}", e);
+                Mouse2JoyError::FailedToReadInput
+            })?;
+            info!("Reading the seat input through {}, no mouse is grabbed", seat.version());
+            Inputs::seat(seat)
+        }
+        None => {
+            // the mice of the pipelines before this one
+            let others = pipeline.map(Pipeline::taken).unwrap_or_default();
+            let mut devices = Vec::new();
+            if steering.mouse_enabled() {
+                let (path, mouse) = select_mouse(&input_dir, &conf, &filter, InputRole::Steering, &others, pipeline)?;
+                sections.extend(profile::device_section(config_path, &mouse).inspect_err(|e| error!("{}", e))?);
+                battery = Battery::find(&mouse, &path, conf.low_battery, Instant::now());
+                wheel_hi_res = mouse
+                    .supported_relative_axes()
+                    .is_some_and(|axes| axes.contains(RelativeAxisType::REL_WHEEL_HI_RES));
+                let mut input = InputDevice::new(path, mouse, conf.grab_mouse, InputRole::Steering);
+                if conf.input_backend == InputBackend::Hidraw {
+                    input.use_hidraw();
+                }
+                devices.push(input);
+            }
+            if input_for(&conf, InputRole::Pedals).is_some() {
+                let mut taken = others.clone();
+                taken.extend(devices.iter().map(|input| input.path.clone()));
+                let (path, mouse) = select_mouse(&input_dir, &conf, &filter, InputRole::Pedals, &taken, pipeline)?;
+                sections.extend(profile::device_section(config_path, &mouse).inspect_err(|e| error!("{}", e))?);
+                devices.push(InputDevice::new(path, mouse, conf.grab_mouse, InputRole::Pedals));
+            }
+            if steering.axis().is_some() {
+                let mut taken = others.clone();
+                taken.extend(devices.iter().map(|input| input.path.clone()));
+                let (path, device) = select_mouse(&input_dir, &conf, &filter, InputRole::Axis, &taken, pipeline)?;
+                devices.push(InputDevice::new(path, device, conf.grab_mouse, InputRole::Axis));
+            }
+            // keyboards are shared, every pipeline reads its keys from them
+            if let Some(pipeline) = pipeline {
+                pipeline.take(devices.iter().map(|input| input.path.clone()));
+            }
+            if !source_keys.is_empty() {
+                let taken: Vec<PathBuf> = devices.iter().map(|input| input.path.clone()).collect();
+                let mut keyboards = find_key_devices(&input_dir, &source_keys, &taken);
+                // never grabbed, and only the configured keys are looked at,
+                // --learn takes whatever is pressed
+                if !args.learn {
+                    let allowed: KeySet = source_keys.iter().copied().collect();
+                    for keyboard in &mut keyboards {
+                        keyboard.only_keys(allowed.clone());
+                    }
+                }
+                devices.extend(keyboards);
+            }
+            if devices.is_empty() {
+                error!("{}", Mouse2JoyError::NoKeyboardError);
+                return Err(Mouse2JoyError::NoKeyboardError);
+            }
+            if let Some(emergency) = emergency {
+                watchdog::start(emergency, input_dir.clone());
+            }
+            Inputs::devices(devices, stale)
+        }
+    };
+    if let Some(pipeline) = pipeline {
+        pipeline.ready();
+    }
+    // a --profile still beats the sections, and so do the other profiles
+    if let Some(axis) = steering.axis() {
+        match inputs.axis_range(axis) {
+            Some((min, max)) => steering.set_axis_range(min, max).inspect_err(|e| error!("{}", e))?,
+            None => warn!("The range of {:?} is unknown, it won't steer", axis),
+        }
+    }
+    if !sections.is_empty() {
+        let sections: Vec<&str> = sections.iter().map(String::as_str).collect();
+        let (device_conf, changed) = profile::for_devices(config_path, profile, &sections, &conf)
+            .inspect_err(|e| error!("{}", e))?;
+        let now = Instant::now();
+        steering
+            .reconfigure(&device_conf, now)
+            .and_then(|()| pedals.reconfigure(&device_conf.pedals, now))
+            .and_then(|()| match scroll_throttle.as_mut() {
+                Some(scroll_throttle) => scroll_throttle.reconfigure(&device_conf.scroll),
+                None => Ok(()),
+            })
+            .inspect_err(|e| error!("{}", e))?;
+        if let Some(switch) = profile_switch.as_mut() {
+            switch.apply_devices(config_path, &sections).inspect_err(|e| error!("{}", e))?;
+        }
+        for section in &sections {
+            info!(device = section; "Using the [device.\"{}\"] settings", section);
+        }
+        if !changed.is_empty() {
+            info!("The device settings change {}", changed.join(", "));
+        }
+    }
+    if args.learn {
+        return learn::run(inputs, &conf, &mapping, &buttons, config_path).inspect_err(|e| error!("{}", e));
+    }
+    if args.calibrate_rotation {
+        return rotation::calibrate(inputs, config_path).inspect_err(|e| error!("{}", e));
+    }
+    let mut guard = Guard::new(inputs);
+    if let Some(rotation) = rotation {
+        info!("Steering mouse movement turned by {} degrees", rotation.angle());
+        guard.set_rotation(rotation);
+    }
+    let mut wheel = Wheel::new(wheel_hi_res);
+    let pedal_axes = pedals.axes().iter().map(|axis| AbsoluteAxisType(axis.code())).collect();
+    let mut script_hook =
+        ScriptHook::new(conf.script.as_deref(), pedal_axes, guard.now()).inspect_err(|e| error!("{}", e))?;
+
+    let (output, create) = if args.bench.is_some() && args.dry_run {
+        (Output::Discard, None)
+    } else if args.dry_run {
+        info!("Dry run, printing events instead of creating a virtual steering wheel");
+        let label = pipeline.map(|pipeline| format!("{} ", pipeline.name)).unwrap_or_default();
+        (Output::DryRun { start: guard.now(), label }, None)
+    } else {
+        let mut specs = specs;
+        specs.extend(pointer);
+        specs.extend(keyboard);
+        match VirtualDevices::create(&specs) {
+            Err(e) if args.bench.is_some() => {
+                warn!("Can't create the virtual steering wheel ({}), benchmarking without emitting", e);
+                (Output::Discard, None)
+            }
+            created => {
+                let mut devices =
+                    created.map_err(Mouse2JoyError::FailedToCreateDevice).inspect_err(|e| error!("{}", e))?;
+                info!("Virtual steering wheel created (900 degree rotation - smooth, no deadzone)");
+                for (device, spec) in devices.parts_mut() {
+                    match find_js_node(device) {
+                        Some(path) => info!("Joystick interface of \"{}\" available at {}", spec.name, path.display()),
+                        None => warn!("No js node was created for \"{}\", legacy games may not see it", spec.name),
+                    }
+                }
+                let create: CreateDevice = Box::new(move || VirtualDevices::create(&specs));
+                (Output::Device(devices), Some(create))
+            }
+        }
+    };
+    let emitting = if matches!(output, Output::Device(_)) { "uinput" } else { "discarded" };
+    guard.set_output(output, create);
+    if let Some(mirror) = mirror {
+        info!("Steering mirrored onto {:?}", mirror);
+        guard.set_mirror(mirror.0);
+    }
+    let inverted = pedals.inverted();
+    if !inverted.is_empty() {
+        info!("Sending {:?} inverted, full at rest", inverted);
+        let rest = guard.set_inverted(inverted.iter().map(|axis| axis.0).collect());
+        guard.emit(&rest, guard.now())?;
+    }
+    // still as root, the priorities stay with the threads after the switch
+    realtime::apply(&conf);
+    privileges::ready(pipeline.map_or(0, |pipeline| pipeline.index), run_as).inspect_err(|e| error!("{}", e))?;
+    if args.trace_input {
+        let duration = Duration::from_secs(args.trace_seconds.unwrap_or(60));
+        let file = match (&args.trace_file, pipeline) {
+            (Some(file), Some(pipeline)) => Some(pipeline.file(file)),
+            (file, _) => file.clone(),
+        };
+        let tracer = Tracer::new(file.as_deref(), duration, guard.now()).inspect_err(|e| error!("{}", e))?;
+        guard.set_tracer(tracer);
+    }
+    let mut data_log = match &args.log_csv {
+        Some(file) => {
+            let file = pipeline.map_or_else(|| file.clone(), |pipeline| pipeline.file(file));
+            let rate = args.log_csv_rate.unwrap_or(50);
+            Some(DataLog::new(&file, rate, guard.now()).inspect_err(|e| error!("{}", e))?)
+        }
+        None => None,
+    };
+
+    // fetch events and send them through to virtual steering wheel
+    let tick = Duration::from_secs(1) / conf.tick_rate.max(1);
+    let mut processed: u64 = 0;
+    let mut emitted: u64 = 0;
+    // the pipelines get theirs passed on from the main thread
+    if pipeline.is_none() {
+        install_signal_handlers();
+    }
+    daemon::ready(pipeline.map_or(0, |pipeline| pipeline.index));
+    let control = control::register(
+        &control::socket(args.config.as_deref()),
+        pipeline.map_or(0, |pipeline| pipeline.index),
+        pipeline.map(|pipeline| pipeline.name),
+    );
+    // with everything set up and as the user it runs as
+    let mut launched = profile.and_then(|name| launch::launch(&conf, name, args.exit_with_launched));
+    if args.exit_with_launched && launched.is_none() {
+        info!("Nothing launched yet, --exit-with-launched waits for a profile switch launching something");
+    }
+    let mut resume = ResumeDetector::new();
+    let mut sweep = conf.startup_sweep.then(|| Sweep::new(0, guard.now()));
+    let mut failsafe = Failsafe::new(&conf.failsafe, guard.now());
+    let mut idle = IdleExit::new(conf.exit_after_idle_minutes, guard.now());
+    if let Some(auto_pause) = auto_pause.as_mut() {
+        auto_pause.input(guard.now());
+    }
+    let mut rates = Rates::new(guard.now());
+    let mut stages = Stages::new(args.bench.is_some());
+    // an unplugged device waiting for the reopen on resume
+    let mut unplugged = false;
+    // by SIGUSR1, the input is ignored and the devices aren't grabbed
+    let mut paused = false;
+    // paused by [auto_pause], with the wheel left where it was
+    let mut auto_paused = false;
+    #[cfg(debug_assertions)]
+    let test_hooks = TestHooks::from_env();
+
     loop {
-        match mouse.fetch_events() {
-            Ok(events) => {
-                for ev in events {
-                    if ev.kind() == InputEventKind::RelAxis(RelativeAxisType::REL_X) {
-                        // Apply sensitivity multiplier from config
-                        let delta = ev.value() * (conf.sensitivity as i32);
-                        steering_position += delta;
-                        
-                        // Clamp to steering wheel range
-                        if steering_position < min {
-                            steering_position = min
-                        } else if steering_position > max {
-                            steering_position = max
+        // again on a new virtual device, it's a new wheel to the games
+        if conf.startup_sweep && guard.take_recreated() {
+            sweep = Some(Sweep::new(steering.position(), guard.now()));
+        }
+        // only wake up periodically while a pedal or steering ramp or the
+        // sweep is moving or a published frame is held back by the rate limit
+        let timeout = [
+            (!paused && (!pedals.is_settled() || !steering.is_settled() || script_hook.is_some() || sweep.is_some()))
+                .then_some(tick),
+            publisher.as_ref().and_then(|publisher| publisher.wakeup(guard.now())),
+            battery.as_ref().map(|battery| battery.wakeup(guard.now())),
+            failsafe.wakeup(guard.now()),
+            auto_pause.as_ref().and_then(|auto_pause| auto_pause.wakeup(guard.now())),
+            chords.as_ref().and_then(|chords| chords.wakeup(guard.now())),
+            debounce.as_ref().and_then(|debounce| debounce.wakeup(guard.now())),
+            macros.as_ref().and_then(|macros| macros.wakeup(guard.now())),
+            flick.as_ref().and_then(|flick| flick.wakeup(guard.now())),
+            data_log.as_ref().map(|data_log| data_log.wakeup(guard.now())),
+            idle_inhibit.as_ref().and_then(|idle_inhibit| idle_inhibit.wakeup(guard.now())),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .filter(|_| !paused);
+        // also while paused, a paused mouse2joy can be forgotten just as well
+        let timeout = timeout.into_iter().chain(idle.as_ref().map(|idle| idle.wakeup(guard.now()))).min();
+        let Some(mut events) = guard.fetch(timeout) else {
+            info!("Input ended, exiting");
+            break;
+        };
+        stages.lap(Stage::Read);
+        if SHUTDOWN.load(Ordering::Relaxed) {
+            info!("Received a termination signal, exiting");
+            break;
+        }
+        if let Some(child) = launched.as_ref().filter(|child| args.exit_with_launched && child.exited()) {
+            info!("{} exited, exiting with it", child.program());
+            // the other pipelines too
+            if pipeline.is_some() {
+                let _ = nix::sys::signal::kill(nix::unistd::getpid(), Signal::SIGTERM);
+            }
+            break;
+        }
+        if let Some(idle) = idle.as_mut() {
+            if idle.expired(!events.is_empty(), guard.now()) {
+                info!("No input for {} minute(s), exiting", idle.minutes());
+                break;
+            }
+        }
+        // also while paused, so the pause key resumes
+        let actions = hotkeys.as_mut().map(|hotkeys| hotkeys.scan(&events)).unwrap_or_default();
+        let requests = control.take();
+        // mouse2joy pause and resume say which one, the rest toggle
+        let toggle = take_signal(&PAUSE_TOGGLES, &PAUSES_SEEN) | actions.contains(&Action::Pause);
+        let pause = requests.iter().rev().find_map(|(request, _)| match request {
+            Request::Pause(pause) => Some(*pause),
+            _ => None,
+        });
+        // pausing by itself, or the deliberate gesture resuming from that
+        let pedals_up =
+            pedals.values() == (0, 0) && scroll_throttle.as_ref().is_none_or(|throttle| throttle.level() == 0.0);
+        let auto = match auto_pause.as_mut() {
+            Some(auto_pause) if paused => auto_paused && auto_pause.resumes(&events, guard.now()),
+            Some(auto_pause) => auto_pause.due(!events.is_empty(), steering.position(), pedals_up, guard.now()),
+            None => false,
+        };
+        if pause.map_or(toggle, |pause| pause != paused) || auto {
+            let now = guard.now();
+            paused = !paused;
+            if paused {
+                let mut neutral = Vec::new();
+                if auto {
+                    steering.release();
+                } else {
+                    neutral.extend(steering.center(now));
+                }
+                neutral.extend(pedals.release(true, now));
+                neutral.extend(scroll_throttle.as_mut().and_then(ScrollThrottle::release));
+                if let Some(look) = mouse_look.as_mut() {
+                    look.release();
+                }
+                neutral.extend(hat.reset());
+                neutral.extend(key_mapping.as_mut().map(KeyMapping::release).unwrap_or_default());
+                neutral.extend(mapping.release());
+                neutral.extend(chords.as_mut().map(Chords::reset).unwrap_or_default());
+                if let Some(debounce) = debounce.as_mut() {
+                    debounce.reset();
+                }
+                neutral.extend(macros.as_mut().map(Macros::cancel).unwrap_or_default());
+                neutral.extend(flick.as_mut().and_then(Flick::release));
+                guard.emit(&neutral, now)?;
+                emitted += neutral.len() as u64;
+                guard.release_inputs();
+                if let Some(lock_led) = lock_led.as_mut() {
+                    lock_led.restore();
+                }
+                if let Some(idle_inhibit) = idle_inhibit.as_mut() {
+                    idle_inhibit.release();
+                }
+                match auto_pause.as_ref().filter(|_| auto) {
+                    Some(auto_pause) => {
+                        let gesture = match auto_pause.burst() {
+                            Some(burst) => format!("the pause hotkey or {} ms of mouse movement", burst.as_millis()),
+                            None => String::from("the pause hotkey"),
+                        };
+                        let body =
+                            format!("No input for {}s, the mice are free until {}", auto_pause.seconds(), gesture);
+                        info!("Auto-paused: {}", body);
+                        if let Some(notifier) = notifier.as_mut() {
+                            notifier.notify(Notice::AutoPause, body, now);
                         }
-                        
-                        let ev = InputEvent::new(
-                            EventType::ABSOLUTE,
-                            AbsoluteAxisType::ABS_X.0,
-                            steering_position,
-                        );
-                        
-                        match steering_wheel.emit(&[ev]) {
-                          Ok(_) => {
-                            info! ("Steering:  {}", steering_position);
-                          },
-                          Err(e) => {
-                            warn!("Failed to emit steering wheel event: {}", e);
-                            continue;
-                          }
+                    }
+                    None => {
+                        info!("Paused, outputs set to neutral and the input devices released until the next SIGUSR1");
+                        if let Some(notifier) = notifier.as_mut() {
+                            notifier.notify(Notice::Pause, String::from("Outputs neutral, the mice are free"), now);
                         }
                     }
                 }
+            } else {
+                guard.grab_inputs();
+                failsafe.input(now);
+                if let Some(auto_pause) = auto_pause.as_mut() {
+                    auto_pause.input(now);
+                }
+                if auto_paused {
+                    info!("Resumed from the auto-pause, steering on from {}", steering.position());
+                    if let Some(notifier) = notifier.as_mut() {
+                        notifier.notify(Notice::AutoResume, String::from("Steering again"), now);
+                    }
+                } else {
+                    info!("Resumed");
+                    if let Some(notifier) = notifier.as_mut() {
+                        notifier.notify(Notice::Resume, String::from("Steering again"), now);
+                    }
+                }
+                // the gesture is no steering
+                if auto {
+                    events.clear();
+                }
             }
-            Err(e) => {
-                warn!("Failed to fetch mouse events:  {}", e);
+            auto_paused = paused && auto;
+        }
+        if take_signal(&STATE_DUMPS, &DUMPS_SEEN) | actions.contains(&Action::LogState) {
+            let (throttle, brake) = pedals.levels();
+            let profile = profile_switch.as_ref().and_then(ProfileSwitch::active);
+            info!(
+                "State: {}, input from {}, steering at {} of {}, throttle {:.0}%{}, brake {:.0}%, sensitivity {}{}, \
+                 profile {}, {} input and {} output events",
+                if paused { "paused" } else { "running" },
+                guard.describe_inputs(),
+                steering.position(),
+                STEERING_MAX,
+                throttle * 100.0,
+                if pedals.is_holding() { " (held)" } else { "" },
+                brake * 100.0,
+                steering.sensitivity(),
+                steering.precision().map_or(String::new(), |scale| format!(" (precision x{})", scale)),
+                profile.map_or(String::from("base"), |name| format!("\"{}\"", name)),
+                processed,
+                emitted
+            );
+        }
+        for (request, reply) in requests {
+            let answer = match request {
+                Request::Status { .. } => {
+                    let (throttle, brake) = pedals.levels();
+                    Reply::Status(Status {
+                        paused,
+                        input: guard.describe_inputs(),
+                        steering: steering.position(),
+                        throttle,
+                        brake,
+                        sensitivity: steering.sensitivity(),
+                        precision: steering.precision().is_some(),
+                        profile: profile_switch.as_ref().and_then(ProfileSwitch::active).map(String::from),
+                        input_events: processed,
+                        output_events: emitted,
+                    })
+                }
+                Request::Pause(_) => Reply::Done(String::from(if paused { "Paused" } else { "Running" })),
+                Request::Set(setting, value) => control::set(&mut steering, &setting, &value),
+            };
+            let _ = reply.send(answer);
+        }
+        // whatever queued up across a suspend is stale, applying it would
+        // be one giant delta
+        if let Some(slept) = resume.check() {
+            let (reopened, recreated) = guard.reestablish();
+            info!(
+                "Resumed after {}s asleep: reopened {} input device(s){}, dropped {} queued event(s)",
+                slept.as_secs(),
+                reopened,
+                if recreated { ", recreated the virtual steering wheel" } else { "" },
+                events.len()
+            );
+            // the reopen grabbed them again
+            if paused {
+                guard.release_inputs();
+            }
+            if unplugged && reopened > 0 {
+                unplugged = false;
+                if let Some(notifier) = notifier.as_mut() {
+                    notifier.notify(Notice::DeviceBack, format!("Reopened {} input device(s)", reopened), guard.now());
+                }
+            }
+            continue;
+        }
+        if paused {
+            continue;
+        }
+        let mut read = aggregate::count(&events);
+        if let Some(input_window) = &input_window {
+            read += input_window.collect(&mut guard, &mut events, spikes.as_mut());
+        } else if let Some(spikes) = spikes.as_mut() {
+            spikes.filter(&mut events);
+        }
+        processed += read as u64;
+
+        let now = guard.now();
+        if let Some(battery) = battery.as_mut() {
+            battery.check(now);
+        }
+        if let Some(telemetry) = &telemetry {
+            steering.set_gain(telemetry.gain());
+        }
+        let mut outgoing = Vec::new();
+        // a stroke that stopped before this input is judged first
+        if let Some(flick) = flick.as_mut() {
+            let (taps, rollback) = flick.tick(now);
+            outgoing.extend(taps);
+            if let Some(mouse) = rollback {
+                outgoing.extend(steering.restore_mouse(mouse, now));
+            }
+        }
+        let input = events.iter().any(|(_, ev)| ev.event_type() != EventType::SYNCHRONIZATION);
+        // the chatter is gone before the chords see a press
+        if let Some(debounce) = debounce.as_mut() {
+            events = debounce.filter(events, now);
+        }
+        if let Some(chords) = chords.as_mut() {
+            events = chords.filter(events, now, &mut outgoing);
+        }
+        stages.lap(Stage::Filter);
+        for (role, ev) in events {
+            // the steering axis device steers with its axis, its keys are
+            // mapped like any others
+            if role == InputRole::Axis && matches!(ev.event_type(), EventType::ABSOLUTE | EventType::RELATIVE) {
+                if ev.kind() == InputEventKind::AbsAxis(steering.axis().unwrap_or(AbsoluteAxisType::ABS_X)) {
+                    outgoing.extend(steering.handle_abs(ev.value(), now));
+                }
+                continue;
+            }
+            // the pedals mouse only moves the pedals
+            if role == InputRole::Pedals && ev.event_type() == EventType::RELATIVE {
+                if ev.kind() == InputEventKind::RelAxis(RelativeAxisType::REL_Y) {
+                    outgoing.extend(pedals.handle_rel_y(ev.value(), now));
+                }
                 continue;
             }
+            // the forwarded axes of the steering mouse only go to the pointer
+            if let (Some(look), InputEventKind::RelAxis(axis)) = (mouse_look.as_ref(), ev.kind()) {
+                if look.route(axis) == Route::Pointer {
+                    outgoing.extend(look.forward(&ev));
+                    continue;
+                }
+            }
+            if let Some(script_hook) = script_hook.as_mut() {
+                script_hook.observe(&ev);
+            }
+            match ev.kind() {
+                InputEventKind::RelAxis(RelativeAxisType::REL_X) => {
+                    if let Some(flick) = flick.as_mut() {
+                        flick.handle_rel_x(ev.value(), steering.mouse(), now);
+                    }
+                    if let Some(data_log) = data_log.as_mut() {
+                        data_log.add_delta(ev.value());
+                    }
+                    outgoing.extend(steering.handle_rel_x(ev.value(), now));
+                }
+                InputEventKind::RelAxis(axis @ (RelativeAxisType::REL_WHEEL | RelativeAxisType::REL_WHEEL_HI_RES)) => {
+                    let Some(units) = wheel.units(axis, ev.value()) else {
+                        continue;
+                    };
+                    let detents = wheel.detents(units);
+                    if let Some(script_hook) = script_hook.as_mut() {
+                        script_hook.scroll(detents, units);
+                    }
+                    if let Some(scroll_throttle) = scroll_throttle.as_mut() {
+                        outgoing.extend(scroll_throttle.handle_wheel(units));
+                    }
+                }
+                InputEventKind::Key(key) => {
+                    // handled by the scan above
+                    if hotkeys.as_ref().is_some_and(|hotkeys| hotkeys.binds(key)) {
+                        continue;
+                    }
+                    // first, so the modifiers of a switch key are seen even
+                    // when they also drive something
+                    if profile_switch.as_mut().is_some_and(|switch| switch.handle_key(key, ev.value())) {
+                        continue;
+                    }
+                    if pedals.handle_key(key, ev.value(), now) || steering.handle_key(key, ev.value(), now) {
+                        continue;
+                    }
+                    if overlay.as_ref().is_some_and(|overlay| overlay.handle_key(key, ev.value())) {
+                        continue;
+                    }
+                    if mouse_look.as_mut().is_some_and(|look| look.handle_key(key, ev.value())) {
+                        continue;
+                    }
+                    if let Some(event) = scroll_throttle.as_mut().and_then(|scroll| scroll.handle_key(key, ev.value())) {
+                        outgoing.extend(event);
+                        continue;
+                    }
+                    if let Some(events) = hat.handle_key(key, ev.value()) {
+                        outgoing.extend(events);
+                        continue;
+                    }
+                    if let Some(events) = macros.as_mut().and_then(|macros| macros.handle_key(key, ev.value(), now)) {
+                        outgoing.extend(events);
+                        continue;
+                    }
+                    if let Some(event) = key_mapping.as_mut().and_then(|keys| keys.handle_key(key, ev.value())) {
+                        outgoing.extend(event);
+                        continue;
+                    }
+                    outgoing.extend(mapping.button_event(key, ev.value()));
+                }
+                _ => {}
+            }
+        }
+        for action in &actions {
+            match action {
+                Action::Recenter => {
+                    info!("Recentered by the hotkey");
+                    outgoing.extend(steering.center(now));
+                }
+                Action::NextProfile => {
+                    if let Some(switch) = profile_switch.as_mut() {
+                        switch.request_next();
+                    }
+                }
+                Action::ToggleOverlay => {
+                    if let Some(overlay) = &overlay {
+                        overlay.toggle();
+                    }
+                }
+                Action::Pause | Action::LogState => {}
+            }
+        }
+        if let Some(switch) = profile_switch.as_mut() {
+            if let Some(index) = switch.take_request() {
+                let profile = switch.config(index);
+                let applied = steering
+                    .reconfigure(profile, now)
+                    .and_then(|()| pedals.reconfigure(&profile.pedals, now))
+                    .and_then(|()| match scroll_throttle.as_mut() {
+                        Some(scroll_throttle) => scroll_throttle.reconfigure(&profile.scroll),
+                        None => Ok(()),
+                    });
+                match applied {
+                    Ok(()) => {
+                        switch.activate(index);
+                        let name = switch.active();
+                        let child = name.and_then(|name| launch::launch(switch.config(index), name, args.exit_with_launched));
+                        if child.is_some() {
+                            launched = child;
+                        }
+                        if let Some(notifier) = notifier.as_mut() {
+                            let body = match switch.active() {
+                                Some(name) => format!("Now using \"{}\"", name),
+                                None => String::from("Back to the base settings"),
+                            };
+                            notifier.notify(Notice::Profile, body, now);
+                        }
+                    }
+                    Err(e) => error!("Failed to switch profiles:  {}", e),
+                }
+            }
+        }
+        stages.lap(Stage::Map);
+        outgoing.extend(steering.tick(now));
+        if let Some(lock_cue) = lock_cue.as_mut() {
+            lock_cue.update(steering.position());
+        }
+        if let Some(lock_led) = lock_led.as_mut() {
+            lock_led.update(steering.position());
+        }
+        outgoing.extend(pedals.tick(now));
+        if let Some(macros) = macros.as_mut() {
+            outgoing.extend(macros.tick(now, |code| guard.axis(code)));
+        }
+        if let Some(script_hook) = script_hook.as_mut() {
+            let (throttle, brake) = pedals.values();
+            let input = TickInput {
+                steering: steering.position(),
+                throttle,
+                brake,
+                speed: telemetry.as_ref().and_then(Telemetry::speed),
+            };
+            script_hook.tick(input, &mut outgoing, now);
+        }
+        if let Some(running) = sweep.as_mut() {
+            if !running.tick(input, steering.position(), &mut outgoing, now) {
+                sweep = None;
+            }
+        }
+        if input {
+            failsafe.input(now);
+        }
+        if let Some(idle_inhibit) = idle_inhibit.as_mut() {
+            idle_inhibit.update(input, now);
+        }
+        let lost = guard.take_lost();
+        if lost {
+            unplugged = true;
+            if let Some(notifier) = notifier.as_mut() {
+                notifier.notify(Notice::DeviceLost, String::from("Reading the other devices on"), now);
+            }
+        }
+        if let Some(reason) = failsafe.check(lost, now) {
+            if let Some(idle_inhibit) = idle_inhibit.as_mut() {
+                idle_inhibit.release();
+            }
+            let mut neutral = Vec::new();
+            if failsafe.center_steering() {
+                neutral.extend(steering.center(now));
+            } else {
+                steering.release();
+            }
+            neutral.extend(pedals.release(failsafe.release_brake(), now));
+            neutral.extend(scroll_throttle.as_mut().and_then(ScrollThrottle::release));
+            neutral.extend(hat.reset());
+            // nothing stays held, as on a pause, the release may have gone
+            // with a lost device
+            if let Some(look) = mouse_look.as_mut() {
+                look.release();
+            }
+            neutral.extend(key_mapping.as_mut().map(KeyMapping::release).unwrap_or_default());
+            neutral.extend(mapping.release());
+            neutral.extend(chords.as_mut().map(Chords::reset).unwrap_or_default());
+            if let Some(debounce) = debounce.as_mut() {
+                debounce.reset();
+            }
+            neutral.extend(macros.as_mut().map(Macros::cancel).unwrap_or_default());
+            neutral.extend(flick.as_mut().and_then(Flick::release));
+            if !neutral.is_empty() {
+                warn!("{}, outputs set to neutral until there is fresh input", reason);
+                if let Some(lock_led) = lock_led.as_mut() {
+                    lock_led.failsafe();
+                }
+                // the lost device was notified already
+                if let Some(notifier) = notifier.as_mut().filter(|_| !lost) {
+                    notifier.notify(Notice::Failsafe, format!("{}, outputs set to neutral", reason), now);
+                }
+            }
+            outgoing.extend(neutral);
+        }
+
+        if publisher.is_some() || overlay.is_some() || data_log.is_some() {
+            let (mut throttle, brake) = pedals.levels();
+            if let Some(scroll_throttle) = &scroll_throttle {
+                throttle = throttle.max(scroll_throttle.level());
+            }
+            if let Some(data_log) = data_log.as_mut() {
+                let profile = profile_switch.as_ref().and_then(ProfileSwitch::active);
+                data_log.sample(steering.position(), throttle, brake, profile, now);
+            }
+            let frame = Frame {
+                angle_deg: steering.position() as f32 / STEERING_MAX as f32 * WHEEL_ROTATION / 2.0,
+                throttle,
+                brake,
+                throttle_hold: pedals.is_holding(),
+                precision: steering.precision().is_some(),
+                low_battery: battery.as_ref().is_some_and(Battery::is_low),
+                profile: profile_switch.as_ref().and_then(ProfileSwitch::active).map(Arc::from),
+            };
+            if let Some(overlay) = &overlay {
+                overlay.update(frame.clone());
+            }
+            if let Some(publisher) = publisher.as_mut() {
+                publisher.update(frame, now);
+            }
+        }
+
+        stages.lap(Stage::Tick);
+        rates.record(read, outgoing.len(), now);
+        if !outgoing.is_empty() {
+            guard.emit(&outgoing, now)?;
+            emitted += outgoing.len() as u64;
+        }
+        stages.lap(Stage::Emit);
+
+        #[cfg(debug_assertions)]
+        test_hooks.check(processed);
+
+        if args.max_events.is_some_and(|max_events| processed >= max_events) {
+            info!("Processed {} input events, exiting", processed);
+            break;
+        }
+    }
+    if args.bench.is_some() {
+        stages.report(processed, emitted, emitting);
+    }
+
+    Ok(())
+}
+
+// Test hooks of debug builds, read once: MOUSE2JOY_PANIC_AFTER stands in for
+// a bug in the loop and MOUSE2JOY_HANG_AFTER for a loop that is stuck, after
+// that many input events.
+#[cfg(debug_assertions)]
+struct TestHooks {
+    panic_after: Option<u64>,
+    hang_after: Option<u64>,
+}
+
+#[cfg(debug_assertions)]
+impl TestHooks {
+    fn from_env() -> Self {
+        let count = |name| std::env::var(name).ok().and_then(|count| count.parse().ok());
+        Self { panic_after: count("MOUSE2JOY_PANIC_AFTER"), hang_after: count("MOUSE2JOY_HANG_AFTER") }
+    }
+
+    fn check(&self, processed: u64) {
+        if self.panic_after == Some(processed) {
+            panic!("MOUSE2JOY_PANIC_AFTER reached after {} events", processed);
+        }
+        if self.hang_after == Some(processed) {
+            warn!("MOUSE2JOY_HANG_AFTER reached after {} events, hanging", processed);
+            loop {
+                std::thread::sleep(Duration::from_secs(60));
+            }
         }
     }
 }
 
-fn create_steering_wheel(abs_info: AbsInfo, name: &str) -> std::io::Result<VirtualDevice> {
-    // Only use ABS_X for steering wheel rotation
-    let abs_x = UinputAbsSetup::new(AbsoluteAxisType:: ABS_X, abs_info);
+// what SIGINT, SIGTERM, SIGUSR1 and SIGUSR2 ask of the loops
+fn record_signal(signal: Signal) {
+    match signal {
+        Signal::SIGINT | Signal::SIGTERM => SHUTDOWN.store(true, Ordering::Relaxed),
+        Signal::SIGUSR1 => {
+            PAUSE_TOGGLES.fetch_add(1, Ordering::Relaxed);
+        }
+        Signal::SIGUSR2 => {
+            STATE_DUMPS.fetch_add(1, Ordering::Relaxed);
+        }
+        _ => {}
+    }
+}
 
-    let mut keys = evdev::AttributeSet::new();
-    for button in KEYS {
-        keys.insert(button)
+extern "C" fn handle_signal(signal: nix::libc::c_int) {
+    if let Ok(signal) = Signal::try_from(signal) {
+        record_signal(signal);
     }
+}
 
-    let steering_wheel = VirtualDeviceBuilder::new()?
-        .name(name)
-        .with_absolute_axis(&abs_x)?
-        .with_keys(&keys)?
-        .build()?;
+// the signals the loops act on
+pub const LOOP_SIGNALS: [Signal; 4] = [Signal::SIGINT, Signal::SIGTERM, Signal::SIGUSR1, Signal::SIGUSR2];
 
-    Ok(steering_wheel)
+// Without SA_RESTART the signal interrupts the poll, so the loop sees the
+// flag right away.
+fn install_signal_handlers() {
+    for signal in LOOP_SIGNALS {
+        let action = SigAction::new(SigHandler::Handler(handle_signal), SaFlags::empty(), SigSet::empty());
+        // SAFETY: the handler only stores to atomics
+        if let Err(e) = unsafe { sigaction(signal, &action) } {
+            warn!("Failed to install a {} handler:  {}", signal, e);
+        }
+    }
 }
 
-// ask user for a usize input within a given range
-fn input_in_range(min: usize, max: usize) -> usize {
+// Pick a mouse for the role and make sure it can be used. One listed in
+// [[inputs]] with a path or name is opened directly, otherwise the user is
+// asked about the mice the filter leaves. A device that vanished or that something else holds gets reported,
+// then the devices are scanned again and the user asked once more. `taken`
+// are the mice other roles and pipelines already use.
+fn select_mouse(
+    input_dir: &Path,
+    conf: &Config,
+    filter: &DeviceFilter,
+    role: InputRole,
+    taken: &[PathBuf],
+    pipeline: Option<&Pipeline>,
+) -> Result<(PathBuf, Device), Mouse2JoyError> {
+    let listed = input_for(conf, role).filter(|input| input.path.is_some() || input.name.is_some());
+    for attempt in 1..=MOUSE_ATTEMPTS {
+        let (path, mut mouse) = match listed {
+            Some(input) => open_listed(input_dir, conf, input, taken)?,
+            None => scan_and_prompt(input_dir, conf, filter, role, taken, pipeline)?,
+        };
+        let name = mouse.name().unwrap_or("Unknown Device").to_string();
+        match check_mouse(&mut mouse, conf.grab_mouse) {
+            Ok(()) => {
+                match role {
+                    InputRole::Steering => info!(device = name.as_str(); "Using \"{}\" as input device", name),
+                    InputRole::Pedals => info!(device = name.as_str(); "Using \"{}\" for the pedals", name),
+                    InputRole::Axis => info!(
+                        device = name.as_str();
+                        "Using {} of \"{}\" to steer", conf.axis_steering.axis, name
+                    ),
+                }
+                return Ok((path, mouse));
+            }
+            Err(e) if attempt < MOUSE_ATTEMPTS => {
+                warn!("Can't use \"{}\":  {}, scanning again", name, e);
+                std::thread::sleep(Duration::from_millis(500));
+            }
+            Err(e) => {
+                let e = Mouse2JoyError::FailedToUseMouse(name, e);
+                error!("{}", e);
+                return Err(e);
+            }
+        }
+    }
+    unreachable!("the last attempt always returns")
+}
+
+// the forwarded movement has to come from a grabbed mouse, or the game gets
+// it twice
+fn check_mouse_look(conf: &Config, mouse_steering: bool) -> Result<(), Mouse2JoyError> {
+    if conf.input_backend == InputBackend::X11 {
+        return Err(Mouse2JoyError::InvalidConfig(String::from(
+            "input_backend = \"x11\" doesn't grab the mouse, the game already gets its movement without [mouse_look]",
+        )));
+    }
+    if !mouse_steering {
+        return Err(Mouse2JoyError::InvalidConfig(String::from(
+            "[mouse_look] forwards movement of the steering mouse, but mouse_steering is off",
+        )));
+    }
+    if !conf.grab_mouse {
+        warn!("grab_mouse is off, the game gets the movement [mouse_look] forwards twice");
+    }
+    Ok(())
+}
+
+// the [[inputs]] entry for a role, at most one each
+fn input_for(conf: &Config, role: InputRole) -> Option<&InputConfig> {
+    conf.inputs.iter().find(|input| input.role == role)
+}
+
+fn check_inputs(conf: &Config, mouse_steering: bool) -> Result<(), Mouse2JoyError> {
+    for (i, input) in conf.inputs.iter().enumerate() {
+        if conf.inputs[..i].iter().any(|other| other.role == input.role) {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "[[inputs]] lists more than one mouse for {:?}",
+                input.role
+            )));
+        }
+        if input.path.is_some() && input.name.is_some() {
+            return Err(Mouse2JoyError::InvalidConfig(String::from(
+                "an [[inputs]] entry takes a path or a name, not both",
+            )));
+        }
+    }
+    if conf.input_backend == InputBackend::X11 {
+        // the seat is one source, there is nothing to tell apart
+        if !conf.inputs.is_empty() {
+            return Err(Mouse2JoyError::InvalidConfig(String::from(
+                "input_backend = \"x11\" reads the whole seat, it takes no [[inputs]]",
+            )));
+        }
+        if conf.axis_steering.enabled {
+            return Err(Mouse2JoyError::InvalidConfig(String::from(
+                "input_backend = \"x11\" has no absolute axes, axis_steering needs the evdev backend",
+            )));
+        }
+    }
+    if !conf.axis_steering.enabled && input_for(conf, InputRole::Axis).is_some() {
+        return Err(Mouse2JoyError::InvalidConfig(String::from(
+            "[[inputs]] lists a device for the axis role but axis_steering is off",
+        )));
+    }
+    if !mouse_steering && input_for(conf, InputRole::Steering).is_some() {
+        return Err(Mouse2JoyError::InvalidConfig(String::from(
+            "[[inputs]] lists a steering mouse but mouse_steering is off",
+        )));
+    }
+    Ok(())
+}
+
+// the mouse an [[inputs]] entry names, no prompt. Of several mice with the
+// name the first one not `taken` yet.
+fn open_listed(
+    input_dir: &Path,
+    conf: &Config,
+    input: &InputConfig,
+    taken: &[PathBuf],
+) -> Result<(PathBuf, Device), Mouse2JoyError> {
+    if let Some(path) = &input.path {
+        let path = PathBuf::from(path);
+        return Device::open(&path)
+            .map(|device| (path.clone(), device))
+            .map_err(|e| Mouse2JoyError::FailedToUseMouse(path.display().to_string(), e))
+            .inspect_err(|e| error!("{}", e));
+    }
+    let name = input.name.as_deref().unwrap_or_default();
+    let found = candidates(input_dir, conf, input.role)
+        .into_iter()
+        .find(|(path, device)| device.name() == Some(name) && !taken.iter().any(|taken| same_device(path, taken)));
+    found.ok_or(Mouse2JoyError::NoMouseError).inspect_err(|e| {
+        match input.role {
+            InputRole::Axis => warn!("No device named \"{}\" with {} in {}", name, conf.axis_steering.axis, input_dir.display()),
+            _ => warn!("No mouse named \"{}\" in {}", name, input_dir.display()),
+        }
+        error!("{}", e)
+    })
+}
+
+// the selected mouse must still be there, grabbable if configured and
+// answer a state query
+fn check_mouse(mouse: &mut Device, grab: bool) -> std::io::Result<()> {
+    if grab {
+        mouse.grab()?;
+    }
+    mouse.get_key_state()?;
+    Ok(())
+}
+
+// every input device that can be used as a mouse, sorted so the prompt
+// order, and the first candidate, stay the same
+fn mouse_devices(input_dir: &Path) -> Vec<(PathBuf, Device)> {
+    scan_mice(input_dir).0
+}
+
+// the devices that can take the role, mice or those with the steering axis
+fn candidates(input_dir: &Path, conf: &Config, role: InputRole) -> Vec<(PathBuf, Device)> {
+    if role != InputRole::Axis {
+        return mouse_devices(input_dir);
+    }
+    let Ok(axis) = conf.axis_steering.axis.parse::<AbsoluteAxisType>() else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = fs::read_dir(input_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    let id = vjoystick_id();
+    paths
+        .into_iter()
+        .filter_map(|path| Device::open(&path).ok().map(|device| (path, device)))
+        .filter(|(_, device)| device.supported_absolute_axes().is_some_and(|axes| axes.contains(axis)))
+        // a virtual wheel of another mouse2joy has the axis too
+        .filter(|(_, device)| device.input_id().vendor() != id.vendor())
+        .collect()
+}
+
+// an input directory entry that isn't a mouse and why
+type Excluded = (PathBuf, String);
+
+// the mice, and every other entry with the reason it isn't one
+fn scan_mice(input_dir: &Path) -> (Vec<(PathBuf, Device)>, Vec<Excluded>) {
+    let mut paths: Vec<PathBuf> = fs::read_dir(input_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect();
+    paths.sort();
+    let (mut mice, mut excluded) = (Vec::new(), Vec::new());
+    for path in paths {
+        match Device::open(&path) {
+            // the pointer of [mouse_look], of this or another mouse2joy
+            Ok(device) if device.input_id().vendor() == vjoystick_id().vendor() => {
+                excluded.push((path, String::from("a virtual device of mouse2joy")))
+            }
+            Ok(device) if device.supported_events().contains(EventType::RELATIVE) => mice.push((path, device)),
+            Ok(_) => excluded.push((path, String::from("no relative axes"))),
+            Err(e) => excluded.push((path, format!("can't open: {}", e))),
+        }
+    }
+    (mice, excluded)
+}
+
+// every mouse with its path and phys, the ones the filter leaves out dimmed
+// (on a terminal) with the rule that does
+fn list_devices(input_dir: &Path, filter: &DeviceFilter) {
+    let (mice, _) = scan_mice(input_dir);
+    if mice.is_empty() {
+        println!("No mice in {}", input_dir.display());
+    }
+    let dim = std::io::stdout().is_terminal();
+    for (path, mouse) in &mice {
+        let line = format!(
+            "{}: \"{}\" ({})",
+            path.display(),
+            mouse.name().unwrap_or("Unknown Device"),
+            mouse.physical_path().unwrap_or("no phys")
+        );
+        match filter.excluded_by(mouse) {
+            Some(rule) if dim => println!("\x1b[2m{}, {}\x1b[0m", line, rule),
+            Some(rule) => println!("{}, {}", line, rule),
+            None => println!("{}", line),
+        }
+    }
+}
+
+// the same node, also through a by-id link
+fn same_device(a: &Path, b: &Path) -> bool {
+    a == b || fs::canonicalize(a).ok() == fs::canonicalize(b).ok()
+}
+
+// find all input devices that can be used as a mouse and let the user pick one
+fn scan_and_prompt(
+    input_dir: &Path,
+    conf: &Config,
+    filter: &DeviceFilter,
+    role: InputRole,
+    taken: &[PathBuf],
+    pipeline: Option<&Pipeline>,
+) -> Result<(PathBuf, Device), Mouse2JoyError> {
+    let mut mouse_devices: Vec<(PathBuf, Device)> = candidates(input_dir, conf, role)
+        .into_iter()
+        .filter(|(path, _)| !taken.iter().any(|taken| same_device(path, taken)))
+        .filter(|(path, mouse)| match filter.excluded_by(mouse) {
+            Some(rule) => {
+                debug!("Skipping {} ({}), {}", path.display(), mouse.name().unwrap_or("Unknown Device"), rule);
+                false
+            }
+            None => true,
+        })
+        .collect();
+
+    if mouse_devices.is_empty() {
+        error!("{}", Mouse2JoyError::NoMouseError);
+        return Err(Mouse2JoyError::NoMouseError);
+    }
+
+    if mouse_devices.len() != 1 && daemon::is_daemon() {
+        let e = Mouse2JoyError::InvalidArguments(String::from(
+            "--daemon can't ask which mouse to use, name it in [[inputs]] or leave one with only_devices",
+        ));
+        error!("{}", e);
+        return Err(e);
+    }
+
+    // ask user which mouse to use
+    let index = if mouse_devices.len() != 1 {
+        if let Some(pipeline) = pipeline {
+            print!("{}: ", pipeline.name);
+        }
+        match (role, conf.inputs.is_empty()) {
+            (InputRole::Axis, _) => println!(
+                "Several devices with {} detected, please select the one to steer with:",
+                conf.axis_steering.axis
+            ),
+            (_, true) => println!("Several mouses detected, please select one:"),
+            (InputRole::Steering, false) => println!("Several mouses detected, please select the one to steer with:"),
+            (InputRole::Pedals, false) => println!("Several mouses detected, please select the one for the pedals:"),
+        }
+        for (i, (_, mouse)) in mouse_devices.iter().enumerate() {
+            println!("{}: {}", i + 1, mouse.name().unwrap_or("Unknown Device"));
+        }
+        let timeout = (conf.prompt_timeout > 0).then(|| Duration::from_secs(conf.prompt_timeout.into()));
+        match input_in_range(1, mouse_devices.len(), timeout) {
+            Some(index) => index,
+            None if conf.prompt_default == PromptDefault::First => {
+                info!("No selection made, using the first mouse");
+                1
+            }
+            None => {
+                error!("{}", Mouse2JoyError::NoSelectionError);
+                return Err(Mouse2JoyError::NoSelectionError);
+            }
+        }
+    } else {
+        1
+    };
+    let mouse = mouse_devices. remove(index - 1);
+
+    // ungrab unwanted mouse devices
+    for (_, mut device) in mouse_devices {
+        device
+            .ungrab()
+            .unwrap_or_else(|e| warn!("Failed to ungrab device:  {}", e));
+    }
+
+    Ok(mouse)
+}
+
+// The virtual devices of the layout. Split, the pedal axes (and the scroll
+// throttle) get a device of their own and the hat stays on the wheel, with
+// the shifter the extra buttons move to a third one.
+fn device_specs(
+    name: &str,
+    layout: OutputLayout,
+    steering: &[UinputAbsSetup],
+    axes: &[UinputAbsSetup],
+    buttons: &[Key],
+) -> Result<Vec<DeviceSpec>, Mouse2JoyError> {
+    let id = vjoystick_id();
+    let spec = |suffix: &str, product: u16, axes: Vec<UinputAbsSetup>, buttons: Vec<Key>| DeviceSpec {
+        name: format!("{}{}", name, suffix),
+        id: InputId::new(id.bus_type(), id.vendor(), id.product() + product, id.version()),
+        axes,
+        rel: Vec::new(),
+        buttons,
+    };
+    let mut wheel_axes = steering.to_vec();
+    if layout == OutputLayout::Combined {
+        wheel_axes.extend(axes);
+        return Ok(vec![spec("", 0, wheel_axes, buttons.to_vec())]);
+    }
+
+    let hat = [AbsoluteAxisType::ABS_HAT0X.0, AbsoluteAxisType::ABS_HAT0Y.0];
+    let (hat_axes, pedal_axes): (Vec<UinputAbsSetup>, Vec<UinputAbsSetup>) =
+        axes.iter().partition(|axis| hat.contains(&axis.code()));
+    if pedal_axes.is_empty() {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "layout = \"{}\" gives the pedals their own device, but no pedal is configured",
+            layout_name(layout)
+        )));
+    }
+    wheel_axes.extend(hat_axes);
+    let (shifter, wheel_buttons): (Vec<Key>, Vec<Key>) = buttons
+        .iter()
+        .partition(|key| layout == OutputLayout::SplitShifter && is_trigger_happy(**key));
+    let mut specs = vec![
+        spec(" wheel", 0, wheel_axes, wheel_buttons),
+        spec(" pedals", 1, pedal_axes, Vec::new()),
+    ];
+    if layout == OutputLayout::SplitShifter {
+        if shifter.is_empty() {
+            return Err(Mouse2JoyError::InvalidConfig(String::from(
+                "layout = \"split_shifter\" puts the extra_buttons on the shifter, but there are none",
+            )));
+        }
+        specs.push(spec(" shifter", 2, Vec::new(), shifter));
+    }
+    Ok(specs)
+}
+
+fn layout_name(layout: OutputLayout) -> &'static str {
+    match layout {
+        OutputLayout::Combined => "combined",
+        OutputLayout::Split => "split",
+        OutputLayout::SplitShifter => "split_shifter",
+    }
+}
+
+// the (optional) joystick buttons and static wheel buttons followed by
+// BTN_TRIGGER_HAPPY1..extra, or nothing at all with no_buttons
+fn virtual_buttons(conf: &Config) -> Result<Vec<Key>, Mouse2JoyError> {
+    let extra = conf.extra_buttons;
+    if extra > MAX_EXTRA_BUTTONS {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "extra_buttons must be between 0 and {}, got {}",
+            MAX_EXTRA_BUTTONS, extra
+        )));
+    }
+    if conf.no_buttons {
+        if extra > 0 {
+            return Err(Mouse2JoyError::InvalidConfig(String::from(
+                "extra_buttons can't be used with no_buttons",
+            )));
+        }
+        // mappings to axes and steering actions still work
+        let mapped = conf.buttons.iter().find_map(|(source, mapping)| match mapping {
+            ButtonMapping::Button(button) => Some((source, button)),
+            _ => None,
+        });
+        if let Some((source, button)) = mapped {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "[buttons] maps {} to the button {}, but no_buttons is set",
+                source, button
+            )));
+        }
+        return Ok(Vec::new());
+    }
+    let mut buttons = Vec::new();
+    if conf.joystick_buttons {
+        buttons.extend(JOYSTICK_KEYS);
+    }
+    buttons.extend(KEYS);
+    buttons.extend((0..extra).map(|i| Key::new(Key::BTN_TRIGGER_HAPPY1.code() + i)));
+    Ok(buttons)
+}
+
+fn print_sdl_mapping(args: &Args, specs: &[DeviceSpec], append: bool) -> Result<(), Mouse2JoyError> {
+    let mut lines = String::new();
+    for spec in specs {
+        let axes: Vec<AbsoluteAxisType> = spec.axes.iter().map(|axis| AbsoluteAxisType(axis.code())).collect();
+        let line = sdl::mapping(spec.id.clone(), &spec.name, &spec.buttons, &axes);
+        println!("{}", line);
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+
+    if let Some(file) = &args.sdl_mapping_file {
+        fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(file)
+            .and_then(|mut out| std::io::Write::write_all(&mut out, lines.as_bytes()))
+            .map_err(|e| Mouse2JoyError::FailedToWrite(file.display().to_string(), e))
+            .inspect_err(|e| error!("{}", e))?;
+        info!("Mapping written, point SDL at it with SDL_GAMECONTROLLERCONFIG_FILE={}", file.display());
+    }
+    Ok(())
+}
+
+// the [debounce] sources with their intervals
+fn debounce_intervals(debounce: &BTreeMap<String, u32>) -> Result<Vec<(Key, Duration)>, Mouse2JoyError> {
+    debounce
+        .iter()
+        .map(|(source, ms)| {
+            let key = parse_key(source)?;
+            if *ms > MAX_DEBOUNCE_MS {
+                return Err(Mouse2JoyError::InvalidConfig(format!(
+                    "debounce.{} must be at most {} ms, got {}",
+                    source, MAX_DEBOUNCE_MS, ms
+                )));
+            }
+            Ok((key, Duration::from_millis((*ms).into())))
+        })
+        .collect()
+}
+
+// every key may only be used by one feature
+fn check_key_conflicts(features: &[(&str, Vec<Key>)]) -> Result<(), Mouse2JoyError> {
+    for (i, (feature, keys)) in features.iter().enumerate() {
+        for (other, other_keys) in &features[i + 1..] {
+            if let Some(key) = keys.iter().find(|key| other_keys.contains(key)) {
+                return Err(Mouse2JoyError::InvalidConfig(format!(
+                    "{:?} is used by both {} and {}",
+                    key, feature, other
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+// the /dev/input/jsX node the joydev driver created for the virtual device
+fn find_js_node(device: &mut VirtualDevice) -> Option<PathBuf> {
+    fs::read_dir(device.get_syspath().ok()?)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .find(|name| name.starts_with("js"))
+        .map(|name| Path::new(INPUT_DIR).join(name))
+}
+
+// find the non-mouse devices (keyboards) able to send any of the given keys
+// `taken` devices already deliver their keys, e.g. a gamepad steering with
+// its stick
+fn find_key_devices(input_dir: &Path, keys: &[Key], taken: &[PathBuf]) -> Vec<InputDevice> {
+    fs::read_dir(input_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| !taken.iter().any(|taken| same_device(&entry.path(), taken)))
+        .filter_map(|entry| {
+            let path = entry.path();
+            Device::open(&path).ok().map(|device| InputDevice::new(path, device, false, InputRole::Steering))
+        })
+        .filter(|input| !input.device.supported_events().contains(EventType::RELATIVE))
+        // the [keys] keyboard, its keys would come back around
+        .filter(|input| input.device.input_id().vendor() != vjoystick_id().vendor())
+        .filter(|input| {
+            input
+                .device
+                .supported_keys()
+                .is_some_and(|supported| keys.iter().any(|key| supported.contains(*key)))
+        })
+        .inspect(|input| {
+            let name = input.device.name().unwrap_or("Unknown Device");
+            info!(device = name; "Reading keys from \"{}\"", name)
+        })
+        .collect()
+}
+
+// Read a number between min and max from stdin. Returns None when stdin is
+// closed or nothing was entered before the timeout, the remaining time is
+// counted down until the user starts answering.
+fn input_in_range(min: usize, max: usize, timeout: Option<Duration>) -> Option<usize> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut answered = false;
     let mut input = String::new();
 
     loop {
+        if let (Some(deadline), false) = (deadline, answered) {
+            if !wait_for_stdin(deadline) {
+                println!();
+                return None;
+            }
+            answered = true;
+        }
+
         input.clear();
-        std::io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
+        match std::io::stdin().read_line(&mut input) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to read the selection:  {}", e);
+                return None;
+            }
+        }
 
         match input.trim().parse::<usize>() {
             Ok(index) if index >= min && index <= max => {
-                return index;
+                return Some(index);
             }
             _ => {
                 println!(
@@ -181,20 +1975,52 @@ fn input_in_range(min: usize, max: usize) -> usize {
     }
 }
 
-fn load_config() -> Config {
-    if Config::exists() {
-      match Config::load() {
-        Ok(conf) => {
-          info!("Using configuration file {}", Config::path());
-          conf
+// count down on one line until stdin is readable, false once the deadline
+// passed
+fn wait_for_stdin(deadline: Instant) -> bool {
+    let stdin = std::io::stdin();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return false;
         }
-        Err(_) => {
-          warn!("Problem laoding the configuration, using default");
-          Config::default()
+        print!("\rSelecting automatically in {}s ", remaining.as_secs_f32().ceil());
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+
+        // wake up at the next full second to update the countdown
+        let step = match remaining.subsec_nanos() {
+            0 => Duration::from_secs(1),
+            nanos => Duration::from_nanos(nanos.into()),
+        };
+        let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+        let timeout = PollTimeout::try_from(step.as_micros().div_ceil(1000)).unwrap_or(PollTimeout::MAX);
+        match poll(&mut fds, timeout) {
+            Ok(0) | Err(nix::errno::Errno::EINTR) => {}
+            Ok(_) => return true,
+            Err(_) => return false,
         }
-      }
+    }
+}
+
+fn load_config(path: &Path) -> Result<Config, Mouse2JoyError> {
+    if path.exists() {
+      let conf = Config::load(path)?;
+      info!("Using configuration file {}", path.display());
+      Ok(conf)
     } else {
       info! ("No configuration found, using default");
-      Config::default()
+      Ok(Config::default())
+    }
+}
+
+// rewrite the config file in the current format, keeping a backup
+fn migrate_config(path: &Path) -> Result<(), Mouse2JoyError> {
+    let (document, migrated) = Config::document(path)?;
+    if !migrated {
+        info!("{} is already in the current format", path.display());
+        return Ok(());
     }
+    Config::save(path, &document, true)?;
+    info!("Updated {}, the previous version is kept in {}.bak", path.display(), path.display());
+    Ok(())
 }
@@ -0,0 +1,183 @@
+use evdev::{AbsoluteAxisType, EventType, InputEvent, Key};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::configuration::{AxisMode, ButtonMapping, MappingAction, SnapRelease};
+use crate::keys::is_keyboard_key;
+use crate::Mouse2JoyError;
+
+// where an input key or button ends up on the virtual device
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Destination {
+    Button(Key),
+    Axis(AbsoluteAxisType),
+    Action(MappingAction, SnapRelease),
+}
+
+// Maps source keys/buttons (mouse buttons, keyboard keys) to virtual device
+// destinations. Axis destinations are driven by the pedal ramps, actions by
+// the steering, button destinations are forwarded directly.
+pub struct Mapping {
+    table: HashMap<Key, Destination>,
+    // "BTN_SIDE+BTN_EXTRA" sources, pressing a button
+    chords: Vec<([Key; 2], Key)>,
+    // sources forwarded as pressed buttons
+    held: Vec<Key>,
+}
+
+impl Mapping {
+    pub fn new(
+        conf: &BTreeMap<String, ButtonMapping>,
+        buttons: &[Key],
+        axes: &[AbsoluteAxisType],
+    ) -> Result<Self, Mouse2JoyError> {
+        let mut table = HashMap::new();
+        let mut chords: Vec<([Key; 2], Key)> = Vec::new();
+        for (source, mapping) in conf {
+            if source.contains('+') {
+                let members = parse_chord(source)?;
+                let ButtonMapping::Button(name) = mapping else {
+                    return Err(Mouse2JoyError::InvalidConfig(format!("the chord {} can only press a button", source)));
+                };
+                if chords.iter().any(|(other, _)| other.contains(&members[0]) && other.contains(&members[1])) {
+                    return Err(Mouse2JoyError::InvalidConfig(format!("the chord {} is mapped twice", source)));
+                }
+                chords.push((members, parse_button(name, buttons)?));
+                continue;
+            }
+            let source = parse_key(source)?;
+            let destination = match mapping {
+                ButtonMapping::Button(name) => Destination::Button(parse_button(name, buttons)?),
+                ButtonMapping::Axis { axis, mode: AxisMode::Ramp } => {
+                    let axis = axis.parse::<AbsoluteAxisType>().map_err(|_| {
+                        Mouse2JoyError::InvalidConfig(format!("unknown axis \"{}\"", axis))
+                    })?;
+                    if !axes.contains(&axis) {
+                        return Err(Mouse2JoyError::InvalidConfig(format!(
+                            "{:?} can not be driven by a button",
+                            axis
+                        )));
+                    }
+                    Destination::Axis(axis)
+                }
+                ButtonMapping::Action { action, release } => Destination::Action(*action, *release),
+                // played by the macros
+                ButtonMapping::Macro { .. } => continue,
+            };
+            table.insert(source, destination);
+        }
+        Ok(Self { table, chords, held: Vec::new() })
+    }
+
+    pub fn destination(&self, key: Key) -> Option<Destination> {
+        self.table.get(&key).copied()
+    }
+
+    // all keys that have a mapping, also as part of a chord
+    pub fn sources(&self) -> Vec<Key> {
+        let mut sources: Vec<Key> = self.table.keys().copied().collect();
+        for key in self.chords.iter().flat_map(|(members, _)| *members) {
+            if !sources.contains(&key) {
+                sources.push(key);
+            }
+        }
+        sources
+    }
+
+    pub fn chords(&self) -> &[([Key; 2], Key)] {
+        &self.chords
+    }
+
+    // keys forwarded as buttons, the others belong to the feature driving
+    // their destination
+    pub fn button_sources(&self) -> Vec<Key> {
+        self.table
+            .iter()
+            .filter(|(_, destination)| matches!(destination, Destination::Button(_)))
+            .map(|(source, _)| *source)
+            .collect()
+    }
+
+    // sources triggering an action
+    pub fn action_sources(&self) -> Vec<(Key, MappingAction, SnapRelease)> {
+        self.table
+            .iter()
+            .filter_map(|(source, destination)| match destination {
+                Destination::Action(action, release) => Some((*source, *action, *release)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // sources driving the given axis
+    pub fn axis_sources(&self, axis: AbsoluteAxisType) -> Vec<Key> {
+        self.table
+            .iter()
+            .filter(|(_, destination)| **destination == Destination::Axis(axis))
+            .map(|(source, _)| *source)
+            .collect()
+    }
+
+    // event to forward for a key mapped to a button, sources mapped to an
+    // axis never produce a button event
+    pub fn button_event(&mut self, key: Key, value: i32) -> Option<InputEvent> {
+        match self.table.get(&key) {
+            // joystick buttons don't auto repeat
+            Some(Destination::Button(button)) if value != 2 => {
+                self.held.retain(|held| *held != key);
+                if value != 0 {
+                    self.held.push(key);
+                }
+                Some(InputEvent::new(EventType::KEY, button.code(), value))
+            }
+            _ => None,
+        }
+    }
+
+    // paused or the failsafe tripped, nothing may stay held in the game
+    pub fn release(&mut self) -> Vec<InputEvent> {
+        let mut buttons: Vec<Key> = self
+            .held
+            .drain(..)
+            .filter_map(|source| match self.table[&source] {
+                Destination::Button(button) => Some(button),
+                _ => None,
+            })
+            .collect();
+        buttons.sort_by_key(|button| button.code());
+        buttons.dedup();
+        buttons.into_iter().map(|button| InputEvent::new(EventType::KEY, button.code(), 0)).collect()
+    }
+}
+
+// a button of the virtual device as a destination
+pub fn parse_button(name: &str, buttons: &[Key]) -> Result<Key, Mouse2JoyError> {
+    let button = parse_key(name)?;
+    if !buttons.contains(&button) && is_trigger_happy(button) {
+        return Err(Mouse2JoyError::InvalidConfig(format!("{:?} is beyond the configured extra_buttons", button)));
+    }
+    if is_keyboard_key(button) {
+        return Err(Mouse2JoyError::InvalidConfig(format!("{:?} is a keyboard key, map it in [keys]", button)));
+    }
+    if !buttons.contains(&button) {
+        return Err(Mouse2JoyError::InvalidConfig(format!("{:?} is not a button of the virtual device", button)));
+    }
+    Ok(button)
+}
+
+// two different sources joined by a '+'
+fn parse_chord(source: &str) -> Result<[Key; 2], Mouse2JoyError> {
+    let members = source.split('+').map(|name| parse_key(name.trim())).collect::<Result<Vec<_>, _>>()?;
+    match members[..] {
+        [first, second] if first != second => Ok([first, second]),
+        _ => Err(Mouse2JoyError::InvalidConfig(format!("the chord {} isn't two different sources", source))),
+    }
+}
+
+pub fn is_trigger_happy(key: Key) -> bool {
+    (Key::BTN_TRIGGER_HAPPY1.code()..=Key::BTN_TRIGGER_HAPPY40.code()).contains(&key.code())
+}
+
+pub fn parse_key(name: &str) -> Result<Key, Mouse2JoyError> {
+    name.parse::<Key>()
+        .map_err(|_| Mouse2JoyError::InvalidConfig(format!("unknown key \"{}\"", name)))
+}
@@ -0,0 +1,132 @@
+use log::warn;
+use std::sync::mpsc::{self, Sender};
+use std::time::{Duration, Instant};
+
+use crate::configuration::NotificationsConfig;
+
+// the state changes worth a desktop notification
+#[derive(Clone, Copy, PartialEq)]
+pub enum Notice {
+    Profile,
+    DeviceLost,
+    DeviceBack,
+    Failsafe,
+    Pause,
+    Resume,
+    AutoPause,
+    AutoResume,
+}
+
+impl Notice {
+    fn summary(self) -> &'static str {
+        match self {
+            Notice::Profile => "Profile switched",
+            Notice::DeviceLost => "Input device lost",
+            Notice::DeviceBack => "Input devices back",
+            Notice::Failsafe => "Failsafe tripped",
+            Notice::Pause => "Paused",
+            Notice::Resume => "Resumed",
+            Notice::AutoPause => "Paused",
+            Notice::AutoResume => "Resumed",
+        }
+    }
+
+    // low, normal or critical
+    fn urgency(self) -> u8 {
+        match self {
+            Notice::Profile | Notice::Pause | Notice::Resume | Notice::AutoPause | Notice::AutoResume => 0,
+            Notice::DeviceBack => 1,
+            Notice::DeviceLost | Notice::Failsafe => 2,
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+struct Notification {
+    summary: &'static str,
+    body: String,
+    urgency: u8,
+}
+
+// Desktop notifications through org.freedesktop.Notifications, sent by a
+// thread of their own so a slow bus never holds up the input. Each kind but
+// profile switches is sent at most once per min_interval_ms, so a flapping
+// device shows once.
+pub struct Notifier {
+    sender: Sender<Notification>,
+    min_interval: Duration,
+    sent: Vec<(Notice, Instant)>,
+}
+
+impl Notifier {
+    pub fn new(conf: &NotificationsConfig) -> Option<Self> {
+        if !conf.enabled {
+            return None;
+        }
+        if !cfg!(feature = "notifications") {
+            warn!("Notifications are enabled but mouse2joy was built without the notifications feature");
+            return None;
+        }
+        let (sender, receiver) = mpsc::channel::<Notification>();
+        #[cfg(feature = "notifications")]
+        std::thread::spawn(move || bus::run(receiver));
+        #[cfg(not(feature = "notifications"))]
+        drop(receiver);
+        Some(Self { sender, min_interval: Duration::from_millis(conf.min_interval_ms.into()), sent: Vec::new() })
+    }
+
+    pub fn notify(&mut self, notice: Notice, body: String, now: Instant) {
+        match self.sent.iter_mut().find(|(sent, _)| *sent == notice) {
+            // switching profiles is deliberate, every switch shows
+            _ if notice == Notice::Profile => {}
+            Some((_, at)) if now.duration_since(*at) < self.min_interval => return,
+            Some((_, at)) => *at = now,
+            None => self.sent.push((notice, now)),
+        }
+        let notification = Notification { summary: notice.summary(), body, urgency: notice.urgency() };
+        // the thread only ends when there is no notification server
+        let _ = self.sender.send(notification);
+    }
+}
+
+#[cfg(feature = "notifications")]
+mod bus {
+    use log::debug;
+    use std::sync::mpsc::Receiver;
+
+    use super::Notification;
+    use crate::dbus::wire::{self, Call};
+    use crate::dbus::Bus;
+
+    // Connects on the first notification. Without a session bus or a
+    // notification server that is logged once and the rest are dropped.
+    pub fn run(receiver: Receiver<Notification>) {
+        let mut bus = None;
+        for notification in receiver {
+            let sent = match bus.as_mut() {
+                Some(bus) => Ok(bus),
+                None => Bus::session().map(|connected| bus.insert(connected)),
+            }
+            .and_then(|bus| notify(bus, &notification));
+            if let Err(e) = sent {
+                debug!("No desktop notifications:  {}", e);
+                return;
+            }
+        }
+    }
+
+    fn notify(bus: &mut Bus, notification: &Notification) -> Result<(), String> {
+        let body =
+            wire::notify_body("mouse2joy", "input-gaming", notification.summary, &notification.body, notification.urgency);
+        bus.call(&Call {
+            destination: "org.freedesktop.Notifications",
+            path: "/org/freedesktop/Notifications",
+            interface: "org.freedesktop.Notifications",
+            member: "Notify",
+            signature: wire::NOTIFY_SIGNATURE,
+            body,
+        })
+        .map(drop)
+        .map_err(|e| format!("no notification server answered ({})", e))
+    }
+}
@@ -0,0 +1,147 @@
+use evdev::{
+    uinput::{VirtualDevice, VirtualDeviceBuilder},
+    AbsoluteAxisType, AttributeSet, EventType, InputEvent, InputId, Key, RelativeAxisType, Synchronization, UinputAbsSetup,
+};
+use log::warn;
+use std::time::Instant;
+
+use crate::classify::{classify, Class};
+
+// what one virtual device advertises
+#[derive(Clone)]
+pub struct DeviceSpec {
+    pub name: String,
+    pub id: InputId,
+    pub axes: Vec<UinputAbsSetup>,
+    // only the pointer of [mouse_look] has any
+    pub rel: Vec<RelativeAxisType>,
+    pub buttons: Vec<Key>,
+}
+
+impl DeviceSpec {
+    // how udev should classify the device, no input property is ever set
+    pub fn class(&self) -> Class {
+        let axes: Vec<u16> = self.axes.iter().map(UinputAbsSetup::code).collect();
+        let buttons: Vec<u16> = self.buttons.iter().map(|key| key.code()).collect();
+        classify(&axes, &buttons, &[])
+    }
+
+    fn advertises(&self, ev: &InputEvent) -> bool {
+        match ev.event_type() {
+            EventType::ABSOLUTE => self.axes.iter().any(|axis| axis.code() == ev.code()),
+            EventType::KEY => self.buttons.iter().any(|key| key.code() == ev.code()),
+            EventType::RELATIVE => self.rel.iter().any(|axis| axis.0 == ev.code()),
+            _ => false,
+        }
+    }
+}
+
+// The virtual devices of the output layout, one combined device or a wheel
+// with separate pedals (and shifter). Every event goes to the device that
+// advertises it.
+pub struct VirtualDevices {
+    parts: Vec<(VirtualDevice, DeviceSpec)>,
+}
+
+impl VirtualDevices {
+    // all or nothing, devices created before a failing one are removed again
+    pub fn create(specs: &[DeviceSpec]) -> std::io::Result<Self> {
+        let mut parts = Vec::new();
+        for spec in specs {
+            match create_device(spec) {
+                Ok(device) => parts.push((device, spec.clone())),
+                Err(e) => {
+                    if !parts.is_empty() {
+                        warn!("Failed to create \"{}\", removing the other virtual devices", spec.name);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(Self { parts })
+    }
+
+    pub fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()> {
+        if let [(device, _)] = &mut self.parts[..] {
+            return device.emit(events);
+        }
+        let mut result = Ok(());
+        for (device, spec) in &mut self.parts {
+            let routed: Vec<InputEvent> = events.iter().filter(|ev| spec.advertises(ev)).copied().collect();
+            if !routed.is_empty() {
+                result = result.and(device.emit(&routed));
+            }
+        }
+        result
+    }
+
+    // every device still has its node in sysfs
+    pub fn is_alive(&mut self) -> bool {
+        self.parts
+            .iter_mut()
+            .all(|(device, _)| device.get_syspath().is_ok_and(|path| path.exists()))
+    }
+
+    pub fn parts_mut(&mut self) -> impl Iterator<Item = (&mut VirtualDevice, &DeviceSpec)> {
+        self.parts.iter_mut().map(|(device, spec)| (device, &*spec))
+    }
+}
+
+fn create_device(spec: &DeviceSpec) -> std::io::Result<VirtualDevice> {
+    let mut builder = VirtualDeviceBuilder::new()?.name(&spec.name).input_id(spec.id.clone());
+    for axis in &spec.axes {
+        builder = builder.with_absolute_axis(axis)?;
+    }
+    if !spec.rel.is_empty() {
+        let mut rel = AttributeSet::new();
+        for axis in &spec.rel {
+            rel.insert(*axis);
+        }
+        builder = builder.with_relative_axes(&rel)?;
+    }
+    // with_keys advertises EV_KEY even for an empty set
+    if !spec.buttons.is_empty() {
+        let mut keys = AttributeSet::new();
+        for button in &spec.buttons {
+            keys.insert(*button);
+        }
+        builder = builder.with_keys(&keys)?;
+    }
+    builder.build()
+}
+
+// Where the processed events go: the virtual steering wheel, or stdout when
+// doing a dry run. The label tells the lines of several pipelines apart.
+pub enum Output {
+    Device(VirtualDevices),
+    DryRun { start: Instant, label: String },
+    // a --bench run without the virtual devices
+    Discard,
+}
+
+impl Output {
+    pub fn emit(&mut self, events: &[InputEvent], now: Instant) -> std::io::Result<()> {
+        match self {
+            Output::Device(devices) => devices.emit(events),
+            Output::DryRun { start, label } => {
+                let time = now.saturating_duration_since(*start).as_secs_f64();
+                for ev in events {
+                    println!("{:10.3} {}{}", time, label, describe(ev));
+                }
+                Ok(())
+            }
+            Output::Discard => Ok(()),
+        }
+    }
+}
+
+// "EV_ABS ABS_X 1200" style description of an event
+pub fn describe(ev: &InputEvent) -> String {
+    match ev.event_type() {
+        EventType::ABSOLUTE => format!("EV_ABS {:?} {}", AbsoluteAxisType(ev.code()), ev.value()),
+        EventType::KEY => format!("EV_KEY {:?} {}", Key::new(ev.code()), ev.value()),
+        EventType::RELATIVE => format!("EV_REL {:?} {}", RelativeAxisType(ev.code()), ev.value()),
+        EventType::SYNCHRONIZATION => format!("EV_SYN {:?} {}", Synchronization(ev.code()), ev.value()),
+        other => format!("{:?} {} {}", other, ev.code(), ev.value()),
+    }
+}
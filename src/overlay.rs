@@ -0,0 +1,202 @@
+use evdev::Key;
+use log::{info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::configuration::OverlayConfig;
+use crate::mapping::parse_key;
+use crate::publisher::Frame;
+use crate::Mouse2JoyError;
+
+// Small always on top window showing the steering angle and pedal levels,
+// drawn by its own thread from the latest frame so it never slows down the
+// input handling.
+pub struct Overlay {
+    frame: Arc<Mutex<Option<Frame>>>,
+    visible: Arc<AtomicBool>,
+    toggle: Option<Key>,
+}
+
+impl Overlay {
+    pub fn new(conf: &OverlayConfig) -> Result<Option<Self>, Mouse2JoyError> {
+        if !conf.enabled {
+            return Ok(None);
+        }
+        let toggle = conf.toggle.as_deref().map(parse_key).transpose()?;
+        if !cfg!(feature = "overlay") {
+            warn!("The overlay is enabled but mouse2joy was built without the overlay feature");
+            return Ok(None);
+        }
+        let overlay = Self {
+            frame: Arc::new(Mutex::new(None)),
+            visible: Arc::new(AtomicBool::new(true)),
+            toggle,
+        };
+        #[cfg(feature = "overlay")]
+        {
+            let (conf, frame, visible) = (conf.clone(), overlay.frame.clone(), overlay.visible.clone());
+            std::thread::spawn(move || {
+                if let Err(e) = x11::run(&conf, &frame, &visible) {
+                    warn!("Overlay unavailable, no X server reachable:  {}", e);
+                }
+            });
+        }
+        Ok(Some(overlay))
+    }
+
+    pub fn keys(&self) -> Vec<Key> {
+        self.toggle.into_iter().collect()
+    }
+
+    pub fn update(&self, frame: Frame) {
+        *self.frame.lock().unwrap_or_else(|e| e.into_inner()) = Some(frame);
+    }
+
+    // returns true if the key toggles the overlay
+    pub fn handle_key(&self, key: Key, value: i32) -> bool {
+        if Some(key) != self.toggle {
+            return false;
+        }
+        if value == 1 {
+            self.toggle();
+        }
+        true
+    }
+
+    pub fn toggle(&self) {
+        let visible = !self.visible.fetch_xor(true, Ordering::Relaxed);
+        info!("Overlay {}", if visible { "shown" } else { "hidden" });
+    }
+}
+
+#[cfg(feature = "overlay")]
+mod x11 {
+    use std::error::Error;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::shape::{self, ConnectionExt as _};
+    use x11rb::protocol::xproto::{
+        AtomEnum, ChangeGCAux, ClipOrdering, ConfigureWindowAux, ConnectionExt as _, CreateGCAux,
+        CreateWindowAux, PropMode, Rectangle, StackMode, WindowClass,
+    };
+    use x11rb::wrapper::ConnectionExt as _;
+    use x11rb::COPY_DEPTH_FROM_PARENT;
+
+    use crate::configuration::OverlayConfig;
+    use crate::publisher::Frame;
+
+    // ~60 fps
+    const FRAME_TIME: Duration = Duration::from_millis(16);
+    const PADDING: i16 = 4;
+    const PEDAL_WIDTH: u16 = 10;
+    const BACKGROUND: u32 = 0x202020;
+    const TRACK: u32 = 0x505050;
+    const STEERING: u32 = 0x3c8ce6;
+    // while the precision key is held
+    const STEERING_PRECISE: u32 = 0xe6c83c;
+    const THROTTLE: u32 = 0x3cc85a;
+    // the throttle while the cruise control holds it
+    const THROTTLE_HELD: u32 = 0x3cc8c8;
+    const BRAKE: u32 = 0xdc3c3c;
+
+    // An override-redirect window the window manager leaves alone, with an
+    // empty input shape so clicks go through to the game. Works on X11 and
+    // under XWayland.
+    pub fn run(conf: &OverlayConfig, frame: &Mutex<Option<Frame>>, visible: &AtomicBool) -> Result<(), Box<dyn Error>> {
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+        let window = conn.generate_id()?;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            conf.x,
+            conf.y,
+            conf.width,
+            conf.height,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new().override_redirect(1).background_pixel(BACKGROUND),
+        )?;
+        // only has an effect with a compositing manager
+        let opacity = conn.intern_atom(false, b"_NET_WM_WINDOW_OPACITY")?.reply()?.atom;
+        conn.change_property32(
+            PropMode::REPLACE,
+            window,
+            opacity,
+            AtomEnum::CARDINAL,
+            &[(conf.opacity.clamp(0.0, 1.0) as f64 * u32::MAX as f64) as u32],
+        )?;
+        conn.shape_rectangles(shape::SO::SET, shape::SK::INPUT, ClipOrdering::UNSORTED, window, 0, 0, &[])?;
+        let gc = conn.generate_id()?;
+        conn.create_gc(gc, window, &CreateGCAux::new())?;
+
+        let mut mapped = false;
+        let mut drawn = None;
+        loop {
+            std::thread::sleep(FRAME_TIME);
+            let show = visible.load(Ordering::Relaxed);
+            if show != mapped {
+                if show {
+                    conn.map_window(window)?;
+                    drawn = None;
+                } else {
+                    conn.unmap_window(window)?;
+                }
+                mapped = show;
+                conn.flush()?;
+            }
+            let latest = frame.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            if !mapped || latest.is_none() || latest == drawn {
+                continue;
+            }
+            drawn = latest.clone();
+            if let Some(latest) = latest {
+                let fill = |color: u32, rectangle: Rectangle| -> Result<(), Box<dyn Error>> {
+                    conn.change_gc(gc, &ChangeGCAux::new().foreground(color))?;
+                    conn.poly_fill_rectangle(window, gc, &[rectangle])?;
+                    Ok(())
+                };
+                draw(conf, &latest, fill)?;
+                // stay above windows raised since
+                conn.configure_window(window, &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE))?;
+                conn.flush()?;
+            }
+        }
+    }
+
+    // steering bar growing from the center, pedal bars on the right
+    fn draw(
+        conf: &OverlayConfig,
+        frame: &Frame,
+        mut fill: impl FnMut(u32, Rectangle) -> Result<(), Box<dyn Error>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let inner_height = conf.height.saturating_sub(2 * PADDING as u16);
+        let track_width = conf.width.saturating_sub(2 * PADDING as u16 + 2 * (PEDAL_WIDTH + PADDING as u16));
+        let track = Rectangle { x: PADDING, y: PADDING, width: track_width, height: inner_height };
+        fill(TRACK, track)?;
+
+        let center = PADDING + (track_width / 2) as i16;
+        let reach = (frame.angle_deg / (crate::WHEEL_ROTATION / 2.0)).clamp(-1.0, 1.0) * (track_width / 2) as f32;
+        let (x, width) = if reach < 0.0 {
+            (center + reach.round() as i16, (-reach).round() as u16)
+        } else {
+            (center, reach.round() as u16)
+        };
+        let steering = if frame.precision { STEERING_PRECISE } else { STEERING };
+        fill(steering, Rectangle { x, y: PADDING, width: width.max(2), height: inner_height })?;
+
+        let throttle = if frame.throttle_hold { THROTTLE_HELD } else { THROTTLE };
+        for (i, (level, color)) in [(frame.throttle, throttle), (frame.brake, BRAKE)].into_iter().enumerate() {
+            let x = PADDING + track_width as i16 + PADDING + i as i16 * (PEDAL_WIDTH as i16 + PADDING);
+            fill(TRACK, Rectangle { x, y: PADDING, width: PEDAL_WIDTH, height: inner_height })?;
+            let height = (level.clamp(0.0, 1.0) * inner_height as f32).round() as u16;
+            let y = PADDING + (inner_height - height) as i16;
+            fill(color, Rectangle { x, y, width: PEDAL_WIDTH, height })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+use evdev::{AbsInfo, AbsoluteAxisType, EventType, InputEvent, Key, UinputAbsSetup};
+use log::info;
+use std::time::{Duration, Instant};
+
+use crate::configuration::{MappingAction, PedalConfig, PedalsConfig};
+use crate::mapping::{parse_key, Mapping};
+use crate::Mouse2JoyError;
+
+mod ramp;
+use ramp::Ramp;
+pub use ramp::PEDAL_MAX;
+
+struct Pedal {
+    keys: Vec<Key>,
+    held: Vec<Key>,
+    axis: AbsoluteAxisType,
+    ramp: Ramp,
+    // driven by a pedals mouse too
+    mouse: bool,
+    // level set by the pedals mouse, the higher of it and the ramp counts
+    analog: i32,
+    emitted: i32,
+    // sent the other way round, the game reads it full at rest
+    inverted: bool,
+}
+
+impl Pedal {
+    fn new(
+        conf: &PedalConfig,
+        axis: AbsoluteAxisType,
+        inverted: bool,
+        mapping: &Mapping,
+        now: Instant,
+    ) -> Result<Self, Mouse2JoyError> {
+        let mut keys = conf
+            .keys
+            .iter()
+            .map(|name| parse_key(name))
+            .collect::<Result<Vec<Key>, Mouse2JoyError>>()?;
+        keys.extend(mapping.axis_sources(axis));
+
+        check_curve(conf.curve)?;
+
+        Ok(Self {
+            keys,
+            held: Vec::new(),
+            axis,
+            ramp: Ramp::new(
+                Duration::from_millis(conf.rise_ms.into()),
+                Duration::from_millis(conf.fall_ms.into()),
+                conf.curve,
+                now,
+            ),
+            mouse: false,
+            analog: 0,
+            emitted: 0,
+            inverted,
+        })
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.keys.is_empty() || self.mouse
+    }
+
+    fn is_held(&self) -> bool {
+        !self.held.is_empty()
+    }
+
+    // returns true if the key belongs to this pedal
+    fn handle_key(&mut self, key: Key, value: i32) -> bool {
+        if !self.keys.contains(&key) {
+            return false;
+        }
+        match value {
+            0 => self.held.retain(|held| *held != key),
+            1 if !self.held.contains(&key) => self.held.push(key),
+            // key repeats (2) don't change anything
+            _ => {}
+        }
+        true
+    }
+
+    // event for the current value if it changed since the last emitted one,
+    // a held value beats the live input
+    fn changed(&mut self, hold: Option<i32>) -> Option<InputEvent> {
+        let value = hold.unwrap_or_else(|| self.ramp.value().max(self.analog));
+        if value == self.emitted {
+            return None;
+        }
+        self.emitted = value;
+        Some(InputEvent::new(EventType::ABSOLUTE, self.axis.0, value))
+    }
+}
+
+fn check_curve(curve: f32) -> Result<(), Mouse2JoyError> {
+    if curve <= 0.0 {
+        return Err(Mouse2JoyError::InvalidConfig(format!("pedal curve must be positive, got {}", curve)));
+    }
+    Ok(())
+}
+
+fn mouse_sensitivity(sensitivity: u32) -> Result<i32, Mouse2JoyError> {
+    if sensitivity == 0 || sensitivity > PEDAL_MAX as u32 {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "pedals mouse_sensitivity must be between 1 and {}, got {}",
+            PEDAL_MAX, sensitivity
+        )));
+    }
+    Ok(sensitivity as i32)
+}
+
+// axes a pedal can live on
+pub const PEDAL_AXES: [AbsoluteAxisType; 2] = [AbsoluteAxisType::ABS_GAS, AbsoluteAxisType::ABS_BRAKE];
+
+// Keyboard (or button) driven throttle and brake pedals. Sources are the
+// pedal keys plus every key/button mapped to the pedal's axis. Ramps are time
+// based so `tick` must be called regularly while `is_settled` is false. A
+// second mouse can drive both pedals as well: pushed forward it gives
+// throttle, pulled back brake, and it stays where it was left.
+pub struct Pedals {
+    throttle: Pedal,
+    brake: Pedal,
+    brake_overrides_throttle: bool,
+    mouse_sensitivity: i32,
+    // pedals mouse position in pedal units, negative is throttle
+    travel: i32,
+    // sources toggling the throttle hold
+    hold_keys: Vec<Key>,
+    // the throttle value held by them
+    hold: Option<i32>,
+}
+
+impl Pedals {
+    pub fn new(conf: &PedalsConfig, mapping: &Mapping, now: Instant) -> Result<Self, Mouse2JoyError> {
+        Ok(Self {
+            throttle: Pedal::new(&conf.throttle, AbsoluteAxisType::ABS_GAS, conf.invert_throttle, mapping, now)?,
+            brake: Pedal::new(&conf.brake, AbsoluteAxisType::ABS_BRAKE, conf.invert_brake, mapping, now)?,
+            brake_overrides_throttle: conf.brake_overrides_throttle,
+            mouse_sensitivity: mouse_sensitivity(conf.mouse_sensitivity)?,
+            travel: 0,
+            hold_keys: mapping
+                .action_sources()
+                .into_iter()
+                .filter(|(_, action, _)| *action == MappingAction::ThrottleHoldToggle)
+                .map(|(key, _, _)| key)
+                .collect(),
+            hold: None,
+        })
+    }
+
+    // a pedals mouse drives both pedals, so both axes get advertised
+    pub fn enable_mouse(&mut self) {
+        self.throttle.mouse = true;
+        self.brake.mouse = true;
+    }
+
+    // vertical movement of the pedals mouse
+    pub fn handle_rel_y(&mut self, dy: i32, now: Instant) -> Vec<InputEvent> {
+        let travel = i64::from(self.travel) + i64::from(dy) * i64::from(self.mouse_sensitivity);
+        self.travel = travel.clamp(-i64::from(PEDAL_MAX), i64::from(PEDAL_MAX)) as i32;
+        self.throttle.analog = (-self.travel).max(0);
+        self.brake.analog = self.travel.max(0);
+        if self.brake.analog > 0 {
+            self.end_hold("braking", now);
+        }
+        self.changed()
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &Pedal> {
+        [&self.throttle, &self.brake]
+            .into_iter()
+            .filter(|pedal| pedal.is_enabled())
+    }
+
+    // all keys driving a pedal or the throttle hold
+    pub fn keys(&self) -> Vec<Key> {
+        let mut keys: Vec<Key> = self.enabled().flat_map(|pedal| pedal.keys.clone()).collect();
+        keys.extend(&self.hold_keys);
+        keys
+    }
+
+    // axes to advertise on the virtual device, each starting out released
+    pub fn axes(&self) -> Vec<UinputAbsSetup> {
+        self.enabled()
+            .map(|pedal| {
+                let rest = if pedal.inverted { PEDAL_MAX } else { 0 };
+                UinputAbsSetup::new(pedal.axis, AbsInfo::new(rest, 0, PEDAL_MAX, 0, 0, 0))
+            })
+            .collect()
+    }
+
+    // the advertised axes sent inverted, see Guard::set_inverted
+    pub fn inverted(&self) -> Vec<AbsoluteAxisType> {
+        self.enabled().filter(|pedal| pedal.inverted).map(|pedal| pedal.axis).collect()
+    }
+
+    // take over the ramp timing of another profile, the keys stay
+    pub fn reconfigure(&mut self, conf: &PedalsConfig, now: Instant) -> Result<(), Mouse2JoyError> {
+        for pedal in [&conf.throttle, &conf.brake] {
+            check_curve(pedal.curve)?;
+        }
+        let sensitivity = mouse_sensitivity(conf.mouse_sensitivity)?;
+        for (pedal, conf) in [(&mut self.throttle, &conf.throttle), (&mut self.brake, &conf.brake)] {
+            pedal.ramp.reshape(
+                Duration::from_millis(conf.rise_ms.into()),
+                Duration::from_millis(conf.fall_ms.into()),
+                conf.curve,
+                now,
+            );
+        }
+        self.brake_overrides_throttle = conf.brake_overrides_throttle;
+        self.mouse_sensitivity = sensitivity;
+        self.update_ramps(now);
+        Ok(())
+    }
+
+    // returns true if the key was consumed by a pedal or the throttle hold
+    pub fn handle_key(&mut self, key: Key, value: i32, now: Instant) -> bool {
+        if self.hold_keys.contains(&key) {
+            if value == 1 {
+                self.toggle_hold(now);
+            }
+            return true;
+        }
+        let consumed = self.throttle.handle_key(key, value) | self.brake.handle_key(key, value);
+        if consumed {
+            if self.brake.is_held() {
+                self.end_hold("braking", now);
+            }
+            self.update_ramps(now);
+        }
+        consumed
+    }
+
+    // Freeze the throttle where it is, or let go of it again. Released, the
+    // throttle falls from the held value with its ramp, or follows the
+    // pedal if that gives more, so it never jumps.
+    fn toggle_hold(&mut self, now: Instant) {
+        if self.hold.is_some() {
+            self.end_hold("toggled off", now);
+            return;
+        }
+        if self.throttle.emitted == 0 {
+            info!("No throttle to hold, give some throttle before engaging the throttle hold");
+            return;
+        }
+        self.hold = Some(self.throttle.emitted);
+        info!("Throttle held at {:.0}%", self.throttle.emitted as f32 / PEDAL_MAX as f32 * 100.0);
+    }
+
+    fn end_hold(&mut self, reason: &str, now: Instant) {
+        if let Some(held) = self.hold.take() {
+            self.throttle.ramp.take_over(held, now);
+            info!("Throttle hold released, {}", reason);
+        }
+    }
+
+    pub fn is_holding(&self) -> bool {
+        self.hold.is_some()
+    }
+
+    fn changed(&mut self) -> Vec<InputEvent> {
+        let hold = self.hold;
+        [(&mut self.throttle, hold), (&mut self.brake, None)]
+            .into_iter()
+            .filter(|(pedal, _)| pedal.is_enabled())
+            .filter_map(|(pedal, hold)| pedal.changed(hold))
+            .collect()
+    }
+
+    // feed the held state of every pedal into its ramp, a held brake cuts
+    // the throttle instantly when it overrides it
+    fn update_ramps(&mut self, now: Instant) {
+        let overridden = self.brake_overrides_throttle && self.brake.is_held();
+        if overridden {
+            self.throttle.ramp.cut();
+        }
+        self.throttle
+            .ramp
+            .set_pressed(self.throttle.is_held() && !overridden, now);
+        self.brake.ramp.set_pressed(self.brake.is_held(), now);
+    }
+
+    // advance the ramps and return events for every axis that changed
+    pub fn tick(&mut self, now: Instant) -> Vec<InputEvent> {
+        self.throttle.ramp.advance(now);
+        self.brake.ramp.advance(now);
+        self.changed()
+    }
+
+    // Drop the throttle, and the brake unless it holds, at once. Held keys
+    // and the throttle hold are forgotten and the pedals mouse goes back to
+    // rest, so it takes a new press or movement to bring them back.
+    pub fn release(&mut self, brake: bool, now: Instant) -> Vec<InputEvent> {
+        if self.hold.take().is_some() {
+            info!("Throttle hold released");
+        }
+        let mut pedals = vec![&mut self.throttle];
+        if brake {
+            pedals.push(&mut self.brake);
+        }
+        for pedal in &mut pedals {
+            pedal.held.clear();
+            pedal.ramp.set_pressed(false, now);
+            pedal.ramp.cut();
+            pedal.analog = 0;
+        }
+        self.travel = if brake { 0 } else { self.travel.max(0) };
+        pedals
+            .into_iter()
+            .filter(|pedal| pedal.is_enabled())
+            .filter_map(|pedal| pedal.changed(None))
+            .collect()
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.throttle.ramp.is_settled() && self.brake.ramp.is_settled()
+    }
+
+    // last emitted throttle and brake values
+    pub fn values(&self) -> (i32, i32) {
+        (self.throttle.emitted, self.brake.emitted)
+    }
+
+    // last emitted throttle and brake values as fractions of full travel
+    pub fn levels(&self) -> (f32, f32) {
+        (
+            self.throttle.emitted as f32 / PEDAL_MAX as f32,
+            self.brake.emitted as f32 / PEDAL_MAX as f32,
+        )
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+// pedal axes range from released (0) to fully pressed
+pub const PEDAL_MAX: i32 = 1023;
+
+// Time based ramp turning a digital (pressed/released) input into an analog
+// level. The level rises from 0 to 1 in `rise` while pressed and falls back
+// in `fall` once released, the curve exponent shapes the emitted value.
+pub struct Ramp {
+    rise: Duration,
+    fall: Duration,
+    curve: f32,
+    level: f32,
+    pressed: bool,
+    last_update: Instant,
+}
+
+impl Ramp {
+    pub fn new(rise: Duration, fall: Duration, curve: f32, now: Instant) -> Self {
+        Self {
+            rise,
+            fall,
+            curve,
+            level: 0.0,
+            pressed: false,
+            last_update: now,
+        }
+    }
+
+    pub fn set_pressed(&mut self, pressed: bool, now: Instant) {
+        self.advance(now);
+        self.pressed = pressed;
+    }
+
+    // move the level towards its target by the time elapsed since the last update
+    pub fn advance(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update);
+        self.last_update = now;
+
+        let (span, direction) = if self.pressed {
+            (self.rise, 1.0)
+        } else {
+            (self.fall, -1.0)
+        };
+
+        if span.is_zero() {
+            self.level = if self.pressed { 1.0 } else { 0.0 };
+        } else {
+            let step = elapsed.as_secs_f32() / span.as_secs_f32();
+            self.level = (self.level + direction * step).clamp(0.0, 1.0);
+        }
+    }
+
+    // new timing and curve, the level carries on from where it is
+    pub fn reshape(&mut self, rise: Duration, fall: Duration, curve: f32, now: Instant) {
+        self.advance(now);
+        self.rise = rise;
+        self.fall = fall;
+        self.curve = curve;
+    }
+
+    // drop the level to zero instantly
+    pub fn cut(&mut self) {
+        self.level = 0.0;
+    }
+
+    // carry on from the level that emits `value`
+    pub fn take_over(&mut self, value: i32, now: Instant) {
+        self.advance(now);
+        let level = (value as f32 / PEDAL_MAX as f32).clamp(0.0, 1.0).powf(1.0 / self.curve);
+        self.level = self.level.max(level);
+    }
+
+    pub fn is_settled(&self) -> bool {
+        if self.pressed {
+            self.level >= 1.0
+        } else {
+            self.level <= 0.0
+        }
+    }
+
+    pub fn value(&self) -> i32 {
+        (self.level.powf(self.curve) * PEDAL_MAX as f32).round() as i32
+    }
+}
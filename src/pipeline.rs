@@ -0,0 +1,213 @@
+use log::{error, warn};
+use nix::sys::pthread::{pthread_kill, pthread_self, Pthread};
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+
+use crate::cli::Args;
+use crate::configuration::PipelineConfig;
+use crate::{daemon, logging, privileges, profile, Mouse2JoyError, LOOP_SIGNALS};
+
+// interrupts the poll of a pipeline so it sees a signal, and tells the main
+// thread that a pipeline ended
+pub const WAKE: Signal = Signal::SIGURG;
+
+// One of several virtual wheels running side by side, each with its own
+// devices, settings and loop. They select their devices one after another,
+// so the prompts don't mix and no mouse goes to two of them.
+pub struct Pipeline<'a> {
+    pub index: usize,
+    pub name: &'a str,
+    pub profile: Option<&'a str>,
+    taken: &'a Mutex<Vec<PathBuf>>,
+    ready: Option<Sender<Pthread>>,
+}
+
+impl Pipeline<'_> {
+    // a file of its own for every pipeline, FILE.NAME
+    pub fn file(&self, file: &Path) -> PathBuf {
+        let mut file = file.to_path_buf().into_os_string();
+        file.push(format!(".{}", self.name));
+        PathBuf::from(file)
+    }
+
+    // the mice the pipelines before this one use
+    pub fn taken(&self) -> Vec<PathBuf> {
+        self.taken.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn take(&self, mice: impl IntoIterator<Item = PathBuf>) {
+        self.taken.lock().unwrap_or_else(|e| e.into_inner()).extend(mice);
+    }
+
+    // the devices are selected, the next pipeline may start
+    pub fn ready(&self) {
+        if let Some(ready) = &self.ready {
+            let _ = ready.send(pthread_self());
+        }
+    }
+}
+
+// counts down when a pipeline thread ends, also by a panic, and wakes the
+// main thread to have a look
+struct Ended<'a> {
+    main: Pthread,
+    running: &'a AtomicUsize,
+}
+
+impl Drop for Ended<'_> {
+    fn drop(&mut self) {
+        self.running.fetch_sub(1, Ordering::Relaxed);
+        let _ = pthread_kill(self.main, WAKE);
+    }
+}
+
+extern "C" fn wake(_: nix::libc::c_int) {}
+
+// the default disposition of the wake signal drops it before it interrupts
+// anything
+pub fn install_wake() -> nix::Result<()> {
+    let action = SigAction::new(SigHandler::Handler(wake), SaFlags::empty(), SigSet::empty());
+    // SAFETY: the handler does nothing
+    unsafe { sigaction(WAKE, &action) }.map(drop)
+}
+
+// Run every pipeline of the file. --doctor and --print-sdl-mapping go through
+// them in turn, otherwise they run at the same time until all of them ended.
+// A failing pipeline leaves the others running; its error is returned once
+// they are done.
+pub fn run<'a>(args: &Args, config_path: &Path, pipelines: &'a [PipelineConfig]) -> Result<(), Mouse2JoyError> {
+    check(args, config_path, pipelines).inspect_err(|e| error!("{}", e))?;
+    let taken = Mutex::new(Vec::new());
+    let pipeline = |index: usize, config: &'a PipelineConfig, ready| Pipeline {
+        index,
+        name: &config.name,
+        profile: config.profile.as_deref(),
+        taken: &taken,
+        ready,
+    };
+    if args.doctor || args.print_sdl_mapping {
+        for (index, config) in pipelines.iter().enumerate() {
+            logging::set_pipeline(Some(&config.name));
+            let result = crate::start_pipeline(args, config_path, Some(&pipeline(index, config, None)));
+            logging::set_pipeline(None);
+            result?;
+        }
+        return Ok(());
+    }
+
+    if let Err(e) = install_wake() {
+        warn!("Failed to install a {} handler, signals reach the pipelines late:  {}", WAKE, e);
+    }
+    // pending for the sigwait below, the pipelines unblock it again
+    let _ = SigSet::from(WAKE).thread_block();
+    let main = pthread_self();
+    let running = &AtomicUsize::new(0);
+    daemon::expect(pipelines.len());
+    privileges::expect(pipelines.len());
+
+    std::thread::scope(|scope| {
+        let mut started = Vec::new();
+        for (index, config) in pipelines.iter().enumerate() {
+            let (ready, readied) = mpsc::channel();
+            let pipeline = pipeline(index, config, Some(ready));
+            running.fetch_add(1, Ordering::Relaxed);
+            let spawned = std::thread::Builder::new().name(pipeline.name.to_string()).spawn_scoped(scope, move || {
+                let _ended = Ended { main, running };
+                // only the main thread takes the signals, and passes them on
+                let _ = SigSet::from_iter(LOOP_SIGNALS).thread_block();
+                let _ = SigSet::from(WAKE).thread_unblock();
+                logging::set_pipeline(Some(pipeline.name));
+                let result = crate::start_pipeline(args, config_path, Some(&pipeline));
+                if result.is_err() {
+                    daemon::failed(pipeline.index);
+                    privileges::failed(pipeline.index);
+                    if running.load(Ordering::Relaxed) > 1 {
+                        warn!("Stopped, the other pipelines keep running");
+                    }
+                }
+                result
+            });
+            match spawned {
+                // the pipeline's thread, once its devices are selected
+                Ok(handle) => started.push((handle, readied.recv().ok())),
+                Err(e) => {
+                    running.fetch_sub(1, Ordering::Relaxed);
+                    error!("Failed to start the pipeline \"{}\":  {}", config.name, e);
+                }
+            }
+        }
+
+        // the signals are taken here from now on, during the prompts they
+        // still end the process right away
+        let mut waited = SigSet::from_iter(LOOP_SIGNALS);
+        waited.add(WAKE);
+        let _ = waited.thread_block();
+        while running.load(Ordering::Relaxed) > 0 {
+            match waited.wait() {
+                Ok(WAKE) => continue,
+                Ok(signal) => crate::record_signal(signal),
+                Err(e) => {
+                    warn!("Failed to wait for signals, they are ignored from now on:  {}", e);
+                    break;
+                }
+            }
+            for thread in started.iter().filter_map(|(_, thread)| *thread) {
+                let _ = pthread_kill(thread, WAKE);
+            }
+        }
+
+        let mut result = Ok(());
+        let mut panic = None;
+        for (handle, _) in started {
+            match handle.join() {
+                Ok(Err(e)) if result.is_ok() => result = Err(e),
+                Ok(_) => {}
+                Err(payload) => panic = Some(payload),
+            }
+        }
+        if let Some(payload) = panic {
+            std::panic::resume_unwind(payload);
+        }
+        result
+    })
+}
+
+// Every pipeline needs a name of its own, and the profiles they name need
+// to exist before any of them starts.
+fn check(args: &Args, config_path: &Path, pipelines: &[PipelineConfig]) -> Result<(), Mouse2JoyError> {
+    if args.profile.is_some() {
+        return Err(Mouse2JoyError::InvalidArguments(String::from(
+            "--profile doesn't apply to [[pipelines]], each of them names its own profile",
+        )));
+    }
+    if !args.input_scripts.is_empty() && args.input_scripts.len() != pipelines.len() {
+        return Err(Mouse2JoyError::InvalidArguments(format!(
+            "--input-script was given {} time(s) for {} pipelines",
+            args.input_scripts.len(),
+            pipelines.len()
+        )));
+    }
+    for (i, pipeline) in pipelines.iter().enumerate() {
+        if pipeline.name.is_empty() {
+            return Err(Mouse2JoyError::InvalidConfig(String::from("every [[pipelines]] entry needs a name")));
+        }
+        if pipelines[..i].iter().any(|other| other.name == pipeline.name) {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "[[pipelines]] lists \"{}\" more than once",
+                pipeline.name
+            )));
+        }
+        if let Some(name) = &pipeline.profile {
+            profile::load(config_path, name).map_err(|e| match e {
+                Mouse2JoyError::InvalidArguments(message) => {
+                    Mouse2JoyError::InvalidConfig(format!("pipeline \"{}\": {}", pipeline.name, message))
+                }
+                e => e,
+            })?;
+        }
+    }
+    Ok(())
+}
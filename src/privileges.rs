@@ -0,0 +1,119 @@
+use log::{error, info};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::{geteuid, getegid, getgid, getuid, initgroups, setgid, setuid, Gid, Pid, Uid, User};
+use std::ffi::CString;
+use std::sync::Mutex;
+
+use crate::configuration::Config;
+use crate::Mouse2JoyError;
+
+// the wheels still setting up, the privileges are dropped once all of them
+// are, the later pipelines still open their devices as root
+static PENDING: Mutex<Pending> = Mutex::new(Pending { wheels: Vec::new(), pipelines: false, user: None });
+
+struct Pending {
+    // empty for the one wheel
+    wheels: Vec<usize>,
+    pipelines: bool,
+    user: Option<Target>,
+}
+
+// the user mouse2joy runs as once the devices are set up
+pub struct Target {
+    name: String,
+    uid: Uid,
+    gid: Gid,
+}
+
+// Whom to become: run_as, a user name or uid, or under sudo the user who ran
+// it. None when not running as root, with --keep-root, or when neither says
+// who.
+pub fn target(conf: &Config, keep_root: bool) -> Result<Option<Target>, Mouse2JoyError> {
+    if !geteuid().is_root() || keep_root {
+        return Ok(None);
+    }
+    let (spec, what) = match (&conf.run_as, std::env::var("SUDO_UID")) {
+        (Some(run_as), _) => (run_as.clone(), "run_as"),
+        (None, Ok(uid)) => (uid, "SUDO_UID"),
+        (None, Err(_)) => return Ok(None),
+    };
+    let found = match spec.parse() {
+        Ok(uid) => User::from_uid(Uid::from_raw(uid)),
+        Err(_) => User::from_name(&spec),
+    };
+    let user = found
+        .map_err(|e| Mouse2JoyError::InvalidConfig(format!("{}: can't look up the user \"{}\":  {}", what, spec, e)))?
+        .ok_or_else(|| Mouse2JoyError::InvalidConfig(format!("{}: there is no user \"{}\"", what, spec)))?;
+    if user.uid.is_root() {
+        return Ok(None);
+    }
+    Ok(Some(Target { name: user.name, uid: user.uid, gid: user.gid }))
+}
+
+// the [[pipelines]] to wait for instead of the one wheel
+pub fn expect(wheels: usize) {
+    let mut pending = lock(&PENDING);
+    pending.wheels = (0..wheels).collect();
+    pending.pipelines = true;
+}
+
+// A wheel is set up. Once all of them are the process becomes the user, the
+// threads all at once. With [[pipelines]] a failing drop stops every wheel,
+// none keeps running as root.
+pub fn ready(wheel: usize, user: Option<Target>) -> Result<(), Mouse2JoyError> {
+    settle(wheel, user).inspect_err(|_| {
+        if lock(&PENDING).pipelines {
+            let _ = kill(Pid::this(), Signal::SIGTERM);
+        }
+    })
+}
+
+// a pipeline that failed to set up doesn't hold back the others
+pub fn failed(wheel: usize) {
+    if let Err(e) = ready(wheel, None) {
+        error!("{}", e);
+    }
+}
+
+fn settle(wheel: usize, user: Option<Target>) -> Result<(), Mouse2JoyError> {
+    let mut pending = lock(&PENDING);
+    pending.wheels.retain(|pending| *pending != wheel);
+    if user.is_some() {
+        pending.user = user;
+    }
+    if !pending.wheels.is_empty() {
+        return Ok(());
+    }
+    let Some(user) = pending.user.take() else {
+        return Ok(());
+    };
+    drop_to(&user)?;
+    info!("Running as {} (uid {}) from now on", user.name, user.uid);
+    Ok(())
+}
+
+// The groups first, they need root, then the gid and the uid. Afterwards
+// every id has to be the user's, and getting root back has to fail.
+fn drop_to(user: &Target) -> Result<(), Mouse2JoyError> {
+    let failed = |step: &str, e: nix::Error| {
+        Mouse2JoyError::FailedToDropPrivileges(format!("{} for {} failed:  {}", step, user.name, e))
+    };
+    let name = CString::new(user.name.as_str()).map_err(|_| {
+        Mouse2JoyError::FailedToDropPrivileges(format!("the user name {:?} has a nul in it", user.name))
+    })?;
+    initgroups(&name, user.gid).map_err(|e| failed("initgroups", e))?;
+    setgid(user.gid).map_err(|e| failed("setgid", e))?;
+    setuid(user.uid).map_err(|e| failed("setuid", e))?;
+    let dropped = getuid() == user.uid && geteuid() == user.uid && getgid() == user.gid && getegid() == user.gid;
+    if !dropped || setuid(Uid::from_raw(0)).is_ok() {
+        return Err(Mouse2JoyError::FailedToDropPrivileges(format!(
+            "still able to act as root after switching to {}",
+            user.name
+        )));
+    }
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
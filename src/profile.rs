@@ -0,0 +1,510 @@
+use evdev::{Device, Key};
+use log::{info, warn};
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+use crate::configuration::{self, Config, ProfileKeysConfig};
+use crate::hotkey::{self, Combo};
+use crate::Mouse2JoyError;
+
+// version of the exported format, bump it and add a step to `migrate` when a
+// config change needs older exports rewritten
+const FORMAT_VERSION: i64 = 1;
+
+// Profiles are named sets of overrides under [profiles.NAME], applied on top
+// of the rest of the file. A profile with `inherit = false` replaces the base
+// settings instead, imported profiles are stored that way so they behave
+// exactly like they did for whoever exported them.
+pub fn load(path: &Path, name: &str) -> Result<Config, Mouse2JoyError> {
+    effective(&read_migrated(path)?, Some(name), &[], path)
+}
+
+fn read_migrated(path: &Path) -> Result<Table, Mouse2JoyError> {
+    let (document, _) = Config::document(path)?;
+    document
+        .to_string()
+        .parse()
+        .map_err(|e| Mouse2JoyError::InvalidConfig(format!("{}: {}", path.display(), e)))
+}
+
+// The settings a file gives for a profile and the selected devices. The
+// profile beats the device sections, which beat the rest of the file.
+fn effective(file: &Table, name: Option<&str>, devices: &[&str], path: &Path) -> Result<Config, Mouse2JoyError> {
+    let profile = name
+        .map(|name| {
+            file.get("profiles")
+                .and_then(Value::as_table)
+                .and_then(|profiles| profiles.get(name))
+                .and_then(Value::as_table)
+                .cloned()
+                .ok_or_else(|| {
+                    Mouse2JoyError::InvalidArguments(format!("no profile \"{}\" in {}", name, path.display()))
+                })
+        })
+        .transpose()?;
+    let inherit = profile
+        .as_ref()
+        .and_then(|profile| profile.get("inherit"))
+        .is_none_or(|inherit| inherit.as_bool() != Some(false));
+
+    let mut settings = if inherit { file.clone() } else { Table::new() };
+    settings.remove("profiles");
+    settings.remove("device");
+    for device in devices {
+        if let Some(section) = device_table(file, device) {
+            merge(&mut settings, section.clone());
+        }
+    }
+    if settings.get("launch").and_then(Value::as_array).is_some_and(|launch| !launch.is_empty()) {
+        return Err(launch_outside_profile(path));
+    }
+    if let Some(mut profile) = profile {
+        if profile.contains_key("allow_launch") {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "profile \"{}\": allow_launch only goes in the base settings",
+                name.unwrap_or_default()
+            )));
+        }
+        profile.remove("inherit");
+        merge(&mut settings, profile);
+    }
+    // only the base settings of the file let a profile launch, also one
+    // that doesn't inherit them
+    match file.get("allow_launch") {
+        Some(allow) => settings.insert(String::from("allow_launch"), allow.clone()),
+        None => settings.remove("allow_launch"),
+    };
+    settings.try_into().map_err(|e| {
+        let section = match (name, devices.last()) {
+            (Some(name), _) => format!("profile \"{}\"", name),
+            (None, Some(device)) => format!("[device.\"{}\"]", device),
+            (None, None) => path.display().to_string(),
+        };
+        Mouse2JoyError::InvalidConfig(format!("{}: {}", section, e))
+    })
+}
+
+// a launch command outside a profile would run with every one of them
+pub fn launch_outside_profile(path: &Path) -> Mouse2JoyError {
+    Mouse2JoyError::InvalidConfig(format!("{}: launch only goes in a [profiles.NAME] table", path.display()))
+}
+
+fn device_table<'a>(file: &'a Table, device: &str) -> Option<&'a Table> {
+    file.get("device").and_then(Value::as_table)?.get(device)?.as_table()
+}
+
+// Per device settings live under [device."NAME"], NAME being the name or the
+// uniq of a mouse. A section applies once its mouse is selected, so it may
+// only hold what a profile switch can apply while running. Every section is
+// checked up front, combined with the profile, and returned for validation.
+pub fn check_devices(path: &Path, profile: Option<&str>) -> Result<Vec<Config>, Mouse2JoyError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = read_migrated(path)?;
+    let names: Vec<String> = file
+        .get("device")
+        .and_then(Value::as_table)
+        .into_iter()
+        .flat_map(Table::keys)
+        .cloned()
+        .collect();
+    let base = effective(&file, None, &[], path)?;
+    let mut configs = Vec::new();
+    for name in names {
+        let cold = restart_settings(&base, &effective(&file, None, &[&name], path)?);
+        if !cold.is_empty() {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "[device.\"{}\"] sets {}, a device section can only change what a profile switch applies",
+                name,
+                cold.join(", ")
+            )));
+        }
+        configs.push(effective(&file, profile, &[&name], path)?);
+    }
+    Ok(configs)
+}
+
+// the section matching the mouse by name or uniq, if any
+pub fn device_section(path: &Path, device: &Device) -> Result<Option<String>, Mouse2JoyError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file = read_migrated(path)?;
+    let Some(sections) = file.get("device").and_then(Value::as_table) else {
+        return Ok(None);
+    };
+    let ids = [device.name(), device.unique_name()];
+    Ok(sections
+        .keys()
+        .find(|section| ids.contains(&Some(section.as_str())))
+        .cloned())
+}
+
+// the settings with the sections of the selected devices, and the dotted
+// keys they change compared to `running`
+pub fn for_devices(
+    path: &Path,
+    profile: Option<&str>,
+    devices: &[&str],
+    running: &Config,
+) -> Result<(Config, Vec<String>), Mouse2JoyError> {
+    let conf = effective(&read_migrated(path)?, profile, devices, path)?;
+    let (Ok(Value::Table(before)), Ok(Value::Table(after))) = (Value::try_from(running), Value::try_from(&conf)) else {
+        return Ok((conf, Vec::new()));
+    };
+    let mut changed = Vec::new();
+    differences(&before, &after, "", &mut changed);
+    Ok((conf, changed))
+}
+
+// tables are merged key by key, anything else is replaced
+fn merge(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overrides)) => merge(base, overrides),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+// write every effective value of a profile to `file`, or stdout
+pub fn export(path: &Path, name: &str, file: Option<&Path>) -> Result<(), Mouse2JoyError> {
+    let mut settings = settings_table(&load(path, name)?)?;
+    // whoever imports it decides that for themselves
+    settings.remove("allow_launch");
+    let mut exported = Table::new();
+    exported.insert(String::from("format_version"), Value::Integer(FORMAT_VERSION));
+    exported.insert(String::from("name"), Value::String(name.to_string()));
+    exported.insert(String::from("settings"), Value::Table(settings));
+    let text = toml::to_string(&exported).expect("a toml table always serializes");
+
+    match file {
+        Some(file) => {
+            fs::write(file, text).map_err(|e| Mouse2JoyError::FailedToWrite(file.display().to_string(), e))?;
+            info!("Profile \"{}\" exported to {}", name, file.display());
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+// add an exported profile to the config at `path`, keeping its formatting
+pub fn import(path: &Path, file: &Path, force: bool) -> Result<(), Mouse2JoyError> {
+    let mut exported = read_table(file)?;
+    let invalid = |reason: String| Mouse2JoyError::InvalidConfig(format!("{}: {}", file.display(), reason));
+
+    let version = exported
+        .get("format_version")
+        .and_then(Value::as_integer)
+        .ok_or_else(|| invalid(String::from("not an exported profile, format_version is missing")))?;
+    if version > FORMAT_VERSION {
+        return Err(invalid(format!(
+            "exported by a newer mouse2joy (format {}), this one reads up to format {}",
+            version, FORMAT_VERSION
+        )));
+    }
+    migrate(version, &mut exported);
+    let name = match exported.get("name") {
+        Some(Value::String(name)) if !name.is_empty() => name.clone(),
+        _ => return Err(invalid(String::from("the profile has no name"))),
+    };
+    let Some(Value::Table(settings)) = exported.remove("settings") else {
+        return Err(invalid(String::from("the profile has no settings")));
+    };
+    // the settings are in the config format of whoever exported them
+    let mut settings: toml_edit::DocumentMut = toml::to_string(&settings)
+        .expect("a toml table always serializes")
+        .parse()
+        .expect("serialized toml parses");
+    for change in configuration::migrate(&mut settings).map_err(invalid)? {
+        info!("Imported profile \"{}\" updated: {}", name, change);
+    }
+    let mut settings: Table = settings.to_string().parse().expect("serialized toml parses");
+    // the profile takes the version of the file it goes into
+    settings.remove("config_version");
+    if settings.remove("allow_launch").is_some() {
+        info!("Imported profile \"{}\" came with allow_launch, left to the base settings", name);
+    }
+
+    // unknown keys mean a typo or a newer setting, either way it wouldn't
+    // behave like it did when exported
+    let conf: Config = settings.clone().try_into().map_err(|e: toml::de::Error| invalid(e.to_string()))?;
+    if let Some(key) = unknown_key(&settings, &settings_table(&conf)?, "settings") {
+        return Err(invalid(format!("unknown setting {}", key)));
+    }
+
+    // an older file is brought up to date first, the new profile is in the
+    // current format
+    let (mut document, migrated) = if path.exists() {
+        Config::document(path)?
+    } else {
+        let mut document = toml_edit::DocumentMut::new();
+        document.insert("config_version", toml_edit::value(i64::from(configuration::CONFIG_VERSION)));
+        (document, false)
+    };
+    if !document.contains_table("profiles") {
+        let mut profiles = toml_edit::Table::new();
+        profiles.set_implicit(true);
+        document.insert("profiles", toml_edit::Item::Table(profiles));
+    }
+    let profiles = document["profiles"]
+        .as_table_mut()
+        .ok_or_else(|| Mouse2JoyError::InvalidConfig(format!("{}: profiles is not a table", path.display())))?;
+    if profiles.contains_key(&name) && !force {
+        return Err(Mouse2JoyError::InvalidArguments(format!(
+            "profile \"{}\" already exists in {}, use --force to replace it",
+            name,
+            path.display()
+        )));
+    }
+
+    settings.insert(String::from("inherit"), Value::Boolean(false));
+    let profile: toml_edit::DocumentMut = toml::to_string(&settings)
+        .expect("a toml table always serializes")
+        .parse()
+        .expect("serialized toml parses");
+    profiles.insert(&name, toml_edit::Item::Table(profile.as_table().clone()));
+    if !conf.launch.is_empty() {
+        warn!(
+            "Imported profile \"{}\" launches {:?} when selected, if allow_launch = true in the base settings",
+            name, conf.launch
+        );
+    }
+
+    Config::save(path, &document, migrated)?;
+    info!("Profile \"{}\" imported into {}", name, path.display());
+    Ok(())
+}
+
+// upgrade an export of an older format in place, nothing to do while there
+// is only format 1
+fn migrate(_version: i64, _exported: &mut Table) {}
+
+fn settings_table(conf: &Config) -> Result<Table, Mouse2JoyError> {
+    match Value::try_from(conf) {
+        Ok(Value::Table(table)) => Ok(table),
+        _ => Err(Mouse2JoyError::InvalidConfig(String::from("the settings can't be written as toml"))),
+    }
+}
+
+// first key of `given` that didn't survive a round trip through Config
+fn unknown_key(given: &Table, known: &Table, prefix: &str) -> Option<String> {
+    given.iter().find_map(|(key, value)| {
+        let path = format!("{}.{}", prefix, key);
+        match (value, known.get(key)) {
+            (_, None) => Some(path),
+            (Value::Table(given), Some(Value::Table(known))) => unknown_key(given, known, &path),
+            _ => None,
+        }
+    })
+}
+
+fn read_table(path: &Path) -> Result<Table, Mouse2JoyError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| Mouse2JoyError::InvalidArguments(format!("can't read {}: {}", path.display(), e)))?;
+    contents
+        .parse()
+        .map_err(|e| Mouse2JoyError::InvalidConfig(format!("{}: {}", path.display(), e)))
+}
+
+// settings a profile switch applies while running, everything else shapes
+// the devices and waits for a restart
+const HOT_SETTINGS: [&str; 11] = [
+    "sensitivity",
+    "steering",
+    "keyboard_steering.lock_ms",
+    "keyboard_steering.return_ms",
+    "precision.scale",
+    "acceleration",
+    "center_detent",
+    "pedals",
+    "scroll.step",
+    "launch",
+    "launch_output",
+];
+
+// pedal keys decide which keyboards are read, the inversion how the virtual
+// device starts out
+const COLD_PEDAL_SETTINGS: [&str; 4] =
+    ["pedals.throttle.keys", "pedals.brake.keys", "pedals.invert_throttle", "pedals.invert_brake"];
+
+// Switches between the base settings and the profiles of the config file
+// while running, from the keys in [profile_keys] and the next_profile hotkey.
+pub struct ProfileSwitch {
+    // the base settings without a name first, then the profiles by name
+    profiles: Vec<(Option<String>, Config)>,
+    active: usize,
+    requested: Option<usize>,
+    next: Option<Combo>,
+    select: Vec<(Combo, usize)>,
+    held: Vec<Key>,
+    // profiles whose restart only settings were already warned about
+    warned: Vec<usize>,
+}
+
+impl ProfileSwitch {
+    // None without any profile keys configured, or the `hotkey` to cycle
+    pub fn new(
+        conf: &ProfileKeysConfig,
+        path: &Path,
+        active: Option<&str>,
+        hotkey: bool,
+    ) -> Result<Option<Self>, Mouse2JoyError> {
+        if conf.next.is_none() && conf.select.is_empty() && !hotkey {
+            return Ok(None);
+        }
+        let profiles = all_profiles(&read_migrated(path)?, &[], path)?;
+        let index = |name: &str| {
+            profiles
+                .iter()
+                .position(|(profile, _)| profile.as_deref() == Some(name))
+                .ok_or_else(|| Mouse2JoyError::InvalidConfig(format!("no profile \"{}\" in {}", name, path.display())))
+        };
+        let select = conf
+            .select
+            .iter()
+            .map(|(name, key)| Ok((hotkey::parse(key)?, index(name)?)))
+            .collect::<Result<Vec<_>, Mouse2JoyError>>()?;
+        let active = active.map(index).transpose()?.unwrap_or(0);
+        Ok(Some(Self {
+            profiles,
+            active,
+            requested: None,
+            next: conf.next.as_deref().map(hotkey::parse).transpose()?,
+            select,
+            held: Vec::new(),
+            warned: Vec::new(),
+        }))
+    }
+
+    // redoes every profile with the sections of the selected devices
+    pub fn apply_devices(&mut self, path: &Path, devices: &[&str]) -> Result<(), Mouse2JoyError> {
+        self.profiles = all_profiles(&read_migrated(path)?, devices, path)?;
+        Ok(())
+    }
+
+    pub fn configs(&self) -> impl Iterator<Item = &Config> {
+        self.profiles.iter().map(|(_, conf)| conf)
+    }
+
+    // the keys that switch, for the conflict check
+    pub fn keys(&self) -> Vec<Key> {
+        self.hotkeys().map(|combo| combo.key).collect()
+    }
+
+    // every key to read, modifiers included
+    pub fn source_keys(&self) -> Vec<Key> {
+        self.hotkeys().flat_map(Combo::keys).collect()
+    }
+
+    fn hotkeys(&self) -> impl Iterator<Item = &Combo> {
+        self.next.iter().chain(self.select.iter().map(|(combo, _)| combo))
+    }
+
+    pub fn active(&self) -> Option<&str> {
+        self.profiles[self.active].0.as_deref()
+    }
+
+    // returns true if the key switches profiles, modifiers are only watched
+    pub fn handle_key(&mut self, key: Key, value: i32) -> bool {
+        let modifier = self.hotkeys().any(|combo| combo.modifiers.contains(&key));
+        if modifier {
+            match value {
+                0 => self.held.retain(|held| *held != key),
+                1 if !self.held.contains(&key) => self.held.push(key),
+                _ => {}
+            }
+        }
+        let pressed = |combo: &Combo| combo.is_pressed(key, &self.held);
+        let target = if self.next.as_ref().is_some_and(pressed) {
+            Some((self.active + 1) % self.profiles.len())
+        } else {
+            self.select.iter().find(|(combo, _)| pressed(combo)).map(|(_, index)| *index)
+        };
+        let Some(target) = target else {
+            // a switch key without its modifiers does nothing
+            return !modifier && self.keys().contains(&key);
+        };
+        if value == 1 {
+            self.requested = Some(target);
+        }
+        true
+    }
+
+    // on to the next profile, like the next key
+    pub fn request_next(&mut self) {
+        self.requested = Some((self.active + 1) % self.profiles.len());
+    }
+
+    // the profile a key asked for since the last call
+    pub fn take_request(&mut self) -> Option<usize> {
+        self.requested.take()
+    }
+
+    pub fn config(&self, index: usize) -> &Config {
+        &self.profiles[index].1
+    }
+
+    pub fn activate(&mut self, index: usize) {
+        self.active = index;
+        let Some(name) = self.profiles[index].0.as_deref() else {
+            info!("Switched back to the base settings");
+            return;
+        };
+        info!(profile = name; "Switched to profile \"{}\"", name);
+
+        // compared to the settings the devices were set up with
+        if self.warned.contains(&index) {
+            return;
+        }
+        self.warned.push(index);
+        let cold = restart_settings(&self.profiles[0].1, &self.profiles[index].1);
+        if !cold.is_empty() {
+            warn!("Profile \"{}\" also changes {}, these need a restart and were skipped", name, cold.join(", "));
+        }
+    }
+}
+
+// the base settings without a name first, then every profile
+fn all_profiles(file: &Table, devices: &[&str], path: &Path) -> Result<Vec<(Option<String>, Config)>, Mouse2JoyError> {
+    let mut profiles = vec![(None, effective(file, None, devices, path)?)];
+    // the table keeps its keys sorted, so the profiles are in name order
+    for name in file.get("profiles").and_then(Value::as_table).into_iter().flat_map(Table::keys) {
+        profiles.push((Some(name.clone()), effective(file, Some(name), devices, path)?));
+    }
+    Ok(profiles)
+}
+
+// dotted keys that differ between the two and can't be applied while running
+fn restart_settings(running: &Config, profile: &Config) -> Vec<String> {
+    let (Ok(Value::Table(running)), Ok(Value::Table(profile))) = (Value::try_from(running), Value::try_from(profile)) else {
+        return Vec::new();
+    };
+    let mut changed = Vec::new();
+    differences(&running, &profile, "", &mut changed);
+    changed.retain(|key| {
+        let hot = HOT_SETTINGS
+            .iter()
+            .any(|hot| key == hot || key.starts_with(&format!("{}.", hot)));
+        !hot || COLD_PEDAL_SETTINGS.contains(&key.as_str())
+    });
+    changed
+}
+
+fn differences(a: &Table, b: &Table, prefix: &str, changed: &mut Vec<String>) {
+    let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let path = format!("{}{}", prefix, key);
+        match (a.get(key), b.get(key)) {
+            (Some(Value::Table(a)), Some(Value::Table(b))) => differences(a, b, &format!("{}.", path), changed),
+            (a, b) if a != b => changed.push(path),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use log::info;
+use nix::fcntl::OFlag;
+use nix::sys::stat::Mode;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::UdpSocket;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::configuration::PublishConfig;
+use crate::Mouse2JoyError;
+
+// snapshot of the outputs sent to overlays and dashboards
+#[derive(Clone, PartialEq)]
+pub struct Frame {
+    pub angle_deg: f32,
+    pub throttle: f32,
+    pub brake: f32,
+    // the throttle is held by the cruise control
+    pub throttle_hold: bool,
+    // the precision key is held
+    pub precision: bool,
+    pub low_battery: bool,
+    // None while running on the base settings
+    pub profile: Option<Arc<str>>,
+}
+
+impl Frame {
+    fn to_json(&self) -> String {
+        let profile = match &self.profile {
+            Some(profile) => json_string(profile),
+            None => String::from("null"),
+        };
+        format!(
+            "{{\"angle_deg\": {:.1}, \"throttle\": {:.3}, \"brake\": {:.3}, \"throttle_hold\": {}, \"precision\": {}, \
+             \"low_battery\": {}, \"profile\": {}}}\n",
+            self.angle_deg, self.throttle, self.brake, self.throttle_hold, self.precision, self.low_battery, profile
+        )
+    }
+}
+
+pub fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+enum Sink {
+    Udp(UdpSocket),
+    // opened lazily since opening a fifo without a reader fails
+    Fifo { path: PathBuf, file: Option<File> },
+}
+
+impl Sink {
+    fn write(&mut self, line: &str) {
+        match self {
+            // a missing listener or full socket buffer just drops the frame
+            Sink::Udp(socket) => {
+                let _ = socket.send(line.as_bytes());
+            }
+            Sink::Fifo { path, file } => {
+                if file.is_none() {
+                    *file = OpenOptions::new()
+                        .write(true)
+                        .custom_flags(OFlag::O_NONBLOCK.bits())
+                        .open(&*path)
+                        .ok();
+                }
+                if let Some(f) = file {
+                    match f.write(line.as_bytes()) {
+                        Ok(_) => {}
+                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                        // reader went away, reopen on the next frame
+                        Err(_) => *file = None,
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Publishes frames as newline delimited JSON to a fifo and/or UDP address at
+// a limited rate. Writing happens on a separate thread, frames the writer
+// can't keep up with are dropped so the steering loop never blocks.
+pub struct Publisher {
+    sender: SyncSender<Frame>,
+    interval: Duration,
+    last_sent: Option<Instant>,
+    last_frame: Option<Frame>,
+    pending: Option<Frame>,
+}
+
+impl Publisher {
+    // None when no output is configured
+    pub fn new(conf: &PublishConfig) -> Result<Option<Self>, Mouse2JoyError> {
+        let mut sinks = Vec::new();
+        if let Some(address) = &conf.udp {
+            let socket = UdpSocket::bind("0.0.0.0:0")
+                .and_then(|socket| socket.connect(address).map(|_| socket))
+                .and_then(|socket| socket.set_nonblocking(true).map(|_| socket))
+                .map_err(|e| {
+                    Mouse2JoyError::InvalidConfig(format!("can't publish to {}: {}", address, e))
+                })?;
+            info!("Publishing wheel state to udp://{}", address);
+            sinks.push(Sink::Udp(socket));
+        }
+        if let Some(path) = &conf.fifo {
+            let path = PathBuf::from(path);
+            if !path.exists() {
+                nix::unistd::mkfifo(&path, Mode::from_bits_truncate(0o644)).map_err(|e| {
+                    Mouse2JoyError::InvalidConfig(format!("can't create fifo {}: {}", path.display(), e))
+                })?;
+            }
+            info!("Publishing wheel state to {}", path.display());
+            sinks.push(Sink::Fifo { path, file: None });
+        }
+        if sinks.is_empty() {
+            return Ok(None);
+        }
+
+        let (sender, receiver) = sync_channel(4);
+        std::thread::spawn(move || write_frames(receiver, sinks));
+
+        Ok(Some(Self {
+            sender,
+            interval: Duration::from_secs(1) / conf.rate.max(1),
+            last_sent: None,
+            last_frame: None,
+            pending: None,
+        }))
+    }
+
+    // queue the current state, it is sent once the rate limit allows it
+    pub fn update(&mut self, frame: Frame, now: Instant) {
+        if self.last_frame.as_ref() != Some(&frame) {
+            self.last_frame = Some(frame.clone());
+            self.pending = Some(frame);
+        }
+        if self.wakeup(now) != Some(Duration::ZERO) {
+            return;
+        }
+        if let Some(frame) = self.pending.take() {
+            // a full channel means the writer is behind, drop this frame
+            let _ = self.sender.try_send(frame);
+            self.last_sent = Some(now);
+        }
+    }
+
+    // time until a pending frame may be sent
+    pub fn wakeup(&self, now: Instant) -> Option<Duration> {
+        self.pending.as_ref()?;
+        match self.last_sent {
+            Some(last_sent) => Some((last_sent + self.interval).saturating_duration_since(now)),
+            None => Some(Duration::ZERO),
+        }
+    }
+}
+
+fn write_frames(receiver: Receiver<Frame>, mut sinks: Vec<Sink>) {
+    while let Ok(mut frame) = receiver.recv() {
+        // only the newest of the queued frames is worth writing
+        while let Ok(newer) = receiver.try_recv() {
+            frame = newer;
+        }
+        let line = frame.to_json();
+        for sink in sinks.iter_mut() {
+            sink.write(&line);
+        }
+    }
+}
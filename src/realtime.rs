@@ -0,0 +1,51 @@
+use log::{info, warn};
+use nix::errno::Errno;
+use nix::libc;
+
+use crate::configuration::Config;
+use crate::Mouse2JoyError;
+
+// SCHED_FIFO priority, above normal tasks but below the kernel threads and
+// audio servers that usually run at 50 and up
+const PRIORITY: i32 = 10;
+
+pub fn check(conf: &Config) -> Result<(), Mouse2JoyError> {
+    match conf.nice {
+        Some(nice) if !(-20..=19).contains(&nice) => Err(Mouse2JoyError::InvalidConfig(format!(
+            "nice must be between -20 and 19, got {}",
+            nice
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// Raise the priority of the calling thread, the one reading the input and
+// emitting the wheel events. Threads started later inherit it. Without the
+// permission mouse2joy keeps running at the normal priority.
+pub fn apply(conf: &Config) {
+    if let Some(nice) = conf.nice {
+        // SAFETY: plain syscall, 0 is the calling thread
+        match Errno::result(unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) }) {
+            Ok(_) => info!("Running at nice {}", nice),
+            Err(e @ (Errno::EACCES | Errno::EPERM)) => warn!(
+                "Can't set nice {} ({}), lowering it needs CAP_SYS_NICE or a high enough RLIMIT_NICE, \
+                 keeping the normal priority",
+                nice, e
+            ),
+            Err(e) => warn!("Can't set nice {}:  {}", nice, e),
+        }
+    }
+    if conf.realtime {
+        let param = libc::sched_param { sched_priority: PRIORITY };
+        // SAFETY: plain syscall reading `param`, 0 is the calling thread
+        match Errno::result(unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) }) {
+            Ok(_) => info!("Running with SCHED_FIFO priority {}", PRIORITY),
+            Err(Errno::EPERM) => warn!(
+                "Can't switch to SCHED_FIFO, it needs CAP_SYS_NICE or an RLIMIT_RTPRIO of at least {}, \
+                 keeping the normal scheduling",
+                PRIORITY
+            ),
+            Err(e) => warn!("Can't switch to SCHED_FIFO:  {}", e),
+        }
+    }
+}
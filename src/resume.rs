@@ -0,0 +1,30 @@
+use nix::time::{clock_gettime, ClockId};
+use std::time::Duration;
+
+// how far the clocks have to drift apart before it counts as a suspend
+const THRESHOLD: Duration = Duration::from_secs(1);
+
+// Detects a system suspend: CLOCK_BOOTTIME keeps counting while suspended,
+// CLOCK_MONOTONIC doesn't, so their difference jumps on resume.
+pub struct ResumeDetector {
+    offset: Duration,
+}
+
+impl ResumeDetector {
+    pub fn new() -> Self {
+        Self { offset: offset() }
+    }
+
+    // how long the system was asleep, once after every resume
+    pub fn check(&mut self) -> Option<Duration> {
+        let offset = offset();
+        let slept = offset.saturating_sub(self.offset);
+        self.offset = offset;
+        (slept > THRESHOLD).then_some(slept)
+    }
+}
+
+fn offset() -> Duration {
+    let now = |clock| clock_gettime(clock).map(Duration::from).unwrap_or_default();
+    now(ClockId::CLOCK_BOOTTIME).saturating_sub(now(ClockId::CLOCK_MONOTONIC))
+}
@@ -0,0 +1,152 @@
+use evdev::{EventType, InputEvent, InputEventKind, RelativeAxisType};
+use log::info;
+use std::io::Write;
+use std::path::Path;
+
+use crate::configuration::{self, Config, CrossMovement, InputRole, RotationConfig};
+use crate::input::Inputs;
+use crate::Mouse2JoyError;
+
+// the calibration measures this much movement, and gives up below the least
+const CALIBRATION_TRAVEL: f64 = 2000.0;
+const MIN_TRAVEL: f64 = 300.0;
+
+// [rotation]: a mouse mounted at an angle on a rim moves along a direction
+// of its own while steering. The (REL_X, REL_Y) movement of the steering
+// mouse is turned by `angle` before anything reads it, so REL_X is the
+// movement along that direction and REL_Y the movement across it, or
+// nothing with cross = "discard". Fractions of a count carry over to the
+// next movement. An angle of 0 leaves the events alone.
+pub struct Rotation {
+    cos: f64,
+    sin: f64,
+    keep_cross: bool,
+    // read since the last sync report
+    pending: (i32, i32),
+    // what rounding left over, along and across
+    remainder: (f64, f64),
+}
+
+impl Rotation {
+    pub fn new(conf: &RotationConfig) -> Result<Option<Self>, Mouse2JoyError> {
+        if !(-180.0..=180.0).contains(&conf.angle) {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "rotation.angle must be between -180 and 180 degrees, got {}",
+                conf.angle
+            )));
+        }
+        if conf.angle == 0.0 {
+            return Ok(None);
+        }
+        let (sin, cos) = f64::from(conf.angle).to_radians().sin_cos();
+        Ok(Some(Self {
+            cos,
+            sin,
+            keep_cross: conf.cross == CrossMovement::Keep,
+            pending: (0, 0),
+            remainder: (0.0, 0.0),
+        }))
+    }
+
+    pub fn angle(&self) -> f64 {
+        self.sin.atan2(self.cos).to_degrees()
+    }
+
+    // The events with the movement of the steering mouse turned, one
+    // REL_X and one REL_Y per sync report. A batch ending within a report
+    // has its movement turned at the end.
+    pub fn apply(&mut self, events: Vec<(InputRole, InputEvent)>) -> Vec<(InputRole, InputEvent)> {
+        let mut turned = Vec::with_capacity(events.len());
+        for (role, ev) in events {
+            if role != InputRole::Steering {
+                turned.push((role, ev));
+                continue;
+            }
+            let (x, y) = &mut self.pending;
+            match ev.kind() {
+                InputEventKind::RelAxis(RelativeAxisType::REL_X) => *x = x.saturating_add(ev.value()),
+                InputEventKind::RelAxis(RelativeAxisType::REL_Y) => *y = y.saturating_add(ev.value()),
+                InputEventKind::Synchronization(_) => {
+                    self.flush(&mut turned);
+                    turned.push((role, ev));
+                }
+                _ => turned.push((role, ev)),
+            }
+        }
+        self.flush(&mut turned);
+        turned
+    }
+
+    fn flush(&mut self, turned: &mut Vec<(InputRole, InputEvent)>) {
+        let (x, y) = std::mem::take(&mut self.pending);
+        if (x, y) == (0, 0) {
+            return;
+        }
+        let (x, y) = (f64::from(x), f64::from(y));
+        let along = x * self.cos + y * self.sin + self.remainder.0;
+        let across = y * self.cos - x * self.sin + self.remainder.1;
+        let (along_counts, across_counts) = (along.round(), across.round());
+        self.remainder = (along - along_counts, across - across_counts);
+        for (axis, counts, keep) in
+            [(RelativeAxisType::REL_X, along_counts, true), (RelativeAxisType::REL_Y, across_counts, self.keep_cross)]
+        {
+            if keep && counts != 0.0 {
+                turned.push((InputRole::Steering, InputEvent::new(EventType::RELATIVE, axis.0, counts as i32)));
+            }
+        }
+    }
+}
+
+// --calibrate-rotation: measure the direction the steering mouse moves in
+// while steering right and write it to [rotation] after asking. The mouse
+// reads as it is, whatever angle the config has.
+pub fn calibrate(mut inputs: Inputs, path: &Path) -> Result<(), Mouse2JoyError> {
+    println!("Turn the wheel steadily to the right, so the mouse moves along its steering direction");
+    let (mut x, mut y) = (0.0, 0.0);
+    while f64::hypot(x, y) < CALIBRATION_TRAVEL {
+        let Some(events) = inputs.fetch(None) else {
+            break;
+        };
+        for (_, ev) in events.iter().filter(|(role, _)| *role == InputRole::Steering) {
+            match ev.kind() {
+                InputEventKind::RelAxis(RelativeAxisType::REL_X) => x += f64::from(ev.value()),
+                InputEventKind::RelAxis(RelativeAxisType::REL_Y) => y += f64::from(ev.value()),
+                _ => {}
+            }
+        }
+    }
+    inputs.release();
+    let travel = f64::hypot(x, y);
+    if travel < MIN_TRAVEL {
+        println!("The mouse moved only {:.0} counts, {} is unchanged", travel, path.display());
+        return Ok(());
+    }
+    // tenths of a degree, what a hand on a rim manages anyway
+    let angle = (y.atan2(x).to_degrees() * 10.0).round() / 10.0;
+    println!("Measured {} degrees over {:.0} counts, {:.0} on its X axis and {:.0} on its Y axis", angle, travel, x, y);
+    print!("Save it to {}? [y/N] ", path.display());
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() || !answer.trim().eq_ignore_ascii_case("y") {
+        println!("Not saved");
+        return Ok(());
+    }
+
+    let (mut document, migrated) = if path.exists() {
+        Config::document(path)?
+    } else {
+        let mut document = toml_edit::DocumentMut::new();
+        document.insert("config_version", toml_edit::value(i64::from(configuration::CONFIG_VERSION)));
+        (document, false)
+    };
+    if !document.contains_table("rotation") {
+        document.insert("rotation", toml_edit::Item::Table(toml_edit::Table::new()));
+    }
+    let table = document["rotation"]
+        .as_table_mut()
+        .ok_or_else(|| Mouse2JoyError::InvalidConfig(format!("{}: rotation is not a table", path.display())))?;
+    table.insert("angle", toml_edit::value(angle));
+    Config::save(path, &document, migrated)?;
+    info!("Saved the rotation of {} degrees to {}", angle, path.display());
+    Ok(())
+}
@@ -0,0 +1,295 @@
+use evdev::{AbsoluteAxisType, EventType, InputEvent, InputEventKind, Key, RelativeAxisType};
+use log::warn;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::pedals::PEDAL_MAX;
+use crate::steering::STEERING_MAX;
+use crate::Mouse2JoyError;
+
+// how often the script file is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+// what the script sees of one loop iteration
+#[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+pub struct TickInput {
+    pub steering: i32,
+    pub throttle: i32,
+    pub brake: i32,
+    // km/h from telemetry, if any
+    pub speed: Option<f32>,
+}
+
+// values the script wants emitted instead of the computed ones
+#[derive(Default)]
+struct TickOutput {
+    steering: Option<i32>,
+    throttle: Option<i32>,
+    brake: Option<i32>,
+}
+
+// User script run once per loop iteration with the raw deltas, the computed
+// axis values and the held buttons, returning values to emit instead. It gets
+// the final say over the steering and pedal axes. A failing script is
+// disabled with an error and the computed values go out unchanged, until the
+// file changes and it is loaded again.
+pub struct ScriptHook {
+    path: PathBuf,
+    engine: engine::Engine,
+    // the pedal axes the virtual device has
+    pedals: Vec<AbsoluteAxisType>,
+    dx: i64,
+    // whole detents and hi-res units
+    wheel: (i64, i64),
+    held: Vec<Key>,
+    last_tick: Instant,
+    emitted: Vec<(AbsoluteAxisType, i32)>,
+    modified: Option<std::time::SystemTime>,
+    next_check: Instant,
+}
+
+impl ScriptHook {
+    pub fn new(path: Option<&str>, pedals: Vec<AbsoluteAxisType>, now: Instant) -> Result<Option<Self>, Mouse2JoyError> {
+        let Some(path) = path else {
+            return Ok(None);
+        };
+        if !cfg!(feature = "scripting") {
+            warn!("A script is configured but mouse2joy was built without the scripting feature");
+            return Ok(None);
+        }
+        let path = PathBuf::from(path);
+        let engine = engine::Engine::load(&path).map_err(Mouse2JoyError::InvalidConfig)?;
+        Ok(Some(Self {
+            modified: modified(&path),
+            path,
+            engine,
+            emitted: [AbsoluteAxisType::ABS_X].into_iter().chain(pedals.iter().copied()).map(|axis| (axis, 0)).collect(),
+            pedals,
+            dx: 0,
+            wheel: (0, 0),
+            held: Vec::new(),
+            last_tick: now,
+            next_check: now + RELOAD_INTERVAL,
+        }))
+    }
+
+    // collect the raw input of this iteration
+    pub fn observe(&mut self, event: &InputEvent) {
+        match event.kind() {
+            InputEventKind::RelAxis(RelativeAxisType::REL_X) => self.dx += i64::from(event.value()),
+            InputEventKind::Key(key) => match event.value() {
+                0 => self.held.retain(|held| *held != key),
+                1 if !self.held.contains(&key) => self.held.push(key),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    // scroll wheel motion as decoded by scroll::Wheel
+    pub fn scroll(&mut self, detents: i32, units: i32) {
+        self.wheel.0 += i64::from(detents);
+        self.wheel.1 += i64::from(units);
+    }
+
+    // Run the script and replace the steering and pedal events in `outgoing`
+    // with what it returned.
+    pub fn tick(&mut self, input: TickInput, outgoing: &mut Vec<InputEvent>, now: Instant) {
+        self.reload_if_changed(now);
+        let elapsed = now.saturating_duration_since(self.last_tick);
+        self.last_tick = now;
+        let output = self
+            .engine
+            .tick(&self.path, &input, (self.dx, self.wheel), &self.held, elapsed)
+            .unwrap_or_default();
+        self.dx = 0;
+        self.wheel = (0, 0);
+
+        let mut axes = vec![(AbsoluteAxisType::ABS_X, output.steering.unwrap_or(input.steering).clamp(-STEERING_MAX, STEERING_MAX))];
+        for (axis, value, computed) in [
+            (AbsoluteAxisType::ABS_GAS, output.throttle, input.throttle),
+            (AbsoluteAxisType::ABS_BRAKE, output.brake, input.brake),
+        ] {
+            if self.pedals.contains(&axis) {
+                axes.push((axis, value.unwrap_or(computed).clamp(0, PEDAL_MAX)));
+            }
+        }
+        outgoing.retain(|event| {
+            event.event_type() != EventType::ABSOLUTE || !axes.iter().any(|(axis, _)| axis.0 == event.code())
+        });
+        for ((axis, value), (_, last)) in axes.into_iter().zip(self.emitted.iter_mut()) {
+            if *last != value {
+                *last = value;
+                outgoing.push(InputEvent::new(EventType::ABSOLUTE, axis.0, value));
+            }
+        }
+    }
+
+    fn reload_if_changed(&mut self, now: Instant) {
+        if now < self.next_check {
+            return;
+        }
+        self.next_check = now + RELOAD_INTERVAL;
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        self.engine.reload(&self.path);
+    }
+}
+
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(feature = "scripting")]
+mod engine {
+    use evdev::Key;
+    use log::{error, info};
+    use rhai::{CallFnOptions, Dynamic, Map, Scope, AST};
+    use std::path::Path;
+    use std::time::Duration;
+
+    use super::{TickInput, TickOutput};
+
+    // keeps a runaway script from stalling the loop
+    const MAX_OPERATIONS: u64 = 100_000;
+
+    // Rhai has no file, network or process access built in, modules are
+    // compiled out, the limits cover endless loops and memory.
+    pub struct Engine {
+        engine: rhai::Engine,
+        ast: Option<AST>,
+        // `this` inside the script, kept between ticks
+        state: Dynamic,
+    }
+
+    impl Engine {
+        pub fn load(path: &Path) -> Result<Self, String> {
+            let mut engine = rhai::Engine::new();
+            engine.set_max_operations(MAX_OPERATIONS);
+            engine.set_max_call_levels(32);
+            engine.set_max_expr_depths(64, 32);
+            engine.set_max_string_size(4096);
+            engine.set_max_array_size(1024);
+            engine.set_max_map_size(1024);
+            engine.on_print(|text| info!("script: {}", text));
+            engine.on_debug(|text, _, _| log::debug!("script: {}", text));
+            let ast = compile(&engine, path)?;
+            Ok(Self { engine, ast: Some(ast), state: Dynamic::from_map(Map::new()) })
+        }
+
+        pub fn reload(&mut self, path: &Path) {
+            match compile(&self.engine, path) {
+                Ok(ast) => {
+                    info!("Reloaded the script {}", path.display());
+                    self.ast = Some(ast);
+                    self.state = Dynamic::from_map(Map::new());
+                }
+                Err(e) => {
+                    error!("{}, script disabled until it changes", e);
+                    self.ast = None;
+                }
+            }
+        }
+
+        pub fn tick(
+            &mut self,
+            path: &Path,
+            input: &TickInput,
+            (dx, (wheel, wheel_hi_res)): (i64, (i64, i64)),
+            held: &[Key],
+            elapsed: Duration,
+        ) -> Option<TickOutput> {
+            let ast = self.ast.as_ref()?;
+            let mut map = Map::new();
+            map.insert("dx".into(), Dynamic::from_int(dx));
+            map.insert("wheel".into(), Dynamic::from_int(wheel));
+            map.insert("wheel_hi_res".into(), Dynamic::from_int(wheel_hi_res));
+            map.insert("steering".into(), Dynamic::from_int(input.steering.into()));
+            map.insert("throttle".into(), Dynamic::from_int(input.throttle.into()));
+            map.insert("brake".into(), Dynamic::from_int(input.brake.into()));
+            map.insert(
+                "speed".into(),
+                input.speed.map_or(Dynamic::UNIT, |speed| Dynamic::from_float(speed.into())),
+            );
+            map.insert("dt_ms".into(), Dynamic::from_float(elapsed.as_secs_f64() * 1000.0));
+            map.insert(
+                "buttons".into(),
+                Dynamic::from_array(held.iter().map(|key| Dynamic::from(format!("{:?}", key))).collect()),
+            );
+
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+            let result = self
+                .engine
+                .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), ast, "tick", (map,))
+                .map_err(|e| e.to_string())
+                .and_then(output);
+            match result {
+                Ok(output) => Some(output),
+                Err(e) => {
+                    error!("Script {} failed, disabled until it changes:  {}", path.display(), e);
+                    self.ast = None;
+                    None
+                }
+            }
+        }
+    }
+
+    fn compile(engine: &rhai::Engine, path: &Path) -> Result<AST, String> {
+        let source =
+            std::fs::read_to_string(path).map_err(|e| format!("can't read the script {}: {}", path.display(), e))?;
+        let ast = engine
+            .compile(source)
+            .map_err(|e| format!("script {}: {}", path.display(), e))?;
+        if !ast.iter_functions().any(|f| f.name == "tick" && f.params.len() == 1) {
+            return Err(format!("script {} has no tick(input) function", path.display()));
+        }
+        Ok(ast)
+    }
+
+    // () keeps every computed value, a map replaces the ones it has
+    fn output(result: Dynamic) -> Result<TickOutput, String> {
+        if result.is_unit() {
+            return Ok(TickOutput::default());
+        }
+        let map = result
+            .try_cast::<Map>()
+            .ok_or_else(|| String::from("tick must return a map or ()"))?;
+        let value = |name: &str| -> Result<Option<i32>, String> {
+            let Some(value) = map.get(name) else {
+                return Ok(None);
+            };
+            let number = match value.as_int() {
+                Ok(int) => int as f64,
+                Err(_) => value.as_float().map_err(|_| format!("{} must be a number", name))?,
+            };
+            Ok(Some(number.round().clamp(i32::MIN as f64, i32::MAX as f64) as i32))
+        };
+        Ok(TickOutput { steering: value("steering")?, throttle: value("throttle")?, brake: value("brake")? })
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+mod engine {
+    use evdev::Key;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use super::{TickInput, TickOutput};
+
+    pub struct Engine;
+
+    impl Engine {
+        pub fn load(_: &Path) -> Result<Self, String> {
+            Ok(Self)
+        }
+
+        pub fn reload(&mut self, _: &Path) {}
+
+        pub fn tick(&mut self, _: &Path, _: &TickInput, _: (i64, (i64, i64)), _: &[Key], _: Duration) -> Option<TickOutput> {
+            None
+        }
+    }
+}
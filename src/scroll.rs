@@ -0,0 +1,139 @@
+use evdev::{AbsInfo, AbsoluteAxisType, EventType, InputEvent, Key, RelativeAxisType, UinputAbsSetup};
+use log::info;
+
+use crate::configuration::ScrollConfig;
+use crate::mapping::parse_key;
+use crate::pedals::PEDAL_MAX;
+use crate::Mouse2JoyError;
+
+// REL_WHEEL_HI_RES units per detent, REL_WHEEL counts whole detents
+pub const WHEEL_DETENT: i32 = 120;
+// full throttle in hi-res units of a percent
+const FULL: i32 = 100 * WHEEL_DETENT;
+
+// Mice with a high resolution wheel report every motion twice, on
+// REL_WHEEL_HI_RES and again on REL_WHEEL once a whole detent is reached.
+// Once the hi-res axis is advertised or seen the legacy one is ignored, so a
+// motion never counts twice. Everything downstream works in hi-res units.
+pub struct Wheel {
+    hi_res: bool,
+    // hi-res units short of a whole detent
+    remainder: i32,
+}
+
+impl Wheel {
+    pub fn new(hi_res: bool) -> Self {
+        if hi_res {
+            info!("Using the high resolution scroll wheel");
+        }
+        Self { hi_res, remainder: 0 }
+    }
+
+    // motion in hi-res units, None if it was already counted
+    pub fn units(&mut self, axis: RelativeAxisType, value: i32) -> Option<i32> {
+        if axis == RelativeAxisType::REL_WHEEL_HI_RES {
+            if !self.hi_res {
+                info!("Using the high resolution scroll wheel");
+                self.hi_res = true;
+            }
+            return Some(value);
+        }
+        (!self.hi_res).then(|| value.saturating_mul(WHEEL_DETENT))
+    }
+
+    // whole detents completed by the motion, fractions add up across calls
+    pub fn detents(&mut self, units: i32) -> i32 {
+        self.remainder = self.remainder.saturating_add(units);
+        let detents = self.remainder / WHEEL_DETENT;
+        self.remainder -= detents * WHEEL_DETENT;
+        detents
+    }
+}
+
+// Cruise style throttle on the scroll wheel: every detent up adds a step,
+// every detent down removes one and the reset button zeroes it. The level is
+// held until changed. A high resolution wheel moves it in fractions of a step.
+pub struct ScrollThrottle {
+    axis: AbsoluteAxisType,
+    reset: Key,
+    // in percent of full throttle
+    step: i32,
+    // in hi-res units of a percent, 100% is 100 * WHEEL_DETENT
+    level: i32,
+}
+
+impl ScrollThrottle {
+    pub fn new(conf: &ScrollConfig) -> Result<Self, Mouse2JoyError> {
+        check_step(conf.step)?;
+        let axis = conf.axis.parse::<AbsoluteAxisType>().map_err(|_| {
+            Mouse2JoyError::InvalidConfig(format!("unknown axis \"{}\"", conf.axis))
+        })?;
+        Ok(Self {
+            axis,
+            reset: parse_key(&conf.reset)?,
+            step: conf.step as i32,
+            level: 0,
+        })
+    }
+
+    // take over the step of another profile, the level is kept
+    pub fn reconfigure(&mut self, conf: &ScrollConfig) -> Result<(), Mouse2JoyError> {
+        check_step(conf.step)?;
+        self.step = conf.step as i32;
+        Ok(())
+    }
+
+    pub fn keys(&self) -> Vec<Key> {
+        vec![self.reset]
+    }
+
+    pub fn axis(&self) -> UinputAbsSetup {
+        UinputAbsSetup::new(self.axis, AbsInfo::new(0, 0, PEDAL_MAX, 0, 0, 0))
+    }
+
+    // held level as a fraction of full throttle
+    pub fn level(&self) -> f32 {
+        self.level as f32 / FULL as f32
+    }
+
+    // motion in hi-res units
+    pub fn handle_wheel(&mut self, units: i32) -> Option<InputEvent> {
+        self.set_level(self.level.saturating_add(units.saturating_mul(self.step)))
+    }
+
+    // None if the key isn't the reset button
+    pub fn handle_key(&mut self, key: Key, value: i32) -> Option<Option<InputEvent>> {
+        if key != self.reset {
+            return None;
+        }
+        Some(if value == 1 { self.set_level(0) } else { None })
+    }
+
+    // back to no throttle
+    pub fn release(&mut self) -> Option<InputEvent> {
+        self.set_level(0)
+    }
+
+    fn set_level(&mut self, level: i32) -> Option<InputEvent> {
+        let level = level.clamp(0, FULL);
+        if level == self.level {
+            return None;
+        }
+        // whole percents only, a hi-res wheel would log every fraction
+        if level / WHEEL_DETENT != self.level / WHEEL_DETENT || level == 0 {
+            info!("Throttle level: {}%", level / WHEEL_DETENT);
+        }
+        self.level = level;
+        Some(InputEvent::new(EventType::ABSOLUTE, self.axis.0, level * PEDAL_MAX / FULL))
+    }
+}
+
+fn check_step(step: u32) -> Result<(), Mouse2JoyError> {
+    if step == 0 || step > 100 {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "scroll step must be between 1 and 100 percent, got {}",
+            step
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use evdev::{AbsoluteAxisType, InputId, Key};
+
+// CRC-16 as implemented by SDL_crc16 (polynomial 0xA001, reflected)
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, byte| {
+        let mut r = (crc as u8 ^ byte) as u16;
+        for _ in 0..8 {
+            r = if r & 1 != 0 { (r >> 1) ^ 0xA001 } else { r >> 1 };
+        }
+        r ^ (crc >> 8)
+    })
+}
+
+// Joystick GUID the way SDL_CreateJoystickGUID computes it for evdev devices:
+// little endian bus, CRC of the name, vendor, product and version.
+pub fn guid(id: InputId, name: &str) -> [u8; 16] {
+    let mut guid = [0u8; 16];
+    guid[0..2].copy_from_slice(&id.bus_type().0.to_le_bytes());
+    guid[2..4].copy_from_slice(&crc16(name.as_bytes()).to_le_bytes());
+    if id.vendor() != 0 {
+        guid[4..6].copy_from_slice(&id.vendor().to_le_bytes());
+        guid[8..10].copy_from_slice(&id.product().to_le_bytes());
+        guid[12..14].copy_from_slice(&id.version().to_le_bytes());
+    } else {
+        let len = name.len().min(11);
+        guid[4..4 + len].copy_from_slice(&name.as_bytes()[..len]);
+    }
+    guid
+}
+
+// SDL game controller slots paired with the virtual device button filling them
+static BUTTON_SLOTS: [(&str, Key); 8] = [
+    ("a", Key::BTN_TRIGGER),
+    ("b", Key::BTN_THUMB),
+    ("x", Key::BTN_THUMB2),
+    ("y", Key::BTN_TOP),
+    ("back", Key::BTN_SELECT),
+    ("start", Key::BTN_START),
+    ("leftshoulder", Key::BTN_TL),
+    ("rightshoulder", Key::BTN_TR),
+];
+
+// Build a gamecontrollerdb style mapping line. SDL numbers buttons and axes
+// by ascending event code, with the hat axes reported as hat 0 instead.
+pub fn mapping(id: InputId, name: &str, buttons: &[Key], axes: &[AbsoluteAxisType]) -> String {
+    let mut buttons = buttons.to_vec();
+    buttons.sort_by_key(|button| button.code());
+    let button_index = |key: Key| buttons.iter().position(|button| *button == key);
+
+    let has_hat = axes.contains(&AbsoluteAxisType::ABS_HAT0X);
+    let mut axes: Vec<AbsoluteAxisType> = axes
+        .iter()
+        .copied()
+        .filter(|axis| !(AbsoluteAxisType::ABS_HAT0X.0..=AbsoluteAxisType::ABS_HAT3Y.0).contains(&axis.0))
+        .collect();
+    axes.sort_by_key(|axis| axis.0);
+    let axis_index = |axis: AbsoluteAxisType| axes.iter().position(|a| *a == axis);
+
+    let guid: String = guid(id, name).iter().map(|byte| format!("{:02x}", byte)).collect();
+    let mut fields = vec![guid, name.to_string()];
+
+    for (slot, key) in BUTTON_SLOTS {
+        if let Some(index) = button_index(key) {
+            fields.push(format!("{}:b{}", slot, index));
+        }
+    }
+    if let Some(index) = axis_index(AbsoluteAxisType::ABS_X) {
+        fields.push(format!("leftx:a{}", index));
+    }
+
+    // pedals make the best triggers, fall back on the trigger buttons
+    let triggers = [
+        ("lefttrigger", AbsoluteAxisType::ABS_BRAKE, Key::BTN_TL2),
+        ("righttrigger", AbsoluteAxisType::ABS_GAS, Key::BTN_TR2),
+    ];
+    for (slot, axis, key) in triggers {
+        if let Some(index) = axis_index(axis) {
+            fields.push(format!("{}:a{}", slot, index));
+        } else if let Some(index) = button_index(key) {
+            fields.push(format!("{}:b{}", slot, index));
+        }
+    }
+
+    if has_hat {
+        fields.push(String::from("dpup:h0.1,dpright:h0.2,dpdown:h0.4,dpleft:h0.8"));
+    }
+    fields.push(String::from("platform:Linux"));
+    fields.join(",") + ","
+}
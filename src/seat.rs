@@ -0,0 +1,210 @@
+use evdev::{EventType, InputEvent, Key, RelativeAxisType};
+use std::time::Duration;
+
+// Pointer motion, buttons and keys of the whole seat, taken from the X server
+// (also XWayland) as XInput2 raw events instead of from the event nodes. No
+// access to /dev/input is needed and there is no device to pick, everything
+// the seat's master pointer and keyboard do arrives. Nothing can be grabbed,
+// the desktop keeps seeing the input too.
+pub struct SeatInput {
+    connection: backend::Connection,
+    // fractions of a count left over by the raw valuators
+    remainder: (f64, f64),
+}
+
+impl SeatInput {
+    pub fn connect() -> Result<Self, String> {
+        Ok(Self { connection: backend::Connection::connect()?, remainder: (0.0, 0.0) })
+    }
+
+    // the XInput version the server speaks, for the doctor
+    pub fn version(&self) -> String {
+        self.connection.version()
+    }
+
+    // Wait up to `timeout` and return the raw events as evdev ones, None
+    // once the X server went away.
+    pub fn fetch(&mut self, timeout: Option<Duration>) -> Option<Vec<InputEvent>> {
+        let raw = self.connection.fetch(timeout)?;
+        let mut events = Vec::new();
+        for event in raw {
+            match event {
+                Raw::Motion(dx, dy) => {
+                    for (delta, remainder, axis) in [
+                        (dx, &mut self.remainder.0, RelativeAxisType::REL_X),
+                        (dy, &mut self.remainder.1, RelativeAxisType::REL_Y),
+                    ] {
+                        let exact = delta + *remainder;
+                        *remainder = exact.fract();
+                        if exact.trunc() != 0.0 {
+                            events.push(InputEvent::new(EventType::RELATIVE, axis.0, exact.trunc() as i32));
+                        }
+                    }
+                }
+                Raw::Button(button, pressed) => events.extend(button_event(button, pressed)),
+                // X keycodes are the evdev ones shifted by 8
+                Raw::Key(keycode, pressed) if keycode >= 8 => {
+                    events.push(InputEvent::new(EventType::KEY, (keycode - 8) as u16, i32::from(pressed)));
+                }
+                Raw::Key(..) => {}
+            }
+        }
+        Some(events)
+    }
+}
+
+// what the backend hands over, before it is turned into evdev events
+#[cfg_attr(not(feature = "x11-input"), allow(dead_code))]
+enum Raw {
+    Motion(f64, f64),
+    Button(u32, bool),
+    Key(u32, bool),
+}
+
+// the X core button numbers, 4 and 5 are wheel clicks, the horizontal 6
+// and 7 aren't used
+fn button_event(button: u32, pressed: bool) -> Option<InputEvent> {
+    let key = match button {
+        1 => Key::BTN_LEFT,
+        2 => Key::BTN_MIDDLE,
+        3 => Key::BTN_RIGHT,
+        8 => Key::BTN_SIDE,
+        9 => Key::BTN_EXTRA,
+        4 | 5 if pressed => {
+            let detent = if button == 4 { 1 } else { -1 };
+            return Some(InputEvent::new(EventType::RELATIVE, RelativeAxisType::REL_WHEEL.0, detent));
+        }
+        _ => return None,
+    };
+    Some(InputEvent::new(EventType::KEY, key.code(), i32::from(pressed)))
+}
+
+#[cfg(feature = "x11-input")]
+mod backend {
+    use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+    use std::os::fd::AsFd;
+    use std::time::Duration;
+    use x11rb::connection::{Connection as _, RequestConnection as _};
+    use x11rb::protocol::xinput::{self, ConnectionExt as _, EventMask, XIEventMask};
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::RustConnection;
+
+    use super::Raw;
+
+    pub struct Connection {
+        connection: RustConnection,
+        version: (u16, u16),
+    }
+
+    impl Connection {
+        pub fn connect() -> Result<Self, String> {
+            let (connection, screen) = x11rb::connect(None).map_err(|e| format!("no X server reachable: {}", e))?;
+            let missing = connection
+                .extension_information(xinput::X11_EXTENSION_NAME)
+                .map_err(|e| e.to_string())?
+                .is_none();
+            if missing {
+                return Err(String::from("the X server has no XInput extension"));
+            }
+            let reply = connection
+                .xinput_xi_query_version(2, 2)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?;
+            if reply.major_version < 2 {
+                return Err(format!("the X server only has XInput {}.{}", reply.major_version, reply.minor_version));
+            }
+            let root = connection.setup().roots[screen].root;
+            let mask = XIEventMask::RAW_MOTION
+                | XIEventMask::RAW_BUTTON_PRESS
+                | XIEventMask::RAW_BUTTON_RELEASE
+                | XIEventMask::RAW_KEY_PRESS
+                | XIEventMask::RAW_KEY_RELEASE;
+            connection
+                .xinput_xi_select_events(root, &[EventMask { deviceid: xinput::Device::ALL_MASTER.into(), mask: vec![mask] }])
+                .map_err(|e| e.to_string())?
+                .check()
+                .map_err(|e| e.to_string())?;
+            Ok(Self { connection, version: (reply.major_version, reply.minor_version) })
+        }
+
+        pub fn version(&self) -> String {
+            format!("XInput {}.{}", self.version.0, self.version.1)
+        }
+
+        pub fn fetch(&mut self, timeout: Option<Duration>) -> Option<Vec<Raw>> {
+            let mut raw = Vec::new();
+            self.read(&mut raw)?;
+            if raw.is_empty() {
+                let mut fds = [PollFd::new(self.connection.stream().as_fd(), PollFlags::POLLIN)];
+                let timeout = timeout
+                    .map(|timeout| PollTimeout::try_from(timeout.as_micros().div_ceil(1000)).unwrap_or(PollTimeout::MAX))
+                    .unwrap_or(PollTimeout::NONE);
+                match poll(&mut fds, timeout) {
+                    Ok(_) | Err(nix::errno::Errno::EINTR) => {}
+                    Err(_) => return None,
+                }
+                self.read(&mut raw)?;
+            }
+            Some(raw)
+        }
+
+        // everything already received, None once the connection broke
+        fn read(&mut self, raw: &mut Vec<Raw>) -> Option<()> {
+            while let Some(event) = self.connection.poll_for_event().ok()? {
+                match event {
+                    Event::XinputRawMotion(event) => {
+                        let (mut dx, mut dy) = (0.0, 0.0);
+                        // the values only list the valuators set in the mask
+                        let mask = event.valuator_mask.first().copied().unwrap_or(0);
+                        let mut values = event.axisvalues_raw.iter();
+                        for valuator in 0..2 {
+                            if mask & (1 << valuator) == 0 {
+                                continue;
+                            }
+                            let Some(value) = values.next() else {
+                                break;
+                            };
+                            let delta = f64::from(value.integral) + f64::from(value.frac) / 4_294_967_296.0;
+                            if valuator == 0 {
+                                dx = delta;
+                            } else {
+                                dy = delta;
+                            }
+                        }
+                        raw.push(Raw::Motion(dx, dy));
+                    }
+                    Event::XinputRawButtonPress(event) => raw.push(Raw::Button(event.detail, true)),
+                    Event::XinputRawButtonRelease(event) => raw.push(Raw::Button(event.detail, false)),
+                    Event::XinputRawKeyPress(event) => raw.push(Raw::Key(event.detail, true)),
+                    Event::XinputRawKeyRelease(event) => raw.push(Raw::Key(event.detail, false)),
+                    _ => {}
+                }
+            }
+            Some(())
+        }
+    }
+}
+
+#[cfg(not(feature = "x11-input"))]
+mod backend {
+    use std::time::Duration;
+
+    use super::Raw;
+
+    pub struct Connection;
+
+    impl Connection {
+        pub fn connect() -> Result<Self, String> {
+            Err(String::from("mouse2joy was built without the x11-input feature"))
+        }
+
+        pub fn version(&self) -> String {
+            String::new()
+        }
+
+        pub fn fetch(&mut self, _: Option<Duration>) -> Option<Vec<Raw>> {
+            None
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use evdev::{uinput::VirtualDevice, AbsoluteAxisType, Device, EventType, InputEvent, Key};
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::classify::{self, classify, Class};
+use crate::output::VirtualDevices;
+use crate::Mouse2JoyError;
+
+// how long the event node may take to show up
+const NODE_TIMEOUT: Duration = Duration::from_secs(2);
+// how long the events of one capability may take to come back
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+// positions of the axis sweep between minimum and maximum
+const SWEEP_STEPS: i32 = 4;
+
+// Reads the virtual device back through its own event node, like evtest,
+// with every axis swept from minimum to maximum and every button pressed and
+// released. Prints what the device advertises and which capabilities made it
+// through, meant to be pasted into bug reports. A split layout is checked
+// device by device. A device udev took for a pointer fails too.
+pub fn run(mut devices: VirtualDevices) -> Result<(), Mouse2JoyError> {
+    let (mut failures, mut total) = (0, 0);
+    let mut pointers = Vec::new();
+    for (index, (device, spec)) in devices.parts_mut().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        let (failed_here, total_here, pointer) = check(device)?;
+        failures += failed_here;
+        total += total_here;
+        pointers.extend(pointer.then(|| spec.name.clone()));
+    }
+    if failures > 0 {
+        return Err(failed(format!("{} of {} capabilities didn't read back", failures, total)));
+    }
+    if !pointers.is_empty() {
+        return Err(failed(format!("udev took {} for a pointing device", pointers.join(", "))));
+    }
+    println!("All {} capabilities read back", total);
+    Ok(())
+}
+
+// failed and checked capabilities of one device, and whether udev took it for
+// a pointer
+fn check(device: &mut VirtualDevice) -> Result<(usize, usize, bool), Mouse2JoyError> {
+    let node = event_node(device)?;
+    let mut readback = open(&node)?;
+    println!("Virtual device \"{}\" at {}", readback.name().unwrap_or("?"), node.display());
+    let id = readback.input_id();
+    println!(
+        "Bus {:?}, vendor {:04x}, product {:04x}, version {:04x}",
+        id.bus_type(),
+        id.vendor(),
+        id.product(),
+        id.version()
+    );
+    match crate::find_js_node(device) {
+        Some(path) => println!("Joystick node: {}", path.display()),
+        None => println!("Joystick node: none, legacy games using /dev/input/js* won't see the device"),
+    }
+
+    let ranges = readback
+        .get_abs_state()
+        .map_err(|e| failed(format!("can't read the axis ranges of {}: {}", node.display(), e)))?;
+    let axes: Vec<(AbsoluteAxisType, i32, i32)> = readback
+        .supported_absolute_axes()
+        .map(|axes| axes.iter().collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|axis| (axis, ranges[axis.0 as usize].minimum, ranges[axis.0 as usize].maximum))
+        .collect();
+    let buttons: Vec<Key> = readback.supported_keys().map(|keys| keys.iter().collect()).unwrap_or_default();
+    let pointer = check_class(&readback, &node, &axes, &buttons);
+    println!("Axes:");
+    for (axis, minimum, maximum) in &axes {
+        println!("  {:<16} {} to {}", format!("{:?}", axis), minimum, maximum);
+    }
+    if !readback.supported_events().contains(EventType::KEY) {
+        println!("Buttons: none, no EV_KEY capability");
+    } else if buttons.is_empty() {
+        println!("Buttons: none, but EV_KEY is advertised");
+    } else {
+        println!("Buttons:");
+    }
+    for line in buttons.chunks(6) {
+        let names: Vec<String> = line.iter().map(|key| format!("{:?}", key)).collect();
+        println!("  {}", names.join(" "));
+    }
+
+    println!("Read back:");
+    let mut failures = 0;
+    let patterns = axes
+        .iter()
+        .map(|(axis, minimum, maximum)| {
+            (format!("{:?}", axis), EventType::ABSOLUTE, axis.0, sweep(*minimum, *maximum))
+        })
+        .chain(buttons.iter().map(|key| (format!("{:?}", key), EventType::KEY, key.code(), vec![1, 0])));
+    for (name, event_type, code, values) in patterns {
+        // one report per value, like a real device moving
+        let result = values
+            .iter()
+            .try_for_each(|value| device.emit(&[InputEvent::new(event_type, code, *value)]))
+            .map_err(|e| format!("can't emit: {}", e))
+            .and_then(|()| {
+                let received = read(&mut readback, event_type, code, values.len());
+                if received == values {
+                    Ok(())
+                } else {
+                    Err(format!("sent {:?}, read back {:?}", values, received))
+                }
+            });
+        match result {
+            Ok(()) => println!("  ok    {}", name),
+            Err(e) => {
+                println!("  FAIL  {}: {}", name, e);
+                failures += 1;
+            }
+        }
+    }
+
+    Ok((failures, axes.len() + buttons.len(), pointer))
+}
+
+// Print how udev classified the node next to what its capabilities should
+// give, returns whether it became a mouse, tablet or touch device.
+fn check_class(readback: &Device, node: &Path, axes: &[(AbsoluteAxisType, i32, i32)], buttons: &[Key]) -> bool {
+    let axes: Vec<u16> = axes.iter().map(|(axis, _, _)| axis.0).collect();
+    let buttons: Vec<u16> = buttons.iter().map(|key| key.code()).collect();
+    let props: Vec<u16> = readback.properties().iter().map(|prop| prop.0).collect();
+    let expected = classify(&axes, &buttons, &props);
+    let Some(classes) = classify::udev_classified(node, NODE_TIMEOUT) else {
+        println!("udev class: unknown without a udev database, the capabilities give {}", expected.property());
+        return false;
+    };
+    let names: Vec<&str> = classes.iter().map(|class| class.property()).collect();
+    match &classes[..] {
+        [Class::Joystick] => println!("udev class: ID_INPUT_JOYSTICK"),
+        [] => println!("udev class: none, games looking for ID_INPUT_JOYSTICK won't see the device"),
+        _ if classes.iter().any(|class| class.is_pointer()) => {
+            println!("udev class: FAIL {}, the desktop may move the cursor with the wheel", names.join(" "));
+            return true;
+        }
+        _ => println!("udev class: {}", names.join(" ")),
+    }
+    false
+}
+
+// minimum to maximum and back to rest, without repeats since the kernel
+// drops a value the axis already has
+fn sweep(minimum: i32, maximum: i32) -> Vec<i32> {
+    let rest = 0.clamp(minimum, maximum);
+    let mut values: Vec<i32> = (0..=SWEEP_STEPS)
+        .map(|step| minimum + (maximum - minimum) / SWEEP_STEPS * step)
+        .chain([maximum, rest])
+        .collect();
+    values.dedup();
+    if values.first() == Some(&rest) {
+        values.remove(0);
+    }
+    values
+}
+
+fn event_node(device: &mut VirtualDevice) -> Result<PathBuf, Mouse2JoyError> {
+    let node = device
+        .enumerate_dev_nodes_blocking()
+        .ok()
+        .and_then(|mut nodes| nodes.next())
+        .and_then(Result::ok)
+        .ok_or_else(|| failed(String::from("the virtual device has no event node")))?;
+    let deadline = Instant::now() + NODE_TIMEOUT;
+    while !node.exists() {
+        if Instant::now() > deadline {
+            return Err(failed(format!("{} never appeared", node.display())));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Ok(node)
+}
+
+// udev may still be setting the permissions, so a refusal is retried
+fn open(node: &Path) -> Result<Device, Mouse2JoyError> {
+    let deadline = Instant::now() + NODE_TIMEOUT;
+    loop {
+        match Device::open(node) {
+            Ok(device) => {
+                fcntl(device.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))
+                    .map_err(|e| failed(format!("can't read {}: {}", node.display(), e)))?;
+                return Ok(device);
+            }
+            Err(e) if Instant::now() > deadline => {
+                return Err(failed(format!("can't open {}: {}", node.display(), e)));
+            }
+            Err(_) => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+// values of the given event until `count` arrived or the read timed out
+fn read(device: &mut Device, event_type: EventType, code: u16, count: usize) -> Vec<i32> {
+    let deadline = Instant::now() + READ_TIMEOUT;
+    let mut values = Vec::new();
+    while values.len() < count && Instant::now() < deadline {
+        match device.fetch_events() {
+            Ok(events) => values.extend(
+                events
+                    .filter(|ev| ev.event_type() == event_type && ev.code() == code)
+                    .map(|ev| ev.value()),
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(Duration::from_millis(2)),
+            Err(_) => break,
+        }
+    }
+    values
+}
+
+fn failed(reason: String) -> Mouse2JoyError {
+    Mouse2JoyError::SelfTestFailed(reason)
+}
@@ -0,0 +1,64 @@
+use evdev::{EventType, InputEvent, RelativeAxisType};
+use log::debug;
+
+use crate::configuration::{InputRole, SpikeAction, SpikeRejectionConfig};
+use crate::Mouse2JoyError;
+
+mod detector;
+use detector::Detector;
+
+// Spike rejection: a single REL_X delta of the steering mouse far above the
+// ones before it, from a sensor glitch, is clamped or dropped before it can
+// throw the wheel across its range. It sees every event as read, before the
+// input window sums them.
+pub struct SpikeFilter {
+    detector: Detector,
+}
+
+impl SpikeFilter {
+    pub fn new(conf: &SpikeRejectionConfig) -> Result<Option<Self>, Mouse2JoyError> {
+        if !conf.enabled {
+            return Ok(None);
+        }
+        if conf.factor.is_nan() || conf.factor < 2.0 {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "spike_rejection.factor must be at least 2, got {}",
+                conf.factor
+            )));
+        }
+        let cap = (conf.max_delta > 0).then_some(conf.max_delta);
+        if cap.is_some_and(|cap| cap < conf.min_delta) {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "spike_rejection.max_delta {} is below min_delta {}",
+                conf.max_delta, conf.min_delta
+            )));
+        }
+        let discard = conf.action == SpikeAction::Discard;
+        Ok(Some(Self { detector: Detector::new(cap, conf.factor, conf.min_delta, discard) }))
+    }
+
+    pub fn filter(&mut self, events: &mut Vec<(InputRole, InputEvent)>) {
+        events.retain_mut(|(role, ev)| {
+            let steering = *role == InputRole::Steering && ev.event_type() == EventType::RELATIVE;
+            if !steering || ev.code() != RelativeAxisType::REL_X.0 {
+                return true;
+            }
+            let average = self.detector.average();
+            let checked = self.detector.check(ev.value());
+            if checked == Some(ev.value()) {
+                return true;
+            }
+            match checked {
+                Some(delta) => {
+                    debug!("Clamped a REL_X delta of {} to {} (recent average {:.0})", ev.value(), delta, average);
+                    *ev = InputEvent::new(EventType::RELATIVE, ev.code(), delta);
+                    true
+                }
+                None => {
+                    debug!("Dropped a REL_X delta of {} (recent average {:.0})", ev.value(), average);
+                    false
+                }
+            }
+        });
+    }
+}
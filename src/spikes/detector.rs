@@ -0,0 +1,55 @@
+// how much of the average each plausible delta makes up
+const SMOOTHING: f32 = 0.2;
+
+// Whether a delta fits the ones before it. A glitch is a single huge delta
+// out of nowhere, a flick ramps up over several events and keeps an average
+// that grows with it. Only one delta in a row is ever rejected: when the
+// next one is as large again the mouse really is moving that fast.
+pub struct Detector {
+    // the largest delta of a single event, if any
+    cap: Option<u32>,
+    factor: f32,
+    floor: u32,
+    discard: bool,
+    // of the magnitudes of the plausible deltas
+    average: f32,
+    rejected: bool,
+}
+
+impl Detector {
+    pub fn new(cap: Option<u32>, factor: f32, floor: u32, discard: bool) -> Self {
+        Self { cap, factor, floor, discard, average: 0.0, rejected: false }
+    }
+
+    // The delta to steer with, clamped, or None when it is dropped.
+    pub fn check(&mut self, delta: i32) -> Option<i32> {
+        let magnitude = delta.unsigned_abs();
+        let cap = self.cap.unwrap_or(u32::MAX);
+        let plausible = if self.rejected {
+            u32::MAX
+        } else {
+            ((self.factor * self.average) as u32).max(self.floor)
+        };
+        if magnitude > plausible {
+            // left out of the average, or one spike would let the next through
+            self.rejected = true;
+            return self.limit(delta, plausible.min(cap));
+        }
+        self.rejected = false;
+        let steered = magnitude.min(cap);
+        self.average += SMOOTHING * (steered as f32 - self.average);
+        if steered < magnitude {
+            return self.limit(delta, cap);
+        }
+        Some(delta)
+    }
+
+    // of the recent deltas, in counts
+    pub fn average(&self) -> f32 {
+        self.average
+    }
+
+    fn limit(&self, delta: i32, limit: u32) -> Option<i32> {
+        (!self.discard).then(|| delta.signum() * limit.min(i32::MAX as u32) as i32)
+    }
+}
@@ -0,0 +1,817 @@
+use evdev::{AbsoluteAxisType, EventType, InputEvent, Key};
+use log::{debug, info};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::configuration::{
+    AccelerationConfig, AxisSteeringConfig, CenterDetentConfig, Config, InertiaConfig, KeyboardSteeringConfig, MappingAction, PrecisionConfig, SnapRelease,
+    SteeringConfig, SteeringMode,
+};
+use crate::mapping::{parse_key, Mapping};
+use crate::telemetry::interpolate;
+use crate::Mouse2JoyError;
+
+mod inertia;
+use inertia::Inertia;
+
+// steering axis range: -STEERING_MAX (full left) to STEERING_MAX (full right)
+pub const STEERING_MAX: i32 = 4500;
+
+// beyond this a single count already moves the wheel lock to lock
+const MAX_SENSITIVITY: i32 = 2 * STEERING_MAX;
+
+// steering units per second to cover center to lock in `ms`, 0 is instant
+fn rate(ms: u32) -> f32 {
+    if ms == 0 {
+        f32::INFINITY
+    } else {
+        STEERING_MAX as f32 / Duration::from_millis(ms.into()).as_secs_f32()
+    }
+}
+
+fn check_sensitivity(sensitivity: i32) -> Result<(), Mouse2JoyError> {
+    if sensitivity.unsigned_abs() > MAX_SENSITIVITY.unsigned_abs() {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "sensitivity must be between -{} and {}, got {}",
+            MAX_SENSITIVITY, MAX_SENSITIVITY, sensitivity
+        )));
+    }
+    Ok(())
+}
+
+// limits of [steering.inertia], past them it is no longer a wheel
+const MAX_RESPONSE_MS: u32 = 1000;
+const DAMPING_RANGE: (f32, f32) = (0.1, 10.0);
+
+fn check_inertia(conf: &InertiaConfig) -> Result<(), Mouse2JoyError> {
+    if !conf.enabled {
+        return Ok(());
+    }
+    if !(1..=MAX_RESPONSE_MS).contains(&conf.response_ms) {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "steering.inertia.response_ms must be between 1 and {}, got {}",
+            MAX_RESPONSE_MS, conf.response_ms
+        )));
+    }
+    if !(DAMPING_RANGE.0..=DAMPING_RANGE.1).contains(&conf.damping) {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "steering.inertia.damping must be between {} and {}, got {}",
+            DAMPING_RANGE.0, DAMPING_RANGE.1, conf.damping
+        )));
+    }
+    Ok(())
+}
+
+fn check_precision(conf: &PrecisionConfig) -> Result<(), Mouse2JoyError> {
+    if conf.enabled && !(conf.scale > 0.0 && conf.scale <= 1.0) {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "precision.scale must be above 0 and at most 1, got {}",
+            conf.scale
+        )));
+    }
+    Ok(())
+}
+
+fn check_acceleration(conf: &Config) -> Result<(), Mouse2JoyError> {
+    let acceleration = &conf.acceleration;
+    if !acceleration.enabled {
+        return Ok(());
+    }
+    if conf.steering.mode == SteeringMode::Velocity {
+        return Err(Mouse2JoyError::InvalidConfig(String::from(
+            "acceleration doesn't go with velocity steering, which follows the mouse speed already",
+        )));
+    }
+    if !(1..=1000).contains(&acceleration.window_ms) {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "acceleration.window_ms must be between 1 and 1000, got {}",
+            acceleration.window_ms
+        )));
+    }
+    if !(2..=3).contains(&acceleration.curve.len()) {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "acceleration.curve needs two or three points, got {}",
+            acceleration.curve.len()
+        )));
+    }
+    if acceleration.curve[0][0] < 0.0 || acceleration.curve.windows(2).any(|pair| pair[0][0] >= pair[1][0]) {
+        return Err(Mouse2JoyError::InvalidConfig(String::from(
+            "acceleration.curve speeds must start at 0 or above and be strictly increasing",
+        )));
+    }
+    if let Some([_, gain]) = acceleration.curve.iter().find(|[_, gain]| !(*gain > 0.0 && gain.is_finite())) {
+        return Err(Mouse2JoyError::InvalidConfig(format!("acceleration.curve gains must be positive, got {}", gain)));
+    }
+    Ok(())
+}
+
+// Mouse acceleration: a gain for how fast the mouse moved over the last
+// window, in either direction, so left and right feel the same. Only the
+// times of the events count, a replayed recording steers the same.
+struct Acceleration {
+    window: Duration,
+    curve: Vec<[f32; 2]>,
+    // the counts in the window, by when they came
+    recent: VecDeque<(Instant, u32)>,
+}
+
+impl Acceleration {
+    fn new(conf: &AccelerationConfig) -> Self {
+        Self {
+            window: Duration::from_millis(conf.window_ms.into()),
+            curve: conf.curve.clone(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    // the gain for this delta, counted in with the ones before
+    fn gain(&mut self, value: i32, now: Instant) -> f32 {
+        self.recent.push_back((now, value.unsigned_abs()));
+        while self.recent.front().is_some_and(|(at, _)| now.duration_since(*at) >= self.window) {
+            self.recent.pop_front();
+        }
+        let counts: u64 = self.recent.iter().map(|(_, counts)| u64::from(*counts)).sum();
+        interpolate(&self.curve, counts as f32 / self.window.as_millis() as f32)
+    }
+}
+
+// the movement of this long decides whether the mouse is slow enough for the
+// center detent to latch
+const DETENT_WINDOW: Duration = Duration::from_millis(100);
+
+fn check_center_detent(conf: &Config) -> Result<(), Mouse2JoyError> {
+    let detent = &conf.center_detent;
+    if detent.band == 0 && detent.breakout == 0 {
+        return Ok(());
+    }
+    if conf.steering.mode == SteeringMode::Velocity {
+        return Err(Mouse2JoyError::InvalidConfig(String::from(
+            "center_detent doesn't go with velocity steering, which returns to center by itself",
+        )));
+    }
+    if detent.breakout <= detent.band || detent.breakout > STEERING_MAX as u32 {
+        return Err(Mouse2JoyError::InvalidConfig(format!(
+            "center_detent.breakout must be above the band of {} and at most {}, got {}",
+            detent.band, STEERING_MAX, detent.breakout
+        )));
+    }
+    Ok(())
+}
+
+// A latch around center, not a deadzone: the positions in the band aren't
+// rescaled. Slow movement ending within the band snaps the mouse part to 0
+// and holds it there, pushing it further than the breakout from there lets
+// go at the breakout, so leaving never jumps further than that. A fast
+// correction passing through center moves too much to latch.
+struct Detent {
+    band: i32,
+    breakout: i32,
+    // how far the mouse pushed while latched
+    pushed: Option<i32>,
+    // the movement in the window, by when it came
+    recent: VecDeque<(Instant, u32)>,
+}
+
+impl Detent {
+    fn new(conf: &CenterDetentConfig) -> Option<Self> {
+        (conf.band > 0).then(|| Self {
+            band: conf.band as i32,
+            breakout: conf.breakout as i32,
+            pushed: None,
+            recent: VecDeque::new(),
+        })
+    }
+
+    // where the mouse part moving from `from` to `to` ends up
+    fn apply(&mut self, from: i32, to: i32, now: Instant) -> i32 {
+        let moved = to - from;
+        self.recent.push_back((now, moved.unsigned_abs()));
+        while self.recent.front().is_some_and(|(at, _)| now.duration_since(*at) >= DETENT_WINDOW) {
+            self.recent.pop_front();
+        }
+        if let Some(pushed) = self.pushed.as_mut() {
+            *pushed += moved;
+            if pushed.abs() <= self.breakout {
+                return 0;
+            }
+            let out = (*pushed).clamp(-self.breakout, self.breakout);
+            self.pushed = None;
+            info!("Out of the center detent");
+            return out;
+        }
+        let recent: u32 = self.recent.iter().map(|(_, moved)| moved).sum();
+        if to.abs() <= self.band && recent <= self.band as u32 {
+            self.pushed = Some(0);
+            info!("In the center detent");
+            return 0;
+        }
+        to
+    }
+
+    // the mouse part was put somewhere else
+    fn release(&mut self) {
+        self.pushed = None;
+        self.recent.clear();
+    }
+}
+
+// Digital steering from two keys. Its offset is added on top of the mouse
+// position: holding a key moves the wheel towards that lock, releasing it
+// returns to the mouse position. Holding both cancels out.
+struct KeyboardSteering {
+    left: Key,
+    right: Key,
+    // steering units per second
+    lock_rate: f32,
+    return_rate: f32,
+    left_held: bool,
+    right_held: bool,
+    offset: f32,
+    last_update: Instant,
+}
+
+impl KeyboardSteering {
+    fn new(conf: &KeyboardSteeringConfig, now: Instant) -> Result<Self, Mouse2JoyError> {
+        Ok(Self {
+            left: parse_key(&conf.left)?,
+            right: parse_key(&conf.right)?,
+            lock_rate: rate(conf.lock_ms),
+            return_rate: rate(conf.return_ms),
+            left_held: false,
+            right_held: false,
+            offset: 0.0,
+            last_update: now,
+        })
+    }
+
+    // offset that puts the wheel where the held keys want it
+    fn target(&self, mouse: i32) -> f32 {
+        match (self.left_held, self.right_held) {
+            (true, false) => (-STEERING_MAX - mouse) as f32,
+            (false, true) => (STEERING_MAX - mouse) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn advance(&mut self, mouse: i32, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        let target = self.target(mouse);
+        let rate = if target == 0.0 { self.return_rate } else { self.lock_rate };
+        let step = rate * elapsed;
+        self.offset = if (target - self.offset).abs() <= step {
+            target
+        } else {
+            self.offset + step * (target - self.offset).signum()
+        };
+    }
+
+    fn is_settled(&self, mouse: i32) -> bool {
+        self.offset == self.target(mouse)
+    }
+}
+
+// Mouse speed turned into a deflection. The speed is averaged over the deltas
+// of the last `window`, the deflection follows it straight away when it grows
+// and at most at `return_rate` when it shrinks, so the wheel glides back to
+// center once the mouse stops.
+struct VelocitySteering {
+    gain: f32,
+    window: Duration,
+    // steering units per second
+    return_rate: f32,
+    deltas: VecDeque<(Instant, f32)>,
+    deflection: f32,
+    last_update: Instant,
+}
+
+impl VelocitySteering {
+    fn new(conf: &SteeringConfig, now: Instant) -> Result<Self, Mouse2JoyError> {
+        if conf.window_ms == 0 {
+            return Err(Mouse2JoyError::InvalidConfig(String::from(
+                "velocity steering window_ms must be at least 1",
+            )));
+        }
+        if conf.gain <= 0.0 {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "velocity steering gain must be positive, got {}",
+                conf.gain
+            )));
+        }
+        Ok(Self {
+            gain: conf.gain,
+            window: Duration::from_millis(conf.window_ms.into()),
+            return_rate: rate(conf.return_ms),
+            deltas: VecDeque::new(),
+            deflection: 0.0,
+            last_update: now,
+        })
+    }
+
+    fn push(&mut self, value: f32, now: Instant) {
+        self.deltas.push_back((now, value));
+    }
+
+    fn advance(&mut self, now: Instant, gain: f32) {
+        let elapsed = now.saturating_duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        while self
+            .deltas
+            .front()
+            .is_some_and(|(time, _)| now.saturating_duration_since(*time) >= self.window)
+        {
+            self.deltas.pop_front();
+        }
+
+        let counts: f32 = self.deltas.iter().map(|(_, value)| *value).sum();
+        let speed = counts / self.window.as_secs_f32();
+        let target = (speed * self.gain * gain).clamp(-STEERING_MAX as f32, STEERING_MAX as f32);
+        let returning = target.abs() < self.deflection.abs() || target * self.deflection < 0.0;
+        let step = self.return_rate * elapsed;
+        self.deflection = if !returning || (target - self.deflection).abs() <= step {
+            target
+        } else {
+            self.deflection + step * (target - self.deflection).signum()
+        };
+    }
+
+    fn is_settled(&self) -> bool {
+        self.deltas.is_empty() && self.deflection == 0.0
+    }
+}
+
+// The absolute axis of a gamepad stick or a small wheel, its full range
+// mapped lock to lock whatever rotation it really has. The position is
+// direct, nothing accumulates.
+struct AxisSteering {
+    axis: AbsoluteAxisType,
+    // source units around the center that read as centered
+    deadzone: i32,
+    invert: bool,
+    // from the device, None until it is known
+    range: Option<(i32, i32)>,
+    position: i32,
+}
+
+impl AxisSteering {
+    fn new(conf: &AxisSteeringConfig) -> Result<Self, Mouse2JoyError> {
+        let axis = conf.axis.parse::<AbsoluteAxisType>().map_err(|_| {
+            Mouse2JoyError::InvalidConfig(format!("unknown axis \"{}\"", conf.axis))
+        })?;
+        if conf.deadzone < 0 {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "axis_steering deadzone can't be negative, got {}",
+                conf.deadzone
+            )));
+        }
+        Ok(Self { axis, deadzone: conf.deadzone, invert: conf.invert, range: None, position: 0 })
+    }
+
+    fn set_range(&mut self, min: i32, max: i32) -> Result<(), Mouse2JoyError> {
+        // the deadzone has to leave some travel on both sides
+        if i64::from(max) - i64::from(min) <= 2 * i64::from(self.deadzone) {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "{:?} only ranges from {} to {}, too little for an axis_steering deadzone of {}",
+                self.axis, min, max, self.deadzone
+            )));
+        }
+        self.range = Some((min, max));
+        Ok(())
+    }
+
+    fn handle(&mut self, value: i32) {
+        let Some((min, max)) = self.range else {
+            return;
+        };
+        // doubled so the center of an even range stays exact
+        let offset = 2 * i64::from(value.clamp(min, max)) - i64::from(min) - i64::from(max);
+        let half = i64::from(max) - i64::from(min);
+        let deadzone = 2 * i64::from(self.deadzone);
+        let travel = (offset.abs() - deadzone).max(0) * offset.signum();
+        let position = travel * i64::from(STEERING_MAX) / (half - deadzone);
+        self.position = if self.invert { -position } else { position } as i32;
+    }
+}
+
+// Button holding the wheel at full lock, overriding everything else while
+// held. The accumulated mouse position keeps updating underneath so a release
+// returns to it exactly, unless the snap is set to stay.
+struct Snap {
+    key: Key,
+    position: i32,
+    stay: bool,
+}
+
+// Key scaling the mouse movement down while held, for small corrections.
+// Only the deltas are scaled, so pressing or releasing it never moves the
+// wheel.
+struct Precision {
+    key: Key,
+    scale: f32,
+    held: bool,
+}
+
+// Steering wheel position built from the accumulated mouse movement plus the
+// position of a steering axis and the keyboard steering offset, clamped to
+// the axis range. In velocity mode the mouse part is the current deflection
+// instead. With inertia the wheel follows that position instead of taking
+// it, ticked at the tick rate while it is moving.
+pub struct Steering {
+    sensitivity: i32,
+    // speed dependent factor on top of the sensitivity, from telemetry
+    gain: f32,
+    // fraction of a steering unit left over by a gain below 1.0
+    remainder: f64,
+    mouse_enabled: bool,
+    // mouse movement only steers while this is held, if set
+    gate: Option<Key>,
+    gate_held: bool,
+    mouse: i32,
+    velocity: Option<VelocitySteering>,
+    axis: Option<AxisSteering>,
+    keyboard: Option<KeyboardSteering>,
+    snaps: Vec<Snap>,
+    // held snap buttons, the last one pressed wins
+    snaps_held: Vec<Key>,
+    precision: Option<Precision>,
+    acceleration: Option<Acceleration>,
+    detent: Option<Detent>,
+    inertia: Option<Inertia>,
+    // the tick of the loop, what inertia integrates with
+    step: Duration,
+    emitted: i32,
+}
+
+impl Steering {
+    pub fn new(conf: &Config, mapping: &Mapping, now: Instant) -> Result<Self, Mouse2JoyError> {
+        let keyboard = if conf.keyboard_steering.enabled {
+            Some(KeyboardSteering::new(&conf.keyboard_steering, now)?)
+        } else {
+            None
+        };
+        let axis = if conf.axis_steering.enabled {
+            Some(AxisSteering::new(&conf.axis_steering)?)
+        } else {
+            None
+        };
+        check_sensitivity(conf.sensitivity)?;
+        if !conf.mouse_steering && keyboard.is_none() && axis.is_none() {
+            return Err(Mouse2JoyError::InvalidConfig(String::from(
+                "mouse_steering is disabled without keyboard_steering or axis_steering, nothing would steer",
+            )));
+        }
+        let velocity = match conf.steering.mode {
+            SteeringMode::Velocity => Some(VelocitySteering::new(&conf.steering, now)?),
+            SteeringMode::Position => None,
+        };
+        check_inertia(&conf.steering.inertia)?;
+        check_precision(&conf.precision)?;
+        check_acceleration(conf)?;
+        check_center_detent(conf)?;
+        let precision = if conf.precision.enabled {
+            Some(Precision { key: parse_key(&conf.precision.key)?, scale: conf.precision.scale, held: false })
+        } else {
+            None
+        };
+        let step = Duration::from_secs(1) / conf.tick_rate.max(1);
+        let inertia = conf.steering.inertia.enabled.then(|| inertia(&conf.steering.inertia, step, 0, now));
+        Ok(Self {
+            sensitivity: conf.sensitivity,
+            gain: 1.0,
+            remainder: 0.0,
+            mouse_enabled: conf.mouse_steering,
+            gate: conf.steer_while_held.as_deref().map(parse_key).transpose()?,
+            gate_held: false,
+            mouse: 0,
+            velocity,
+            axis,
+            keyboard,
+            snaps: mapping
+                .action_sources()
+                .into_iter()
+                .filter_map(|(key, action, release)| {
+                    let position = match action {
+                        MappingAction::SnapLeft => -STEERING_MAX,
+                        MappingAction::SnapRight => STEERING_MAX,
+                        // the pedals hold the throttle
+                        MappingAction::ThrottleHoldToggle => return None,
+                    };
+                    Some(Snap { key, position, stay: release == SnapRelease::Stay })
+                })
+                .collect(),
+            snaps_held: Vec::new(),
+            precision,
+            acceleration: conf.acceleration.enabled.then(|| Acceleration::new(&conf.acceleration)),
+            detent: Detent::new(&conf.center_detent),
+            inertia,
+            step,
+            emitted: 0,
+        })
+    }
+
+    // Take over the tuning of another profile without moving the wheel: the
+    // sensitivity, the steering mode with its parameters, the keyboard
+    // steering rates, the precision scale, the acceleration and the center
+    // detent. Keys and gates stay as they are.
+    pub fn reconfigure(&mut self, conf: &Config, now: Instant) -> Result<(), Mouse2JoyError> {
+        check_sensitivity(conf.sensitivity)?;
+        check_inertia(&conf.steering.inertia)?;
+        check_precision(&conf.precision)?;
+        check_acceleration(conf)?;
+        check_center_detent(conf)?;
+        let velocity = match conf.steering.mode {
+            SteeringMode::Velocity => {
+                let mut velocity = VelocitySteering::new(&conf.steering, now)?;
+                match self.velocity.take() {
+                    Some(old) => {
+                        velocity.deltas = old.deltas;
+                        velocity.deflection = old.deflection;
+                    }
+                    // glides back to center from where the mouse left it
+                    None => velocity.deflection = self.mouse as f32,
+                }
+                Some(velocity)
+            }
+            SteeringMode::Position => None,
+        };
+        self.sensitivity = conf.sensitivity;
+        self.remainder = 0.0;
+        self.velocity = velocity;
+        if let Some(precision) = self.precision.as_mut() {
+            precision.scale = conf.precision.scale;
+        }
+        self.acceleration = conf.acceleration.enabled.then(|| Acceleration::new(&conf.acceleration));
+        // latched again by the next slow movement
+        self.detent = Detent::new(&conf.center_detent);
+        let tuning = &conf.steering.inertia;
+        self.inertia = match self.inertia.take() {
+            Some(mut inertia) if tuning.enabled => {
+                inertia.tune(Duration::from_millis(tuning.response_ms.into()), tuning.damping);
+                Some(inertia)
+            }
+            _ if tuning.enabled => Some(inertia(tuning, self.step, self.emitted, now)),
+            _ => None,
+        };
+        let base = self.base();
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.advance(base, now);
+            keyboard.lock_rate = rate(conf.keyboard_steering.lock_ms);
+            keyboard.return_rate = rate(conf.keyboard_steering.return_ms);
+        }
+        Ok(())
+    }
+
+    pub fn mouse_enabled(&self) -> bool {
+        self.mouse_enabled
+    }
+
+    pub fn axis(&self) -> Option<AbsoluteAxisType> {
+        self.axis.as_ref().map(|axis| axis.axis)
+    }
+
+    // the range the steering axis device reports, until then it is ignored
+    pub fn set_axis_range(&mut self, min: i32, max: i32) -> Result<(), Mouse2JoyError> {
+        match self.axis.as_mut() {
+            Some(axis) => axis.set_range(min, max),
+            None => Ok(()),
+        }
+    }
+
+    // the mouse and the steering axis, what the keyboard steers on top of
+    fn base(&self) -> i32 {
+        self.mouse + self.axis.as_ref().map_or(0, |axis| axis.position)
+    }
+
+    pub fn keys(&self) -> Vec<Key> {
+        let mut keys: Vec<Key> = self.gate.into_iter().collect();
+        if let Some(keyboard) = &self.keyboard {
+            keys.extend([keyboard.left, keyboard.right]);
+        }
+        keys.extend(self.snaps.iter().map(|snap| snap.key));
+        keys.extend(self.precision.as_ref().map(|precision| precision.key));
+        keys
+    }
+
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+    }
+
+    pub fn sensitivity(&self) -> i32 {
+        self.sensitivity
+    }
+
+    // by `mouse2joy set`, until the next profile switch or exit
+    pub fn set_sensitivity(&mut self, sensitivity: i32) -> Result<(), Mouse2JoyError> {
+        check_sensitivity(sensitivity)?;
+        self.sensitivity = sensitivity;
+        self.remainder = 0.0;
+        Ok(())
+    }
+
+    // the scale while the precision key is held
+    pub fn precision(&self) -> Option<f32> {
+        self.precision.as_ref().filter(|precision| precision.held).map(|precision| precision.scale)
+    }
+
+    pub fn position(&self) -> i32 {
+        self.emitted
+    }
+
+    // the part of the position the mouse added
+    pub fn mouse(&self) -> i32 {
+        self.mouse
+    }
+
+    // Put the mouse part back where it was, undoing the movement since. In
+    // velocity mode the deflection goes there and the speed is forgotten.
+    pub fn restore_mouse(&mut self, mouse: i32, now: Instant) -> Option<InputEvent> {
+        self.mouse = mouse;
+        self.remainder = 0.0;
+        if let Some(detent) = self.detent.as_mut() {
+            detent.release();
+        }
+        if let Some(velocity) = self.velocity.as_mut() {
+            velocity.deltas.clear();
+            velocity.deflection = mouse as f32;
+        }
+        self.tick(now)
+    }
+
+    pub fn handle_rel_x(&mut self, value: i32, now: Instant) -> Option<InputEvent> {
+        // movement outside the gate is dropped, not queued, so pressing it
+        // doesn't make the wheel jump
+        if !self.mouse_enabled || (self.gate.is_some() && !self.gate_held) {
+            return None;
+        }
+        // never both, acceleration is for the position mode
+        let scale = self.precision().unwrap_or(1.0)
+            * self.acceleration.as_mut().map_or(1.0, |acceleration| acceleration.gain(value, now));
+        let from = self.mouse;
+        match self.velocity.as_mut() {
+            Some(velocity) => velocity.push(value as f32 * scale, now),
+            // Apply sensitivity multiplier from config and clamp to steering wheel range,
+            // in i64 since big deltas from high resolution sensors overflow i32. One
+            // event moves the wheel lock to lock at most.
+            None if self.gain * scale == 1.0 => {
+                let span = 2 * i64::from(STEERING_MAX);
+                let delta = (i64::from(value) * i64::from(self.sensitivity)).clamp(-span, span);
+                self.mouse = (i64::from(self.mouse) + delta).clamp(-i64::from(STEERING_MAX), i64::from(STEERING_MAX)) as i32;
+            }
+            // the same scaled by the telemetry gain, the precision key and
+            // the acceleration, carrying the fraction so small movements
+            // still add up
+            None => {
+                let span = 2 * i64::from(STEERING_MAX);
+                let factor = f64::from(self.gain) * f64::from(scale);
+                let exact = f64::from(value) * f64::from(self.sensitivity) * factor + self.remainder;
+                let delta = (exact.trunc() as i64).clamp(-span, span);
+                self.remainder = if delta.abs() < span { exact.fract() } else { 0.0 };
+                self.mouse = (i64::from(self.mouse) + delta).clamp(-i64::from(STEERING_MAX), i64::from(STEERING_MAX)) as i32;
+            }
+        }
+        // like the acceleration, only in position mode
+        if let Some(detent) = self.detent.as_mut() {
+            self.mouse = detent.apply(from, self.mouse, now);
+        }
+        self.tick(now)
+    }
+
+    pub fn handle_abs(&mut self, value: i32, now: Instant) -> Option<InputEvent> {
+        self.axis.as_mut()?.handle(value);
+        self.tick(now)
+    }
+
+    // returns true if the key steers
+    pub fn handle_key(&mut self, key: Key, value: i32, now: Instant) -> bool {
+        if Some(key) == self.gate {
+            if value != 2 {
+                self.gate_held = value != 0;
+            }
+            return true;
+        }
+        if let Some(precision) = self.precision.as_mut().filter(|precision| precision.key == key) {
+            if value != 2 {
+                precision.held = value != 0;
+            }
+            return true;
+        }
+        if let Some(snap) = self.snaps.iter().find(|snap| snap.key == key) {
+            match value {
+                0 => {
+                    self.snaps_held.retain(|held| *held != key);
+                    if snap.stay {
+                        self.mouse = snap.position;
+                        if let Some(detent) = self.detent.as_mut() {
+                            detent.release();
+                        }
+                    }
+                }
+                1 if !self.snaps_held.contains(&key) => self.snaps_held.push(key),
+                _ => {}
+            }
+            return true;
+        }
+        let base = self.base();
+        let Some(keyboard) = self.keyboard.as_mut() else {
+            return false;
+        };
+        if key != keyboard.left && key != keyboard.right {
+            return false;
+        }
+        if value != 2 {
+            keyboard.advance(base, now);
+            if key == keyboard.left {
+                keyboard.left_held = value != 0;
+            } else {
+                keyboard.right_held = value != 0;
+            }
+        }
+        true
+    }
+
+    // advance the velocity and keyboard ramps, returns the new position if it
+    // changed
+    pub fn tick(&mut self, now: Instant) -> Option<InputEvent> {
+        if let Some(velocity) = self.velocity.as_mut() {
+            velocity.advance(now, self.gain);
+            self.mouse = velocity.deflection.round() as i32;
+        }
+        let base = self.base();
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.advance(base, now);
+        }
+        let position = self.target();
+        let position = match self.inertia.as_mut() {
+            Some(inertia) => inertia.advance(position as f32, now).round() as i32,
+            None => position,
+        };
+        if position == self.emitted {
+            return None;
+        }
+        self.emitted = position;
+        // at the rate of the mouse, only when asked for
+        debug!(steering = position; "Steering:  {}", position);
+        Some(InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_X.0, position))
+    }
+
+    // where the wheel is heading, what inertia follows
+    pub fn target(&self) -> i32 {
+        let position = self.base() + self.keyboard.as_ref().map_or(0, |keyboard| keyboard.offset.round() as i32);
+        let snapped = self.snaps_held.last().and_then(|key| self.snaps.iter().find(|snap| snap.key == *key));
+        match snapped {
+            Some(snap) => snap.position,
+            None => position.clamp(-STEERING_MAX, STEERING_MAX),
+        }
+    }
+
+    // paused, the keys held may come up unseen
+    pub fn release(&mut self) {
+        self.gate_held = false;
+        if let Some(precision) = self.precision.as_mut() {
+            precision.held = false;
+        }
+    }
+
+    // Back to center with nothing held, the keys and the mouse have to act
+    // again to move it.
+    pub fn center(&mut self, now: Instant) -> Option<InputEvent> {
+        self.release();
+        self.mouse = 0;
+        self.remainder = 0.0;
+        if let Some(detent) = self.detent.as_mut() {
+            detent.release();
+        }
+        if let Some(axis) = self.axis.as_mut() {
+            axis.position = 0;
+        }
+        if let Some(velocity) = self.velocity.as_mut() {
+            velocity.deltas.clear();
+            velocity.deflection = 0.0;
+        }
+        if let Some(keyboard) = self.keyboard.as_mut() {
+            keyboard.left_held = false;
+            keyboard.right_held = false;
+            keyboard.offset = 0.0;
+        }
+        self.snaps_held.clear();
+        if let Some(inertia) = self.inertia.as_mut() {
+            inertia.jump(0.0, now);
+        }
+        self.tick(now)
+    }
+
+    pub fn is_settled(&self) -> bool {
+        self.velocity.as_ref().is_none_or(VelocitySteering::is_settled)
+            && self.inertia.as_ref().is_none_or(Inertia::is_settled)
+            && self
+                .keyboard
+                .as_ref()
+                .is_none_or(|keyboard| keyboard.is_settled(self.base()))
+    }
+}
+
+fn inertia(conf: &InertiaConfig, step: Duration, position: i32, now: Instant) -> Inertia {
+    let response = Duration::from_millis(conf.response_ms.into());
+    Inertia::new(response, conf.damping, step, STEERING_MAX as f32, position as f32, now)
+}
@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+// closer than this to the target and slower than SETTLED_SPEED units per
+// second the wheel has arrived
+const SETTLED_DISTANCE: f32 = 0.5;
+const SETTLED_SPEED: f32 = 10.0;
+// steps integrated at most per advance, after a longer gap the wheel is
+// where it was headed anyway
+const MAX_STEPS: u32 = 1000;
+
+// A heavy wheel on a spring pulled towards the target by the mouse, with a
+// damper. Integrated with fixed steps of implicit Euler, which stays stable
+// for any stiffness and damping, and never taken past the axis limits.
+pub struct Inertia {
+    // undamped angular frequency, 1/s
+    omega: f32,
+    // damping ratio, 1.0 gets there fastest without overshooting
+    zeta: f32,
+    step: Duration,
+    limit: f32,
+    position: f32,
+    velocity: f32,
+    target: f32,
+    // integrated up to here
+    time: Instant,
+}
+
+impl Inertia {
+    // `response` is about how long the wheel takes to catch up with a jump
+    // of the target when critically damped
+    pub fn new(response: Duration, damping: f32, step: Duration, limit: f32, position: f32, now: Instant) -> Self {
+        let mut inertia = Self {
+            omega: 0.0,
+            zeta: 0.0,
+            step,
+            limit,
+            position,
+            velocity: 0.0,
+            target: position,
+            time: now,
+        };
+        inertia.tune(response, damping);
+        inertia
+    }
+
+    // new parameters, the wheel keeps moving as it was
+    pub fn tune(&mut self, response: Duration, damping: f32) {
+        // within 5% after `response`
+        self.omega = 5.0 / response.as_secs_f32().max(f32::EPSILON);
+        self.zeta = damping.max(0.0);
+    }
+
+    // where the wheel is at `now` heading for `target`
+    pub fn advance(&mut self, target: f32, now: Instant) -> f32 {
+        // at rest nothing was integrated while the loop slept
+        if self.is_settled() {
+            self.time = self.time.max(now);
+        }
+        self.target = target.clamp(-self.limit, self.limit);
+        let dt = self.step.as_secs_f32();
+        let mut steps = 0;
+        while self.time + self.step <= now {
+            if steps == MAX_STEPS {
+                self.position = self.target;
+                self.velocity = 0.0;
+                self.time = now;
+                break;
+            }
+            let pull = self.omega * self.omega;
+            self.velocity = (self.velocity + dt * pull * (self.target - self.position))
+                / (1.0 + 2.0 * self.zeta * self.omega * dt + pull * dt * dt);
+            self.position += dt * self.velocity;
+            // the end stops take the momentum
+            if self.position.abs() >= self.limit {
+                self.position = self.position.clamp(-self.limit, self.limit);
+                self.velocity = 0.0;
+            }
+            self.time += self.step;
+            steps += 1;
+        }
+        self.position
+    }
+
+    pub fn is_settled(&self) -> bool {
+        (self.position - self.target).abs() < SETTLED_DISTANCE && self.velocity.abs() < SETTLED_SPEED
+    }
+
+    // straight there, e.g. centered when paused
+    pub fn jump(&mut self, position: f32, now: Instant) {
+        self.position = position;
+        self.target = position;
+        self.velocity = 0.0;
+        self.time = now;
+    }
+}
@@ -0,0 +1,50 @@
+use evdev::{AbsoluteAxisType, EventType, InputEvent};
+use log::info;
+use std::f32::consts::TAU;
+use std::time::{Duration, Instant};
+
+use crate::steering::STEERING_MAX;
+
+// lock to lock and back, about what a real wheel takes on connect
+const DURATION: Duration = Duration::from_millis(1500);
+
+// Turns the wheel from center to full right, over to full left and back to
+// center on one sine period, the way a real wheel calibrates on connect, so
+// games learning the range see all of it. Any real input aborts it.
+pub struct Sweep {
+    start: Instant,
+    emitted: i32,
+}
+
+impl Sweep {
+    // `current` is what the virtual wheel shows right now
+    pub fn new(current: i32, now: Instant) -> Self {
+        info!("Sweeping the steering lock to lock for calibration");
+        Self { start: now, emitted: current }
+    }
+
+    // Replace the steering events in `outgoing` with the sweep position.
+    // Returns false once the sweep is over or was interrupted by `input`,
+    // `live` is sent then so the wheel jumps to the real position.
+    pub fn tick(&mut self, input: bool, live: i32, outgoing: &mut Vec<InputEvent>, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.start);
+        let done = input || elapsed >= DURATION;
+        if input {
+            info!("Calibration sweep interrupted by input");
+        }
+        let value = if done {
+            live
+        } else {
+            let phase = elapsed.as_secs_f32() / DURATION.as_secs_f32();
+            ((phase * TAU).sin() * STEERING_MAX as f32).round() as i32
+        };
+        outgoing.retain(|event| {
+            event.event_type() != EventType::ABSOLUTE || event.code() != AbsoluteAxisType::ABS_X.0
+        });
+        if value != self.emitted {
+            self.emitted = value;
+            outgoing.push(InputEvent::new(EventType::ABSOLUTE, AbsoluteAxisType::ABS_X.0, value));
+        }
+        !done
+    }
+}
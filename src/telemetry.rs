@@ -0,0 +1,128 @@
+use log::{info, warn};
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::configuration::{TelemetryConfig, TelemetryFormat};
+use crate::Mouse2JoyError;
+
+mod packet;
+
+// Vehicle speed received from the game, turned into a steering gain through
+// the configured speed to gain curve. When packets stop coming the gain
+// fades back to 1.0 over `decay` after `timeout` instead of sticking at the
+// last value.
+pub struct Telemetry {
+    // speed in km/h and when it arrived
+    latest: Arc<Mutex<Option<(f32, Instant)>>>,
+    curve: Vec<[f32; 2]>,
+    timeout: Duration,
+    decay: Duration,
+}
+
+impl Telemetry {
+    pub fn new(conf: &TelemetryConfig) -> Result<Option<Self>, Mouse2JoyError> {
+        let decode = match conf.format {
+            TelemetryFormat::None => return Ok(None),
+            TelemetryFormat::Codemasters => packet::codemasters,
+            TelemetryFormat::Forza => packet::forza,
+        };
+        if conf.curve.is_empty() {
+            return Err(Mouse2JoyError::InvalidConfig(String::from("telemetry curve needs at least one point")));
+        }
+        if conf.curve.windows(2).any(|pair| pair[0][0] >= pair[1][0]) {
+            return Err(Mouse2JoyError::InvalidConfig(String::from(
+                "telemetry curve speeds must be strictly increasing",
+            )));
+        }
+        if let Some([_, gain]) = conf.curve.iter().find(|[_, gain]| !(*gain > 0.0 && gain.is_finite())) {
+            return Err(Mouse2JoyError::InvalidConfig(format!(
+                "telemetry curve gains must be positive, got {}",
+                gain
+            )));
+        }
+        let timeout = Duration::from_millis(conf.timeout_ms.max(1).into());
+        let socket = UdpSocket::bind(&conf.listen)
+            .and_then(|socket| socket.set_read_timeout(Some(timeout)).map(|_| socket))
+            .map_err(|e| Mouse2JoyError::InvalidConfig(format!("can't listen on {}: {}", conf.listen, e)))?;
+        info!("Listening for {:?} telemetry on udp://{}", conf.format, conf.listen);
+
+        let latest = Arc::new(Mutex::new(None));
+        let shared = latest.clone();
+        std::thread::spawn(move || receive(socket, decode, &shared));
+
+        Ok(Some(Self {
+            latest,
+            curve: conf.curve.clone(),
+            timeout,
+            decay: Duration::from_millis(conf.decay_ms.into()),
+        }))
+    }
+
+    // steering gain for the current speed, 1.0 without telemetry
+    pub fn gain(&self) -> f32 {
+        let Some((speed, received)) = *self.latest.lock().unwrap_or_else(|e| e.into_inner()) else {
+            return 1.0;
+        };
+        let gain = interpolate(&self.curve, speed);
+        let stale = received.elapsed().saturating_sub(self.timeout);
+        if stale.is_zero() {
+            return gain;
+        }
+        if stale >= self.decay {
+            return 1.0;
+        }
+        let faded = stale.as_secs_f32() / self.decay.as_secs_f32();
+        gain + (1.0 - gain) * faded
+    }
+
+    // the latest speed in km/h, unless it is older than the timeout
+    pub fn speed(&self) -> Option<f32> {
+        let (speed, received) = (*self.latest.lock().unwrap_or_else(|e| e.into_inner()))?;
+        (received.elapsed() <= self.timeout).then_some(speed)
+    }
+
+}
+
+// linear between the points, flat beyond the first and last one
+pub fn interpolate(curve: &[[f32; 2]], speed: f32) -> f32 {
+    let [first, last] = [curve[0], curve[curve.len() - 1]];
+    if speed <= first[0] {
+        return first[1];
+    }
+    if speed >= last[0] {
+        return last[1];
+    }
+    let upper = curve.iter().position(|[point, _]| *point > speed).unwrap_or(curve.len() - 1);
+    let ([s0, g0], [s1, g1]) = (curve[upper - 1], curve[upper]);
+    g0 + (g1 - g0) * (speed - s0) / (s1 - s0)
+}
+
+fn receive(socket: UdpSocket, decode: fn(&[u8]) -> Option<f32>, latest: &Mutex<Option<(f32, Instant)>>) {
+    let mut buffer = [0u8; 2048];
+    let mut receiving = false;
+    loop {
+        match socket.recv(&mut buffer) {
+            Ok(len) => {
+                let Some(speed) = decode(&buffer[..len]) else {
+                    continue;
+                };
+                if !receiving {
+                    info!("Receiving telemetry, steering follows the vehicle speed");
+                    receiving = true;
+                }
+                *latest.lock().unwrap_or_else(|e| e.into_inner()) = Some((speed, Instant::now()));
+            }
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {
+                if receiving {
+                    info!("Telemetry lost, steering gain returns to default");
+                    receiving = false;
+                }
+            }
+            Err(e) => {
+                warn!("Stopped receiving telemetry:  {}", e);
+                return;
+            }
+        }
+    }
+}
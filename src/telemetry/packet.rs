@@ -0,0 +1,37 @@
+// Decoders for the telemetry packets games broadcast over UDP. Each returns
+// the vehicle speed in km/h, or None for packets it doesn't understand or
+// that carry no live data.
+
+// Codemasters' legacy format (DiRT Rally, DiRT 4, GRID, F1 with the legacy
+// UDP format, "extradata" 0 to 3): a flat array of little endian f32, the
+// speed in m/s at index 7.
+pub fn codemasters(packet: &[u8]) -> Option<f32> {
+    const SPEED: usize = 7 * 4;
+    if !packet.len().is_multiple_of(4) {
+        return None;
+    }
+    let speed = f32_at(packet, SPEED)?;
+    (speed.is_finite() && speed >= 0.0).then_some(speed * 3.6)
+}
+
+// Forza "Data Out" (Motorsport 7 and later, Horizon 4 and later). All
+// variants start with the same "sled" block: IsRaceOn as s32 at 0, the local
+// velocity in m/s as three f32 at 32. Menus and pauses send IsRaceOn 0.
+pub fn forza(packet: &[u8]) -> Option<f32> {
+    const SLED_LEN: usize = 232;
+    const VELOCITY: usize = 32;
+    if packet.len() < SLED_LEN || i32::from_le_bytes(packet[0..4].try_into().ok()?) == 0 {
+        return None;
+    }
+    let (x, y, z) = (
+        f32_at(packet, VELOCITY)?,
+        f32_at(packet, VELOCITY + 4)?,
+        f32_at(packet, VELOCITY + 8)?,
+    );
+    let speed = (x * x + y * y + z * z).sqrt();
+    speed.is_finite().then_some(speed * 3.6)
+}
+
+fn f32_at(packet: &[u8], offset: usize) -> Option<f32> {
+    packet.get(offset..offset + 4).map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+}
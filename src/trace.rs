@@ -0,0 +1,75 @@
+use evdev::InputEvent;
+use log::{info, warn};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::configuration::InputRole;
+use crate::output::describe;
+use crate::Mouse2JoyError;
+
+// Every event read from the input devices and every event sent to the
+// virtual device, one per line in a fixed field order:
+//
+//   seconds direction source timestamp type code value
+//
+// e.g. `  1.204311 in  steering 1712345678.123456 EV_REL REL_X 5` or
+// `  1.204311 out virtual  - EV_ABS ABS_X 1200`. The seconds count from the
+// start of the trace on mouse2joy's clock, the timestamp of an input event
+// is the kernel's. Written straight to stderr or the file, whatever the log
+// level, and stopped after `duration`.
+pub struct Tracer {
+    out: Option<Box<dyn Write>>,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Tracer {
+    pub fn new(file: Option<&Path>, duration: Duration, now: Instant) -> Result<Self, Mouse2JoyError> {
+        let out: Box<dyn Write> = match file {
+            Some(path) => Box::new(LineWriter::new(
+                File::create(path).map_err(|e| Mouse2JoyError::FailedToWrite(path.display().to_string(), e))?,
+            )),
+            None => Box::new(std::io::stderr()),
+        };
+        match file {
+            Some(path) => info!("Tracing the input and output events to {} for {}s", path.display(), duration.as_secs()),
+            None => info!("Tracing the input and output events for {}s", duration.as_secs()),
+        }
+        Ok(Self { out: Some(out), start: now, duration })
+    }
+
+    pub fn input(&mut self, role: InputRole, event: &InputEvent, now: Instant) {
+        let source = match role {
+            InputRole::Steering => "steering",
+            InputRole::Pedals => "pedals",
+            InputRole::Axis => "axis",
+        };
+        let stamp = event.timestamp().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        self.write(now, format_args!("in  {:8} {:.6} {}", source, stamp.as_secs_f64(), describe(event)));
+    }
+
+    pub fn output(&mut self, events: &[InputEvent], now: Instant) {
+        for event in events {
+            self.write(now, format_args!("out virtual  - {}", describe(event)));
+        }
+    }
+
+    fn write(&mut self, now: Instant, line: std::fmt::Arguments) {
+        let elapsed = now.saturating_duration_since(self.start);
+        let Some(out) = self.out.as_mut() else {
+            return;
+        };
+        if elapsed >= self.duration {
+            let _ = writeln!(out, "# stopped after {}s", self.duration.as_secs());
+            info!("Stopped tracing after {}s", self.duration.as_secs());
+            self.out = None;
+            return;
+        }
+        if let Err(e) = writeln!(out, "{:10.6} {}", elapsed.as_secs_f64(), line) {
+            warn!("Failed to write the trace, stopped tracing:  {}", e);
+            self.out = None;
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use log::{info, warn};
+use nix::unistd::Group;
+use std::fs;
+use std::io::ErrorKind;
+use std::process::Command;
+
+use crate::Mouse2JoyError;
+
+mod rule;
+
+// before 70-uaccess.rules, which only hands joysticks to the seat's user
+// when ID_INPUT_JOYSTICK is already set
+const RULE_FILE: &str = "/etc/udev/rules.d/60-mouse2joy.rules";
+// the wheel and, with the split layouts, the pedals and the shifter; the
+// [mouse_look] pointer and the [keys] keyboard come after them and stay
+// what they are
+const PRODUCTS: u16 = 3;
+
+// Write the udev rule, or with `dry_run` or without the permission print it
+// along with what to do with it.
+pub fn install(group: &str, joystick: bool, dry_run: bool) -> Result<(), Mouse2JoyError> {
+    if !rule::valid_group(group) {
+        return Err(Mouse2JoyError::InvalidArguments(format!("\"{}\" isn't a valid group name", group)));
+    }
+    if !matches!(Group::from_name(group), Ok(Some(_))) {
+        return Err(Mouse2JoyError::InvalidArguments(format!(
+            "there is no group \"{}\", create it first with `sudo groupadd --system {}`",
+            group, group
+        )));
+    }
+    let id = crate::vjoystick_id();
+    let products: Vec<u16> = (0..PRODUCTS).map(|offset| id.product() + offset).collect();
+    let text = rule::render(group, joystick.then_some((id.vendor(), products.as_slice())));
+
+    if dry_run {
+        print!("{}", text);
+        print_steps(group, true);
+        return Ok(());
+    }
+    match fs::write(RULE_FILE, &text) {
+        Ok(()) => info!("Wrote {}", RULE_FILE),
+        Err(e) if matches!(e.kind(), ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem) => {
+            warn!("Not allowed to write {} ({}), printing the rule instead", RULE_FILE, e);
+            print!("{}", text);
+            print_steps(group, true);
+            return Ok(());
+        }
+        Err(e) => return Err(Mouse2JoyError::FailedToWrite(String::from(RULE_FILE), e)),
+    }
+    if reload() {
+        info!("Reloaded the udev rules");
+    }
+    print_steps(group, false);
+    Ok(())
+}
+
+// on stderr, so the rule alone can be piped into sudo tee
+fn print_steps(group: &str, save: bool) {
+    if save {
+        info!(
+            "Save the rule as {} (e.g. piped into `sudo tee {}`), then reload udev with `sudo udevadm control \
+             --reload-rules && sudo udevadm trigger --subsystem-match=misc --sysname-match=uinput`",
+            RULE_FILE, RULE_FILE
+        );
+    }
+    let groups = if group == "input" { String::from(group) } else { format!("input,{}", group) };
+    info!(
+        "Reading the mice needs the input group and uinput then needs \"{}\", add the user with \
+         `sudo usermod -aG {} $USER` and log in again",
+        group, groups
+    );
+}
+
+// let udev pick the new rule up and apply it to the existing uinput node,
+// returns whether that worked
+fn reload() -> bool {
+    let commands: [&[&str]; 2] =
+        [&["control", "--reload-rules"], &["trigger", "--subsystem-match=misc", "--sysname-match=uinput"]];
+    commands.iter().all(|args| match Command::new("udevadm").args(*args).status() {
+        Ok(status) if status.success() => true,
+        Ok(status) => {
+            warn!("`udevadm {}` failed ({}), run it by hand", args.join(" "), status);
+            false
+        }
+        Err(e) => {
+            warn!("Can't run `udevadm {}` ({}), run it by hand", args.join(" "), e);
+            false
+        }
+    })
+}
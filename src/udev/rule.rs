@@ -0,0 +1,29 @@
+// The udev rule file for running without root: write access to uinput for
+// a group and, optionally, the joystick tag on the virtual devices so Steam
+// and the uaccess rules treat them as game controllers.
+
+// a group name udev takes as is, nothing that could end the quoted value
+pub fn valid_group(group: &str) -> bool {
+    let mut chars = group.chars();
+    chars.next().is_some_and(|first| first.is_ascii_lowercase() || first == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-'))
+}
+
+// `joystick` holds the vendor and the products of the virtual devices
+pub fn render(group: &str, joystick: Option<(u16, &[u16])>) -> String {
+    let mut rule = String::from("# Written by mouse2joy --install-udev-rule\n");
+    rule.push_str(&format!(
+        "KERNEL==\"uinput\", SUBSYSTEM==\"misc\", OPTIONS+=\"static_node=uinput\", GROUP=\"{}\", MODE=\"0660\"\n",
+        group
+    ));
+    if let Some((vendor, products)) = joystick {
+        for product in products {
+            rule.push_str(&format!(
+                "SUBSYSTEM==\"input\", ATTRS{{id/vendor}}==\"{:04x}\", ATTRS{{id/product}}==\"{:04x}\", \
+                 ENV{{ID_INPUT_JOYSTICK}}=\"1\"\n",
+                vendor, product
+            ));
+        }
+    }
+    rule
+}
@@ -0,0 +1,129 @@
+use evdev::{Device, InputEventKind, Key};
+use log::{info, warn};
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::hotkey::Combo;
+use crate::Mouse2JoyError;
+
+// the documented exit code of an emergency release
+const EXIT_CODE: i32 = 14;
+// how often keyboards plugged in later are looked for
+const RESCAN: Duration = Duration::from_secs(5);
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+// the emergency_release combination, None when it is ""
+pub fn combo(spec: &str) -> Result<Option<Combo>, Mouse2JoyError> {
+    if spec.is_empty() {
+        return Ok(None);
+    }
+    let combo =
+        Combo::parse(spec).map_err(|e| Mouse2JoyError::InvalidConfig(format!("emergency_release: {}", e)))?;
+    Ok(Some(combo))
+}
+
+// The safety net of the grabs. A thread of its own reads the keyboards
+// through handles of its own, and on the combination ends the process on
+// the spot whatever the loop is doing, even stuck in an emit. Closing the
+// process's file descriptors releases the grabbed mice and removes the
+// virtual devices, the kernel does that however far the loop got, so
+// nothing the loop holds is needed. Started once, the pipelines share it.
+pub fn start(combo: Combo, input_dir: PathBuf) {
+    if STARTED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    info!("{} releases the devices and exits in an emergency", combo);
+    std::thread::spawn(move || watch(&combo, &input_dir));
+}
+
+fn watch(combo: &Combo, input_dir: &Path) {
+    let mut keyboards: Vec<(PathBuf, Device)> = Vec::new();
+    let mut scanned: Option<Instant> = None;
+    // the modifiers down, on any keyboard
+    let mut held: Vec<Key> = Vec::new();
+    loop {
+        if scanned.is_none_or(|scanned| scanned.elapsed() >= RESCAN) {
+            let found = scan(combo, input_dir, &mut keyboards);
+            if scanned.is_none() && found == 0 {
+                warn!("No keyboard with {:?} found yet, the emergency release can't be pressed", combo.key);
+            }
+            scanned = Some(Instant::now());
+        }
+        let ready: Vec<bool> = {
+            // SAFETY: the devices outlive the poll call, so their fds stay open
+            let mut fds: Vec<PollFd> = keyboards
+                .iter()
+                .map(|(_, device)| PollFd::new(unsafe { BorrowedFd::borrow_raw(device.as_raw_fd()) }, PollFlags::POLLIN))
+                .collect();
+            let timeout = PollTimeout::try_from(RESCAN.as_millis()).unwrap_or(PollTimeout::MAX);
+            if poll(&mut fds, timeout).is_err() {
+                // interrupted by a signal
+                continue;
+            }
+            fds.iter().map(|fd| fd.revents().is_some_and(|revents| !revents.is_empty())).collect()
+        };
+        let mut gone = Vec::new();
+        for (index, (_, device)) in keyboards.iter_mut().enumerate().filter(|(index, _)| ready[*index]) {
+            let events = match device.fetch_events() {
+                Ok(events) => events,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+                Err(_) => {
+                    gone.push(index);
+                    continue;
+                }
+            };
+            for ev in events {
+                let InputEventKind::Key(key) = ev.kind() else {
+                    continue;
+                };
+                if combo.modifiers.contains(&key) {
+                    match ev.value() {
+                        0 => held.retain(|held| *held != key),
+                        1 if !held.contains(&key) => held.push(key),
+                        _ => {}
+                    }
+                }
+                if ev.value() == 1 && combo.is_pressed(key, &held) {
+                    release(combo);
+                }
+            }
+        }
+        for index in gone.into_iter().rev() {
+            keyboards.remove(index);
+        }
+    }
+}
+
+// Opens the keyboards with any of the keys that aren't open yet, the key
+// and the modifiers may be on different ones. How many are open after.
+fn scan(combo: &Combo, input_dir: &Path, keyboards: &mut Vec<(PathBuf, Device)>) -> usize {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(input_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| !keyboards.iter().any(|(open, _)| open == path))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let Ok(device) = Device::open(&path) else {
+            continue;
+        };
+        if device.supported_keys().is_some_and(|keys| combo.keys().any(|key| keys.contains(key))) {
+            keyboards.push((path, device));
+        }
+    }
+    keyboards.len()
+}
+
+// Straight to stderr, the logger may be what the loop is stuck in. The exit
+// flushes stdout only if it isn't locked.
+fn release(combo: &Combo) -> ! {
+    let message = format!("{} pressed, emergency release: exiting with the devices let go\n", combo);
+    let _ = nix::unistd::write(std::io::stderr().as_fd(), message.as_bytes());
+    std::process::exit(EXIT_CODE);
+}
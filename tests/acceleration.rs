@@ -0,0 +1,61 @@
+// [acceleration] replays the same recorded trace with and without the
+// speed dependent gain, on a dry run of an input script.
+
+mod common;
+
+use common::steering;
+use std::process::Output;
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("acceleration", name, &format!("log_backend = \"stderr\"\nsensitivity = 10\n{}", config), script)
+}
+
+// a slow correction, one count every 10ms, then after a pause a fast sweep
+// of 40 counts every ms
+fn trace(direction: i32) -> String {
+    let mut trace = String::new();
+    for _ in 0..5 {
+        trace.push_str(&format!("10 EV_REL REL_X {}\n", direction));
+    }
+    trace.push_str(&format!("100 EV_REL REL_X {}\n", 40 * direction));
+    for _ in 0..4 {
+        trace.push_str(&format!("1 EV_REL REL_X {}\n", 40 * direction));
+    }
+    trace
+}
+
+const ACCELERATION: &str = "[acceleration]\nenabled = true\nwindow_ms = 20\ncurve = [[1.0, 0.5], [2.0, 1.0], [10.0, 3.0]]\n";
+
+#[test]
+fn slow_moves_steer_finer_and_fast_ones_further() {
+    let off = steering(&run("off", "", &trace(1)));
+    let on = steering(&run("on", ACCELERATION, &trace(1)));
+    assert_eq!(off, [10, 20, 30, 40, 50, 450, 850, 1250, 1650, 2050]);
+    // half the gain below 1 count per ms, then 2 to 10 counts per ms over
+    // the window ramp it from 1 to 3
+    assert_eq!(on, [5, 10, 15, 20, 25, 425, 1025, 1825, 2825, 4025]);
+}
+
+#[test]
+fn both_directions_accelerate_the_same() {
+    let right = steering(&run("right", ACCELERATION, &trace(1)));
+    let left = steering(&run("left", ACCELERATION, &trace(-1)));
+    assert_eq!(left, right.iter().map(|value| -value).collect::<Vec<_>>());
+}
+
+#[test]
+fn the_curve_is_checked() {
+    const ON: &str = "[acceleration]\nenabled = true\n";
+    for (name, config, message) in [
+        ("points", "curve = [[1.0, 1.0]]\n", "acceleration.curve needs two or three points, got 1"),
+        ("order", "curve = [[2.0, 1.0], [1.0, 2.0]]\n", "acceleration.curve speeds must start at 0 or above"),
+        ("gain", "curve = [[1.0, 1.0], [2.0, 0.0]]\n", "acceleration.curve gains must be positive, got 0"),
+        ("window", "window_ms = 0\n", "acceleration.window_ms must be between 1 and 1000, got 0"),
+        ("velocity", "[steering]\nmode = \"velocity\"\n", "acceleration doesn't go with velocity steering"),
+    ] {
+        let output = run(name, &format!("{}{}", ON, config), "0 EV_REL REL_X 10\n");
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{}: {}", name, log);
+        assert!(log.contains(message), "{}: {}", name, log);
+    }
+}
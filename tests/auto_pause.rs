@@ -0,0 +1,83 @@
+// [auto_pause] pauses after the idle time with the wheel near center and the
+// pedals up, leaving the wheel where it is, and only the pause hotkey or a
+// long burst of movement resumes. On the script's simulated clock.
+
+mod common;
+
+use common::events;
+use std::process::Output;
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    let config = format!("sensitivity = 10\nlog_backend = \"stderr\"\n[auto_pause]\nidle_seconds = 1\n{}", config);
+    let output = common::run_logging("auto-pause", name, &config, script, Some("info"));
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+#[test]
+fn idle_near_center_pauses_and_desktop_movement_stays_out() {
+    let output = run(
+        "idle",
+        "",
+        "0 EV_REL REL_X 10\n\
+         1500 EV_REL REL_X 10\n\
+         50 EV_REL REL_X 10\n",
+    );
+    // the wheel stays at 100 until the exit, the moves on the desktop steer
+    // nothing
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_X 100", "1.000 EV_ABS ABS_X 0"]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(log.contains("Auto-paused: No input for 1s, the mice are free until the pause hotkey"), "{}", log);
+}
+
+#[test]
+fn a_long_burst_resumes_from_the_held_position() {
+    let output = run(
+        "burst",
+        "resume_burst_ms = 200\n",
+        "0 EV_REL REL_X 10\n\
+         1500 EV_REL REL_X 10\n\
+         50 EV_REL REL_X 10\n\
+         # a break starts the burst over\n\
+         200 EV_REL REL_X 10\n\
+         50 EV_REL REL_X 10\n\
+         50 EV_REL REL_X 10\n\
+         50 EV_REL REL_X 10\n\
+         50 EV_REL REL_X 10\n\
+         200 EV_REL REL_X 10\n",
+    );
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_X 100", "2.150 EV_ABS ABS_X 200", "2.150 EV_ABS ABS_X 0"]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(log.contains("until the pause hotkey or 200 ms of mouse movement"), "{}", log);
+    assert!(log.contains("Resumed from the auto-pause, steering on from 100"), "{}", log);
+}
+
+#[test]
+fn the_pause_hotkey_resumes() {
+    let output = run(
+        "hotkey",
+        "[hotkeys]\npause = \"KEY_F8\"\n",
+        "0 EV_REL REL_X 10\n\
+         1500 EV_KEY KEY_F8 1\n\
+         0 EV_KEY KEY_F8 0\n\
+         100 EV_REL REL_X 10\n",
+    );
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_X 100", "1.600 EV_ABS ABS_X 200", "1.600 EV_ABS ABS_X 0"]);
+}
+
+#[test]
+fn off_center_or_a_pressed_pedal_keeps_it_running() {
+    let output = run("off-center", "", "0 EV_REL REL_X 40\n1500 EV_REL REL_X 10\n");
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_X 400", "1.500 EV_ABS ABS_X 500", "1.500 EV_ABS ABS_X 0"]);
+
+    let output = run(
+        "pedal",
+        "[pedals.throttle]\nkeys = [\"KEY_W\"]\nrise_ms = 0\n",
+        "0 EV_KEY KEY_W 1\n\
+         1500 EV_REL REL_X 10\n\
+         0 EV_KEY KEY_W 0\n",
+    );
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(!log.contains("Auto-paused"), "{}", log);
+    assert!(events(&output).contains(&String::from("1.500 EV_ABS ABS_X 100")), "{:?}", events(&output));
+}
@@ -0,0 +1,80 @@
+// --bench runs its synthetic input through the pipeline and reports in a
+// fixed "key value" format, the counts the same on every run.
+
+use std::process::{Command, Output};
+
+fn bench(name: &str, config: &str, args: &[&str]) -> Output {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-bench-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), format!("log_backend = \"stderr\"\n{}", config)).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .args(args)
+        .env("RUST_LOG", "warn")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+fn report(output: &Output) -> Vec<(String, String)> {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            let (key, value) = line.split_once(' ').unwrap();
+            (key.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+fn value<'a>(report: &'a [(String, String)], key: &str) -> &'a str {
+    &report.iter().find(|(name, _)| name == key).unwrap().1
+}
+
+#[test]
+fn the_report_keeps_its_format() {
+    let report = report(&bench("format", "", &["--bench", "20000", "--dry-run"]));
+    let keys: Vec<&str> = report.iter().map(|(key, _)| key.as_str()).collect();
+    let mut expected: Vec<String> =
+        ["format", "version", "output", "events", "emitted", "seconds", "events_per_second", "allocations_per_event"]
+            .map(String::from)
+            .to_vec();
+    for stage in ["read", "filter", "map", "tick", "emit"] {
+        expected.push(format!("{}_ns_per_event", stage));
+        expected.push(format!("{}_allocations_per_event", stage));
+    }
+    assert_eq!(keys, expected);
+    assert_eq!(value(&report, "format"), "1");
+    assert_eq!(value(&report, "output"), "discarded");
+    assert_eq!(value(&report, "events"), "20000");
+    assert!(value(&report, "events_per_second").parse::<f64>().unwrap() > 0.0);
+}
+
+#[test]
+fn the_synthetic_input_is_the_same_every_run() {
+    let config = "[buttons]\nBTN_LEFT = \"BTN_TRIGGER\"\n";
+    let first = report(&bench("first", config, &["--bench", "30000", "--dry-run"]));
+    let second = report(&bench("second", config, &["--bench", "30000", "--dry-run"]));
+    assert_eq!(value(&first, "emitted"), value(&second, "emitted"));
+    assert!(value(&first, "emitted").parse::<u64>().unwrap() > 0);
+}
+
+#[test]
+fn the_input_window_cuts_the_updates() {
+    let plain = report(&bench("plain", "", &["--bench", "30000", "--dry-run"]));
+    let windowed = report(&bench("window", "input_window_ms = 4\n", &["--bench", "30000", "--dry-run"]));
+    let emitted = |report: &[(String, String)]| value(report, "emitted").parse::<u64>().unwrap();
+    assert!(emitted(&windowed) < emitted(&plain));
+}
+
+#[test]
+fn bad_arguments_are_refused() {
+    let output = bench("zero", "", &["--bench", "0"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid event count \"0\""));
+    let output = bench("script", "", &["--bench", "--input-script", "-"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--bench makes up its input"));
+}
@@ -0,0 +1,251 @@
+// Two sources pressed within the chord window press a button of their own,
+// otherwise each goes on late and does what it does alone. Every withheld
+// press ends up passed on or taken by a chord.
+
+#[path = "../src/chords.rs"]
+mod chords;
+
+mod common;
+
+use chords::Chords;
+use evdev::{EventType, InputEvent, Key};
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_millis(50);
+
+fn key(key: Key, value: i32) -> ((), InputEvent) {
+    ((), InputEvent::new(EventType::KEY, key.code(), value))
+}
+
+// what went on and what the chords pressed, as "KEY 1" strings
+fn step(chords: &mut Chords<()>, events: &[((), InputEvent)], at: Instant) -> (Vec<String>, Vec<String>) {
+    let mut outgoing = Vec::new();
+    let passed = chords.filter(events.to_vec(), at, &mut outgoing);
+    let describe = |ev: &InputEvent| match ev.event_type() {
+        EventType::KEY => format!("{:?} {}", Key::new(ev.code()), ev.value()),
+        other => format!("{:?} {} {}", other, ev.code(), ev.value()),
+    };
+    (passed.iter().map(|(_, ev)| describe(ev)).collect(), outgoing.iter().map(describe).collect())
+}
+
+fn chords(list: &[([Key; 2], Key)]) -> Chords<()> {
+    Chords::new(list.to_vec(), WINDOW).unwrap()
+}
+
+#[test]
+fn a_chord_presses_its_button_and_swallows_the_members() {
+    let mut chords = chords(&[([Key::BTN_SIDE, Key::BTN_EXTRA], Key::BTN_TL2)]);
+    assert_eq!(chords.members(), [Key::BTN_SIDE, Key::BTN_EXTRA]);
+    let start = Instant::now();
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_SIDE, 1)], start);
+    assert!(passed.is_empty() && pressed.is_empty());
+    assert_eq!(chords.wakeup(start + Duration::from_millis(20)), Some(Duration::from_millis(30)));
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_EXTRA, 1)], start + Duration::from_millis(20));
+    assert!(passed.is_empty());
+    assert_eq!(pressed, ["BTN_TL2 1"]);
+    assert_eq!(chords.wakeup(start), None);
+    // repeats and the second release go nowhere
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_SIDE, 2), key(Key::BTN_SIDE, 0)], start + WINDOW);
+    assert!(passed.is_empty());
+    assert_eq!(pressed, ["BTN_TL2 0"]);
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_EXTRA, 0)], start + WINDOW * 2);
+    assert!(passed.is_empty() && pressed.is_empty());
+}
+
+#[test]
+fn a_member_alone_goes_on_late_or_on_release() {
+    let mut chords = chords(&[([Key::BTN_SIDE, Key::BTN_EXTRA], Key::BTN_TL2)]);
+    let start = Instant::now();
+    // held past the window
+    step(&mut chords, &[key(Key::BTN_SIDE, 1), key(Key::BTN_LEFT, 1)], start);
+    let (passed, _) = step(&mut chords, &[], start + WINDOW - Duration::from_millis(1));
+    assert!(passed.is_empty());
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_EXTRA, 1)], start + WINDOW);
+    assert_eq!(passed, ["BTN_SIDE 1"]);
+    assert!(pressed.is_empty());
+    // now the other one waits, and is tapped
+    let (passed, _) = step(&mut chords, &[key(Key::BTN_EXTRA, 0)], start + WINDOW + Duration::from_millis(10));
+    assert_eq!(passed, ["BTN_EXTRA 1", "BTN_EXTRA 0"]);
+    let (passed, _) = step(&mut chords, &[key(Key::BTN_SIDE, 0)], start + WINDOW * 2);
+    assert_eq!(passed, ["BTN_SIDE 0"]);
+    assert_eq!(chords.wakeup(start), None);
+}
+
+#[test]
+fn non_members_pass_through_untouched() {
+    let mut chords = chords(&[([Key::BTN_SIDE, Key::BTN_EXTRA], Key::BTN_TL2)]);
+    let start = Instant::now();
+    let rel = ((), InputEvent::new(EventType::RELATIVE, 0, 5));
+    let (passed, _) = step(&mut chords, &[key(Key::BTN_LEFT, 1), rel, key(Key::BTN_LEFT, 2)], start);
+    assert_eq!(passed, ["BTN_LEFT 1", "RELATIVE 0 5", "BTN_LEFT 2"]);
+}
+
+#[test]
+fn overlapping_chords_take_the_partner_pressed_first() {
+    // BTN_SIDE is in two chords
+    let mut chords = chords(&[
+        ([Key::BTN_SIDE, Key::BTN_EXTRA], Key::BTN_TL2),
+        ([Key::BTN_SIDE, Key::BTN_FORWARD], Key::BTN_TR2),
+        ([Key::BTN_EXTRA, Key::BTN_FORWARD], Key::BTN_START),
+    ]);
+    let start = Instant::now();
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_EXTRA, 1), key(Key::BTN_FORWARD, 1)], start);
+    assert!(passed.is_empty());
+    assert_eq!(pressed, ["BTN_START 1"]);
+    // both partners are taken, BTN_SIDE can only go on alone
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_SIDE, 1)], start + Duration::from_millis(5));
+    assert!(passed.is_empty() && pressed.is_empty());
+    let (passed, pressed) = step(&mut chords, &[], start + Duration::from_millis(55));
+    assert_eq!(passed, ["BTN_SIDE 1"]);
+    assert!(pressed.is_empty());
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_FORWARD, 0)], start + Duration::from_millis(60));
+    assert!(passed.is_empty());
+    assert_eq!(pressed, ["BTN_START 0"]);
+    // BTN_EXTRA goes up again and then forms a chord with BTN_SIDE
+    let later = start + Duration::from_millis(100);
+    step(&mut chords, &[key(Key::BTN_EXTRA, 0), key(Key::BTN_SIDE, 0)], later);
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_SIDE, 1), key(Key::BTN_EXTRA, 1)], later);
+    assert!(passed.is_empty());
+    assert_eq!(pressed, ["BTN_TL2 1"]);
+}
+
+#[test]
+fn two_chords_on_one_button_hold_it_until_both_are_up() {
+    let mut chords = chords(&[
+        ([Key::BTN_SIDE, Key::BTN_EXTRA], Key::BTN_TL2),
+        ([Key::BTN_FORWARD, Key::BTN_BACK], Key::BTN_TL2),
+    ]);
+    let start = Instant::now();
+    let (_, pressed) = step(
+        &mut chords,
+        &[key(Key::BTN_SIDE, 1), key(Key::BTN_EXTRA, 1), key(Key::BTN_FORWARD, 1), key(Key::BTN_BACK, 1)],
+        start,
+    );
+    assert_eq!(pressed, ["BTN_TL2 1"]);
+    let (_, pressed) = step(&mut chords, &[key(Key::BTN_SIDE, 0)], start);
+    assert!(pressed.is_empty());
+    let (_, pressed) = step(&mut chords, &[key(Key::BTN_BACK, 0)], start);
+    assert_eq!(pressed, ["BTN_TL2 0"]);
+}
+
+#[test]
+fn rapid_sequences_lose_and_stick_nothing() {
+    let mut chords = chords(&[([Key::BTN_SIDE, Key::BTN_EXTRA], Key::BTN_TL2)]);
+    let start = Instant::now();
+    let mut passed = Vec::new();
+    let mut pressed = Vec::new();
+    // taps, chords and a tap of one member during a chord, 5 ms apart
+    let script = [
+        key(Key::BTN_SIDE, 1),
+        key(Key::BTN_SIDE, 0),
+        key(Key::BTN_SIDE, 1),
+        key(Key::BTN_EXTRA, 1),
+        key(Key::BTN_EXTRA, 0),
+        key(Key::BTN_EXTRA, 1),
+        key(Key::BTN_EXTRA, 0),
+        key(Key::BTN_SIDE, 0),
+        key(Key::BTN_EXTRA, 1),
+        key(Key::BTN_SIDE, 1),
+        key(Key::BTN_SIDE, 0),
+        key(Key::BTN_EXTRA, 0),
+    ];
+    for (i, event) in script.iter().enumerate() {
+        let (p, o) = step(&mut chords, &[*event], start + Duration::from_millis(5 * i as u64));
+        passed.extend(p);
+        pressed.extend(o);
+    }
+    let (p, o) = step(&mut chords, &[], start + Duration::from_secs(1));
+    passed.extend(p);
+    pressed.extend(o);
+    assert_eq!(
+        passed,
+        [
+            "BTN_SIDE 1",
+            "BTN_SIDE 0",
+            // BTN_SIDE is still held by the first chord, BTN_EXTRA alone
+            "BTN_EXTRA 1",
+            "BTN_EXTRA 0",
+        ]
+    );
+    assert_eq!(pressed, ["BTN_TL2 1", "BTN_TL2 0", "BTN_TL2 1", "BTN_TL2 0"]);
+    assert_eq!(chords.wakeup(start), None);
+}
+
+#[test]
+fn a_reset_drops_the_withheld_presses_and_releases_the_chords() {
+    let mut chords = chords(&[
+        ([Key::BTN_SIDE, Key::BTN_EXTRA], Key::BTN_TL2),
+        ([Key::BTN_FORWARD, Key::BTN_BACK], Key::BTN_TR2),
+    ]);
+    let start = Instant::now();
+    step(&mut chords, &[key(Key::BTN_SIDE, 1), key(Key::BTN_EXTRA, 1), key(Key::BTN_FORWARD, 1)], start);
+    let released = chords.reset();
+    assert_eq!(released.len(), 1);
+    assert_eq!((released[0].code(), released[0].value()), (Key::BTN_TL2.code(), 0));
+    assert_eq!(chords.wakeup(start), None);
+    // the releases seen after it go on as they are
+    let (passed, pressed) = step(&mut chords, &[key(Key::BTN_SIDE, 0), key(Key::BTN_FORWARD, 0)], start + WINDOW);
+    assert_eq!(passed, ["BTN_SIDE 0", "BTN_FORWARD 0"]);
+    assert!(pressed.is_empty());
+    assert!(chords.reset().is_empty());
+}
+
+fn run(name: &str, config: &str, script: &str) -> (Option<i32>, Vec<String>, String) {
+    let output = common::run("chords", name, &format!("log_backend = \"stderr\"\n{}", config), script);
+    (output.status.code(), common::lines(&output), String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+const CONFIG: &str = "[buttons]\nBTN_SIDE = \"BTN_TR\"\nBTN_EXTRA = \"BTN_TL\"\n\"BTN_SIDE+BTN_EXTRA\" = \"BTN_TL2\"\n";
+
+#[test]
+fn side_shifts_up_and_side_with_extra_pulls_the_handbrake() {
+    let (code, events, log) = run(
+        "handbrake",
+        CONFIG,
+        "0 EV_KEY BTN_SIDE 1\n\
+         10 EV_KEY BTN_SIDE 0\n\
+         100 EV_KEY BTN_SIDE 1\n\
+         20 EV_KEY BTN_EXTRA 1\n\
+         100 EV_KEY BTN_SIDE 0\n\
+         0 EV_KEY BTN_EXTRA 0\n\
+         100 EV_KEY BTN_EXTRA 1\n\
+         100 EV_KEY BTN_EXTRA 0\n",
+    );
+    assert_eq!(code, Some(0), "{}", log);
+    assert_eq!(
+        events,
+        [
+            "0.010 EV_KEY BTN_TR 1",
+            "0.010 EV_KEY BTN_TR 0",
+            "0.130 EV_KEY BTN_TL2 1",
+            "0.230 EV_KEY BTN_TL2 0",
+            // alone past the window
+            "0.380 EV_KEY BTN_TL 1",
+            "0.430 EV_KEY BTN_TL 0",
+        ]
+    );
+}
+
+#[test]
+fn bad_chords_are_refused() {
+    for (name, config, message) in [
+        (
+            "twice",
+            "[buttons]\n\"BTN_SIDE+BTN_EXTRA\" = \"BTN_TL2\"\n\"BTN_EXTRA+BTN_SIDE\" = \"BTN_TR2\"\n",
+            "mapped twice",
+        ),
+        ("three", "[buttons]\n\"BTN_SIDE+BTN_EXTRA+BTN_LEFT\" = \"BTN_TL2\"\n", "isn't two different sources"),
+        ("same", "[buttons]\n\"BTN_SIDE+BTN_SIDE\" = \"BTN_TL2\"\n", "isn't two different sources"),
+        ("axis", "[buttons]\n\"BTN_SIDE+BTN_EXTRA\" = { axis = \"ABS_GAS\" }\n", "can only press a button"),
+        ("window", "chord_window_ms = 1000\n[buttons]\n\"BTN_SIDE+BTN_EXTRA\" = \"BTN_TL2\"\n", "at most 500"),
+        (
+            "hotkey",
+            "[hotkeys]\npause = \"BTN_EXTRA\"\n[buttons]\n\"BTN_SIDE+BTN_EXTRA\" = \"BTN_TL2\"\n",
+            "BTN_EXTRA is part of a chord and bound to a hotkey",
+        ),
+    ] {
+        let (code, _, log) = run(name, config, "0 EV_REL REL_X 10\n");
+        assert_eq!(code, Some(3), "{}: {}", name, log);
+        assert!(log.contains(message), "{}: {}", name, log);
+    }
+}
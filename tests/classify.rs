@@ -0,0 +1,103 @@
+// The udev input_id heuristics the virtual devices are checked against, and
+// the devices of every output layout coming out as joysticks.
+
+// the udev database is only read by --self-test and --doctor
+#[allow(dead_code)]
+#[path = "../src/classify.rs"]
+mod classify;
+
+use classify::{classify, udev_classes, Class};
+use std::process::Command;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_GAS: u16 = 0x09;
+const ABS_BRAKE: u16 = 0x0a;
+const ABS_HAT0X: u16 = 0x10;
+const BTN_LEFT: u16 = 0x110;
+const BTN_TRIGGER: u16 = 0x120;
+const BTN_SOUTH: u16 = 0x130;
+const BTN_TOOL_PEN: u16 = 0x140;
+const BTN_TOOL_FINGER: u16 = 0x145;
+const BTN_TOUCH: u16 = 0x14a;
+const BTN_TRIGGER_HAPPY1: u16 = 0x2c0;
+const INPUT_PROP_DIRECT: u16 = 0x01;
+
+#[test]
+fn wheels_pedals_and_shifters_are_joysticks() {
+    assert_eq!(classify(&[ABS_X], &[BTN_TRIGGER], &[]), Class::Joystick);
+    assert_eq!(classify(&[ABS_X], &[BTN_SOUTH], &[]), Class::Joystick);
+    assert_eq!(classify(&[ABS_X, ABS_HAT0X], &[], &[]), Class::Joystick);
+    assert_eq!(classify(&[ABS_GAS, ABS_BRAKE], &[], &[]), Class::Joystick);
+    assert_eq!(classify(&[], &[BTN_TRIGGER_HAPPY1], &[]), Class::Joystick);
+    // a gamepad with both sticks
+    assert_eq!(classify(&[ABS_X, ABS_Y, ABS_GAS], &[BTN_SOUTH], &[]), Class::Joystick);
+}
+
+#[test]
+fn pointer_capabilities_win_over_joystick_ones() {
+    // like VMware's absolute USB mouse
+    assert_eq!(classify(&[ABS_X, ABS_Y], &[BTN_LEFT, BTN_TRIGGER], &[]), Class::Mouse);
+    assert_eq!(classify(&[ABS_X, ABS_Y], &[BTN_TOOL_PEN, BTN_TOUCH], &[]), Class::Tablet);
+    assert_eq!(classify(&[ABS_X, ABS_Y], &[BTN_TOOL_FINGER, BTN_TOUCH], &[]), Class::Touchpad);
+    assert_eq!(classify(&[ABS_X, ABS_Y, ABS_GAS], &[], &[INPUT_PROP_DIRECT]), Class::Touchscreen);
+    // without ABS_Y there are no coordinates, the mouse button still counts
+    assert_eq!(classify(&[ABS_X], &[BTN_LEFT], &[]), Class::Mouse);
+}
+
+#[test]
+fn lone_steering_axis_is_unclassified() {
+    assert_eq!(classify(&[ABS_X], &[], &[]), Class::Unclassified);
+    // a mouse with 16 buttons reaches BTN_TRIGGER, it is no joystick
+    let buttons: Vec<u16> = (BTN_LEFT..=BTN_TRIGGER).collect();
+    assert_eq!(classify(&[ABS_X], &buttons, &[]), Class::Mouse);
+}
+
+#[test]
+fn udev_database_entries_are_read() {
+    let data = "I:1234\nE:ID_INPUT=1\nE:ID_INPUT_JOYSTICK=1\nE:ID_INPUT_MOUSE=0\nG:uaccess\n";
+    assert_eq!(udev_classes(data), [Class::Joystick]);
+    assert_eq!(udev_classes("E:ID_INPUT=1\nE:ID_INPUT_MOUSE=1\nE:ID_INPUT_TABLET=1\n"), [Class::Mouse, Class::Tablet]);
+    assert_eq!(udev_classes("E:ID_INPUT=1\n"), []);
+}
+
+// the startup log of a dry run on the given config
+fn log(name: &str, config: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-classify-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), format!("log_backend = \"stderr\"\n{}", config)).unwrap();
+    std::fs::write(dir.join("input.txt"), "0 EV_REL REL_X 1\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    let log = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", log);
+    log
+}
+
+#[test]
+fn every_layout_makes_joysticks() {
+    let pedal = "[pedals.throttle]\nkeys = [\"KEY_W\"]\n";
+    for (name, config) in [
+        ("combined", String::new()),
+        ("gamepad-buttons", String::from("joystick_buttons = false\n")),
+        ("no-buttons-pedal", format!("no_buttons = true\n{}", pedal)),
+        ("split", format!("layout = \"split\"\n{}", pedal)),
+        ("split-shifter", format!("layout = \"split_shifter\"\nextra_buttons = 4\n{}", pedal)),
+    ] {
+        let log = log(name, &config);
+        assert!(!log.contains("for a joystick"), "{}: {}", name, log);
+    }
+}
+
+#[test]
+fn a_lone_steering_axis_is_warned_about() {
+    let log = log("lone-axis", "no_buttons = true\n");
+    assert!(log.contains("udev won't take \"mouse2joy\" for a joystick (no ID_INPUT class)"), "{}", log);
+}
@@ -0,0 +1,68 @@
+// The dry run most integration tests drive: a config and an input script
+// written to a directory of their own and mouse2joy run on them, printing
+// the events it would have sent.
+
+// every test file uses some of it
+#![allow(dead_code)]
+
+use std::process::{Command, Output};
+
+// the dry run of `script` with `config`, in a directory named after the test
+// file `test` and `name`
+pub fn run(test: &str, name: &str, config: &str, script: &str) -> Output {
+    run_logging(test, name, config, script, None)
+}
+
+// the same with RUST_LOG set to `filter`, everything is logged without it
+pub fn run_logging(test: &str, name: &str, config: &str, script: &str, filter: Option<&str>) -> Output {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-{}-{}-{}", test, name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_mouse2joy"));
+    if let Some(filter) = filter {
+        command.env("RUST_LOG", filter);
+    }
+    let output = command
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+// the events printed, their fields a single space apart
+pub fn lines(output: &Output) -> Vec<String> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+// the events of a run that succeeded
+pub fn events(output: &Output) -> Vec<String> {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    lines(output)
+}
+
+// the steering values sent, without the time and the centering on exit, which
+// only comes when the wheel isn't centered already
+pub fn steering(output: &Output) -> Vec<i32> {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let mut values: Vec<i32> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [_, "EV_ABS", "ABS_X", value] => value.parse().ok(),
+            _ => None,
+        })
+        .collect();
+    if values.last() == Some(&0) {
+        values.pop();
+    }
+    values
+}
@@ -0,0 +1,118 @@
+// The order the configuration file is looked for in: $XDG_CONFIG_HOME,
+// ~/.config, a legacy location that gets moved, then /etc. Temporary
+// directories stand in for all of them.
+
+#[path = "../src/configuration/lookup.rs"]
+mod lookup;
+
+use lookup::{Lookup, Places, Source};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-lookup-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, contents: &str) -> PathBuf {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+    path.to_path_buf()
+}
+
+fn places(dir: &Path) -> Places {
+    Places {
+        config_home: Some(dir.join("xdg")),
+        home: dir.join("home"),
+        legacy: vec![dir.join("home/.config/mouse2joy/config.toml")],
+        system: dir.join("etc/mouse2joy/config.toml"),
+    }
+}
+
+#[test]
+fn the_xdg_file_comes_first() {
+    let dir = dir("xdg");
+    let user = write(&dir.join("xdg/mouse2joy/config.toml"), "");
+    write(&dir.join("home/.config/mouse2joy/config.toml"), "");
+    write(&dir.join("etc/mouse2joy/config.toml"), "");
+    let found = places(&dir).find();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(found, Lookup { path: user.clone(), user, source: Source::ConfigHome });
+}
+
+#[test]
+fn without_xdg_config_home_it_is_in_dot_config() {
+    let dir = dir("home");
+    let user = write(&dir.join("home/.config/mouse2joy/config.toml"), "");
+    // relative, so ignored
+    let places = Places { config_home: Some(PathBuf::from("xdg")), legacy: Vec::new(), ..places(&dir) };
+    let found = places.find();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(found, Lookup { path: user.clone(), user, source: Source::Home });
+}
+
+#[test]
+fn a_legacy_file_is_moved_to_the_xdg_location() {
+    let dir = dir("legacy");
+    let legacy = write(&dir.join("home/.config/mouse2joy/config.toml"), "sensitivity = 12\n");
+    write(&dir.join("etc/mouse2joy/config.toml"), "");
+    let found = places(&dir).find();
+    let user = dir.join("xdg/mouse2joy/config.toml");
+    let moved = std::fs::read_to_string(&user);
+    let left = legacy.exists();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(found, Lookup { path: user.clone(), user, source: Source::Migrated(legacy) });
+    assert_eq!(moved.unwrap(), "sensitivity = 12\n");
+    assert!(!left);
+}
+
+#[test]
+fn without_a_user_file_the_system_one_is_used() {
+    let dir = dir("system");
+    let system = write(&dir.join("etc/mouse2joy/config.toml"), "");
+    let found = places(&dir).find();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(found, Lookup { path: system, user: dir.join("xdg/mouse2joy/config.toml"), source: Source::System });
+}
+
+#[test]
+fn without_any_file_the_user_one_is_where_it_would_go() {
+    let dir = dir("missing");
+    let found = places(&dir).find();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let user = dir.join("xdg/mouse2joy/config.toml");
+    assert_eq!(found, Lookup { path: user.clone(), user, source: Source::Missing });
+    assert_eq!(Lookup::given(Path::new("my.toml")).to_string(), "Configuration file my.toml given with --config");
+}
+
+fn run(dir: &Path, args: &[&str]) -> String {
+    let script = write(&dir.join("input.txt"), "0 EV_REL REL_X 10\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("XDG_CONFIG_HOME", dir.join("xdg"))
+        .env("HOME", dir.join("home"))
+        .env_remove("SUDO_USER")
+        .args(args)
+        .arg("--input-script")
+        .arg(script)
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    let log = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(output.status.success(), "{}", log);
+    log
+}
+
+#[test]
+fn the_binary_follows_the_environment_and_config_overrides_it() {
+    let dir = dir("binary");
+    let user = write(&dir.join("xdg/mouse2joy/config.toml"), "sensitivity = 42\nlog_backend = \"stderr\"\n");
+    let given = write(&dir.join("given.toml"), "sensitivity = 7\nlog_backend = \"stderr\"\n");
+    let found = run(&dir, &[]);
+    let overridden = run(&dir, &["--config", given.to_str().unwrap()]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(found.contains(&format!("Configuration file {} found in $XDG_CONFIG_HOME", user.display())), "{}", found);
+    assert!(found.contains("sensitivity: 42"), "{}", found);
+    assert!(overridden.contains("sensitivity: 7"), "{}", overridden);
+}
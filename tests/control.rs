@@ -0,0 +1,137 @@
+// mouse2joy status, pause and set reach a running instance through its
+// socket in $XDG_RUNTIME_DIR, here one waiting on a `wait` script line.
+
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-control-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), "sensitivity = 100\nlog_backend = \"stderr\"\n").unwrap();
+    dir
+}
+
+// mouse2joy on the script, once it answers on its socket
+fn start(dir: &Path, script: &str) -> Child {
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+    let child = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("XDG_RUNTIME_DIR", dir)
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let start = Instant::now();
+    let socket = || std::fs::read_dir(dir).unwrap().flatten().any(|entry| entry.path().extension() == Some("sock".as_ref()));
+    while !socket() {
+        assert!(start.elapsed() < Duration::from_secs(10), "no socket in {}", dir.display());
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    child
+}
+
+fn client(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("XDG_RUNTIME_DIR", dir)
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// without the time, a wait takes real time
+fn events(output: &Output) -> Vec<String> {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.split_whitespace().skip(1).collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+#[test]
+fn status_shows_the_running_wheel() {
+    let dir = dir("status");
+    let child = start(&dir, "0 EV_REL REL_X 10\n10000 wait\n10000 wait\n");
+    let status = client(&dir, &["status"]);
+    let json = client(&dir, &["status", "--json"]);
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(status.status.success(), "{}", String::from_utf8_lossy(&status.stderr));
+    let text = String::from_utf8_lossy(&status.stdout);
+    for line in ["state          running", "steering       1000 of 4500", "sensitivity    100", "profile        base"] {
+        assert!(text.contains(line), "{}", text);
+    }
+    assert!(json.status.success(), "{}", String::from_utf8_lossy(&json.stderr));
+    let json = String::from_utf8_lossy(&json.stdout);
+    assert!(
+        json.starts_with("{\"pipeline\": null, \"paused\": false, \"input\": \"the input script\", \"steering\": 1000,"),
+        "{}",
+        json
+    );
+    assert!(json.contains("\"input_events\": 1, \"output_events\": 1}"), "{}", json);
+    assert_eq!(events(&output), ["EV_ABS ABS_X 1000", "EV_ABS ABS_X 0"]);
+}
+
+#[test]
+fn set_changes_the_sensitivity_of_the_next_moves() {
+    let dir = dir("set");
+    let child = start(&dir, "0 EV_REL REL_X 10\n10000 wait\n0 EV_REL REL_X 10\n");
+    let set = client(&dir, &["set", "sensitivity", "50"]);
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(set.status.success(), "{}", String::from_utf8_lossy(&set.stderr));
+    assert_eq!(String::from_utf8_lossy(&set.stdout), "Sensitivity set to 50\n");
+    assert_eq!(events(&output), ["EV_ABS ABS_X 1000", "EV_ABS ABS_X 1500", "EV_ABS ABS_X 0"]);
+}
+
+#[test]
+fn pause_and_resume_say_which() {
+    let dir = dir("pause");
+    let child = start(&dir, "0 EV_REL REL_X 10\n10000 wait\n10000 wait\n10000 wait\n0 EV_REL REL_X 10\n");
+    let pause = client(&dir, &["pause"]);
+    let again = client(&dir, &["pause"]);
+    let resume = client(&dir, &["resume"]);
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    for (output, answer) in [(pause, "Paused\n"), (again, "Paused\n"), (resume, "Running\n")] {
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), answer);
+    }
+    // centered by the pause, the second one changes nothing
+    assert_eq!(events(&output), ["EV_ABS ABS_X 1000", "EV_ABS ABS_X 0", "EV_ABS ABS_X 1000", "EV_ABS ABS_X 0"]);
+}
+
+#[test]
+fn a_rejected_setting_is_an_argument_error() {
+    let dir = dir("rejected");
+    let child = start(&dir, "0 EV_REL REL_X 10\n10000 wait\n10000 wait\n");
+    let word = client(&dir, &["set", "sensitivity", "lots"]);
+    let unknown = client(&dir, &["set", "gain", "2"]);
+    child.wait_with_output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    for (output, message) in [(word, "invalid sensitivity \"lots\""), (unknown, "unknown setting \"gain\"")] {
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2), "{}", log);
+        assert!(log.contains(message), "{}", log);
+    }
+}
+
+#[test]
+fn nothing_running_is_its_own_exit_code() {
+    let dir = dir("none");
+    let output = client(&dir, &["status"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(15), "{}", log);
+    assert!(log.contains("No running mouse2joy answered"), "{}", log);
+}
@@ -0,0 +1,91 @@
+// --log-csv writes a row of the raw input and the output values per sample,
+// on the script's simulated clock.
+
+use std::process::Command;
+
+fn log(name: &str, config: &str, script: &str, args: &[&str]) -> Vec<String> {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-csv-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), format!("sensitivity = 100\nlog_backend = \"stderr\"\n{}", config))
+        .unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("RUST_LOG", "off")
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .arg("--log-csv")
+        .arg(dir.join("log.csv"))
+        .args(args)
+        .output()
+        .unwrap();
+    let log = std::fs::read_to_string(dir.join("log.csv")).unwrap_or_default();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    log.lines().map(String::from).collect()
+}
+
+#[test]
+fn rows_are_sampled_at_the_rate() {
+    let rows = log(
+        "rate",
+        "",
+        "0 EV_REL REL_X 10\n\
+         1 EV_REL REL_X 5\n\
+         1 EV_REL REL_X 5\n\
+         15 EV_REL REL_X -4\n\
+         15 EV_SYN 0 0\n",
+        &["--log-csv-rate", "50"],
+    );
+    assert_eq!(
+        rows,
+        [
+            "seconds,raw_delta,steering,throttle,brake,profile",
+            "0.000,10,1000,0.000,0.000,",
+            // the deltas in between add up
+            "0.020,6,1600,0.000,0.000,",
+        ]
+    );
+}
+
+#[test]
+fn rows_go_on_while_the_input_is_still() {
+    let rows = log("still", "", "0 EV_REL REL_X 10\n100 EV_SYN 0 0\n", &["--log-csv-rate", "20"]);
+    assert_eq!(
+        rows,
+        [
+            "seconds,raw_delta,steering,throttle,brake,profile",
+            "0.000,10,1000,0.000,0.000,",
+            "0.050,0,1000,0.000,0.000,",
+            "0.100,0,1000,0.000,0.000,",
+        ]
+    );
+}
+
+#[test]
+fn the_pedals_and_profile_are_logged() {
+    let rows = log(
+        "pedals",
+        "[pedals.throttle]\nkeys = [\"KEY_W\"]\nrise_ms = 100\n\
+         [profile_keys]\nnext = \"KEY_F9\"\n\
+         [profiles.\"Rally, gravel\"]\nsensitivity = 50\n",
+        "0 EV_KEY KEY_F9 1\n\
+         0 EV_KEY KEY_W 1\n\
+         50 EV_SYN 0 0\n",
+        &["--log-csv-rate", "20"],
+    );
+    assert_eq!(rows[0], "seconds,raw_delta,steering,throttle,brake,profile");
+    assert_eq!(rows[2], "0.050,0,0,0.500,0.000,\"Rally, gravel\"");
+}
+
+#[test]
+fn an_invalid_rate_is_refused() {
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .args(["--log-csv", "/dev/null", "--log-csv-rate", "0"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,43 @@
+// The full lock cue fires once per visit to full lock, backing off within
+// the hysteresis band doesn't re-arm it.
+
+use std::process::Command;
+
+#[test]
+fn full_lock_cue_respects_the_hysteresis() {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-cue-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "sensitivity = 100\nlog_backend = \"stderr\"\n[full_lock_cue]\nmode = \"bell\"\nhysteresis = 200\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("input.txt"),
+        "0 EV_REL REL_X 45\n\
+         # still within the band\n\
+         10 EV_REL REL_X -1\n\
+         10 EV_REL REL_X 1\n\
+         # left it, re-armed\n\
+         10 EV_REL REL_X -5\n\
+         10 EV_REL REL_X 5\n\
+         # the other side counts as well\n\
+         10 EV_REL REL_X -90\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("RUST_LOG", "debug")
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.matches("Full lock cue").count(), 3, "{}", stderr);
+}
@@ -0,0 +1,65 @@
+// --show-curve plots the mouse steering the configuration makes, from the
+// steering code itself.
+
+use std::process::{Command, Output};
+
+fn run(name: &str, config: &str) -> Output {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-curve-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), format!("log_backend = \"stderr\"\n{}", config)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--show-curve")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+fn lines(output: &Output) -> Vec<String> {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+#[test]
+fn the_sensitivity_alone_is_a_straight_line_to_full_lock() {
+    let lines = lines(&run("linear", "sensitivity = 100\n"));
+    assert_eq!(lines[0], "Mouse travel in counts, one per 1 ms, against ABS_X with sensitivity 100");
+    // the plot, full lock at the top and bottom corners
+    assert_eq!(lines[2], "4500 | *******");
+    assert_eq!(lines[22], "-4500 ******* |");
+    assert_eq!(lines[23], "-56 0 56");
+    for sample in ["0 0 0", "5 -500 500", "45 -4500 4500", "56 -4500 4500"] {
+        assert!(lines.iter().any(|line| line == sample), "{:?}", lines);
+    }
+    assert!(lines.contains(&String::from("dead zone edge left none, 1 count at -100; right none, 1 count at 100")));
+    assert!(lines.contains(&String::from("saturation knee left 45 counts at -4500; right 45 counts at 4500")));
+}
+
+#[test]
+fn the_center_detent_holds_the_first_counts() {
+    let lines = lines(&run("detent", "sensitivity = 100\n[center_detent]\nband = 200\nbreakout = 800\n"));
+    assert!(lines[0].ends_with("with sensitivity 100, the center detent, band 200 and breakout 800"), "{}", lines[0]);
+    let edge = "dead zone edge left 9 counts at 0, 10 at -800; right 9 counts at 0, 10 at 800";
+    assert!(lines.iter().any(|line| line == edge), "{:?}", lines);
+    assert!(lines.contains(&String::from("saturation knee left 47 counts at -4500; right 47 counts at 4500")));
+}
+
+#[test]
+fn a_negative_sensitivity_falls_without_failing() {
+    let lines = lines(&run("inverted", "sensitivity = -100\n"));
+    assert!(lines.contains(&String::from("45 4500 -4500")), "{:?}", lines);
+}
+
+#[test]
+fn velocity_steering_has_no_curve() {
+    let output = run("velocity", "sensitivity = 100\n[steering]\nmode = \"velocity\"\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{}", log);
+    assert!(log.contains("velocity steering follows the speed"), "{}", log);
+}
@@ -0,0 +1,74 @@
+// --daemon goes to the background once the wheel is up and --stop ends it
+// through the pidfile. A temporary directory stands in for $XDG_RUNTIME_DIR.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+fn dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-daemon-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), "log_backend = \"stderr\"\n").unwrap();
+    dir
+}
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("XDG_RUNTIME_DIR", dir)
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn a_daemon_writes_its_pidfile_and_stop_ends_it() {
+    let dir = dir("stop");
+    let script = dir.join("input.txt");
+    std::fs::write(&script, "0 EV_REL REL_X 10\n10000 wait\n").unwrap();
+    let started = Instant::now();
+    let output = run(&dir, &["--daemon", "--dry-run", "--input-script", script.to_str().unwrap()]);
+    let log = String::from_utf8_lossy(&output.stderr).into_owned();
+    let pid = std::fs::read_to_string(dir.join("mouse2joy.pid"));
+    let stopped = run(&dir, &["--stop"]);
+    let stop_log = String::from_utf8_lossy(&stopped.stderr).into_owned();
+    let left = dir.join("mouse2joy.pid").exists();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // back long before the script ends
+    assert!(output.status.success(), "{}", log);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    let pid = pid.unwrap();
+    assert!(log.contains(&format!("Running in the background as pid {}", pid.trim())), "{}", log);
+    assert!(stopped.status.success(), "{}", stop_log);
+    assert!(stop_log.contains(&format!("Stopped mouse2joy (pid {})", pid.trim())), "{}", stop_log);
+    assert!(!left);
+}
+
+#[test]
+fn errors_before_detaching_reach_the_terminal() {
+    let dir = dir("error");
+    let missing = dir.join("missing.txt");
+    let output = run(&dir, &["--daemon", "--dry-run", "--input-script", missing.to_str().unwrap()]);
+    let left = dir.join("mouse2joy.pid").exists();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "{}", log);
+    assert!(log.contains("missing.txt"), "{}", log);
+    assert!(!left);
+}
+
+#[test]
+fn stop_without_a_daemon_and_daemon_without_a_terminal_fail() {
+    let dir = dir("refused");
+    let stop = run(&dir, &["--stop"]);
+    let stdin = run(&dir, &["--daemon", "--dry-run", "--input-script", "-"]);
+    let learn = run(&dir, &["--daemon", "--learn"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(stop.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&stop.stderr).contains("no mouse2joy runs in the background"));
+    assert_eq!(stdin.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&stdin.stderr).contains("can't read --input-script from stdin"));
+    assert_eq!(learn.status.code(), Some(2));
+}
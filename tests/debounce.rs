@@ -0,0 +1,160 @@
+// [debounce] turns the chatter of a worn switch back into one press and one
+// release. The press goes on at once, only the release waits.
+
+#[path = "../src/debounce.rs"]
+mod debounce;
+
+mod common;
+
+use debounce::Debounce;
+use evdev::{EventType, InputEvent, Key};
+use std::time::{Duration, Instant};
+
+const INTERVAL: Duration = Duration::from_millis(20);
+
+fn key(key: Key, value: i32) -> ((), InputEvent) {
+    ((), InputEvent::new(EventType::KEY, key.code(), value))
+}
+
+fn describe(events: &[((), InputEvent)]) -> Vec<String> {
+    events
+        .iter()
+        .map(|(_, ev)| match ev.event_type() {
+            EventType::KEY => format!("{:?} {}", Key::new(ev.code()), ev.value()),
+            other => format!("{:?} {} {}", other, ev.code(), ev.value()),
+        })
+        .collect()
+}
+
+// A recording as (milliseconds since the last event, source, value), what
+// went on at every event and every wakeup in between.
+fn replay(debounce: &mut Debounce<()>, recording: &[(u64, Key, i32)]) -> Vec<(u64, String)> {
+    let start = Instant::now();
+    let mut at = Duration::ZERO;
+    let mut passed = Vec::new();
+    let mut note = |at: Duration, events: Vec<((), InputEvent)>| {
+        passed.extend(describe(&events).into_iter().map(|event| (at.as_millis() as u64, event)));
+    };
+    for (delay, source, value) in recording {
+        let next = at + Duration::from_millis(*delay);
+        while let Some(wait) = debounce.wakeup(start + at).filter(|wait| at + *wait <= next) {
+            at += wait;
+            note(at, debounce.filter(Vec::new(), start + at));
+        }
+        at = next;
+        note(at, debounce.filter(vec![key(*source, *value)], start + at));
+    }
+    while let Some(wait) = debounce.wakeup(start + at) {
+        at += wait;
+        note(at, debounce.filter(Vec::new(), start + at));
+    }
+    passed
+}
+
+fn debounce() -> Debounce<()> {
+    Debounce::new(vec![(Key::BTN_LEFT, INTERVAL)]).unwrap()
+}
+
+#[test]
+fn chatter_on_release_is_one_release() {
+    // a shift paddle let go, bouncing three times
+    let passed = replay(
+        &mut debounce(),
+        &[
+            (0, Key::BTN_LEFT, 1),
+            (120, Key::BTN_LEFT, 0),
+            (3, Key::BTN_LEFT, 1),
+            (2, Key::BTN_LEFT, 0),
+            (4, Key::BTN_LEFT, 1),
+            (1, Key::BTN_LEFT, 0),
+        ],
+    );
+    assert_eq!(passed, [(0, String::from("BTN_LEFT 1")), (150, String::from("BTN_LEFT 0"))]);
+}
+
+#[test]
+fn chatter_on_press_goes_on_at_once() {
+    let passed = replay(
+        &mut debounce(),
+        &[
+            (0, Key::BTN_LEFT, 1),
+            (1, Key::BTN_LEFT, 0),
+            (2, Key::BTN_LEFT, 1),
+            // a second press without a release between
+            (1, Key::BTN_LEFT, 1),
+            (90, Key::BTN_LEFT, 0),
+        ],
+    );
+    assert_eq!(passed, [(0, String::from("BTN_LEFT 1")), (114, String::from("BTN_LEFT 0"))]);
+}
+
+#[test]
+fn presses_further_apart_than_the_interval_are_separate() {
+    let passed = replay(
+        &mut debounce(),
+        &[(0, Key::BTN_LEFT, 1), (50, Key::BTN_LEFT, 0), (30, Key::BTN_LEFT, 1), (50, Key::BTN_LEFT, 0)],
+    );
+    let expected = [(0, "BTN_LEFT 1"), (70, "BTN_LEFT 0"), (80, "BTN_LEFT 1"), (150, "BTN_LEFT 0")];
+    assert_eq!(passed, expected.map(|(at, event)| (at, String::from(event))));
+}
+
+#[test]
+fn other_sources_and_events_pass_untouched() {
+    let mut debounce = debounce();
+    let start = Instant::now();
+    let motion = ((), InputEvent::new(EventType::RELATIVE, 0, 5));
+    let passed =
+        debounce.filter(vec![key(Key::BTN_RIGHT, 1), key(Key::BTN_RIGHT, 0), key(Key::BTN_RIGHT, 1), motion], start);
+    assert_eq!(describe(&passed), ["BTN_RIGHT 1", "BTN_RIGHT 0", "BTN_RIGHT 1", "RELATIVE 0 5"]);
+    // a release not seen pressed, e.g. held since before a pause
+    assert_eq!(describe(&debounce.filter(vec![key(Key::BTN_LEFT, 0)], start)), ["BTN_LEFT 0"]);
+    assert!(Debounce::<()>::new(vec![(Key::BTN_LEFT, Duration::ZERO)]).is_none());
+}
+
+#[test]
+fn reset_drops_the_withheld_release() {
+    let mut debounce = debounce();
+    let start = Instant::now();
+    debounce.filter(vec![key(Key::BTN_LEFT, 1), key(Key::BTN_LEFT, 0)], start);
+    assert_eq!(debounce.wakeup(start), Some(INTERVAL));
+    debounce.reset();
+    assert_eq!(debounce.wakeup(start), None);
+    assert_eq!(describe(&debounce.filter(vec![key(Key::BTN_LEFT, 1)], start + INTERVAL)), ["BTN_LEFT 1"]);
+}
+
+fn run(name: &str, config: &str, script: &str) -> (Option<i32>, Vec<String>, String) {
+    let output = common::run("debounce", name, &format!("log_backend = \"stderr\"\n{}", config), script);
+    (output.status.code(), common::lines(&output), String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+#[test]
+fn a_chattering_shift_paddle_shifts_once() {
+    let (code, events, log) = run(
+        "shift",
+        "[buttons]\nBTN_LEFT = \"BTN_TR\"\n[debounce]\nBTN_LEFT = 15\n",
+        "0 EV_KEY BTN_LEFT 1\n\
+         2 EV_KEY BTN_LEFT 0\n\
+         3 EV_KEY BTN_LEFT 1\n\
+         100 EV_KEY BTN_LEFT 0\n\
+         4 EV_KEY BTN_LEFT 1\n\
+         2 EV_KEY BTN_LEFT 0\n\
+         100 EV_REL REL_X 10\n",
+    );
+    assert_eq!(code, Some(0), "{}", log);
+    assert_eq!(
+        events,
+        ["0.000 EV_KEY BTN_TR 1", "0.126 EV_KEY BTN_TR 0", "0.211 EV_ABS ABS_X 30", "0.211 EV_ABS ABS_X 0"]
+    );
+}
+
+#[test]
+fn bad_debounce_settings_are_refused() {
+    for (name, config, message) in [
+        ("long", "[debounce]\nBTN_LEFT = 500\n", "debounce.BTN_LEFT must be at most 200 ms, got 500"),
+        ("unknown", "[debounce]\nBTN_NOPE = 10\n", "unknown key \"BTN_NOPE\""),
+    ] {
+        let (code, _, log) = run(name, config, "0 EV_REL REL_X 10\n");
+        assert_eq!(code, Some(3), "{}: {}", name, log);
+        assert!(log.contains(message), "{}: {}", name, log);
+    }
+}
@@ -0,0 +1,61 @@
+// [center_detent] latches the mouse steering at center after slow movement
+// into the band, on a dry run of an input script.
+
+mod common;
+
+use common::steering;
+use std::process::Output;
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("detent", name, &format!("log_backend = \"stderr\"\nsensitivity = 10\n{}", config), script)
+}
+
+const DETENT: &str = "[center_detent]\nband = 100\nbreakout = 300\n";
+
+// out to 300, back towards center 50 units every 200ms, then slowly right
+fn slow() -> String {
+    let mut script = String::from("0 EV_REL REL_X 30\n");
+    for _ in 0..4 {
+        script.push_str("200 EV_REL REL_X -5\n");
+    }
+    for _ in 0..8 {
+        script.push_str("50 EV_REL REL_X 5\n");
+    }
+    script
+}
+
+#[test]
+fn slow_movement_latches_and_breaks_out_at_the_breakout() {
+    let latched = steering(&run("latched", DETENT, &slow()));
+    // 100 is in the band, held at 0 until the push goes past 300, which
+    // lets go at 300 and not at the 350 pushed
+    assert_eq!(latched, [300, 250, 200, 150, 0, 300, 350]);
+    let free = steering(&run("free", "", &slow()));
+    assert_eq!(free, [300, 250, 200, 150, 100, 150, 200, 250, 300, 350, 400, 450, 500]);
+}
+
+#[test]
+fn a_fast_correction_passes_through_center() {
+    let mut script = String::from("0 EV_REL REL_X 30\n");
+    for _ in 0..6 {
+        script.push_str("5 EV_REL REL_X -10\n");
+    }
+    assert_eq!(steering(&run("fast", DETENT, &script)), [300, 200, 100, 0, -100, -200, -300]);
+}
+
+#[test]
+fn the_breakout_lies_beyond_the_band() {
+    for (name, config, message) in [
+        ("breakout", "[center_detent]\nband = 100\nbreakout = 100\n", "center_detent.breakout must be above the band"),
+        (
+            "velocity",
+            "[center_detent]\nband = 100\nbreakout = 300\n[steering]\nmode = \"velocity\"\n",
+            "center_detent doesn't go with velocity steering",
+        ),
+    ] {
+        let output = run(name, config, "0 EV_REL REL_X 10\n");
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{}: {}", name, log);
+        assert!(log.contains(message), "{}: {}", name, log);
+    }
+}
@@ -0,0 +1,118 @@
+// Runs --doctor against a scan directory holding the given files, one test
+// per check. The uinput dependent checks accept either outcome since the
+// test machine may not have uinput.
+
+use std::process::Command;
+
+// exit code and stdout lines, `config` None leaves the config file missing
+fn doctor(name: &str, config: Option<&str>, files: &[&str]) -> (i32, Vec<String>) {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-doctor-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(dir.join("input")).unwrap();
+    if let Some(config) = config {
+        std::fs::write(dir.join("config.toml"), config).unwrap();
+    }
+    for file in files {
+        std::fs::write(dir.join("input").join(file), "").unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--doctor")
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-dir")
+        .arg(dir.join("input"))
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    let lines = String::from_utf8(output.stdout).unwrap().lines().map(String::from).collect();
+    (output.status.code().unwrap(), lines)
+}
+
+fn line<'a>(lines: &'a [String], check: &str) -> &'a str {
+    let prefix = format!(" {}: ", check);
+    lines
+        .iter()
+        .find(|line| line.contains(&prefix))
+        .unwrap_or_else(|| panic!("no {} line in {:?}", check, lines))
+}
+
+#[test]
+fn no_mouse_is_a_blocking_problem() {
+    let (code, lines) = doctor("exit", Some(""), &[]);
+    assert_eq!(code, 13);
+    assert!(line(&lines, "mice").starts_with("FAIL"));
+}
+
+#[test]
+fn uinput_is_checked() {
+    let (_, lines) = doctor("uinput", Some(""), &[]);
+    let uinput = line(&lines, "uinput");
+    assert!(uinput.starts_with("ok") || uinput.starts_with("FAIL"), "{}", uinput);
+}
+
+#[test]
+fn empty_input_directory_fails_the_permission_check() {
+    let (_, lines) = doctor("input-empty", Some(""), &[]);
+    assert!(line(&lines, "input").starts_with("FAIL  input: no event devices in"));
+    let (_, lines) = doctor("input", Some(""), &["event0", "event1"]);
+    assert_eq!(line(&lines, "input"), "ok    input: all 2 event devices are readable");
+}
+
+#[test]
+fn excluded_devices_come_with_a_reason() {
+    let (_, lines) = doctor("mice", Some(""), &["event0", "mice"]);
+    assert!(line(&lines, "mice").starts_with("FAIL  mice: none found"));
+    let excluded: Vec<&String> = lines.iter().filter(|line| line.contains("excluded")).collect();
+    assert_eq!(excluded.len(), 1, "{:?}", lines);
+    assert!(excluded[0].contains("event0 excluded, can't open"), "{}", excluded[0]);
+}
+
+#[test]
+fn no_leftover_device_in_an_empty_directory() {
+    let (_, lines) = doctor("leftover", Some(""), &[]);
+    assert_eq!(line(&lines, "leftover"), "ok    leftover: no mouse2joy device present");
+}
+
+#[test]
+fn config_is_validated() {
+    let (_, lines) = doctor("config", Some("sensitivity = 3\n"), &[]);
+    assert!(line(&lines, "config").starts_with("ok    config:"));
+    let (_, lines) = doctor("config-missing", None, &[]);
+    assert!(line(&lines, "config").ends_with("the defaults are valid"));
+    let (code, lines) = doctor("config-invalid", Some("sensitivity = 9001\n"), &[]);
+    assert_eq!(code, 13);
+    assert!(line(&lines, "config").starts_with("FAIL  config: Invalid configuration:"));
+}
+
+#[test]
+fn js_node_needs_uinput() {
+    let (_, lines) = doctor("js", Some(""), &[]);
+    let js = line(&lines, "js");
+    if line(&lines, "uinput").starts_with("FAIL") {
+        assert_eq!(js, "skip  js: needs uinput");
+    } else {
+        assert!(js.starts_with("ok") || js.starts_with("warn"), "{}", js);
+    }
+}
+
+#[test]
+fn device_class_needs_uinput() {
+    let (_, lines) = doctor("class", Some(""), &[]);
+    let class = line(&lines, "class");
+    if line(&lines, "uinput").starts_with("FAIL") {
+        assert_eq!(class, "skip  class: needs uinput");
+    } else {
+        assert!(!class.starts_with("FAIL"), "{}", class);
+    }
+}
+
+#[test]
+fn x11_backend_skips_the_event_devices() {
+    let (_, lines) = doctor("x11", Some("input_backend = \"x11\"\n"), &["event0"]);
+    assert_eq!(line(&lines, "input"), "skip  input: input_backend = \"x11\" doesn't read the event devices");
+    assert!(!lines.iter().any(|line| line.contains(" mice: ")), "{:?}", lines);
+    let uinput = line(&lines, "uinput");
+    assert!(uinput.starts_with("ok") || uinput.ends_with("even with input_backend = \"x11\""), "{}", uinput);
+    let x11 = line(&lines, "x11");
+    assert!(x11.starts_with("ok") || x11.starts_with("FAIL"), "{}", x11);
+}
@@ -0,0 +1,131 @@
+// Pins the documented exit codes, launchers rely on these numbers.
+
+use std::process::Command;
+
+fn exit_code(name: &str, config: &str, args: &[&str]) -> i32 {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-exit-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(dir.join("input")).unwrap();
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        // an empty scan directory, so no devices are found
+        .arg("--input-dir")
+        .arg(dir.join("input"))
+        .args(args)
+        .status()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    status.code().unwrap()
+}
+
+#[test]
+fn success_is_0() {
+    assert_eq!(exit_code("success", "", &["--help"]), 0);
+}
+
+#[test]
+fn invalid_arguments_is_2() {
+    assert_eq!(exit_code("arguments", "", &["--bogus"]), 2);
+}
+
+#[test]
+fn invalid_config_is_3() {
+    assert_eq!(exit_code("config", "[scroll]\nmode = \"throttle\"\nstep = 0\n", &[]), 3);
+}
+
+#[test]
+fn no_mouse_is_4() {
+    assert_eq!(exit_code("mouse", "", &[]), 4);
+}
+
+#[test]
+fn no_keyboard_is_5() {
+    let config = "mouse_steering = false\n[keyboard_steering]\nenabled = true\n";
+    assert_eq!(exit_code("keyboard", config, &[]), 5);
+}
+
+#[test]
+fn sensitivity_beyond_the_maximum_is_invalid_config() {
+    assert_eq!(exit_code("sensitivity", "sensitivity = -2147483648\n", &[]), 3);
+    assert_eq!(exit_code("sensitivity-max", "sensitivity = 9001\n", &[]), 3);
+}
+
+#[test]
+fn unknown_overlay_toggle_is_invalid_config() {
+    let config = "[overlay]\nenabled = true\ntoggle = \"KEY_BOGUS\"\n";
+    assert_eq!(exit_code("overlay", config, &[]), 3);
+}
+
+#[test]
+fn button_mapping_without_buttons_is_invalid_config() {
+    let config = "no_buttons = true\n[buttons]\nBTN_SIDE = \"BTN_TR\"\n";
+    assert_eq!(exit_code("no-buttons", config, &[]), 3);
+    assert_eq!(exit_code("no-buttons-extra", "no_buttons = true\nextra_buttons = 2\n", &[]), 3);
+}
+
+#[test]
+fn two_mice_for_one_role_is_invalid_config() {
+    let config = "[[inputs]]\nname = \"A\"\nrole = \"pedals\"\n[[inputs]]\nname = \"B\"\nrole = \"pedals\"\n";
+    assert_eq!(exit_code("inputs", config, &[]), 3);
+}
+
+#[test]
+fn split_layout_without_its_parts_is_invalid_config() {
+    assert_eq!(exit_code("split", "layout = \"split\"\n", &[]), 3);
+    let config = "layout = \"split_shifter\"\n[pedals.throttle]\nkeys = [\"KEY_W\"]\n";
+    assert_eq!(exit_code("split-shifter", config, &[]), 3);
+}
+
+#[test]
+fn device_section_with_restart_settings_is_invalid_config() {
+    assert_eq!(exit_code("device", "[device.\"G305\"]\nextra_buttons = 2\n", &[]), 3);
+    assert_eq!(exit_code("device-type", "[device.\"G305\"]\nsensitivity = \"high\"\n", &[]), 3);
+}
+
+#[test]
+fn empty_device_filter_is_invalid_config() {
+    assert_eq!(exit_code("ignore-devices", "ignore_devices = [\"\"]\n", &["--list-devices"]), 3);
+    assert_eq!(exit_code("only-devices", "only_devices = [\"G305\", \"\"]\n", &[]), 3);
+    assert_eq!(exit_code("list-devices", "only_devices = [\"G305\"]\n", &["--list-devices"]), 0);
+}
+
+#[test]
+fn nice_out_of_range_is_invalid_config() {
+    assert_eq!(exit_code("nice", "nice = -21\n", &[]), 3);
+    assert_eq!(exit_code("nice-max", "nice = 20\n", &[]), 3);
+}
+
+#[test]
+fn axis_steering_mistakes_are_invalid_config() {
+    let config = "[[inputs]]\nname = \"Toy Wheel\"\nrole = \"axis\"\n";
+    assert_eq!(exit_code("axis-off", config, &[]), 3);
+    let config = "[axis_steering]\nenabled = true\naxis = \"ABS_BOGUS\"\n";
+    assert_eq!(exit_code("axis-unknown", config, &[]), 3);
+}
+
+#[test]
+fn x11_backend_takes_no_inputs() {
+    let config = "input_backend = \"x11\"\n[[inputs]]\nname = \"G305\"\nrole = \"pedals\"\n";
+    assert_eq!(exit_code("x11-inputs", config, &[]), 3);
+    let config = "input_backend = \"x11\"\n[axis_steering]\nenabled = true\n";
+    assert_eq!(exit_code("x11-axis", config, &[]), 3);
+}
+
+#[test]
+fn long_input_window_is_invalid_config() {
+    assert_eq!(exit_code("input-window", "input_window_ms = 21\n", &[]), 3);
+}
+
+#[test]
+fn udev_rule_for_a_bad_group_is_invalid_arguments() {
+    assert_eq!(exit_code("udev-quote", "", &["--install-udev-rule", "x\"y", "--dry-run"]), 2);
+    assert_eq!(exit_code("udev-missing", "", &["--install-udev-rule", "no-such-group", "--dry-run"]), 2);
+    assert_eq!(exit_code("udev", "", &["--install-udev-rule", "root", "--dry-run"]), 0);
+}
+
+#[test]
+fn endless_trace_is_invalid_arguments() {
+    assert_eq!(exit_code("trace", "", &["--trace-input", "--trace-seconds", "0"]), 2);
+}
@@ -0,0 +1,159 @@
+// A short sharp sideways flick taps a button and rolls the steering back,
+// steering the way one drives never does.
+
+#[path = "../src/flick/detector.rs"]
+mod detector;
+
+mod common;
+
+use common::events;
+use detector::{Detector, Direction, Thresholds};
+use std::process::Output;
+use std::time::{Duration, Instant};
+
+// REL_X per millisecond of a 1000 Hz mouse, flicks recorded as a glance
+const FLICK_RIGHT: [i32; 15] = [4, 12, 27, 46, 63, 74, 78, 75, 66, 52, 37, 23, 12, 5, 2];
+const FLICK_LEFT: [i32; 22] = [
+    -3, -5, -11, -19, -30, -41, -52, -60, -64, -63, -58, -50, -41, -32, -23, -15, -9, -5, -3, -2, 1, 2,
+];
+// a slower flick that overshoots and comes back a little
+const FLICK_OVERSHOOT: [i32; 30] = [
+    4, 9, 16, 24, 32, 39, 45, 50, 54, 56, 55, 52, 47, 41, 34, 27, 20, 14, 9, 5, 2, 0, -3, -5, -6, -5, -3, -2, -1, 0,
+];
+
+// the defaults of [flick]
+fn detector() -> Detector {
+    Detector::new(Thresholds {
+        speed: 10000.0,
+        distance: 500,
+        max: Duration::from_millis(100),
+        stop: Duration::from_millis(80),
+    })
+}
+
+// the flicks seen feeding the trace a delta per millisecond and a
+// millisecond at a time after it
+fn flicks(trace: &[i32]) -> Vec<Direction> {
+    let mut detector = detector();
+    let start = Instant::now();
+    let mut seen = Vec::new();
+    for (ms, delta) in trace.iter().enumerate() {
+        let now = start + Duration::from_millis(ms as u64);
+        seen.extend(detector.poll(now));
+        detector.push(*delta, now);
+    }
+    for ms in trace.len()..trace.len() + 200 {
+        seen.extend(detector.poll(start + Duration::from_millis(ms as u64)));
+    }
+    assert_eq!(detector.wakeup(start + Duration::from_secs(1)), None);
+    seen
+}
+
+// driving, recorded on a twisty road
+fn corrections() -> Vec<i32> {
+    let mut trace = Vec::new();
+    // small corrections both ways
+    for ms in 0..400 {
+        trace.push([1, 3, 6, 4, 2, 0, -2, -5, -3, -1][ms % 10]);
+    }
+    // a quick counter-steer: hard right and straight back left, no stop
+    trace.extend([5, 11, 17, 21, 24, 25, 24, 22, 18, 12, 6, 0].repeat(5));
+    trace.extend([-6, -12, -18, -22, -24, -25, -24, -22, -18, -12, -6, 0].repeat(5));
+    trace.extend([0; 100]);
+    trace
+}
+
+#[test]
+fn recorded_flicks_are_seen() {
+    assert_eq!(flicks(&FLICK_RIGHT), [Direction::Right]);
+    assert_eq!(flicks(&FLICK_LEFT), [Direction::Left]);
+    assert_eq!(flicks(&FLICK_OVERSHOOT), [Direction::Right]);
+}
+
+#[test]
+fn driving_never_flicks() {
+    assert_eq!(flicks(&corrections()), []);
+    // turning in hard, fast but for far too long
+    assert_eq!(flicks(&[10; 150]), []);
+    // a brisk short correction that doesn't get far
+    assert_eq!(flicks(&[6; 60]), []);
+    // a flick that runs straight into steering isn't followed by a stop
+    let mut trace = FLICK_RIGHT.to_vec();
+    trace.extend([-4, -6, -3].repeat(40));
+    assert_eq!(flicks(&trace), []);
+}
+
+#[test]
+fn flicks_need_the_stop() {
+    let mut detector = detector();
+    let start = Instant::now();
+    for (ms, delta) in FLICK_RIGHT.iter().enumerate() {
+        detector.push(*delta, start + Duration::from_millis(ms as u64));
+    }
+    // the last delta above the jitter came at 13 ms
+    assert_eq!(detector.wakeup(start), Some(Duration::from_millis(93)));
+    assert_eq!(detector.poll(start + Duration::from_millis(92)), None);
+    assert_eq!(detector.poll(start + Duration::from_millis(93)), Some(Direction::Right));
+    detector.push(80, start + Duration::from_millis(200));
+    detector.reset();
+    assert_eq!(detector.poll(start + Duration::from_secs(1)), None);
+}
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("flick", name, &format!("log_backend = \"stderr\"\n{}", config), script)
+}
+
+fn script(trace: &[i32]) -> String {
+    let moves: String = trace.iter().map(|delta| format!("1 EV_REL REL_X {}\n", delta)).collect();
+    format!("{}500 EV_SYN 0 0\n", moves)
+}
+
+const CONFIG: &str = "sensitivity = 2\n[flick]\nenabled = true\nleft = \"BTN_TL2\"\nright = \"BTN_SELECT\"\n";
+
+#[test]
+fn a_flick_taps_its_button_and_rolls_back() {
+    let mut trace = vec![20];
+    trace.extend(std::iter::repeat_n(0, 100));
+    trace.extend(FLICK_LEFT);
+    let events = events(&run("tap", CONFIG, &script(&trace)));
+    // steered while it was made
+    assert_eq!(events.len(), 27, "{:?}", events);
+    assert_eq!(
+        events[20..],
+        [
+            "0.121 EV_ABS ABS_X -1132",
+            "0.122 EV_ABS ABS_X -1130",
+            "0.123 EV_ABS ABS_X -1126",
+            "0.200 EV_KEY BTN_TL2 1",
+            // back to where the wheel was before the flick
+            "0.200 EV_ABS ABS_X 40",
+            "0.300 EV_KEY BTN_TL2 0",
+            "0.300 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn without_rollback_the_wheel_stays() {
+    let events = events(&run("stay", &format!("{}rollback = false\n", CONFIG), &script(&FLICK_RIGHT)));
+    assert_eq!(
+        events[events.len() - 3..],
+        ["0.094 EV_KEY BTN_SELECT 1", "0.194 EV_KEY BTN_SELECT 0", "0.194 EV_ABS ABS_X 0"]
+    );
+}
+
+#[test]
+fn driving_taps_nothing() {
+    let events = events(&run("drive", CONFIG, &script(&corrections())));
+    assert!(events.iter().all(|line| line.contains("EV_ABS ABS_X")), "{:?}", events);
+}
+
+#[test]
+fn a_flick_needs_a_button() {
+    let output = run("none", "[flick]\nenabled = true\n", "");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("[flick] has neither a left nor a right button"));
+    let output = run("key", "[flick]\nenabled = true\nleft = \"KEY_Q\"\n", "");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("KEY_Q is a keyboard key"));
+}
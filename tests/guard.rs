@@ -0,0 +1,50 @@
+// A panic inside the event loop must still run the cleanup guard, which
+// centers the wheel and releases the input devices. The panic hook only
+// exists in debug builds.
+#![cfg(debug_assertions)]
+
+use std::process::Command;
+
+#[test]
+fn panic_in_the_loop_runs_the_cleanup_guard() {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-guard-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), "sensitivity = 100\nlog_backend = \"stderr\"\n").unwrap();
+    std::fs::write(
+        dir.join("input.txt"),
+        "0 EV_REL REL_X 10\n\
+         10 EV_REL REL_X 10\n\
+         10 EV_REL REL_X 10\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("MOUSE2JOY_PANIC_AFTER", "2")
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let stdout: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(stderr.contains("MOUSE2JOY_PANIC_AFTER reached after 2 events"), "{}", stderr);
+    assert!(stderr.contains("Released the input devices"), "{}", stderr);
+    assert_eq!(
+        stdout,
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.010 EV_ABS ABS_X 2000",
+            // centered by the guard while unwinding
+            "0.010 EV_ABS ABS_X 0",
+        ]
+    );
+}
@@ -0,0 +1,62 @@
+// Finds the X field in report descriptors of common mouse layouts. The
+// Logitech ones are what the kernel's hid-logitech-dj driver reports for
+// mice behind a receiver, the others follow a boot protocol mouse, a gaming
+// keyboard and mouse combo with several reports, a tablet and a keyboard.
+
+#[path = "../src/hidraw/descriptor.rs"]
+mod descriptor;
+
+use descriptor::Field;
+
+fn x_field(name: &str) -> Result<Field, String> {
+    let path = format!("{}/tests/fixtures/hidraw/{}", env!("CARGO_MANIFEST_DIR"), name);
+    descriptor::x_field(&std::fs::read(path).unwrap())
+}
+
+#[test]
+fn receiver_mouse_packs_x_into_twelve_bits() {
+    let field = x_field("logitech_receiver.bin").unwrap();
+    assert_eq!(field, Field { report_id: Some(2), offset: 16, size: 12, signed: true });
+    // X -5 and Y 1 share the middle byte
+    assert_eq!(field.value(&[0x02, 0x00, 0x00, 0xfb, 0x1f, 0x00, 0x00, 0x00]), Some(-5));
+    assert_eq!(field.value(&[0x02, 0x00, 0x00, 0xff, 0x07, 0x00, 0x00, 0x00]), Some(2047));
+}
+
+#[test]
+fn high_res_receiver_mouse_has_sixteen_bits() {
+    let field = x_field("logitech_high_res.bin").unwrap();
+    assert_eq!(field, Field { report_id: Some(2), offset: 16, size: 16, signed: true });
+    assert_eq!(field.value(&[0x02, 0x01, 0x00, 0x18, 0xfc, 0x00, 0x00, 0x00, 0x00]), Some(-1000));
+}
+
+#[test]
+fn boot_mouse_has_no_report_id() {
+    let field = x_field("boot_mouse.bin").unwrap();
+    assert_eq!(field, Field { report_id: None, offset: 8, size: 8, signed: true });
+    assert_eq!(field.value(&[0x01, 0x7f, 0x00, 0x00]), Some(127));
+    assert_eq!(field.value(&[0x00, 0x80, 0x00, 0x00]), Some(-128));
+}
+
+#[test]
+fn combo_counts_the_bits_of_the_mouse_report_alone() {
+    let field = x_field("gaming_keyboard_mouse.bin").unwrap();
+    assert_eq!(field, Field { report_id: Some(2), offset: 8, size: 16, signed: true });
+    assert_eq!(field.value(&[0x02, 0x01, 0xe8, 0x03, 0x00, 0x00, 0x00]), Some(1000));
+    // the keyboard report isn't a mouse report
+    assert_eq!(field.value(&[0x01, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00]), None);
+}
+
+#[test]
+fn short_reports_carry_no_x() {
+    let field = x_field("logitech_high_res.bin").unwrap();
+    assert_eq!(field.value(&[0x02, 0x00, 0x00, 0x18]), None);
+    assert_eq!(field.value(&[]), None);
+}
+
+#[test]
+fn unsupported_descriptors_are_refused() {
+    assert!(x_field("tablet.bin").unwrap_err().contains("absolute"));
+    assert!(x_field("keyboard.bin").unwrap_err().contains("no relative X axis"));
+    // a Logical Minimum missing its second byte
+    assert!(descriptor::x_field(&[0x05, 0x01, 0x16, 0x01]).unwrap_err().contains("truncated"));
+}
@@ -0,0 +1,155 @@
+// The key combinations of [hotkeys] and the actions they dispatch, on a dry
+// run of an input script.
+
+#[path = "../src/hotkey/combo.rs"]
+mod combo;
+
+mod common;
+
+use combo::Combo;
+use common::events;
+use evdev::Key;
+use std::process::Output;
+
+#[test]
+fn single_keys_and_buttons_parse() {
+    assert_eq!(Combo::parse("KEY_F8"), Ok(Combo { key: Key::KEY_F8, modifiers: Vec::new() }));
+    assert_eq!(Combo::parse("BTN_MIDDLE"), Ok(Combo { key: Key::BTN_MIDDLE, modifiers: Vec::new() }));
+}
+
+#[test]
+fn modifiers_come_before_the_key() {
+    let combo = Combo::parse("KEY_LEFTCTRL + KEY_LEFTSHIFT+KEY_F8").unwrap();
+    assert_eq!(combo.key, Key::KEY_F8);
+    assert_eq!(combo.modifiers, [Key::KEY_LEFTCTRL, Key::KEY_LEFTSHIFT]);
+    assert_eq!(combo.to_string(), "KEY_LEFTCTRL+KEY_LEFTSHIFT+KEY_F8");
+    assert_eq!(combo.keys().collect::<Vec<_>>(), [Key::KEY_LEFTCTRL, Key::KEY_LEFTSHIFT, Key::KEY_F8]);
+    assert!(combo.is_pressed(Key::KEY_F8, &[Key::KEY_LEFTSHIFT, Key::KEY_LEFTCTRL]));
+    assert!(!combo.is_pressed(Key::KEY_F8, &[Key::KEY_LEFTCTRL]));
+    assert!(!combo.is_pressed(Key::KEY_LEFTCTRL, &[Key::KEY_LEFTCTRL, Key::KEY_LEFTSHIFT]));
+}
+
+#[test]
+fn bad_combinations_are_rejected() {
+    assert_eq!(Combo::parse("KEY_F99"), Err(String::from("unknown key \"KEY_F99\" in \"KEY_F99\"")));
+    assert_eq!(Combo::parse("F8"), Err(String::from("unknown key \"F8\" in \"F8\"")));
+    assert_eq!(Combo::parse(""), Err(String::from("\"\" has an empty key name")));
+    assert_eq!(Combo::parse("KEY_LEFTCTRL+"), Err(String::from("\"KEY_LEFTCTRL+\" has an empty key name")));
+    assert_eq!(Combo::parse("KEY_A+KEY_A"), Err(String::from("\"KEY_A+KEY_A\" lists KEY_A twice")));
+}
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("hotkeys", name, &format!("sensitivity = 100\nlog_backend = \"stderr\"\n{}", config), script)
+}
+
+#[test]
+fn recenter_and_pause_are_dispatched() {
+    let output = run(
+        "dispatch",
+        "[hotkeys]\nrecenter = \"BTN_MIDDLE\"\npause = \"KEY_LEFTCTRL+KEY_F8\"\n",
+        "0 EV_REL REL_X 10\n\
+         10 EV_KEY BTN_MIDDLE 1\n\
+         0 EV_KEY BTN_MIDDLE 0\n\
+         10 EV_REL REL_X 5\n\
+         # F8 alone is taken by the hotkey but does nothing\n\
+         10 EV_KEY KEY_F8 1\n\
+         0 EV_KEY KEY_F8 0\n\
+         10 EV_KEY KEY_LEFTCTRL 1\n\
+         0 EV_KEY KEY_F8 1\n\
+         # ignored while paused\n\
+         10 EV_REL REL_X 20\n\
+         10 EV_KEY KEY_F8 1\n\
+         10 EV_REL REL_X 10\n",
+    );
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.010 EV_ABS ABS_X 0",
+            "0.020 EV_ABS ABS_X 500",
+            // paused
+            "0.040 EV_ABS ABS_X 0",
+            // resumed, steering from the center
+            "0.070 EV_ABS ABS_X 1000",
+            "0.070 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn pausing_centers_the_hat_and_releases_the_buttons() {
+    let output = run(
+        "pause-held",
+        "[hotkeys]\npause = \"KEY_F8\"\n[buttons]\nBTN_SIDE = \"BTN_TR\"\n[hat]\nenabled = true\n",
+        "0 EV_KEY BTN_SIDE 1\n\
+         0 EV_KEY KEY_UP 1\n\
+         10 EV_KEY KEY_F8 1\n\
+         # let go of while paused\n\
+         10 EV_KEY BTN_SIDE 0\n\
+         0 EV_KEY KEY_UP 0\n",
+    );
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_KEY BTN_TR 1",
+            "0.000 EV_ABS ABS_HAT0X 0",
+            "0.000 EV_ABS ABS_HAT0Y -1",
+            // paused
+            "0.010 EV_ABS ABS_HAT0X 0",
+            "0.010 EV_ABS ABS_HAT0Y 0",
+            "0.010 EV_KEY BTN_TR 0",
+        ]
+    );
+}
+
+#[test]
+fn next_profile_needs_no_profile_keys() {
+    let output = run(
+        "next-profile",
+        "[hotkeys]\nnext_profile = \"KEY_F9\"\n[profiles.fast]\nsensitivity = 200\n",
+        "0 EV_REL REL_X 10\n\
+         10 EV_KEY KEY_F9 1\n\
+         10 EV_REL REL_X 10\n",
+    );
+    assert_eq!(
+        events(&output),
+        ["0.000 EV_ABS ABS_X 1000", "0.020 EV_ABS ABS_X 3000", "0.020 EV_ABS ABS_X 0"]
+    );
+}
+
+#[test]
+fn a_hotkey_beats_a_button_mapping() {
+    let output = run(
+        "button",
+        "[hotkeys]\nrecenter = \"BTN_MIDDLE\"\n[buttons]\nBTN_MIDDLE = \"BTN_START\"\n",
+        "0 EV_REL REL_X 10\n\
+         10 EV_KEY BTN_MIDDLE 1\n",
+    );
+    let log = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_X 1000", "0.010 EV_ABS ABS_X 0"]);
+    assert!(log.contains("BTN_MIDDLE is mapped in [buttons] and bound to the recenter hotkey"), "{}", log);
+}
+
+#[test]
+fn bad_bindings_are_config_errors() {
+    for (name, config, message) in [
+        ("action", "[hotkeys]\nwarp = \"KEY_F8\"\n", "unknown hotkey action \"warp\""),
+        ("key", "[hotkeys]\npause = \"KEY_F8+\"\n", "\"KEY_F8+\" has an empty key name"),
+        (
+            "duplicate",
+            "[hotkeys]\npause = \"KEY_LEFTCTRL+KEY_LEFTALT+KEY_F8\"\nrecenter = \"KEY_LEFTALT+KEY_LEFTCTRL+KEY_F8\"\n",
+            "KEY_LEFTALT+KEY_LEFTCTRL+KEY_F8 is bound to both pause and recenter",
+        ),
+        (
+            "pedal",
+            "[hotkeys]\npause = \"KEY_W\"\n[pedals.throttle]\nkeys = [\"KEY_W\"]\n",
+            "KEY_W is used by both hotkeys and pedals",
+        ),
+        ("emergency", "emergency_release = \"KEY_END+\"\n", "emergency_release: \"KEY_END+\" has an empty key name"),
+    ] {
+        let output = run(name, config, "0 EV_REL REL_X 10\n");
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{}: {}", name, log);
+        assert!(log.contains(message), "{}: {}", name, log);
+    }
+}
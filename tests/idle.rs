@@ -0,0 +1,111 @@
+// exit_after_idle_minutes ends the run after that long without input, on
+// the script's simulated clock, counting down in the systemd status.
+
+use std::os::unix::net::UnixDatagram;
+use std::process::{Command, Output};
+
+fn run(name: &str, minutes: u32, script: &str, status: Option<&UnixDatagram>) -> Output {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-idle-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = format!("sensitivity = 100\nlog_backend = \"stderr\"\nexit_after_idle_minutes = {}\n", minutes);
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_mouse2joy"));
+    command
+        .env("RUST_LOG", "info")
+        .env_remove("NOTIFY_SOCKET")
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run");
+    if let Some(socket) = status {
+        command.env("NOTIFY_SOCKET", socket.local_addr().unwrap().as_pathname().unwrap());
+    }
+    let output = command.output().unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+fn events(output: &Output) -> Vec<String> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+#[test]
+fn no_input_for_the_timeout_exits() {
+    let output = run(
+        "exits",
+        1,
+        "0 EV_REL REL_X 10\n\
+         90000 EV_REL REL_X 10\n",
+        None,
+    );
+    // the second move never comes, the wheel is centered on the way out
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_X 1000", "0.000 EV_ABS ABS_X 0"]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(log.contains("No input for 1 minute(s), exiting"), "{}", log);
+}
+
+#[test]
+fn input_starts_the_timeout_over() {
+    let output = run(
+        "resets",
+        1,
+        "0 EV_REL REL_X 10\n\
+         50000 EV_REL REL_X 10\n\
+         50000 EV_REL REL_X 10\n\
+         70000 EV_REL REL_X 10\n",
+        None,
+    );
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "50.000 EV_ABS ABS_X 2000",
+            "100.000 EV_ABS ABS_X 3000",
+            "100.000 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn the_status_counts_down_the_last_minutes() {
+    let path = std::env::temp_dir().join(format!("mouse2joy-idle-notify-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let socket = UnixDatagram::bind(&path).unwrap();
+    socket.set_nonblocking(true).unwrap();
+    run(
+        "status",
+        7,
+        "0 EV_REL REL_X 10\n\
+         240000 EV_REL REL_X 10\n\
+         600000 EV_REL REL_X 10\n",
+        Some(&socket),
+    );
+    let mut messages = Vec::new();
+    let mut buffer = [0; 256];
+    while let Ok(read) = socket.recv(&mut buffer) {
+        messages.push(String::from_utf8_lossy(&buffer[..read]).into_owned());
+    }
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        messages,
+        [
+            // from 2 minutes on, until the move at 4
+            "STATUS=No input, exiting in 5 min",
+            "STATUS=No input, exiting in 4 min",
+            "STATUS=Running",
+            // then all the way from 6 minutes
+            "STATUS=No input, exiting in 5 min",
+            "STATUS=No input, exiting in 4 min",
+            "STATUS=No input, exiting in 3 min",
+            "STATUS=No input, exiting in 2 min",
+            "STATUS=No input, exiting in 1 min",
+        ]
+    );
+}
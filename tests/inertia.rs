@@ -0,0 +1,136 @@
+// The wheel following the mouse on a damped spring: stable whatever the
+// parameters, never past the axis limits.
+
+#[path = "../src/steering/inertia.rs"]
+mod inertia;
+
+mod common;
+
+use inertia::Inertia;
+use std::process::Output;
+use std::time::{Duration, Instant};
+
+const LIMIT: f32 = 4500.0;
+// the default tick rate of 250 Hz
+const STEP: Duration = Duration::from_millis(4);
+
+// the positions of every tick for a second heading for `target`
+fn follow(response_ms: u64, damping: f32, from: f32, target: f32) -> (Inertia, Vec<f32>) {
+    let start = Instant::now();
+    let mut inertia = Inertia::new(Duration::from_millis(response_ms), damping, STEP, LIMIT, from, start);
+    let positions = (1..=250).map(|tick| inertia.advance(target, start + STEP * tick)).collect();
+    (inertia, positions)
+}
+
+#[test]
+fn stable_at_extreme_parameters() {
+    for response_ms in [1, 2, 4, 80, 1000, 60_000] {
+        for damping in [0.0, 0.01, 0.1, 1.0, 10.0, 1000.0] {
+            let (_, positions) = follow(response_ms, damping, -3000.0, 2000.0);
+            for position in &positions {
+                assert!(position.is_finite(), "{} ms, damping {}: {:?}", response_ms, damping, positions);
+                assert!(position.abs() <= LIMIT, "{} ms, damping {}: {}", response_ms, damping, position);
+            }
+            // never gets further off than it started
+            assert!(positions.iter().all(|position| (position - 2000.0).abs() <= 5000.0));
+        }
+    }
+}
+
+#[test]
+fn settles_on_the_target() {
+    for (response_ms, damping) in [(1, 0.1), (1, 10.0), (80, 0.8), (200, 1.0), (100, 3.0)] {
+        let (inertia, positions) = follow(response_ms, damping, 0.0, 1000.0);
+        assert!((positions.last().unwrap() - 1000.0).abs() < 0.5, "{} ms, damping {}", response_ms, damping);
+        assert!(inertia.is_settled(), "{} ms, damping {}: {:?}", response_ms, damping, &positions[240..]);
+    }
+}
+
+#[test]
+fn lags_behind_quick_input() {
+    let (_, positions) = follow(80, 0.8, 0.0, 1000.0);
+    // neither there right away nor slow to get going
+    // the first tick starts the movement
+    assert_eq!(positions[0], 0.0);
+    assert!(positions[1] < 100.0 && positions[1] > 0.0, "{:?}", &positions[..6]);
+    assert!(positions[5] > 300.0, "{:?}", &positions[..6]);
+    // about there once the response is over
+    assert!((positions[20] - 1000.0).abs() < 50.0, "{}", positions[20]);
+}
+
+#[test]
+fn critical_damping_does_not_overshoot() {
+    let (_, positions) = follow(80, 1.0, 0.0, 1000.0);
+    assert!(positions.iter().all(|position| *position <= 1000.0));
+    assert!(positions.windows(2).all(|pair| pair[1] >= pair[0]));
+    // light damping swings past the mouse and back, doesn't stop dead
+    let (_, positions) = follow(80, 0.3, 0.0, 1000.0);
+    assert!(positions.iter().any(|position| *position > 1000.0));
+}
+
+#[test]
+fn never_past_the_limits() {
+    // light damping at full speed into the end stop
+    for target in [LIMIT, -LIMIT, 2.0 * LIMIT, -1e9] {
+        let (_, positions) = follow(20, 0.05, -target.signum() * LIMIT, target);
+        assert!(positions.iter().all(|position| position.abs() <= LIMIT), "{:?}", positions);
+        assert_eq!(*positions.last().unwrap(), target.clamp(-LIMIT, LIMIT));
+    }
+}
+
+#[test]
+fn a_rest_is_not_integrated() {
+    let start = Instant::now();
+    let mut inertia = Inertia::new(Duration::from_millis(80), 0.8, STEP, LIMIT, 0.0, start);
+    // ten seconds at rest, then the mouse moves
+    let later = start + Duration::from_secs(10);
+    assert_eq!(inertia.advance(1000.0, later), 0.0);
+    assert!(!inertia.is_settled());
+    assert!(inertia.advance(1000.0, later + STEP) < 100.0);
+    // a long gap while moving lands where it was headed
+    assert_eq!(inertia.advance(1000.0, later + Duration::from_secs(60)), 1000.0);
+    inertia.jump(-200.0, later + Duration::from_secs(61));
+    assert!(inertia.is_settled());
+    inertia.tune(Duration::from_millis(1), 10.0);
+    assert_eq!(inertia.advance(-200.0, later + Duration::from_secs(62)), -200.0);
+}
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("inertia", name, &format!("log_backend = \"stderr\"\n{}", config), script)
+}
+
+fn positions(output: &Output) -> Vec<(f64, i32)> {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(fields[1..3], ["EV_ABS", "ABS_X"], "{}", line);
+            (fields[0].parse().unwrap(), fields[3].parse().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn the_wheel_follows_the_mouse_on_the_tick() {
+    let config = "sensitivity = 10\n[steering.inertia]\nenabled = true\ndamping = 1.0\n";
+    let positions = positions(&run("follow", config, "10 EV_REL REL_X 100\n500 EV_SYN 0 0\n"));
+    // one tick after the movement, then every tick getting closer
+    assert_eq!(positions[0].0, 0.014);
+    assert!(positions[0].1 < 200);
+    let (moving, centered) = positions.split_at(positions.len() - 1);
+    assert!(moving.windows(2).all(|pair| pair[1].1 > pair[0].1 && pair[1].0 > pair[0].0));
+    assert_eq!(moving.last().unwrap().1, 1000);
+    assert_eq!(centered[0].1, 0);
+}
+
+#[test]
+fn bad_parameters_are_refused() {
+    let output = run("response", "[steering.inertia]\nenabled = true\nresponse_ms = 0\n", "");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("steering.inertia.response_ms must be between 1 and 1000, got 0"));
+    let output = run("damping", "[steering.inertia]\nenabled = true\ndamping = 0.0\n", "");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("steering.inertia.damping must be between 0.1 and 10"));
+}
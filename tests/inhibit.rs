@@ -0,0 +1,86 @@
+// The idle inhibit needs mouse2joy built with the inhibit feature, the tests
+// run without it. The calls it makes were checked against dbus-daemon with
+// a stand-in for the screensaver and logind.
+
+use std::process::{Command, Output};
+
+// the idle inhibit reads the cookie and the descriptor index, no test does
+// more than that
+#[allow(dead_code)]
+#[path = "../src/dbus/wire.rs"]
+mod dbus;
+
+fn run(name: &str, config: &str) -> Output {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-inhibit-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), format!("sensitivity = 100\nlog_backend = \"stderr\"\n{}", config)).unwrap();
+    std::fs::write(dir.join("input.txt"), "0 EV_REL REL_X 45\n0 EV_SYN 0 0\n10 EV_REL REL_X -45\n0 EV_SYN 0 0\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("RUST_LOG", "debug")
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+#[test]
+fn inhibit_takes_two_strings() {
+    let body = dbus::strings_body(&["mouse2joy", "Steering"]);
+    assert_eq!(&body[..14], b"\x09\0\0\0mouse2joy\0");
+    // the second length aligned to 4
+    assert_eq!(&body[14..16], [0, 0]);
+    assert_eq!(&body[16..], b"\x08\0\0\0Steering\0");
+}
+
+#[test]
+fn the_number_replied_with_is_read_from_the_body() {
+    let mut body = dbus::Writer::default();
+    body.u32(42);
+    let call = dbus::Call {
+        destination: "org.freedesktop.ScreenSaver",
+        path: "/org/freedesktop/ScreenSaver",
+        interface: "org.freedesktop.ScreenSaver",
+        member: "UnInhibit",
+        signature: "u",
+        body: body.buf,
+    };
+    assert_eq!(dbus::body_u32(&dbus::method_call(3, &call)), Some(42));
+    let empty = dbus::Call { signature: "", body: Vec::new(), ..call };
+    assert_eq!(dbus::body_u32(&dbus::method_call(4, &empty)), None);
+}
+
+#[cfg(not(feature = "inhibit"))]
+#[test]
+fn without_the_feature_it_says_so() {
+    let output = run("feature", "[idle_inhibit]\nenabled = true\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert_eq!(
+        log.matches("The idle inhibit is enabled but mouse2joy was built without the inhibit feature").count(),
+        1,
+        "{}",
+        log
+    );
+}
+
+#[test]
+fn disabled_it_says_nothing() {
+    let output = run("disabled", "[idle_inhibit]\ninput_seconds = 30\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(!log.contains("idle inhibit"), "{}", log);
+}
+
+#[test]
+fn input_seconds_must_be_given() {
+    let output = run("zero", "[idle_inhibit]\nenabled = true\ninput_seconds = 0\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{}", log);
+    assert!(log.contains("idle_inhibit.input_seconds must be between 1 and 3600, got 0"), "{}", log);
+}
@@ -0,0 +1,83 @@
+// Keyboards are read without a grab and only their configured keys are
+// looked at, so typing and the desktop's own shortcuts go on as before.
+
+#[path = "../src/input/keyset.rs"]
+mod keyset;
+
+mod common;
+
+use evdev::Key;
+use keyset::KeySet;
+
+#[test]
+fn only_the_configured_keys_are_in_the_set() {
+    let keys: KeySet = [Key::KEY_W, Key::KEY_LEFTALT, Key::BTN_TRIGGER_HAPPY40].into_iter().collect();
+    for key in [Key::KEY_W, Key::KEY_LEFTALT, Key::BTN_TRIGGER_HAPPY40] {
+        assert!(keys.contains(key.code()), "{:?}", key);
+    }
+    for key in [Key::KEY_RESERVED, Key::KEY_Q, Key::KEY_E, Key::KEY_TAB, Key::KEY_RIGHTALT] {
+        assert!(!keys.contains(key.code()), "{:?}", key);
+    }
+    // beyond KEY_MAX
+    assert!(!keys.contains(0x300));
+    assert!(!keys.contains(u16::MAX));
+}
+
+fn run(name: &str, config: &str, script: &str) -> Vec<String> {
+    common::events(&common::run("keyboard", name, &format!("sensitivity = 100\n{}", config), script))
+}
+
+#[test]
+fn alt_tab_goes_by_a_combo_with_alt() {
+    // Alt is a modifier of the hotkey and drives a button of its own
+    let events = run(
+        "alt-tab",
+        "[hotkeys]\nrecenter = \"KEY_LEFTALT+KEY_F8\"\n[buttons]\nKEY_LEFTALT = \"BTN_SELECT\"\n",
+        "0 EV_REL REL_X 10\n\
+         10 EV_KEY KEY_LEFTALT 1\n\
+         10 EV_KEY KEY_TAB 1\n\
+         0 EV_KEY KEY_TAB 0\n\
+         10 EV_KEY KEY_TAB 1\n\
+         0 EV_KEY KEY_TAB 0\n\
+         10 EV_KEY KEY_LEFTALT 0\n\
+         # the key of the combo alone does nothing\n\
+         10 EV_KEY KEY_F8 1\n\
+         0 EV_KEY KEY_F8 0\n\
+         10 EV_KEY KEY_LEFTALT 1\n\
+         0 EV_KEY KEY_F8 1\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.010 EV_KEY BTN_SELECT 1",
+            "0.040 EV_KEY BTN_SELECT 0",
+            "0.060 EV_KEY BTN_SELECT 1",
+            "0.060 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn half_a_combo_leaves_the_modifiers_to_the_rest() {
+    // Ctrl and Alt both held without the F8 of their combo
+    let events = run(
+        "partial",
+        "[hotkeys]\npause = \"KEY_LEFTCTRL+KEY_LEFTALT+KEY_F8\"\n[buttons]\nKEY_LEFTCTRL = \"BTN_START\"\n",
+        "0 EV_KEY KEY_LEFTCTRL 1\n\
+         0 EV_KEY KEY_LEFTALT 1\n\
+         10 EV_KEY KEY_DELETE 1\n\
+         10 EV_REL REL_X 10\n\
+         10 EV_KEY KEY_LEFTCTRL 0\n\
+         0 EV_KEY KEY_LEFTALT 0\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_KEY BTN_START 1",
+            "0.020 EV_ABS ABS_X 1000",
+            "0.030 EV_KEY BTN_START 0",
+            "0.030 EV_ABS ABS_X 0",
+        ]
+    );
+}
@@ -0,0 +1,83 @@
+// [keys] sends sources on as keyboard keys through a virtual keyboard, held
+// while the source is held.
+
+mod common;
+
+use common::events;
+use std::process::Output;
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("keys", name, &format!("log_backend = \"stderr\"\nsensitivity = 100\n{}", config), script)
+}
+
+#[test]
+fn a_side_button_presses_a_key() {
+    let output = run(
+        "press",
+        "[keys]\nBTN_SIDE = \"KEY_L\"\n[buttons]\nBTN_EXTRA = \"BTN_TL\"\n",
+        "0 EV_KEY BTN_SIDE 1\n\
+         10 EV_KEY BTN_SIDE 0\n\
+         10 EV_KEY BTN_EXTRA 1\n\
+         10 EV_KEY BTN_EXTRA 0\n",
+    );
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_KEY KEY_L 1",
+            "0.010 EV_KEY KEY_L 0",
+            "0.020 EV_KEY BTN_TL 1",
+            "0.030 EV_KEY BTN_TL 0",
+        ]
+    );
+}
+
+#[test]
+fn two_sources_hold_one_key_until_both_are_up() {
+    let output = run(
+        "shared",
+        "[keys]\nBTN_SIDE = \"KEY_L\"\nKEY_F1 = \"KEY_L\"\n",
+        "0 EV_KEY BTN_SIDE 1\n\
+         10 EV_KEY KEY_F1 1\n\
+         10 EV_KEY KEY_F1 2\n\
+         10 EV_KEY BTN_SIDE 0\n\
+         10 EV_KEY KEY_F1 0\n",
+    );
+    assert_eq!(events(&output), ["0.000 EV_KEY KEY_L 1", "0.040 EV_KEY KEY_L 0"]);
+}
+
+#[test]
+fn losing_a_device_releases_the_held_key() {
+    let output = run(
+        "lost",
+        "[keys]\nBTN_SIDE = \"KEY_L\"\n",
+        "0 EV_KEY BTN_SIDE 1\n\
+         10 lose\n\
+         # its release went with the device\n\
+         10 EV_REL REL_X 1\n",
+    );
+    assert_eq!(
+        events(&output),
+        ["0.000 EV_KEY KEY_L 1", "0.010 EV_KEY KEY_L 0", "0.020 EV_ABS ABS_X 100", "0.020 EV_ABS ABS_X 0"]
+    );
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(log.contains("Lost an input device, outputs set to neutral"), "{}", log);
+}
+
+#[test]
+fn bad_mappings_are_refused() {
+    for (name, config, message) in [
+        (
+            "both",
+            "[keys]\nBTN_SIDE = \"KEY_L\"\n[buttons]\nBTN_SIDE = \"BTN_TL\"\n",
+            "BTN_SIDE is used by both buttons and keys",
+        ),
+        ("button", "[keys]\nBTN_SIDE = \"BTN_TL\"\n", "BTN_TL is not a keyboard key, map it in [buttons]"),
+        ("key", "[buttons]\nBTN_SIDE = \"KEY_L\"\n", "KEY_L is a keyboard key, map it in [keys]"),
+        ("unknown", "[keys]\nBTN_SIDE = \"KEY_NOPE\"\n", "unknown key \"KEY_NOPE\""),
+    ] {
+        let output = run(name, config, "0 EV_REL REL_X 10\n");
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{}: {}", name, log);
+        assert!(log.contains(message), "{}: {}", name, log);
+    }
+}
@@ -0,0 +1,149 @@
+// A profile's launch command runs when the profile gets selected, only with
+// allow_launch in the base settings, and --exit-with-launched ends mouse2joy
+// with it.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::{Duration, Instant};
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-launch-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(dir: &Path, config: &str, script: &str, args: &[&str]) -> Output {
+    std::fs::write(dir.join("config.toml"), format!("log_backend = \"stderr\"\n{}", config)).unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("RUST_LOG", "info")
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// a long wait for a signal, the exit of the launched command ends it early
+const WAITING: &str = "0 EV_REL REL_X 1\n10000 wait\n0 EV_REL REL_X 1\n";
+
+#[test]
+fn the_launched_command_ends_mouse2joy() {
+    let dir = scratch("exit");
+    let config = "allow_launch = true\n\
+                  [profiles.game]\n\
+                  launch = [\"sh\", \"-c\", \"echo started; exit 3\"]\n\
+                  launch_output = true\n";
+    let start = Instant::now();
+    let output = run(&dir, config, WAITING, &["--profile", "game", "--exit-with-launched"]);
+    std::fs::remove_dir_all(dir).unwrap();
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(start.elapsed() < Duration::from_secs(8), "{}", log);
+    assert!(log.contains("Launching [\"sh\", \"-c\", \"echo started; exit 3\"] for profile \"game\""), "{}", log);
+    assert!(log.contains("sh: started"), "{}", log);
+    assert!(log.contains("exited, exit status: 3"), "{}", log);
+    assert!(log.contains("sh exited, exiting with it"), "{}", log);
+}
+
+#[test]
+fn without_the_flag_mouse2joy_keeps_running() {
+    let dir = scratch("keep");
+    let config = "allow_launch = true\n[profiles.game]\nlaunch = [\"true\"]\n";
+    let output = run(&dir, config, "0 EV_REL REL_X 1\n1000 wait\n0 EV_REL REL_X 1\n", &["--profile", "game"]);
+    std::fs::remove_dir_all(dir).unwrap();
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(log.contains("true (pid"), "{}", log);
+    assert!(log.contains("Input ended, exiting"), "{}", log);
+    // its output isn't asked for
+    assert!(!log.contains("true: "), "{}", log);
+}
+
+#[test]
+fn nothing_runs_without_allow_launch() {
+    let dir = scratch("denied");
+    let marker = dir.join("launched");
+    let config = format!("[profiles.game]\nlaunch = [\"touch\", \"{}\"]\n", marker.display());
+    let output = run(&dir, &config, "0 EV_REL REL_X 1\n", &["--profile", "game"]);
+    let launched = marker.exists();
+    std::fs::remove_dir_all(dir).unwrap();
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(!launched);
+    assert!(log.contains("not run without allow_launch = true in the base settings"), "{}", log);
+}
+
+#[test]
+fn a_switch_launches_the_profile_switched_to() {
+    let dir = scratch("switch");
+    let marker = dir.join("launched");
+    let config = format!(
+        "allow_launch = true\n\
+         [profile_keys]\nnext = \"KEY_F1\"\n\
+         [profiles.game]\nlaunch = [\"touch\", \"{}\"]\n",
+        marker.display()
+    );
+    let output = run(&dir, &config, "0 EV_KEY KEY_F1 1\n10000 wait\n", &["--exit-with-launched"]);
+    let launched = marker.exists();
+    std::fs::remove_dir_all(dir).unwrap();
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(launched, "{}", log);
+    assert!(log.contains("Nothing launched yet"), "{}", log);
+    assert!(log.find("Switched to profile \"game\"").unwrap() < log.find("Launching").unwrap(), "{}", log);
+    assert!(log.contains("touch exited, exiting with it"), "{}", log);
+}
+
+#[test]
+fn launch_and_allow_launch_stay_in_their_places() {
+    let dir = scratch("places");
+    let output = run(&dir, "launch = [\"true\"]\n", "0 EV_REL REL_X 1\n", &[]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{}", log);
+    assert!(log.contains("launch only goes in a [profiles.NAME] table"), "{}", log);
+
+    let output = run(&dir, "[profiles.game]\nallow_launch = true\n", "0 EV_REL REL_X 1\n", &["--profile", "game"]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{}", log);
+    assert!(log.contains("profile \"game\": allow_launch only goes in the base settings"), "{}", log);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn an_export_leaves_the_permission_behind() {
+    let dir = scratch("export");
+    std::fs::write(dir.join("shared.toml"), "allow_launch = true\n[profiles.game]\nlaunch = [\"steam\"]\n").unwrap();
+    std::fs::write(dir.join("friend.toml"), "sensitivity = 5\n").unwrap();
+    let exported = dir.join("game.toml");
+    let export = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("shared.toml"))
+        .arg("--export-profile")
+        .arg("game")
+        .arg(&exported)
+        .status()
+        .unwrap();
+    assert!(export.success());
+    let text = std::fs::read_to_string(&exported).unwrap();
+    assert!(text.contains("launch = [\"steam\"]"), "{}", text);
+    assert!(!text.contains("allow_launch"), "{}", text);
+
+    let import = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("RUST_LOG", "info")
+        .arg("--config")
+        .arg(dir.join("friend.toml"))
+        .arg("--import-profile")
+        .arg(&exported)
+        .output()
+        .unwrap();
+    let friend = std::fs::read_to_string(dir.join("friend.toml")).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    let log = String::from_utf8_lossy(&import.stderr);
+    assert!(import.status.success(), "{}", log);
+    assert!(log.contains("Imported profile \"game\" launches [\"steam\"] when selected"), "{}", log);
+    assert!(!friend.contains("allow_launch"), "{}", friend);
+}
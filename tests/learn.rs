@@ -0,0 +1,80 @@
+// Runs --learn on a synthetic input script, answering the save prompt
+// through stdin, and returns the config file afterwards.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn learn(name: &str, config: Option<&str>, script: &str, answer: &str) -> Option<String> {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-learn-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    if let Some(config) = config {
+        std::fs::write(&path, config).unwrap();
+    }
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(&path)
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--learn")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(answer.as_bytes()).unwrap();
+    assert!(child.wait().unwrap().success());
+    let config = std::fs::read_to_string(&path).ok();
+    std::fs::remove_dir_all(dir).unwrap();
+    config
+}
+
+#[test]
+fn learned_inputs_become_mappings() {
+    let config = learn(
+        "defaults",
+        None,
+        "0 EV_KEY BTN_SIDE 1\n\
+         0 EV_KEY BTN_SIDE 0\n\
+         # skips the brake\n\
+         10 EV_KEY KEY_ESC 1\n\
+         # already the throttle, ignored\n\
+         10 EV_KEY BTN_SIDE 1\n\
+         10 EV_KEY BTN_EXTRA 1\n",
+        "y\n",
+    );
+    assert_eq!(
+        config.unwrap(),
+        "config_version = 1\n\
+         \n\
+         [buttons]\n\
+         BTN_SIDE = { axis = \"ABS_GAS\", mode = \"ramp\" }\n\
+         BTN_EXTRA = \"BTN_TR\"\n"
+    );
+}
+
+#[test]
+fn relearning_replaces_only_the_learned_functions() {
+    let config = learn(
+        "replace",
+        Some("[buttons]\nBTN_LEFT = { axis = \"ABS_GAS\", mode = \"ramp\" }\nBTN_MIDDLE = \"BTN_TR\"\n"),
+        "0 EV_KEY BTN_SIDE 1\n\
+         10 EV_KEY KEY_ESC 1\n",
+        "y\n",
+    );
+    // brought up to the current format on the way
+    assert_eq!(
+        config.unwrap(),
+        "config_version = 1\n\
+         [buttons]\n\
+         BTN_MIDDLE = \"BTN_TR\"\n\
+         BTN_SIDE = { axis = \"ABS_GAS\", mode = \"ramp\" }\n"
+    );
+}
+
+#[test]
+fn nothing_is_saved_without_confirmation() {
+    let config = learn("declined", Some("sensitivity = 5\n"), "0 EV_KEY BTN_SIDE 1\n", "n\n");
+    assert_eq!(config.unwrap(), "sensitivity = 5\n");
+}
@@ -0,0 +1,67 @@
+// The full lock LED needs a keyboard having it, without one it turns itself
+// off after saying so once.
+
+use std::process::{Command, Output};
+
+fn run(name: &str, config: &str) -> Output {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-lock-led-{}-{}", name, std::process::id()));
+    let input_dir = dir.join("input");
+    std::fs::create_dir_all(&input_dir).unwrap();
+    std::fs::write(dir.join("config.toml"), format!("sensitivity = 100\nlog_backend = \"stderr\"\n{}", config)).unwrap();
+    std::fs::write(dir.join("input.txt"), "0 EV_REL REL_X 45\n10 EV_REL REL_X -45\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("RUST_LOG", "debug")
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--input-dir")
+        .arg(&input_dir)
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+#[test]
+fn without_a_keyboard_it_is_off() {
+    let output = run("none", "[lock_led]\nenabled = true\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert_eq!(log.matches("No keyboard with LED_SCROLLL found, no full lock LED").count(), 1, "{}", log);
+    assert!(!log.contains("Lighting"), "{}", log);
+}
+
+#[test]
+fn a_named_keyboard_is_looked_for() {
+    let output = run("named", "[lock_led]\nenabled = true\nled = \"LED_CAPSL\"\ndevice = \"Some Keyboard\"\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(log.contains("No keyboard with LED_CAPSL named \"Some Keyboard\" found"), "{}", log);
+}
+
+#[test]
+fn disabled_it_looks_for_nothing() {
+    let output = run("disabled", "[lock_led]\nled = \"LED_CAPSL\"\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(!log.contains("No keyboard with"), "{}", log);
+}
+
+#[test]
+fn an_unknown_led_is_a_config_error() {
+    let output = run("unknown", "[lock_led]\nenabled = true\nled = \"LED_NOPE\"\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{}", log);
+    assert!(log.contains("lock_led: unknown LED \"LED_NOPE\""), "{}", log);
+}
+
+#[test]
+fn the_cue_hysteresis_is_checked_for_the_led_too() {
+    let output = run("hysteresis", "[full_lock_cue]\nhysteresis = -1\n[lock_led]\nenabled = true\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{}", log);
+    assert!(log.contains("cue hysteresis must be between 0 and 4500, got -1"), "{}", log);
+}
@@ -0,0 +1,36 @@
+// Logging runs through one level filter whatever the backend, check that
+// RUST_LOG is honoured on stderr.
+
+use std::process::Command;
+
+#[test]
+fn rust_log_filters_records() {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-logging-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), "log_backend = \"stderr\"\n").unwrap();
+    std::fs::write(dir.join("input.txt"), "0 EV_REL REL_X 10\n").unwrap();
+
+    let run = |filter: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+            .env("RUST_LOG", filter)
+            .arg("--config")
+            .arg(dir.join("config.toml"))
+            .arg("--input-script")
+            .arg(dir.join("input.txt"))
+            .arg("--dry-run")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    };
+    let debug = run("debug");
+    let info = run("info");
+    let warn = run("warn");
+    std::fs::remove_dir_all(dir).unwrap();
+
+    assert!(debug.contains("Steering:  30"), "{}", debug);
+    // every steering change is logged, but no longer at the info level
+    assert!(!info.contains("Steering:"), "{}", info);
+    assert!(info.contains("INFO"), "{}", info);
+    assert!(!warn.contains("INFO"), "{}", warn);
+}
@@ -0,0 +1,585 @@
+// End to end test feeding a virtual mouse into mouse2joy and reading back the
+// virtual steering wheel it creates. Needs uinput and read access to
+// /dev/input, so it only runs when MOUSE2JOY_UINPUT_TESTS is set.
+
+use evdev::{
+    uinput::VirtualDevice, uinput::VirtualDeviceBuilder, AbsInfo, AbsoluteAxisType, AttributeSet, Device, EventType,
+    InputEvent, Key, RelativeAxisType, UinputAbsSetup,
+};
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn uinput_tests_enabled() -> bool {
+    if std::env::var_os("MOUSE2JOY_UINPUT_TESTS").is_none() {
+        eprintln!("skipping, set MOUSE2JOY_UINPUT_TESTS=1 to run the uinput loopback tests");
+        return false;
+    }
+    if let Err(e) = VirtualDeviceBuilder::new() {
+        eprintln!("skipping, uinput is not available: {}", e);
+        return false;
+    }
+    true
+}
+
+fn create_mouse() -> VirtualDevice {
+    create_named_mouse("mouse2joy loopback mouse")
+}
+
+fn create_named_mouse(name: &str) -> VirtualDevice {
+    let mut axes = AttributeSet::new();
+    axes.insert(RelativeAxisType::REL_X);
+    axes.insert(RelativeAxisType::REL_Y);
+    let mut keys = AttributeSet::new();
+    for key in [Key::BTN_LEFT, Key::BTN_RIGHT, Key::BTN_SIDE] {
+        keys.insert(key);
+    }
+    VirtualDeviceBuilder::new()
+        .unwrap()
+        .name(name)
+        .with_relative_axes(&axes)
+        .unwrap()
+        .with_keys(&keys)
+        .unwrap()
+        .build()
+        .unwrap()
+}
+
+// directory holding only the virtual mouse, used as mouse2joy's scan root
+fn scan_dir(mouse: &mut VirtualDevice, name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    link_mouse(mouse, &dir, "event0");
+    dir
+}
+
+// add another mouse to a scan directory
+fn link_mouse(mouse: &mut VirtualDevice, dir: &Path, name: &str) {
+    let node = mouse.enumerate_dev_nodes_blocking().unwrap().next().unwrap().unwrap();
+    let deadline = Instant::now() + TIMEOUT;
+    while !node.exists() {
+        assert!(Instant::now() < deadline, "{} never appeared", node.display());
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    std::os::unix::fs::symlink(&node, dir.join(name)).unwrap();
+}
+
+fn spawn(dir: &Path, config: &str, max_events: u64) -> Child {
+    spawn_with(dir, config, max_events, &[])
+}
+
+fn spawn_with(dir: &Path, config: &str, max_events: u64, args: &[&str]) -> Child {
+    let config_path = dir.join("config.toml");
+    std::fs::write(&config_path, config).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--input-dir")
+        .arg(dir)
+        .arg("--max-events")
+        .arg(max_events.to_string())
+        .args(args)
+        // kept open and silent, like a user away from the keyboard
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap()
+}
+
+// wait for the virtual steering wheel and open it non-blocking
+fn open_wheel() -> Device {
+    open_device("mouse2joy")
+}
+
+fn open_device(name: &str) -> Device {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        let wheel = evdev::enumerate()
+            .map(|(_, device)| device)
+            .find(|device| device.name() == Some(name));
+        if let Some(wheel) = wheel {
+            fcntl(wheel.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK)).unwrap();
+            return wheel;
+        }
+        assert!(Instant::now() < deadline, "mouse2joy never created {}", name);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+// read (type, code, value) triples until `count` non-SYN events arrived
+fn read_events(wheel: &mut Device, count: usize) -> Vec<(EventType, u16, i32)> {
+    let deadline = Instant::now() + TIMEOUT;
+    let mut events = Vec::new();
+    while events.len() < count {
+        assert!(Instant::now() < deadline, "only received {:?}", events);
+        match wheel.fetch_events() {
+            Ok(fetched) => events.extend(
+                fetched
+                    .filter(|ev| ev.event_type() != EventType::SYNCHRONIZATION)
+                    .map(|ev| (ev.event_type(), ev.code(), ev.value())),
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(5))
+            }
+            Err(e) => panic!("failed to read the wheel: {}", e),
+        }
+    }
+    events
+}
+
+fn rel(axis: RelativeAxisType, value: i32) -> InputEvent {
+    InputEvent::new(EventType::RELATIVE, axis.0, value)
+}
+
+fn rel_x(value: i32) -> InputEvent {
+    rel(RelativeAxisType::REL_X, value)
+}
+
+fn key(key: Key, value: i32) -> InputEvent {
+    InputEvent::new(EventType::KEY, key.code(), value)
+}
+
+// exit status of mouse2joy, killing it if it doesn't exit in time
+fn exit_status(mut child: Child) -> ExitStatus {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            return status;
+        }
+        if Instant::now() > deadline {
+            child.kill().unwrap();
+            panic!("mouse2joy didn't exit");
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn wait_for_exit(child: Child) {
+    assert!(exit_status(child).success());
+}
+
+#[test]
+fn steering_and_buttons_loop_back() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut mouse = create_mouse();
+    let dir = scan_dir(&mut mouse, "steering");
+
+    let inputs = [
+        rel_x(10),
+        rel_x(20),
+        // past full lock
+        rel_x(30),
+        rel_x(-90),
+        rel_x(5),
+        key(Key::BTN_SIDE, 1),
+        key(Key::BTN_SIDE, 0),
+    ];
+    // one extra REL_Y event, which emits nothing, ends the run
+    let child = spawn(
+        &dir,
+        "sensitivity = 100\n[buttons]\nBTN_SIDE = \"BTN_TR\"\n",
+        inputs.len() as u64 + 1,
+    );
+    let mut wheel = open_wheel();
+
+    for ev in inputs {
+        mouse.emit(&[ev]).unwrap();
+    }
+    let abs_x = |value| (EventType::ABSOLUTE, 0, value);
+    assert_eq!(
+        read_events(&mut wheel, inputs.len()),
+        vec![
+            abs_x(1000),
+            abs_x(3000),
+            abs_x(4500),
+            abs_x(-4500),
+            abs_x(-4000),
+            (EventType::KEY, Key::BTN_TR.code(), 1),
+            (EventType::KEY, Key::BTN_TR.code(), 0),
+        ]
+    );
+    mouse.emit(&[rel(RelativeAxisType::REL_Y, 1)]).unwrap();
+
+    wait_for_exit(child);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn gated_steering_leaves_the_mouse_ungrabbed() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut mouse = create_mouse();
+    let dir = scan_dir(&mut mouse, "gate");
+
+    let inputs = [
+        // not held, ignored
+        rel_x(10),
+        key(Key::BTN_LEFT, 1),
+        rel_x(20),
+        key(Key::BTN_LEFT, 0),
+        rel_x(10),
+    ];
+    let child = spawn(
+        &dir,
+        "sensitivity = 100\nsteer_while_held = \"BTN_LEFT\"\n",
+        inputs.len() as u64 + 1,
+    );
+    let mut wheel = open_wheel();
+
+    // mouse2joy never grabs the mouse, so the desktop pointer keeps working
+    // between gated moves and anyone else can still grab it
+    let mut other = Device::open(dir.join("event0")).unwrap();
+    other.grab().unwrap();
+    other.ungrab().unwrap();
+
+    for ev in inputs {
+        mouse.emit(&[ev]).unwrap();
+    }
+    assert_eq!(read_events(&mut wheel, 1), vec![(EventType::ABSOLUTE, 0, 2000)]);
+    mouse.emit(&[rel(RelativeAxisType::REL_Y, 1)]).unwrap();
+
+    wait_for_exit(child);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn sigterm_shuts_down_cleanly() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut mouse = create_mouse();
+    let dir = scan_dir(&mut mouse, "sigterm");
+    let child = spawn(&dir, "sensitivity = 100\n", u64::MAX);
+    let mut wheel = open_wheel();
+
+    // once an event came back the loop and its signal handlers are running
+    mouse.emit(&[rel_x(10)]).unwrap();
+    assert_eq!(read_events(&mut wheel, 1), vec![(EventType::ABSOLUTE, 0, 1000)]);
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM).unwrap();
+
+    wait_for_exit(child);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn prompt_times_out_to_the_first_mouse() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut first = create_mouse();
+    let mut second = create_mouse();
+    let dir = scan_dir(&mut first, "prompt-first");
+    link_mouse(&mut second, &dir, "event1");
+
+    let child = spawn(&dir, "sensitivity = 100\nprompt_timeout = 1\n", 1);
+    let mut wheel = open_wheel();
+    first.emit(&[rel_x(10)]).unwrap();
+    assert_eq!(read_events(&mut wheel, 1), vec![(EventType::ABSOLUTE, 0, 1000)]);
+
+    wait_for_exit(child);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn prompt_timeout_without_default_fails() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut first = create_mouse();
+    let mut second = create_mouse();
+    let dir = scan_dir(&mut first, "prompt-none");
+    link_mouse(&mut second, &dir, "event1");
+
+    let child = spawn(&dir, "prompt_timeout = 1\nprompt_default = \"none\"\n", 1);
+    assert_eq!(exit_status(child).code(), Some(9));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn mouse_held_by_someone_else_is_retried_then_fails() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut mouse = create_mouse();
+    let dir = scan_dir(&mut mouse, "grab");
+    let mut holder = Device::open(dir.join("event0")).unwrap();
+    holder.grab().unwrap();
+
+    let child = spawn(&dir, "grab_mouse = true\n", 1);
+    assert_eq!(exit_status(child).code(), Some(10));
+    holder.ungrab().unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn emergency_release_lets_go_of_a_hung_loop() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut mouse = create_mouse();
+    let dir = scan_dir(&mut mouse, "emergency");
+    let mut keys = AttributeSet::new();
+    for key in [Key::KEY_LEFTCTRL, Key::KEY_LEFTALT, Key::KEY_END] {
+        keys.insert(key);
+    }
+    let mut keyboard = VirtualDeviceBuilder::new()
+        .unwrap()
+        .name("mouse2joy loopback keyboard")
+        .with_keys(&keys)
+        .unwrap()
+        .build()
+        .unwrap();
+    link_mouse(&mut keyboard, &dir, "event1");
+
+    let config_path = dir.join("config.toml");
+    std::fs::write(&config_path, "sensitivity = 100\ngrab_mouse = true\n").unwrap();
+    let child = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg("--input-dir")
+        .arg(&dir)
+        // the loop stops for good after the first event
+        .env("MOUSE2JOY_HANG_AFTER", "1")
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut wheel = open_wheel();
+    mouse.emit(&[rel_x(10)]).unwrap();
+    assert_eq!(read_events(&mut wheel, 1), vec![(EventType::ABSOLUTE, 0, 1000)]);
+
+    keyboard.emit(&[key(Key::KEY_LEFTCTRL, 1), key(Key::KEY_LEFTALT, 1), key(Key::KEY_END, 1)]).unwrap();
+    assert_eq!(exit_status(child).code(), Some(14));
+    // the grab went with the process, and so did the wheel
+    let mut other = Device::open(dir.join("event0")).unwrap();
+    other.grab().unwrap();
+    other.ungrab().unwrap();
+    assert!(!evdev::enumerate().any(|(_, device)| device.name() == Some("mouse2joy")));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn self_test_reads_every_capability_back() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let dir = std::env::temp_dir().join(format!("mouse2joy-self-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), "[pedals.throttle]\nkeys = [\"KEY_W\"]\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--self-test")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", report);
+    for line in ["ABS_X            -4500 to 4500", "ABS_GAS          0 to 1023", "ok    ABS_X", "ok    BTN_TR2"] {
+        assert!(report.contains(line), "no \"{}\" in\n{}", line, report);
+    }
+    assert!(!report.contains("FAIL"), "{}", report);
+}
+
+#[test]
+fn self_test_sees_no_keys_without_buttons() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let dir = std::env::temp_dir().join(format!("mouse2joy-self-test-keys-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), "no_buttons = true\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--self-test")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", report);
+    assert!(report.contains("Buttons: none, no EV_KEY capability"), "{}", report);
+    assert!(report.contains("All 1 capabilities read back"), "{}", report);
+}
+
+#[test]
+fn second_mouse_drives_the_pedals() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut steering = create_mouse();
+    let mut pedals = create_mouse();
+    let dir = scan_dir(&mut steering, "pedals-mouse");
+    link_mouse(&mut pedals, &dir, "event1");
+
+    let config = format!(
+        "sensitivity = 100\n\
+         [[inputs]]\npath = \"{0}/event0\"\nrole = \"steering\"\n\
+         [[inputs]]\npath = \"{0}/event1\"\nrole = \"pedals\"\n",
+        dir.display()
+    );
+    let child = spawn(&dir, &config, 5);
+    let mut wheel = open_wheel();
+    let abs = |axis: u16, value| (EventType::ABSOLUTE, axis, value);
+    let (abs_x, abs_gas, abs_brake) = (0, 0x09, 0x0a);
+
+    steering.emit(&[rel_x(10)]).unwrap();
+    assert_eq!(read_events(&mut wheel, 1), vec![abs(abs_x, 1000)]);
+    // forward is throttle, sideways does nothing, back goes over to the brake
+    pedals.emit(&[rel(RelativeAxisType::REL_Y, -100)]).unwrap();
+    assert_eq!(read_events(&mut wheel, 1), vec![abs(abs_gas, 200)]);
+    pedals.emit(&[rel_x(50)]).unwrap();
+    pedals.emit(&[rel(RelativeAxisType::REL_Y, 150)]).unwrap();
+    assert_eq!(read_events(&mut wheel, 2), vec![abs(abs_gas, 0), abs(abs_brake, 100)]);
+
+    // unplugging the pedals leaves the steering working
+    drop(pedals);
+    steering.emit(&[rel_x(10)]).unwrap();
+    assert_eq!(read_events(&mut wheel, 1), vec![abs(abs_x, 2000)]);
+
+    wait_for_exit(child);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn split_layout_sends_the_pedals_to_their_own_device() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut steering = create_mouse();
+    let mut pedals = create_mouse();
+    let dir = scan_dir(&mut steering, "split");
+    link_mouse(&mut pedals, &dir, "event1");
+
+    let config = format!(
+        "sensitivity = 100\nlayout = \"split\"\n\
+         [[inputs]]\npath = \"{0}/event0\"\nrole = \"steering\"\n\
+         [[inputs]]\npath = \"{0}/event1\"\nrole = \"pedals\"\n",
+        dir.display()
+    );
+    let child = spawn(&dir, &config, 5);
+    let mut wheel = open_device("mouse2joy wheel");
+    let mut pedal_device = open_device("mouse2joy pedals");
+    assert!(!pedal_device.supported_absolute_axes().unwrap().contains(AbsoluteAxisType::ABS_X));
+    assert!(!wheel.supported_absolute_axes().unwrap().contains(AbsoluteAxisType::ABS_GAS));
+
+    steering.emit(&[rel_x(10)]).unwrap();
+    assert_eq!(read_events(&mut wheel, 1), vec![(EventType::ABSOLUTE, 0, 1000)]);
+    pedals.emit(&[rel(RelativeAxisType::REL_Y, -100)]).unwrap();
+    assert_eq!(read_events(&mut pedal_device, 1), vec![(EventType::ABSOLUTE, 0x09, 200)]);
+
+    wait_for_exit(child);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn device_section_beats_the_globals_and_loses_to_the_profile() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let config = "sensitivity = 3\n\
+                  [device.\"mouse2joy loopback mouse\"]\nsensitivity = 100\n\
+                  [device.\"some other mouse\"]\nsensitivity = 7\n\
+                  [profiles.slow]\nsensitivity = 50\n";
+    for (args, expected) in [(&[][..], 1000), (&["--profile", "slow"][..], 500)] {
+        let mut mouse = create_mouse();
+        let dir = scan_dir(&mut mouse, "device-section");
+        let child = spawn_with(&dir, config, 1, args);
+        let mut wheel = open_wheel();
+
+        mouse.emit(&[rel_x(10)]).unwrap();
+        assert_eq!(read_events(&mut wheel, 1), vec![(EventType::ABSOLUTE, 0, expected)], "{:?}", args);
+
+        wait_for_exit(child);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn ignored_devices_leave_a_single_candidate_without_a_prompt() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut mouse = create_mouse();
+    let mut tablet = create_named_mouse("mouse2joy loopback tablet");
+    let dir = scan_dir(&mut mouse, "ignore-devices");
+    link_mouse(&mut tablet, &dir, "event1");
+    let config = "sensitivity = 100\nignore_devices = [\"*tablet\"]\n";
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-dir")
+        .arg(&dir)
+        .arg("--list-devices")
+        .output()
+        .unwrap();
+    let listed = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(listed.contains("\"mouse2joy loopback tablet\" (no phys), ignored by ignore_devices \"*tablet\""), "{}", listed);
+    assert!(listed.contains("\"mouse2joy loopback mouse\" (no phys)\n"), "{}", listed);
+
+    // two mice would prompt, and nobody answers
+    let child = spawn(&dir, config, 1);
+    let mut wheel = open_wheel();
+    mouse.emit(&[rel_x(10)]).unwrap();
+    assert_eq!(read_events(&mut wheel, 1), vec![(EventType::ABSOLUTE, 0, 1000)]);
+
+    wait_for_exit(child);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn toy_wheel_axis_drives_the_full_rotation() {
+    if !uinput_tests_enabled() {
+        return;
+    }
+    let mut keys = AttributeSet::new();
+    keys.insert(Key::BTN_SOUTH);
+    // a 180 degree wheel reporting 0 to 255
+    let mut toy = VirtualDeviceBuilder::new()
+        .unwrap()
+        .name("mouse2joy loopback toy wheel")
+        .with_absolute_axis(&UinputAbsSetup::new(AbsoluteAxisType::ABS_X, AbsInfo::new(128, 0, 255, 0, 0, 0)))
+        .unwrap()
+        .with_keys(&keys)
+        .unwrap()
+        .build()
+        .unwrap();
+    let dir = std::env::temp_dir().join(format!("mouse2joy-axis-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    link_mouse(&mut toy, &dir, "event0");
+
+    let config = "mouse_steering = false\n[axis_steering]\nenabled = true\n[buttons]\nBTN_SOUTH = \"BTN_TR\"\n";
+    let child = spawn(&dir, config, 4);
+    let mut wheel = open_wheel();
+    let abs = |axis: AbsoluteAxisType, value| InputEvent::new(EventType::ABSOLUTE, axis.0, value);
+    toy.emit(&[abs(AbsoluteAxisType::ABS_X, 255)]).unwrap();
+    toy.emit(&[abs(AbsoluteAxisType::ABS_X, 0)]).unwrap();
+    toy.emit(&[key(Key::BTN_SOUTH, 1)]).unwrap();
+    toy.emit(&[key(Key::BTN_SOUTH, 0)]).unwrap();
+    assert_eq!(
+        read_events(&mut wheel, 4),
+        vec![
+            (EventType::ABSOLUTE, 0, 4500),
+            (EventType::ABSOLUTE, 0, -4500),
+            (EventType::KEY, Key::BTN_TR.code(), 1),
+            (EventType::KEY, Key::BTN_TR.code(), 0),
+        ]
+    );
+
+    wait_for_exit(child);
+    std::fs::remove_dir_all(dir).unwrap();
+}
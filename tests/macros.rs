@@ -0,0 +1,150 @@
+// A [buttons] source mapped to a macro plays its timed steps from the loop's
+// timer, on the simulated clock of an input script here.
+
+#[path = "../src/macros/step.rs"]
+mod step;
+
+mod common;
+
+use common::events;
+use evdev::{AbsoluteAxisType, Key};
+use std::process::Output;
+use std::time::Duration;
+use step::Step;
+
+#[test]
+fn steps_parse() {
+    assert_eq!(Step::parse("press BTN_TL2"), Ok(Step::Press(Key::BTN_TL2)));
+    assert_eq!(Step::parse("  release   BTN_START "), Ok(Step::Release(Key::BTN_START)));
+    assert_eq!(Step::parse("wait 1000"), Ok(Step::Wait(Duration::from_secs(1))));
+    assert_eq!(
+        Step::parse("axis ABS_GAS 32767"),
+        Ok(Step::Axis { axis: AbsoluteAxisType::ABS_GAS, value: 32767, keep: false })
+    );
+    assert_eq!(
+        Step::parse("axis ABS_X -4500 keep"),
+        Ok(Step::Axis { axis: AbsoluteAxisType::ABS_X, value: -4500, keep: true })
+    );
+}
+
+#[test]
+fn bad_steps_are_refused() {
+    for (spec, message) in [
+        ("", "empty step"),
+        ("press BTN_NOPE", "unknown button \"BTN_NOPE\""),
+        ("wait soon", "invalid number of milliseconds \"soon\""),
+        ("wait -5", "invalid number of milliseconds \"-5\""),
+        ("axis ABS_NOPE 5", "unknown axis \"ABS_NOPE\""),
+        ("axis ABS_GAS full", "invalid axis value \"full\""),
+        ("axis ABS_GAS 5 forever", "is none of"),
+        ("jump BTN_TL2", "is none of"),
+        ("press", "is none of"),
+    ] {
+        let e = Step::parse(spec).unwrap_err();
+        assert!(e.contains(message), "{}: {}", spec, e);
+    }
+}
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("macros", name, &format!("log_backend = \"stderr\"\n{}", config), script)
+}
+
+const ENGINE: &str = "[pedals.brake]\nkeys = [\"KEY_S\"]\n[buttons]\nKEY_I = { macro = \"start_engine\" }\n\
+    [macros]\nstart_engine = [\"axis ABS_BRAKE 1023\", \"press BTN_TL2\", \"wait 1000\", \"release BTN_TL2\", \
+    \"wait 200\"]\n";
+
+#[test]
+fn the_engine_ritual_plays_on_the_clock() {
+    let output = run("engine", ENGINE, "100 EV_KEY KEY_I 1\n50 EV_KEY KEY_I 0\n3000 EV_KEY KEY_Q 1\n");
+    assert_eq!(
+        events(&output),
+        [
+            "0.100 EV_ABS ABS_BRAKE 1023",
+            "0.100 EV_KEY BTN_TL2 1",
+            "1.100 EV_KEY BTN_TL2 0",
+            // the brake back where it was
+            "1.300 EV_ABS ABS_BRAKE 0",
+        ]
+    );
+}
+
+#[test]
+fn the_failsafe_cancels_a_running_macro() {
+    let config = format!("[failsafe]\ntimeout_ms = 500\n{}", ENGINE);
+    let output = run("failsafe", &config, "100 EV_KEY KEY_I 1\n50 EV_KEY KEY_I 0\n3000 EV_KEY KEY_Q 1\n");
+    assert_eq!(
+        events(&output),
+        [
+            "0.100 EV_ABS ABS_BRAKE 1023",
+            "0.100 EV_KEY BTN_TL2 1",
+            // 500ms after the trigger went up, nothing of it plays on
+            "0.650 EV_KEY BTN_TL2 0",
+            "0.650 EV_ABS ABS_BRAKE 0",
+        ]
+    );
+}
+
+#[test]
+fn the_trigger_again_cancels_and_restores() {
+    let output = run(
+        "cancel",
+        ENGINE,
+        "100 EV_KEY KEY_I 1\n0 EV_KEY KEY_I 0\n400 EV_KEY KEY_I 1\n3000 EV_KEY KEY_Q 1\n",
+    );
+    assert_eq!(
+        events(&output),
+        [
+            "0.100 EV_ABS ABS_BRAKE 1023",
+            "0.100 EV_KEY BTN_TL2 1",
+            "0.500 EV_KEY BTN_TL2 0",
+            "0.500 EV_ABS ABS_BRAKE 0",
+        ]
+    );
+}
+
+#[test]
+fn a_kept_axis_stays_and_buttons_left_down_are_released() {
+    let output = run(
+        "keep",
+        "[pedals.throttle]\nkeys = [\"KEY_W\"]\n[buttons]\nBTN_SIDE = { macro = \"cruise\" }\n\
+         [macros]\ncruise = [\"axis ABS_GAS 500 keep\", \"press BTN_START\", \"wait 100\"]\n",
+        "0 EV_KEY BTN_SIDE 1\n1000 EV_KEY KEY_Q 1\n",
+    );
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_ABS ABS_GAS 500",
+            "0.000 EV_KEY BTN_START 1",
+            "0.100 EV_KEY BTN_START 0",
+            // centered on exit
+            "0.100 EV_ABS ABS_GAS 0",
+        ]
+    );
+}
+
+#[test]
+fn bad_macros_are_refused() {
+    let trigger = "[pedals.throttle]\nkeys = [\"KEY_W\"]\n[buttons]\nKEY_I = { macro = \"m\" }\n[macros]\n";
+    for (name, config, message) in [
+        ("unknown", "[buttons]\nKEY_I = { macro = \"nope\" }\n".to_string(), "unknown macro \"nope\""),
+        ("empty", format!("{}m = []\n", trigger), "[macros] m has no steps"),
+        ("syntax", format!("{}m = [\"wait 5\", \"hop\"]\n", trigger), "[macros] m, step 2: \"hop\" is none of"),
+        (
+            "button",
+            format!("{}m = [\"press BTN_SOUTH\"]\n", trigger),
+            "BTN_SOUTH is not a button of the virtual device",
+        ),
+        ("axis", format!("{}m = [\"axis ABS_RZ 5\"]\n", trigger), "ABS_RZ is not an axis of the virtual device"),
+        ("range", format!("{}m = [\"axis ABS_GAS 2000\"]\n", trigger), "2000 is outside ABS_GAS's range 0 to 1023"),
+        (
+            "conflict",
+            format!("[pedals.brake]\nkeys = [\"KEY_I\"]\n{}m = [\"wait 5\"]\n", trigger),
+            "KEY_I is used by both",
+        ),
+    ] {
+        let output = run(name, &config, "0 EV_REL REL_X 10\n");
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{}: {}", name, log);
+        assert!(log.contains(message), "{}: {}", name, log);
+    }
+}
@@ -0,0 +1,76 @@
+// Older config files are upgraded on load, with a warning per changed key.
+// Each migration has a fixture in the format it upgrades from.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn mouse2joy(config: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("RUST_LOG", "warn")
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+// a copy of the fixture to run against
+fn fixture(name: &str, test: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-migration-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    std::fs::copy(format!("{}/tests/fixtures/config/{}", env!("CARGO_MANIFEST_DIR"), name), &config).unwrap();
+    (dir, config)
+}
+
+#[test]
+fn version0_drops_gui_in_memory() {
+    let (dir, config) = fixture("version0.toml", "memory");
+    let before = std::fs::read_to_string(&config).unwrap();
+    let output = mouse2joy(&config, &["--profile", "rally", "--print-sdl-mapping"]);
+    let after = std::fs::read_to_string(&config).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("dropped gui, it never had an effect"), "{}", stderr);
+    assert!(stderr.contains("dropped profiles.rally.gui, it never had an effect"), "{}", stderr);
+    assert_eq!(before, after);
+}
+
+#[test]
+fn version0_rewritten_with_a_backup() {
+    let (dir, config) = fixture("version0.toml", "rewrite");
+    let before = std::fs::read_to_string(&config).unwrap();
+    let migrated = mouse2joy(&config, &["--migrate-config"]);
+    let after = std::fs::read_to_string(&config).unwrap();
+    let backup = std::fs::read_to_string(dir.join("config.toml.bak")).unwrap();
+    let again = mouse2joy(&config, &["--migrate-config"]);
+    let unchanged = std::fs::read_to_string(&config).unwrap();
+    let loaded = mouse2joy(&config, &["--print-sdl-mapping"]);
+    std::fs::remove_dir_all(dir).unwrap();
+
+    assert!(migrated.status.success());
+    assert_eq!(backup, before);
+    assert!(!after.contains("gui"));
+    assert!(after.contains("config_version = 1"));
+    // comments and the remaining keys survive
+    assert!(after.contains("dead_zone = 10        # Dead zone for mouse input"));
+    assert!(after.contains("[profiles.rally]\nsensitivity = 60"));
+    assert!(again.status.success());
+    assert_eq!(after, unchanged);
+    assert!(String::from_utf8_lossy(&loaded.stderr).is_empty());
+}
+
+#[test]
+fn newer_version_is_an_error() {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-migration-newer-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("config.toml");
+    std::fs::write(&config, "config_version = 99\nsensitivity = 10\n").unwrap();
+    let output = mouse2joy(&config, &["--print-sdl-mapping"]);
+    std::fs::remove_dir_all(dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("config_version 99 is newer"));
+}
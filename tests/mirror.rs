@@ -0,0 +1,79 @@
+// mirror_axis sends every steering value on a second axis, in the same
+// frame as ABS_X, on a dry run of an input script.
+
+mod common;
+
+use common::events;
+use std::process::Output;
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("mirror", name, &format!("log_backend = \"stderr\"\n{}", config), script)
+}
+
+#[test]
+fn steering_goes_to_both_axes() {
+    let output = run(
+        "both",
+        "sensitivity = 100\nmirror_axis = \"ABS_RX\"\n",
+        "0 EV_REL REL_X 10\n\
+         10 EV_REL REL_X 40\n",
+    );
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.000 EV_ABS ABS_RX 1000",
+            "0.010 EV_ABS ABS_X 4500",
+            "0.010 EV_ABS ABS_RX 4500",
+            // both centered on exit
+            "0.010 EV_ABS ABS_X 0",
+            "0.010 EV_ABS ABS_RX 0",
+        ]
+    );
+}
+
+#[test]
+fn the_pedals_are_not_mirrored() {
+    let output = run(
+        "pedals",
+        "sensitivity = 100\nmirror_axis = \"ABS_RX\"\ntick_rate = 10\n\
+         [pedals.throttle]\nkeys = [\"KEY_W\"]\nrise_ms = 0\nfall_ms = 0\n",
+        "0 EV_KEY KEY_W 1\n\
+         0 EV_REL REL_X -10\n\
+         10 EV_KEY KEY_W 0\n",
+    );
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_ABS ABS_GAS 1023",
+            "0.000 EV_ABS ABS_X -1000",
+            "0.000 EV_ABS ABS_RX -1000",
+            "0.010 EV_ABS ABS_GAS 0",
+            "0.010 EV_ABS ABS_X 0",
+            "0.010 EV_ABS ABS_RX 0",
+        ]
+    );
+}
+
+#[test]
+fn the_mirror_needs_a_free_axis() {
+    for (name, config, message) in [
+        ("unknown", "mirror_axis = \"ABS_NOPE\"\n", "mirror_axis: unknown axis \"ABS_NOPE\""),
+        ("steering", "mirror_axis = \"ABS_X\"\n", "the mirror axis ABS_X is already in use"),
+        (
+            "pedal",
+            "mirror_axis = \"ABS_GAS\"\n[pedals.throttle]\nkeys = [\"KEY_W\"]\n",
+            "the mirror axis ABS_GAS is already in use",
+        ),
+        (
+            "scroll",
+            "mirror_axis = \"ABS_RZ\"\n[scroll]\nmode = \"throttle\"\naxis = \"ABS_RZ\"\n",
+            "the mirror axis ABS_RZ is already in use",
+        ),
+    ] {
+        let output = run(name, config, "0 EV_REL REL_X 10\n");
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{}: {}", name, log);
+        assert!(log.contains(message), "{}: {}", name, log);
+    }
+}
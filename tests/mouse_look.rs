@@ -0,0 +1,67 @@
+// [mouse_look] splits the steering mouse: REL_X steers, the forwarded axes go
+// to a virtual pointer as read.
+
+mod common;
+
+use common::events;
+use std::process::Output;
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("look", name, &format!("sensitivity = 100\n{}", config), script)
+}
+
+#[test]
+fn a_diagonal_report_is_split() {
+    let output = run(
+        "diagonal",
+        "[mouse_look]\nenabled = true\n",
+        "0 EV_REL REL_X 10\n0 EV_REL REL_Y 5\n10 EV_REL REL_Y -3\n",
+    );
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.000 EV_REL REL_Y 5",
+            "0.010 EV_REL REL_Y -3",
+            "0.010 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn with_a_hold_key_only_while_held() {
+    let output = run(
+        "hold",
+        "[mouse_look]\nenabled = true\nhold = \"BTN_RIGHT\"\n",
+        "0 EV_REL REL_Y 5\n\
+         10 EV_KEY BTN_RIGHT 1\n\
+         10 EV_REL REL_Y 7\n\
+         10 EV_KEY BTN_RIGHT 0\n\
+         10 EV_REL REL_Y 9\n",
+    );
+    assert_eq!(events(&output), ["0.020 EV_REL REL_Y 7"]);
+}
+
+#[test]
+fn bad_configurations_are_refused() {
+    for (name, config, message) in [
+        ("x", "[mouse_look]\nenabled = true\nforward = [\"REL_X\"]\n", "can't forward REL_X"),
+        ("unknown", "[mouse_look]\nenabled = true\nforward = [\"REL_Q\"]\n", "unknown axis \"REL_Q\""),
+        ("none", "[mouse_look]\nenabled = true\nforward = []\n", "forwards no axis"),
+        (
+            "wheel",
+            "[scroll]\nmode = \"throttle\"\n[mouse_look]\nenabled = true\nforward = [\"REL_WHEEL\"]\n",
+            "the scroll throttle reads the wheel",
+        ),
+        (
+            "conflict",
+            "[mouse_look]\nenabled = true\nhold = \"BTN_RIGHT\"\n[buttons]\nBTN_RIGHT = \"BTN_SELECT\"\n",
+            "BTN_RIGHT",
+        ),
+    ] {
+        let output = run(name, &format!("log_backend = \"stderr\"\n{}", config), "0 EV_REL REL_X 10\n");
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{}: {}", name, log);
+        assert!(log.contains(message), "{}: {}", name, log);
+    }
+}
@@ -0,0 +1,91 @@
+// Marshals the D-Bus messages the desktop notifications send and reads the
+// reply headers. The Hello call was checked against dbus-daemon, the
+// ServiceUnknown reply is what it sends without a notification server.
+
+// the bus thread checks for METHOD_RETURN, no test does
+#[allow(dead_code)]
+#[path = "../src/dbus/wire.rs"]
+mod dbus;
+
+fn service_unknown() -> Vec<u8> {
+    std::fs::read(format!("{}/tests/fixtures/dbus/service_unknown.bin", env!("CARGO_MANIFEST_DIR"))).unwrap()
+}
+
+#[test]
+fn hello_has_the_header_fields_dbus_daemon_expects() {
+    let hello = dbus::method_call(
+        1,
+        &dbus::Call {
+            destination: "org.freedesktop.DBus",
+            path: "/org/freedesktop/DBus",
+            interface: "org.freedesktop.DBus",
+            member: "Hello",
+            signature: "",
+            body: Vec::new(),
+        },
+    );
+    assert_eq!(&hello[..16], [b'l', 1, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 109, 0, 0, 0]);
+    // PATH, an object path variant
+    assert_eq!(&hello[16..24], [1, 1, b'o', 0, 21, 0, 0, 0]);
+    // the last field padded to 8, no body
+    assert_eq!(hello.len(), 128);
+    assert_eq!(dbus::message_len(hello[..16].try_into().unwrap()), Some(128));
+}
+
+#[test]
+fn notify_body_pads_the_hints_to_their_dict_entries() {
+    let body = dbus::notify_body("a", "", "s", "b", 2);
+    assert_eq!(body.len(), 68);
+    // no actions
+    assert_eq!(&body[36..40], [0; 4]);
+    // 16 bytes of hints after the padding to 8
+    assert_eq!(&body[40..48], [16, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(&body[48..52], [7, 0, 0, 0]);
+    assert_eq!(&body[52..60], b"urgency\0");
+    assert_eq!(&body[60..64], [1, b'y', 0, 2]);
+    // the default expiry
+    assert_eq!(&body[64..], [0xff; 4]);
+}
+
+#[test]
+fn notify_call_carries_its_signature() {
+    let body = dbus::notify_body("mouse2joy", "input-gaming", "Profile switched", "Now using \"slow\"", 0);
+    let call = dbus::method_call(
+        7,
+        &dbus::Call {
+            destination: "org.freedesktop.Notifications",
+            path: "/org/freedesktop/Notifications",
+            interface: "org.freedesktop.Notifications",
+            member: "Notify",
+            signature: dbus::NOTIFY_SIGNATURE,
+            body: body.clone(),
+        },
+    );
+    assert!(call.windows(15).any(|window| window == b"\x0dsusssasa{sv}i\0"));
+    assert!(call.ends_with(&body));
+    assert_eq!((call.len() - body.len()) % 8, 0);
+    assert_eq!(dbus::message_len(call[..16].try_into().unwrap()), Some(call.len()));
+}
+
+#[test]
+fn error_reply_names_the_error() {
+    let reply = service_unknown();
+    assert_eq!(dbus::message_len(reply[..16].try_into().unwrap()), Some(reply.len()));
+    assert_eq!(
+        dbus::header(&reply),
+        Some(dbus::Header {
+            kind: dbus::ERROR,
+            reply_serial: Some(2),
+            error_name: Some(String::from("org.freedesktop.DBus.Error.ServiceUnknown")),
+            unix_fds: 0,
+        })
+    );
+}
+
+#[test]
+fn broken_messages_are_refused() {
+    assert_eq!(dbus::message_len(&[b'x'; 16]), None);
+    let mut reply = service_unknown();
+    reply.truncate(40);
+    assert_eq!(dbus::header(&reply), None);
+}
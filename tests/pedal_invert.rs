@@ -0,0 +1,135 @@
+// invert_throttle and invert_brake flip the sent range of that pedal only,
+// from the moment the virtual device exists until the exit releases it.
+
+use std::process::{Command, Output};
+
+fn run(name: &str, invert: &str, script: &str) -> Output {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-invert-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = format!(
+        "log_backend = \"stderr\"\n\
+         [pedals]\n{}\n\
+         [pedals.throttle]\nkeys = [\"KEY_W\"]\nrise_ms = 0\nfall_ms = 0\n\
+         [pedals.brake]\nkeys = [\"KEY_S\"]\nrise_ms = 0\nfall_ms = 0\n",
+        invert
+    );
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+fn events(output: &Output) -> Vec<String> {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+// both pedals pressed and released in turn, the throttle held down to the exit
+const SCRIPT: &str = "10 EV_KEY KEY_W 1\n\
+                      10 EV_KEY KEY_W 0\n\
+                      10 EV_KEY KEY_S 1\n\
+                      10 EV_KEY KEY_S 0\n\
+                      10 EV_KEY KEY_W 1\n";
+
+#[test]
+fn upright_pedals_rest_at_0() {
+    assert_eq!(
+        events(&run("upright", "", SCRIPT)),
+        [
+            "0.010 EV_ABS ABS_GAS 1023",
+            "0.020 EV_ABS ABS_GAS 0",
+            "0.030 EV_ABS ABS_BRAKE 1023",
+            "0.040 EV_ABS ABS_BRAKE 0",
+            "0.050 EV_ABS ABS_GAS 1023",
+            // released on exit
+            "0.050 EV_ABS ABS_GAS 0",
+        ]
+    );
+}
+
+#[test]
+fn an_inverted_brake_rests_at_full() {
+    assert_eq!(
+        events(&run("brake", "invert_brake = true", SCRIPT)),
+        [
+            // released as soon as the device is there
+            "0.000 EV_ABS ABS_BRAKE 1023",
+            "0.010 EV_ABS ABS_GAS 1023",
+            "0.020 EV_ABS ABS_GAS 0",
+            "0.030 EV_ABS ABS_BRAKE 0",
+            "0.040 EV_ABS ABS_BRAKE 1023",
+            "0.050 EV_ABS ABS_GAS 1023",
+            "0.050 EV_ABS ABS_GAS 0",
+        ]
+    );
+}
+
+#[test]
+fn an_inverted_throttle_rests_at_full() {
+    assert_eq!(
+        events(&run("throttle", "invert_throttle = true", SCRIPT)),
+        [
+            "0.000 EV_ABS ABS_GAS 1023",
+            "0.010 EV_ABS ABS_GAS 0",
+            "0.020 EV_ABS ABS_GAS 1023",
+            "0.030 EV_ABS ABS_BRAKE 1023",
+            "0.040 EV_ABS ABS_BRAKE 0",
+            "0.050 EV_ABS ABS_GAS 0",
+            "0.050 EV_ABS ABS_GAS 1023",
+        ]
+    );
+}
+
+#[test]
+fn both_inverted_start_and_end_released() {
+    let output = run("both", "invert_throttle = true\ninvert_brake = true", "10 EV_KEY KEY_S 1\n");
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_ABS ABS_GAS 1023",
+            "0.000 EV_ABS ABS_BRAKE 1023",
+            "0.010 EV_ABS ABS_BRAKE 0",
+            "0.010 EV_ABS ABS_BRAKE 1023",
+        ]
+    );
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(log.contains("Sending [ABS_GAS, ABS_BRAKE] inverted, full at rest"), "{}", log);
+}
+
+#[test]
+fn a_profile_switch_leaves_the_inversion() {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-invert-profile-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("config.toml"),
+        "log_backend = \"stderr\"\n\
+         [profile_keys]\nnext = \"KEY_F1\"\n\
+         [pedals.brake]\nkeys = [\"KEY_S\"]\n\
+         [profiles.inverted.pedals]\ninvert_brake = true\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("input.txt"), "10 EV_KEY KEY_F1 1\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(events(&output).is_empty(), "{}", log);
+    assert!(log.contains("also changes pedals.invert_brake, these need a restart"), "{}", log);
+}
@@ -0,0 +1,199 @@
+// [[pipelines]] run several wheels side by side, each from its own input
+// with the settings of its profile. The input scripts stand in for the mice,
+// one for each pipeline.
+
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+const CONFIG: &str = "\
+sensitivity = 100
+log_backend = \"stderr\"
+
+[[pipelines]]
+name = \"P1\"
+
+[[pipelines]]
+name = \"P2\"
+profile = \"kid\"
+
+[profiles.kid]
+sensitivity = 50
+";
+
+fn dir(name: &str, config: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-pipelines-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+    dir
+}
+
+fn run(name: &str, config: &str, scripts: &[&str], args: &[&str]) -> Output {
+    let dir = dir(name, config);
+    let mut command = Command::new(env!("CARGO_BIN_EXE_mouse2joy"));
+    command.arg("--config").arg(dir.join("config.toml")).arg("--dry-run").args(args);
+    for (i, script) in scripts.iter().enumerate() {
+        let path = dir.join(format!("input{}.txt", i));
+        std::fs::write(&path, script).unwrap();
+        command.arg("--input-script").arg(path);
+    }
+    let output = command.output().unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+// the event lines of a pipeline, without the time
+fn events(output: &Output, pipeline: &str) -> Vec<String> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.split_whitespace().skip(1).collect::<Vec<_>>())
+        .filter(|fields| fields.first() == Some(&pipeline))
+        .map(|fields| fields[1..].join(" "))
+        .collect()
+}
+
+#[test]
+fn every_pipeline_steers_with_its_own_settings() {
+    let script = "0 EV_REL REL_X 10\n";
+    let output = run("settings", CONFIG, &[script, script], &[]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert_eq!(events(&output, "P1"), ["EV_ABS ABS_X 1000", "EV_ABS ABS_X 0"]);
+    assert_eq!(events(&output, "P2"), ["EV_ABS ABS_X 500", "EV_ABS ABS_X 0"]);
+    assert!(log.contains("[P2] Using profile \"kid\""), "{}", log);
+    assert!(log.contains("[P1] sensitivity: 100"), "{}", log);
+}
+
+#[test]
+fn a_failing_pipeline_leaves_the_other_running() {
+    let dir = dir("failing", CONFIG);
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--dry-run")
+        .arg("--input-script")
+        .arg(dir.join("missing.txt"))
+        .arg("--input-script")
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            std::io::Write::write_all(&mut child.stdin.take().unwrap(), b"0 EV_REL REL_X 10\n")?;
+            child.wait_with_output()
+        })
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    // the error of P1 once P2 is done
+    assert_eq!(output.status.code(), Some(2));
+    assert!(events(&output, "P1").is_empty());
+    assert_eq!(events(&output, "P2"), ["EV_ABS ABS_X 500", "EV_ABS ABS_X 0"]);
+}
+
+#[test]
+fn the_virtual_devices_carry_the_pipeline_name() {
+    let output = run("names", CONFIG, &[], &["--print-sdl-mapping"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let mappings = String::from_utf8(output.stdout).unwrap();
+    let names: Vec<&str> = mappings.lines().filter_map(|line| line.split(',').nth(1)).collect();
+    assert_eq!(names, ["mouse2joy P1", "mouse2joy P2"]);
+}
+
+#[test]
+fn pipelines_are_checked_before_any_starts() {
+    let script = "0 EV_REL REL_X 10\n";
+    for (name, config, scripts, args, code) in [
+        ("unnamed", "[[pipelines]]\n", vec![script], vec![], 3),
+        ("twice", "[[pipelines]]\nname = \"P1\"\n[[pipelines]]\nname = \"P1\"\n", vec![script, script], vec![], 3),
+        ("profile", "[[pipelines]]\nname = \"P1\"\nprofile = \"nope\"\n", vec![script], vec![], 3),
+        ("scripts", CONFIG, vec![script], vec![], 2),
+        ("cli-profile", CONFIG, vec![script, script], vec!["--profile", "kid"], 2),
+    ] {
+        let output = run(name, config, &scripts, &args);
+        assert_eq!(output.status.code(), Some(code), "{}: {}", name, String::from_utf8_lossy(&output.stderr));
+        assert!(String::from_utf8_lossy(&output.stdout).is_empty(), "{}", name);
+    }
+}
+
+// the log lines of each pipeline as they are written
+fn lines(output: impl Read + Send + 'static) -> [Receiver<String>; 2] {
+    let (p1, p1_lines) = mpsc::channel();
+    let (p2, p2_lines) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(output).lines().map_while(Result::ok) {
+            let sender = if line.contains("[P1]") {
+                &p1
+            } else if line.contains("[P2]") {
+                &p2
+            } else {
+                continue;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    [p1_lines, p2_lines]
+}
+
+// the lines of every pipeline up to its first one containing `text`
+fn until(pipelines: &[Receiver<String>; 2], text: &str) -> Vec<String> {
+    let mut seen = Vec::new();
+    for lines in pipelines {
+        loop {
+            let line = lines
+                .recv_timeout(Duration::from_secs(10))
+                .unwrap_or_else(|_| panic!("no \"{}\" in {:?}", text, seen));
+            let found = line.contains(text);
+            seen.push(line);
+            if found {
+                break;
+            }
+        }
+    }
+    seen
+}
+
+#[test]
+fn signals_reach_every_pipeline() {
+    let dir = dir("signals", CONFIG);
+    let script = "0 EV_REL REL_X 10\n10000 wait\n10000 wait\n10000 wait\n";
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--dry-run")
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stderr = lines(child.stderr.take().unwrap());
+    let pid = Pid::from_raw(child.id() as i32);
+
+    // a signal sent once both are waiting is handled by both before their next step
+    until(&stderr, "Waiting");
+    kill(pid, Signal::SIGUSR1).unwrap();
+    until(&stderr, "Paused");
+    until(&stderr, "Waiting");
+    kill(pid, Signal::SIGUSR2).unwrap();
+    let seen = until(&stderr, "State:");
+    until(&stderr, "Waiting");
+    kill(pid, Signal::SIGTERM).unwrap();
+    until(&stderr, "Received a termination signal");
+
+    let status = child.wait().unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(status.success());
+    let state = |pipeline: &str| seen.iter().find(|line| line.contains(&format!("[{}] State:", pipeline))).unwrap();
+    assert!(state("P1").contains("State: paused") && state("P1").contains("sensitivity 100"), "{}", state("P1"));
+    assert!(state("P2").contains("State: paused") && state("P2").contains("sensitivity 50"), "{}", state("P2"));
+}
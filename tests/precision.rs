@@ -0,0 +1,82 @@
+// Holding the [precision] key scales the mouse steering down, only the
+// movement while held, on a dry run of an input script.
+
+mod common;
+
+use common::steering;
+use std::process::Output;
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run_logging("precision", name, &format!("log_backend = \"stderr\"\n{}", config), script, Some("info"))
+}
+
+const CONFIG: &str = "sensitivity = 10\n[precision]\nenabled = true\n";
+
+#[test]
+fn held_movement_adds_up_to_the_scaled_counts() {
+    let mut script = String::from("0 EV_REL REL_X 10\n0 EV_KEY KEY_LEFTSHIFT 1\n");
+    for _ in 0..10 {
+        script.push_str("1 EV_REL REL_X 1\n");
+    }
+    script.push_str("1 EV_KEY KEY_LEFTSHIFT 0\n1 EV_REL REL_X 1\n");
+    let output = run("exact", CONFIG, &script);
+    // 2.5 units a count, the halves carried over, and no step on the press
+    // or the release
+    assert_eq!(steering(&output), [100, 102, 105, 107, 110, 112, 115, 117, 120, 122, 125, 135]);
+}
+
+#[test]
+fn a_release_while_paused_is_not_missed() {
+    let output = run(
+        "pause",
+        &format!("{}[hotkeys]\npause = \"KEY_F8\"\n", CONFIG),
+        "0 EV_KEY KEY_LEFTSHIFT 1\n\
+         0 EV_REL REL_X 10\n\
+         10 EV_KEY KEY_F8 1\n\
+         # the desktop gets the release\n\
+         10 EV_KEY KEY_LEFTSHIFT 0\n\
+         10 EV_KEY KEY_F8 1\n\
+         10 EV_REL REL_X 10\n",
+    );
+    // scaled down, centered by the pause and then at full speed again
+    assert_eq!(steering(&output), [25, 0, 100]);
+}
+
+#[test]
+fn the_key_is_used_up_by_precision() {
+    let output = run(
+        "key",
+        "sensitivity = 10\n[precision]\nenabled = true\nkey = \"BTN_SIDE\"\nscale = 0.5\n\
+         [buttons]\nBTN_SIDE = \"BTN_TR\"\n",
+        "0 EV_REL REL_X 10\n",
+    );
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{}", log);
+    assert!(log.contains("BTN_SIDE"), "{}", log);
+}
+
+#[test]
+fn the_state_log_shows_it_held() {
+    let output = run(
+        "state",
+        "sensitivity = 10\n[precision]\nenabled = true\nscale = 0.5\n[hotkeys]\nlog_state = \"KEY_F10\"\n",
+        "0 EV_KEY KEY_F10 1\n\
+         0 EV_KEY KEY_F10 0\n\
+         0 EV_KEY KEY_LEFTSHIFT 1\n\
+         0 EV_KEY KEY_F10 1\n",
+    );
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(log.contains("sensitivity 10, profile base"), "{}", log);
+    assert!(log.contains("sensitivity 10 (precision x0.5), profile base"), "{}", log);
+}
+
+#[test]
+fn the_scale_has_to_slow_down() {
+    for scale in ["0.0", "-0.5", "2.0"] {
+        let output = run("scale", &format!("[precision]\nenabled = true\nscale = {}\n", scale), "0 EV_REL REL_X 10\n");
+        let log = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{}", log);
+        assert!(log.contains("precision.scale must be above 0 and at most 1"), "{}", log);
+    }
+}
@@ -0,0 +1,79 @@
+// Started as root, mouse2joy becomes the run_as or sudo user once the wheel
+// is set up. Only run as root, otherwise there is nothing to drop.
+
+use std::os::unix::fs::MetadataExt;
+use std::process::{Command, Output};
+
+fn root() -> bool {
+    std::fs::metadata("/proc/self").is_ok_and(|proc| proc.uid() == 0)
+}
+
+fn run(name: &str, config: &str, sudo_uid: Option<&str>, args: &[&str]) -> Output {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-privileges-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), format!("sensitivity = 100\nlog_backend = \"stderr\"\n{}", config)).unwrap();
+    std::fs::write(dir.join("input.txt"), "0 EV_REL REL_X 10\n").unwrap();
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_mouse2joy"));
+    command
+        .env("RUST_LOG", "debug")
+        .env_remove("SUDO_UID")
+        .env("XDG_RUNTIME_DIR", &dir)
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .args(args);
+    if let Some(uid) = sudo_uid {
+        command.env("SUDO_UID", uid);
+    }
+    let output = command.output().unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+#[test]
+fn run_as_switches_before_the_input_is_read() {
+    if !root() {
+        return;
+    }
+    let output = run("run-as", "run_as = \"nobody\"\n", None, &[]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(log.contains("Running as nobody (uid 65534) from now on"), "{}", log);
+    assert!(log.find("Running as nobody").unwrap() < log.find("Steering:").unwrap(), "{}", log);
+}
+
+#[test]
+fn sudo_names_the_user_without_run_as() {
+    if !root() {
+        return;
+    }
+    let output = run("sudo", "", Some("65534"), &[]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(log.contains("Running as nobody (uid 65534) from now on"), "{}", log);
+}
+
+#[test]
+fn keep_root_stays_root() {
+    if !root() {
+        return;
+    }
+    let output = run("keep", "run_as = \"nobody\"\n", Some("65534"), &["--keep-root"]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", log);
+    assert!(!log.contains("Running as"), "{}", log);
+}
+
+#[test]
+fn an_unknown_user_is_a_config_error() {
+    if !root() {
+        return;
+    }
+    let output = run("unknown", "run_as = \"no-such-user-here\"\n", None, &[]);
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{}", log);
+    assert!(log.contains("run_as: there is no user \"no-such-user-here\""), "{}", log);
+}
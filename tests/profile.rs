@@ -0,0 +1,102 @@
+// Exported profiles carry every effective setting: importing one into a
+// different configuration and exporting it again gives back the same file.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn mouse2joy(config: &Path, args: &[&str]) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(config)
+        .args(args)
+        .status()
+        .unwrap()
+        .code()
+        .unwrap()
+}
+
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-profile-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+const SHARED: &str = "\
+# tuned at home
+sensitivity = 30
+steer_while_held = \"BTN_LEFT\"
+[buttons]
+KEY_A = \"BTN_SOUTH\"
+[profiles.ets2]
+sensitivity = 12
+[profiles.ets2.pedals]
+throttle_key = \"KEY_W\"
+[profiles.ets2.buttons]
+KEY_B = { axis = \"ABS_GAS\" }
+";
+
+// the friend's own settings must not leak into the imported profile
+const FRIEND: &str = "\
+# the friend's config
+sensitivity = 50
+scroll = { mode = \"throttle\" }
+[buttons]
+KEY_C = \"BTN_EAST\"
+";
+
+#[test]
+fn export_import_round_trip() {
+    let dir = scratch("round-trip");
+    std::fs::write(dir.join("shared.toml"), SHARED).unwrap();
+    std::fs::write(dir.join("friend.toml"), FRIEND).unwrap();
+    let exported = dir.join("ets2.toml");
+    let again = dir.join("again.toml");
+    let exported_arg = exported.to_str().unwrap();
+
+    assert_eq!(mouse2joy(&dir.join("shared.toml"), &["--export-profile", "ets2", exported_arg]), 0);
+    assert_eq!(mouse2joy(&dir.join("friend.toml"), &["--import-profile", exported_arg]), 0);
+    assert_eq!(mouse2joy(&dir.join("friend.toml"), &["--export-profile", "ets2", again.to_str().unwrap()]), 0);
+
+    let friend = std::fs::read_to_string(dir.join("friend.toml")).unwrap();
+    let (exported, again) = (std::fs::read_to_string(&exported).unwrap(), std::fs::read_to_string(&again).unwrap());
+    std::fs::remove_dir_all(dir).unwrap();
+    assert_eq!(exported, again);
+    assert!(exported.contains("sensitivity = 12"));
+    assert!(exported.contains("steer_while_held = \"BTN_LEFT\""));
+    // the rest of the file is left as it was
+    assert!(friend.starts_with("# the friend's config\nsensitivity = 50\n"));
+    assert!(friend.contains("KEY_C = \"BTN_EAST\""));
+}
+
+#[test]
+fn import_refuses_to_replace_a_profile_without_force() {
+    let dir = scratch("force");
+    std::fs::write(dir.join("shared.toml"), SHARED).unwrap();
+    let exported = dir.join("ets2.toml");
+    let exported_arg = exported.to_str().unwrap();
+
+    assert_eq!(mouse2joy(&dir.join("shared.toml"), &["--export-profile", "ets2", exported_arg]), 0);
+    let refused = mouse2joy(&dir.join("shared.toml"), &["--import-profile", exported_arg]);
+    let unchanged = std::fs::read_to_string(dir.join("shared.toml")).unwrap();
+    let forced = mouse2joy(&dir.join("shared.toml"), &["--import-profile", exported_arg, "--force"]);
+    std::fs::remove_dir_all(dir).unwrap();
+    assert_eq!(refused, 2);
+    assert_eq!(unchanged, SHARED);
+    assert_eq!(forced, 0);
+}
+
+#[test]
+fn import_rejects_newer_formats_and_unknown_settings() {
+    let dir = scratch("invalid");
+    let config = dir.join("config.toml");
+    std::fs::write(dir.join("newer.toml"), "format_version = 99\nname = \"x\"\n[settings]\n").unwrap();
+    std::fs::write(dir.join("typo.toml"), "format_version = 1\nname = \"x\"\n[settings]\nsensitivty = 3\n").unwrap();
+
+    let newer = mouse2joy(&config, &["--import-profile", dir.join("newer.toml").to_str().unwrap()]);
+    let typo = mouse2joy(&config, &["--import-profile", dir.join("typo.toml").to_str().unwrap()]);
+    let written = config.exists();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert_eq!(newer, 3);
+    assert_eq!(typo, 3);
+    assert!(!written);
+}
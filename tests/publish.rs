@@ -0,0 +1,96 @@
+// The published wheel state as a UDP listener gets it: newline delimited
+// JSON records, no more of them than the rate allows.
+
+use std::collections::BTreeMap;
+use std::net::UdpSocket;
+use std::process::Command;
+use std::time::Duration;
+
+// the records received while mouse2joy runs `script` publishing at `rate`,
+// with their fields
+fn publish(name: &str, rate: u32, script: &str) -> Vec<BTreeMap<String, String>> {
+    let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let dir = std::env::temp_dir().join(format!("mouse2joy-publish-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = format!("sensitivity = 100\n[publish]\nudp = \"{}\"\nrate = {}\n", address, rate);
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    listener.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+    let mut records = Vec::new();
+    let mut buf = [0; 1024];
+    while let Ok(len) = listener.recv(&mut buf) {
+        let record = std::str::from_utf8(&buf[..len]).unwrap();
+        records.push(parse(record));
+    }
+    records
+}
+
+// the flat records published, values as written
+fn parse(record: &str) -> BTreeMap<String, String> {
+    let fields = record.strip_suffix("}\n").and_then(|record| record.strip_prefix('{'));
+    let fields = fields.unwrap_or_else(|| panic!("not a JSON record: {:?}", record));
+    fields
+        .split(", ")
+        .map(|field| {
+            let (key, value) = field.split_once(": ").unwrap();
+            (key.trim_matches('"').to_string(), value.to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn records_carry_the_wheel_state() {
+    // the wait gives the writer thread time to send it
+    let records = publish(
+        "state",
+        30,
+        "0 EV_REL REL_X -10\n\
+         0 EV_SYN 0 0\n\
+         100 wait\n",
+    );
+    assert_eq!(records.len(), 1, "{:?}", records);
+    let expected = [
+        ("angle_deg", "-100.0"),
+        ("brake", "0.000"),
+        ("low_battery", "false"),
+        ("precision", "false"),
+        ("profile", "null"),
+        ("throttle", "0.000"),
+        ("throttle_hold", "false"),
+    ];
+    assert_eq!(records[0], expected.map(|(key, value)| (key.to_string(), value.to_string())).into());
+}
+
+#[test]
+fn the_rate_limit_sends_only_the_latest_state() {
+    let records = publish(
+        "rate",
+        10,
+        // 10 records a second: the first goes at once, the moves 50ms later
+        // wait until 100ms and go as one
+        "0 EV_REL REL_X 10\n\
+         0 EV_SYN 0 0\n\
+         50 wait\n\
+         0 EV_REL REL_X 10\n\
+         0 EV_SYN 0 0\n\
+         0 EV_REL REL_X 10\n\
+         0 EV_SYN 0 0\n\
+         200 EV_SYN 0 0\n\
+         100 wait\n",
+    );
+    let angles: Vec<&str> = records.iter().map(|record| record["angle_deg"].as_str()).collect();
+    assert_eq!(angles, ["100.0", "300.0"]);
+}
@@ -0,0 +1,104 @@
+// The pedal ramp on fixed timestamps: rising while pressed, falling once
+// released, shaped by the curve and cut by an overriding brake.
+
+// the pedals reshape and take over ramps too, no test does
+#[allow(dead_code)]
+#[path = "../src/pedals/ramp.rs"]
+mod ramp;
+
+use ramp::{Ramp, PEDAL_MAX};
+use std::time::{Duration, Instant};
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+// the values after each of `steps` milliseconds since the start
+fn values(ramp: &mut Ramp, start: Instant, steps: &[u64]) -> Vec<i32> {
+    steps
+        .iter()
+        .map(|step| {
+            ramp.advance(start + ms(*step));
+            ramp.value()
+        })
+        .collect()
+}
+
+#[test]
+fn a_press_rises_and_a_release_falls_linearly() {
+    let start = Instant::now();
+    let mut ramp = Ramp::new(ms(400), ms(200), 1.0, start);
+    assert_eq!(ramp.value(), 0);
+    assert!(ramp.is_settled());
+
+    ramp.set_pressed(true, start);
+    assert!(!ramp.is_settled());
+    assert_eq!(values(&mut ramp, start, &[100, 300, 400, 600]), [256, 767, PEDAL_MAX, PEDAL_MAX]);
+    assert!(ramp.is_settled());
+
+    ramp.set_pressed(false, start + ms(600));
+    assert_eq!(values(&mut ramp, start, &[650, 750, 800, 900]), [767, 256, 0, 0]);
+    assert!(ramp.is_settled());
+}
+
+#[test]
+fn the_curve_gives_a_gentler_start() {
+    let start = Instant::now();
+    let mut ramp = Ramp::new(ms(400), ms(400), 2.0, start);
+    ramp.set_pressed(true, start);
+    // the level squared: 0.25, 0.5 and 0.75 of the way up
+    assert_eq!(values(&mut ramp, start, &[100, 200, 300, 400]), [64, 256, 575, PEDAL_MAX]);
+
+    ramp.set_pressed(false, start + ms(400));
+    assert_eq!(values(&mut ramp, start, &[500, 600, 800]), [575, 256, 0]);
+}
+
+#[test]
+fn a_release_during_the_rise_falls_from_where_it_got() {
+    let start = Instant::now();
+    let mut ramp = Ramp::new(ms(400), ms(200), 1.0, start);
+    ramp.set_pressed(true, start);
+    ramp.set_pressed(false, start + ms(100));
+    assert_eq!(ramp.value(), 256);
+    // a quarter of the way up takes a quarter of the fall to come down
+    assert_eq!(values(&mut ramp, start, &[125, 150, 200]), [128, 0, 0]);
+    assert!(ramp.is_settled());
+
+    // pressed again partway down it rises from there
+    ramp.set_pressed(true, start + ms(200));
+    ramp.set_pressed(false, start + ms(300));
+    ramp.set_pressed(true, start + ms(325));
+    assert_eq!(values(&mut ramp, start, &[325, 425]), [128, 384]);
+}
+
+#[test]
+fn a_cut_drops_the_throttle_at_once() {
+    let start = Instant::now();
+    let mut throttle = Ramp::new(ms(400), ms(200), 1.0, start);
+    throttle.set_pressed(true, start);
+    throttle.advance(start + ms(300));
+    assert_eq!(throttle.value(), 767);
+
+    // the brake overriding it: cut and released while the brake is held
+    throttle.cut();
+    throttle.set_pressed(false, start + ms(300));
+    assert_eq!(throttle.value(), 0);
+    assert!(throttle.is_settled());
+    assert_eq!(values(&mut throttle, start, &[350, 500]), [0, 0]);
+
+    // once the brake is up the held key brings it back from zero
+    throttle.set_pressed(true, start + ms(500));
+    assert_eq!(values(&mut throttle, start, &[600]), [256]);
+}
+
+#[test]
+fn a_zero_span_jumps() {
+    let start = Instant::now();
+    let mut ramp = Ramp::new(Duration::ZERO, Duration::ZERO, 1.0, start);
+    ramp.set_pressed(true, start);
+    ramp.advance(start);
+    assert_eq!(ramp.value(), PEDAL_MAX);
+    ramp.set_pressed(false, start);
+    ramp.advance(start);
+    assert_eq!(ramp.value(), 0);
+}
@@ -0,0 +1,114 @@
+// [rotation] turns the movement of the steering mouse before it steers, and
+// --calibrate-rotation measures the angle to write there.
+
+mod common;
+
+use common::events;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Output, Stdio};
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run("rotation", name, &format!("sensitivity = 100\nlog_backend = \"stderr\"\n{}", config), script)
+}
+
+const DIAGONAL: &str = "0 EV_REL REL_X 10\n0 EV_REL REL_Y 5\n0 EV_SYN 0 0\n10 EV_REL REL_Y -3\n0 EV_SYN 0 0\n";
+
+#[test]
+fn an_angle_of_0_changes_nothing() {
+    let look = "[mouse_look]\nenabled = true\n";
+    let plain = run("plain", look, DIAGONAL);
+    let rotated = run("zero", &format!("{}[rotation]\nangle = 0.0\ncross = \"discard\"\n", look), DIAGONAL);
+    assert_eq!(events(&rotated), events(&plain));
+    assert!(!String::from_utf8_lossy(&rotated.stderr).contains("turned by"));
+}
+
+#[test]
+fn a_vertical_mouse_steers_with_its_y_axis() {
+    let output = run(
+        "vertical",
+        "[mouse_look]\nenabled = true\n[rotation]\nangle = 90\n",
+        "0 EV_REL REL_Y 10\n0 EV_SYN 0 0\n10 EV_REL REL_X 10\n0 EV_SYN 0 0\n",
+    );
+    // its X axis goes across, on to the pointer, the other way round
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_X 1000", "0.010 EV_REL REL_Y -10", "0.010 EV_ABS ABS_X 0"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Steering mouse movement turned by 90 degrees"));
+}
+
+#[test]
+fn the_cross_movement_can_be_dropped() {
+    let output = run(
+        "discard",
+        "[mouse_look]\nenabled = true\n[rotation]\nangle = 90\ncross = \"discard\"\n",
+        "0 EV_REL REL_Y 10\n0 EV_SYN 0 0\n10 EV_REL REL_X 10\n0 EV_SYN 0 0\n",
+    );
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_X 1000", "0.000 EV_ABS ABS_X 0"]);
+}
+
+#[test]
+fn fractions_of_a_count_add_up() {
+    // 0.866 of a count along 30 degrees each time, 8.66 in all
+    let output = run("fractions", "[rotation]\nangle = 30\n", &"10 EV_REL REL_X 1\n".repeat(10));
+    let events = events(&output);
+    assert_eq!(events[events.len() - 2], "0.100 EV_ABS ABS_X 900", "{:?}", events);
+    assert_eq!(events.len(), 10, "{:?}", events);
+}
+
+#[test]
+fn an_angle_past_180_is_a_config_error() {
+    let output = run("range", "[rotation]\nangle = 200\n", "0 EV_REL REL_X 1\n");
+    let log = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{}", log);
+    assert!(log.contains("rotation.angle must be between -180 and 180 degrees, got 200"), "{}", log);
+}
+
+fn calibrate(name: &str, config: &str, script: &str, answer: &str) -> (String, String) {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-calibrate-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+    std::fs::write(&path, config).unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(&path)
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--calibrate-rotation")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // without enough movement it exits before asking
+    if let Err(e) = child.stdin.take().unwrap().write_all(answer.as_bytes()) {
+        assert_eq!(e.kind(), ErrorKind::BrokenPipe);
+    }
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let config = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    (String::from_utf8_lossy(&output.stdout).into_owned(), config)
+}
+
+#[test]
+fn calibration_writes_the_measured_angle() {
+    // steering right moves the mouse down and right, measured until 2000 counts
+    let script = "10 EV_REL REL_X 87\n0 EV_REL REL_Y 50\n".repeat(25);
+    let (stdout, config) = calibrate("measure", "config_version = 1\nsensitivity = 20\n", &script, "y\n");
+    assert!(stdout.contains("Measured 29.9 degrees over 2007 counts"), "{}", stdout);
+    assert_eq!(config, "config_version = 1\nsensitivity = 20\n\n[rotation]\nangle = 29.9\n");
+
+    // a measured angle replaces the old one, whatever it turned before
+    let (_, config) = calibrate("again", "[rotation]\nangle = 90.0\n", "0 EV_REL REL_Y -1000\n", "y\n");
+    assert!(config.contains("[rotation]\nangle = -90.0\n"), "{}", config);
+}
+
+#[test]
+fn calibration_needs_movement_and_a_yes() {
+    let (stdout, config) = calibrate("little", "sensitivity = 20\n", "0 EV_REL REL_X 100\n", "y\n");
+    assert!(stdout.contains("The mouse moved only 100 counts"), "{}", stdout);
+    assert_eq!(config, "sensitivity = 20\n");
+
+    let (stdout, config) = calibrate("declined", "sensitivity = 20\n", "0 EV_REL REL_X 2000\n", "n\n");
+    assert!(stdout.contains("Measured 0 degrees"), "{}", stdout);
+    assert_eq!(config, "sensitivity = 20\n");
+}
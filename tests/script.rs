@@ -0,0 +1,605 @@
+// Regression tests running mouse2joy on a synthetic input script with a dry
+// run, so they need neither uinput nor a mouse.
+
+use std::process::Command;
+
+// run mouse2joy on the given config and script, returns the printed events
+fn run(name: &str, config: &str, script: &str) -> Vec<String> {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-script-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+#[test]
+fn steering_is_scaled_and_clamped() {
+    let events = run(
+        "steering",
+        "sensitivity = 100\n",
+        "0 EV_REL REL_X 10\n\
+         10 EV_REL REL_X 40\n\
+         10 EV_REL REL_X -100\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.010 EV_ABS ABS_X 4500",
+            "0.020 EV_ABS ABS_X -4500",
+            // centered on exit
+            "0.020 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn pedal_ramps_on_simulated_time() {
+    let events = run(
+        "ramp",
+        "tick_rate = 10\n\
+         [pedals.throttle]\nkeys = [\"KEY_W\"]\nrise_ms = 200\nfall_ms = 100\n",
+        "0 EV_KEY KEY_W 1\n\
+         300 EV_KEY KEY_W 0\n\
+         # let the throttle fall back\n\
+         200 EV_SYN 0 0\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.100 EV_ABS ABS_GAS 512",
+            "0.200 EV_ABS ABS_GAS 1023",
+            "0.400 EV_ABS ABS_GAS 0",
+        ]
+    );
+}
+
+#[test]
+fn keyboard_steering_blends_with_the_mouse() {
+    let events = run(
+        "keyboard-steering",
+        "sensitivity = 100\n\
+         tick_rate = 10\n\
+         [keyboard_steering]\nenabled = true\nlock_ms = 450\nreturn_ms = 450\n",
+        "0 EV_REL REL_X 10\n\
+         0 EV_KEY KEY_RIGHT 1\n\
+         200 EV_KEY KEY_LEFT 1\n\
+         # both held cancel out, back towards the mouse position\n\
+         100 EV_KEY KEY_RIGHT 0\n\
+         100 EV_KEY KEY_LEFT 0\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.100 EV_ABS ABS_X 2000",
+            "0.200 EV_ABS ABS_X 3000",
+            "0.300 EV_ABS ABS_X 2000",
+            "0.400 EV_ABS ABS_X 1000",
+            // centered on exit
+            "0.400 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn scroll_wheel_steps_a_held_throttle() {
+    let events = run(
+        "scroll-throttle",
+        "[scroll]\nmode = \"throttle\"\nstep = 40\n",
+        "0 EV_REL REL_WHEEL 1\n\
+         100 EV_REL REL_WHEEL 2\n\
+         # already at full throttle\n\
+         100 EV_REL REL_WHEEL 1\n\
+         100 EV_REL REL_WHEEL -1\n\
+         100 EV_KEY BTN_MIDDLE 1\n\
+         0 EV_KEY BTN_MIDDLE 0\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_THROTTLE 409",
+            "0.100 EV_ABS ABS_THROTTLE 1023",
+            "0.300 EV_ABS ABS_THROTTLE 613",
+            "0.400 EV_ABS ABS_THROTTLE 0",
+        ]
+    );
+}
+
+#[test]
+fn hi_res_wheel_moves_the_throttle_in_fractions() {
+    let events = run(
+        "scroll-hi-res",
+        "[scroll]\nmode = \"throttle\"\nstep = 40\n",
+        "0 EV_REL REL_WHEEL_HI_RES 60\n\
+         # the legacy event for the same motion is ignored\n\
+         100 EV_REL REL_WHEEL_HI_RES 60\n\
+         0 EV_REL REL_WHEEL 1\n\
+         100 EV_REL REL_WHEEL_HI_RES -30\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_THROTTLE 204",
+            "0.100 EV_ABS ABS_THROTTLE 409",
+            "0.200 EV_ABS ABS_THROTTLE 306",
+            // released on exit
+            "0.200 EV_ABS ABS_THROTTLE 0",
+        ]
+    );
+}
+
+#[test]
+fn velocity_steering_returns_to_center() {
+    let events = run(
+        "velocity-steering",
+        "tick_rate = 10\n\
+         [steering]\nmode = \"velocity\"\ngain = 9.0\nwindow_ms = 100\nreturn_ms = 450\n",
+        "0 EV_REL REL_X 50\n\
+         # the mouse stopped, let the wheel come back\n\
+         600 EV_SYN 0 0\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 4500",
+            "0.100 EV_ABS ABS_X 3500",
+            "0.200 EV_ABS ABS_X 2500",
+            "0.300 EV_ABS ABS_X 1500",
+            "0.400 EV_ABS ABS_X 500",
+            "0.500 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn steering_only_accumulates_while_the_gate_is_held() {
+    let events = run(
+        "steer-while-held",
+        "sensitivity = 100\nsteer_while_held = \"BTN_LEFT\"\n",
+        "0 EV_REL REL_X 10\n\
+         10 EV_KEY BTN_LEFT 1\n\
+         10 EV_REL REL_X 5\n\
+         10 EV_KEY BTN_LEFT 0\n\
+         # released, the wheel holds its position\n\
+         10 EV_REL REL_X 20\n\
+         10 EV_KEY BTN_LEFT 1\n\
+         10 EV_REL REL_X -10\n",
+    );
+    assert_eq!(
+        events,
+        ["0.020 EV_ABS ABS_X 500", "0.060 EV_ABS ABS_X -500", "0.060 EV_ABS ABS_X 0"]
+    );
+}
+
+#[test]
+fn the_gate_is_let_go_of_by_a_pause() {
+    let events = run(
+        "steer-while-held-pause",
+        "sensitivity = 100\nsteer_while_held = \"BTN_LEFT\"\n[hotkeys]\npause = \"KEY_F8\"\n",
+        "0 EV_KEY BTN_LEFT 1\n\
+         0 EV_REL REL_X 10\n\
+         10 EV_KEY KEY_F8 1\n\
+         # the desktop gets the release\n\
+         10 EV_KEY BTN_LEFT 0\n\
+         10 EV_KEY KEY_F8 1\n\
+         # resumed, the gate has to be held again\n\
+         10 EV_REL REL_X 10\n",
+    );
+    assert_eq!(events, ["0.000 EV_ABS ABS_X 1000", "0.010 EV_ABS ABS_X 0"]);
+}
+
+#[test]
+fn snap_restores_the_updated_mouse_position() {
+    let events = run(
+        "snap",
+        "sensitivity = 100\n\
+         [buttons]\n\
+         BTN_SIDE = { action = \"snap_left\" }\n\
+         BTN_EXTRA = { action = \"snap_right\", release = \"stay\" }\n\
+         BTN_LEFT = { axis = \"ABS_GAS\" }\n",
+        "0 EV_REL REL_X 10\n\
+         10 EV_KEY BTN_SIDE 1\n\
+         # moving while snapped only updates the position underneath\n\
+         10 EV_REL REL_X 15\n\
+         10 EV_KEY BTN_SIDE 0\n\
+         10 EV_KEY BTN_EXTRA 1\n\
+         10 EV_KEY BTN_EXTRA 0\n\
+         10 EV_REL REL_X -5\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.010 EV_ABS ABS_X -4500",
+            "0.030 EV_ABS ABS_X 2500",
+            "0.040 EV_ABS ABS_X 4500",
+            "0.060 EV_ABS ABS_X 4000",
+            "0.060 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn huge_deltas_saturate_instead_of_overflowing() {
+    let events = run(
+        "huge-deltas",
+        "sensitivity = 9000\n",
+        "0 EV_REL REL_X 2147483647\n\
+         10 EV_REL REL_X -2147483648\n\
+         # a single event moves lock to lock at most\n\
+         10 EV_REL REL_X 1\n\
+         10 EV_REL REL_X 2147483647\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 4500",
+            "0.010 EV_ABS ABS_X -4500",
+            "0.020 EV_ABS ABS_X 4500",
+            // centered on exit
+            "0.020 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn huge_deltas_in_velocity_mode_saturate() {
+    let events = run(
+        "huge-velocity",
+        "[steering]\nmode = \"velocity\"\n",
+        "0 EV_REL REL_X 2147483647\n\
+         0 EV_REL REL_X 2147483647\n",
+    );
+    assert_eq!(events.first().map(String::as_str), Some("0.000 EV_ABS ABS_X 4500"));
+}
+
+#[test]
+fn profile_keys_switch_the_sensitivity() {
+    let events = run(
+        "profile-keys",
+        "sensitivity = 100\n\
+         [profile_keys]\nnext = \"KEY_LEFTCTRL+KEY_F9\"\nselect = { slow = \"KEY_F10\" }\n\
+         [profiles.fast]\nsensitivity = 200\n\
+         [profiles.slow]\nsensitivity = 50\n",
+        "0 EV_REL REL_X 10\n\
+         # cycles from the base settings to the first profile\n\
+         10 EV_KEY KEY_LEFTCTRL 1\n\
+         0 EV_KEY KEY_F9 1\n\
+         0 EV_KEY KEY_F9 0\n\
+         0 EV_KEY KEY_LEFTCTRL 0\n\
+         10 EV_REL REL_X 10\n\
+         # without the modifier nothing happens\n\
+         10 EV_KEY KEY_F9 1\n\
+         10 EV_KEY KEY_F9 0\n\
+         10 EV_REL REL_X 1\n\
+         10 EV_KEY KEY_F10 1\n\
+         10 EV_REL REL_X -10\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.020 EV_ABS ABS_X 3000",
+            "0.050 EV_ABS ABS_X 3200",
+            "0.070 EV_ABS ABS_X 2700",
+            "0.070 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn script_brakes_near_full_lock() {
+    let config = format!(
+        "sensitivity = 100\nscript = \"{}/scripts/auto_brake.rhai\"\n\
+         [pedals.brake]\nkeys = [\"KEY_S\"]\n",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let events = run(
+        "auto-brake",
+        &config,
+        "0 EV_REL REL_X 20\n\
+         10 EV_REL REL_X 20\n\
+         10 EV_REL REL_X -20\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 2000",
+            "0.010 EV_ABS ABS_X 4000",
+            "0.010 EV_ABS ABS_BRAKE 205",
+            "0.020 EV_ABS ABS_X 2000",
+            "0.020 EV_ABS ABS_BRAKE 0",
+            "0.020 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn failing_script_is_disabled() {
+    let script = std::env::temp_dir().join(format!("mouse2joy-failing-{}.rhai", std::process::id()));
+    std::fs::write(&script, "fn tick(input) { throw \"broken\"; }\n").unwrap();
+    let events = run(
+        "failing-script",
+        &format!("sensitivity = 100\nscript = \"{}\"\n", script.display()),
+        "0 EV_REL REL_X 10\n\
+         10 EV_REL REL_X 10\n",
+    );
+    std::fs::remove_file(script).unwrap();
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.010 EV_ABS ABS_X 2000",
+            "0.010 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn script_state_smooths_the_steering() {
+    let config = format!(
+        "sensitivity = 100\ntick_rate = 20\nscript = \"{}/scripts/smooth_steering.rhai\"\n",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let events = run(
+        "smooth-steering",
+        &config,
+        "0 EV_REL REL_X 20\n\
+         100 EV_SYN 0 0\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.050 EV_ABS ABS_X 1000",
+            "0.100 EV_ABS ABS_X 1500",
+            "0.100 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn hi_res_wheel_fractions_add_up_to_detents() {
+    let script = std::env::temp_dir().join(format!("mouse2joy-gears-{}.rhai", std::process::id()));
+    std::fs::write(
+        &script,
+        "fn tick(input) {\n\
+             if this.gear == () { this.gear = 0; }\n\
+             this.gear += input.wheel;\n\
+             #{ steering: this.gear * 1000 }\n\
+         }\n",
+    )
+    .unwrap();
+    let events = run(
+        "hi-res-detents",
+        &format!("script = \"{}\"\n", script.display()),
+        "0 EV_REL REL_WHEEL_HI_RES 60\n\
+         10 EV_REL REL_WHEEL_HI_RES 60\n\
+         0 EV_REL REL_WHEEL 1\n\
+         10 EV_REL REL_WHEEL_HI_RES 120\n\
+         0 EV_REL REL_WHEEL 1\n\
+         # half a detent back isn't a shift yet\n\
+         10 EV_REL REL_WHEEL_HI_RES -60\n\
+         10 EV_REL REL_WHEEL_HI_RES -60\n\
+         0 EV_REL REL_WHEEL -1\n",
+    );
+    std::fs::remove_file(script).unwrap();
+    assert_eq!(
+        events,
+        [
+            "0.010 EV_ABS ABS_X 1000",
+            "0.020 EV_ABS ABS_X 2000",
+            "0.040 EV_ABS ABS_X 1000",
+            "0.040 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn startup_sweep_turns_lock_to_lock() {
+    let events = run("sweep", "tick_rate = 8\nstartup_sweep = true\n", "1600 EV_SYN 0 0\n");
+    assert_eq!(
+        events,
+        [
+            "0.125 EV_ABS ABS_X 2250",
+            "0.250 EV_ABS ABS_X 3897",
+            "0.375 EV_ABS ABS_X 4500",
+            "0.500 EV_ABS ABS_X 3897",
+            "0.625 EV_ABS ABS_X 2250",
+            "0.750 EV_ABS ABS_X 0",
+            "0.875 EV_ABS ABS_X -2250",
+            "1.000 EV_ABS ABS_X -3897",
+            "1.125 EV_ABS ABS_X -4500",
+            "1.250 EV_ABS ABS_X -3897",
+            "1.375 EV_ABS ABS_X -2250",
+            "1.500 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn input_interrupts_the_startup_sweep() {
+    let events = run(
+        "sweep-interrupted",
+        "sensitivity = 100\ntick_rate = 8\nstartup_sweep = true\n",
+        "300 EV_REL REL_X 10\n\
+         10 EV_REL REL_X 10\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.125 EV_ABS ABS_X 2250",
+            "0.250 EV_ABS ABS_X 3897",
+            // straight to the live position
+            "0.300 EV_ABS ABS_X 1000",
+            "0.310 EV_ABS ABS_X 2000",
+            "0.310 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn failsafe_neutralizes_quiet_input() {
+    let events = run(
+        "failsafe",
+        "sensitivity = 100\n\
+         [failsafe]\ntimeout_ms = 500\n\
+         [pedals.throttle]\nkeys = [\"KEY_W\"]\nrise_ms = 0\n",
+        "0 EV_REL REL_X 10\n\
+         0 EV_KEY KEY_W 1\n\
+         1000 EV_REL REL_X 5\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.000 EV_ABS ABS_GAS 1023",
+            "0.500 EV_ABS ABS_X 0",
+            "0.500 EV_ABS ABS_GAS 0",
+            // fresh input starts from center, the key has to be pressed again
+            "1.000 EV_ABS ABS_X 500",
+            // centered on exit
+            "1.000 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn failsafe_can_hold_the_steering() {
+    let events = run(
+        "failsafe-hold",
+        "sensitivity = 100\n\
+         [failsafe]\ntimeout_ms = 500\nsteering = \"hold\"\n\
+         [scroll]\nmode = \"throttle\"\n",
+        "0 EV_REL REL_X 10\n\
+         0 EV_REL REL_WHEEL 3\n\
+         1000 EV_REL REL_X 5\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 1000",
+            "0.000 EV_ABS ABS_THROTTLE 306",
+            // the scroll throttle always drops
+            "0.500 EV_ABS ABS_THROTTLE 0",
+            "1.000 EV_ABS ABS_X 1500",
+            // centered on exit
+            "1.000 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn failsafe_centers_the_hat_and_releases_the_buttons() {
+    let events = run(
+        "failsafe-held",
+        "sensitivity = 100\n\
+         [failsafe]\ntimeout_ms = 500\n\
+         [buttons]\nBTN_SIDE = \"BTN_TR\"\n\
+         [hat]\nenabled = true\n",
+        "0 EV_KEY BTN_SIDE 1\n\
+         0 EV_KEY KEY_LEFT 1\n\
+         1000 EV_REL REL_X 5\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_KEY BTN_TR 1",
+            "0.000 EV_ABS ABS_HAT0X -1",
+            "0.000 EV_ABS ABS_HAT0Y 0",
+            "0.500 EV_ABS ABS_HAT0X 0",
+            "0.500 EV_ABS ABS_HAT0Y 0",
+            "0.500 EV_KEY BTN_TR 0",
+            "1.000 EV_ABS ABS_X 500",
+            // centered on exit
+            "1.000 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn events_queued_before_the_device_opens_are_dropped() {
+    let script = "0 EV_REL REL_X 10\n\
+                  5 EV_REL REL_X 10\n\
+                  10 open\n\
+                  5 EV_REL REL_X 5\n";
+    let events = run("stale", "sensitivity = 100\n", script);
+    assert_eq!(
+        events,
+        [
+            // both queued moves are gone, only the one after opening counts
+            "0.020 EV_ABS ABS_X 500",
+            "0.020 EV_ABS ABS_X 0",
+        ]
+    );
+
+    // the queued move younger than stale_input_ms is kept and comes first
+    let events = run("stale-kept", "sensitivity = 100\nstale_input_ms = 12\n", script);
+    assert_eq!(
+        events,
+        [
+            "0.015 EV_ABS ABS_X 1000",
+            "0.020 EV_ABS ABS_X 1500",
+            "0.020 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn steering_axis_spans_the_whole_wheel_with_the_mouse_on_top() {
+    let events = run(
+        "axis",
+        "sensitivity = 100\n[axis_steering]\nenabled = true\ndeadzone = 10\n",
+        "range ABS_X -100 100\n\
+         0 EV_ABS ABS_X 55\n\
+         10 EV_REL REL_X 1\n\
+         10 EV_ABS ABS_X 100\n\
+         # inside the deadzone, only the mouse correction is left\n\
+         10 EV_ABS ABS_X 5\n\
+         10 EV_ABS ABS_Y 50\n",
+    );
+    assert_eq!(
+        events,
+        [
+            "0.000 EV_ABS ABS_X 2250",
+            "0.010 EV_ABS ABS_X 2350",
+            "0.020 EV_ABS ABS_X 4500",
+            "0.030 EV_ABS ABS_X 100",
+            // centered on exit
+            "0.030 EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn input_window_sums_the_movement_without_losing_counts() {
+    let script: String = (0..12).map(|i| format!("{} EV_REL REL_X 10\n0 EV_SYN 0 0\n", i.min(1))).collect();
+    let events = run("input-window", "sensitivity = 10\ninput_window_ms = 4\n", &script);
+    assert_eq!(
+        events,
+        [
+            // the first event opens a window, reports at its end count too
+            "0.004 EV_ABS ABS_X 500",
+            "0.009 EV_ABS ABS_X 1000",
+            // the input ended inside the last window
+            "0.011 EV_ABS ABS_X 1200",
+            "0.011 EV_ABS ABS_X 0",
+        ]
+    );
+}
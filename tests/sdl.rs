@@ -0,0 +1,36 @@
+// The joystick GUID has to match what SDL computes for the same device, or
+// no mapping line applies to it. SDL_crc16 is CRC-16/ARC, whose check value
+// for "123456789" is 0xbb3d.
+
+#[allow(dead_code)]
+#[path = "../src/sdl.rs"]
+mod sdl;
+
+use evdev::{BusType, InputId};
+
+fn hex(guid: [u8; 16]) -> String {
+    guid.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+fn the_name_crc_is_sdl_crc16() {
+    let guid = sdl::guid(InputId::new(BusType::BUS_USB, 0x045e, 0x028e, 0x0114), "123456789");
+    assert_eq!(guid[2..4], [0x3d, 0xbb]);
+}
+
+#[test]
+fn a_known_device_has_its_pinned_guid() {
+    // an Xbox 360 pad: bus, name CRC, vendor, product and version, all
+    // little endian
+    let id = InputId::new(BusType::BUS_USB, 0x045e, 0x028e, 0x0114);
+    assert_eq!(hex(sdl::guid(id, "Microsoft X-Box 360 pad")), "030081b85e0400008e02000014010000");
+    // the virtual wheel
+    let id = InputId::new(BusType::BUS_USB, 0x1234, 0x5678, 0x111);
+    assert_eq!(hex(sdl::guid(id, "mouse2joy")), "03001780341200007856000011010000");
+}
+
+#[test]
+fn without_a_vendor_the_name_fills_in() {
+    let id = InputId::new(BusType::BUS_VIRTUAL, 0, 0, 0);
+    assert_eq!(hex(sdl::guid(id, "A Long Device Name")), "060088cb41204c6f6e67204465766900");
+}
@@ -0,0 +1,106 @@
+// SIGUSR1 pauses and resumes mouse2joy, SIGUSR2 logs its state. The input
+// script's `wait` steps keep it running until the signal arrives.
+
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+// the lines of a child's output as they are written
+fn lines(output: impl Read + Send + 'static) -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(output).lines().map_while(Result::ok) {
+            let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+// the lines up to the first one containing `text`
+fn until(lines: &Receiver<String>, text: &str) -> Vec<String> {
+    let mut seen = Vec::new();
+    loop {
+        let line = lines
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap_or_else(|_| panic!("no \"{}\" in {:?}", text, seen));
+        let found = line.contains(text);
+        seen.push(line);
+        if found {
+            return seen;
+        }
+    }
+}
+
+fn signal(child: &Child, signal: Signal) {
+    kill(Pid::from_raw(child.id() as i32), signal).unwrap();
+}
+
+#[test]
+fn sigusr1_pauses_and_sigusr2_logs_the_state() {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-signals-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), "sensitivity = 100\nlog_backend = \"stderr\"\n").unwrap();
+    std::fs::write(
+        dir.join("input.txt"),
+        "0 EV_REL REL_X 10\n\
+         10000 wait\n\
+         # ignored while paused\n\
+         0 EV_REL REL_X 20\n\
+         10000 wait\n\
+         10000 wait\n\
+         0 EV_REL REL_X 10\n",
+    )
+    .unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = lines(child.stdout.take().unwrap());
+    let stderr = lines(child.stderr.take().unwrap());
+
+    // a signal sent once a wait step began is handled before the next step
+    until(&stderr, "Waiting");
+    signal(&child, Signal::SIGUSR1);
+    until(&stderr, "Paused");
+    until(&stderr, "Waiting");
+    signal(&child, Signal::SIGUSR2);
+    let state = until(&stderr, "State:").pop().unwrap();
+    until(&stderr, "Waiting");
+    signal(&child, Signal::SIGUSR1);
+    until(&stderr, "Resumed");
+
+    let status = child.wait().unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(status.success());
+    assert!(state.contains("State: paused, input from the input script, steering at 0 of 4500"), "{}", state);
+    assert!(state.contains("sensitivity 100, profile base, 1 input and 2 output events"), "{}", state);
+
+    // timestamps follow the real waits, only the events are compared
+    let events: Vec<String> =
+        stdout.iter().map(|line| line.split(' ').skip(1).collect::<Vec<_>>().join(" ")).collect();
+    assert_eq!(
+        events,
+        [
+            "EV_ABS ABS_X 1000",
+            // centered by the pause
+            "EV_ABS ABS_X 0",
+            // the paused REL_X 20 is gone, steering starts over from the center
+            "EV_ABS ABS_X 1000",
+            // centered on exit
+            "EV_ABS ABS_X 0",
+        ]
+    );
+}
@@ -0,0 +1,122 @@
+// A single glitching REL_X delta is clamped or dropped, a real flick of
+// large but consistent deltas goes through untouched.
+
+#[path = "../src/spikes/detector.rs"]
+mod detector;
+
+mod common;
+
+use common::events;
+use detector::Detector;
+use std::process::Output;
+
+// a fast flick to the right and back, as read from a 1000 Hz mouse
+const FLICK: [i32; 36] = [
+    1, 3, 6, 11, 19, 30, 44, 61, 80, 101, 122, 141, 156, 166, 170, 167, 158, 142, 120, 95, 68, 42, 20, 6, -4, -15,
+    -34, -60, -89, -118, -142, -156, -160, -149, -110, -52,
+];
+
+#[test]
+fn a_recorded_flick_goes_through() {
+    for discard in [false, true] {
+        let mut detector = Detector::new(None, 8.0, 100, discard);
+        let steered: Vec<Option<i32>> = FLICK.iter().map(|delta| detector.check(*delta)).collect();
+        assert_eq!(steered, FLICK.map(Some));
+        assert!(detector.average() > 50.0);
+    }
+}
+
+#[test]
+fn a_single_spike_is_clamped_or_dropped() {
+    let glitch = [2, -1, 3, 2, 2600, 1, -2];
+    let mut detector = Detector::new(None, 8.0, 100, false);
+    let clamped: Vec<Option<i32>> = glitch.iter().map(|delta| detector.check(*delta)).collect();
+    assert_eq!(clamped, [Some(2), Some(-1), Some(3), Some(2), Some(100), Some(1), Some(-2)]);
+    // the spike didn't count towards the average
+    assert!(detector.average() < 3.0);
+
+    let mut detector = Detector::new(None, 8.0, 100, true);
+    let dropped: Vec<Option<i32>> = glitch.iter().map(|delta| detector.check(-*delta)).collect();
+    assert_eq!(dropped, [Some(-2), Some(1), Some(-3), Some(-2), None, Some(-1), Some(2)]);
+}
+
+#[test]
+fn a_spike_in_the_middle_of_a_flick_is_measured_against_it() {
+    let mut detector = Detector::new(None, 8.0, 100, false);
+    for delta in &FLICK[..14] {
+        detector.check(*delta);
+    }
+    let plausible = (8.0 * detector.average()) as i32;
+    assert_eq!(detector.check(30000), Some(plausible));
+    assert_eq!(detector.check(170), Some(170));
+}
+
+#[test]
+fn only_one_delta_in_a_row_is_rejected() {
+    // the mouse really started moving that fast
+    let mut detector = Detector::new(None, 8.0, 100, true);
+    let steered: Vec<Option<i32>> = [1, 0, 400, 420, 410].iter().map(|delta| detector.check(*delta)).collect();
+    assert_eq!(steered, [Some(1), Some(0), None, Some(420), Some(410)]);
+}
+
+#[test]
+fn the_cap_goes_for_every_delta() {
+    let mut detector = Detector::new(Some(150), 8.0, 100, false);
+    let steered: Vec<Option<i32>> = FLICK.iter().map(|delta| detector.check(*delta)).collect();
+    let capped = FLICK.map(|delta| Some(delta.clamp(-150, 150)));
+    assert_eq!(steered, capped);
+}
+
+fn run(name: &str, config: &str, script: &str) -> Output {
+    common::run_logging("spikes", name, &format!("log_backend = \"stderr\"\n{}", config), script, Some("debug"))
+}
+
+const SCRIPT: &str = "10 EV_REL REL_X 3\n1 EV_REL REL_X 2\n1 EV_REL REL_X 2500\n1 EV_REL REL_X 3\n";
+
+#[test]
+fn the_glitch_no_longer_throws_the_wheel() {
+    let output = run("clamp", "sensitivity = 10\n[spike_rejection]\nenabled = true\n", SCRIPT);
+    assert_eq!(
+        events(&output),
+        [
+            "0.010 EV_ABS ABS_X 30",
+            "0.011 EV_ABS ABS_X 50",
+            "0.012 EV_ABS ABS_X 1050",
+            "0.013 EV_ABS ABS_X 1080",
+            "0.013 EV_ABS ABS_X 0",
+        ]
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Clamped a REL_X delta of 2500 to 100"), "{}", stderr);
+
+    let output = run("discard", "sensitivity = 10\n[spike_rejection]\nenabled = true\naction = \"discard\"\n", SCRIPT);
+    assert_eq!(
+        events(&output),
+        ["0.010 EV_ABS ABS_X 30", "0.011 EV_ABS ABS_X 50", "0.013 EV_ABS ABS_X 80", "0.013 EV_ABS ABS_X 0"]
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Dropped a REL_X delta of 2500"));
+}
+
+#[test]
+fn spikes_are_rejected_before_the_input_window_sums_them() {
+    let output = run(
+        "window",
+        "sensitivity = 10\ninput_window_ms = 20\n[spike_rejection]\nenabled = true\n",
+        SCRIPT,
+    );
+    // one update for the whole window
+    assert_eq!(events(&output), ["0.013 EV_ABS ABS_X 1080", "0.013 EV_ABS ABS_X 0"]);
+}
+
+#[test]
+fn off_by_default() {
+    let output = run("off", "sensitivity = 1\n", SCRIPT);
+    assert_eq!(events(&output)[2], "0.012 EV_ABS ABS_X 2505");
+}
+
+#[test]
+fn a_factor_below_two_is_refused() {
+    let output = run("factor", "[spike_rejection]\nenabled = true\nfactor = 1.5\n", "");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("spike_rejection.factor must be at least 2, got 1.5"));
+}
@@ -0,0 +1,38 @@
+// Decodes sample packets of each supported telemetry format. The fixtures
+// follow the published packet layouts byte for byte.
+
+#[path = "../src/telemetry/packet.rs"]
+mod packet;
+
+fn fixture(name: &str) -> Vec<u8> {
+    std::fs::read(format!("{}/tests/fixtures/telemetry/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+fn assert_speed(speed: Option<f32>, expected: f32) {
+    let speed = speed.unwrap();
+    assert!((speed - expected).abs() < 0.01, "{} km/h instead of {}", speed, expected);
+}
+
+#[test]
+fn codemasters_speed() {
+    assert_speed(packet::codemasters(&fixture("codemasters.bin")), 100.0);
+}
+
+#[test]
+fn codemasters_rejects_truncated_packets() {
+    let packet = fixture("codemasters.bin");
+    assert_eq!(packet::codemasters(&packet[..28]), None);
+    assert_eq!(packet::codemasters(&packet[..30]), None);
+}
+
+#[test]
+fn forza_speed() {
+    assert_speed(packet::forza(&fixture("forza_dash.bin")), 200.0);
+    assert_speed(packet::forza(&fixture("forza_sled.bin")), 50.0);
+}
+
+#[test]
+fn forza_ignores_menus_and_truncated_packets() {
+    assert_eq!(packet::forza(&fixture("forza_paused.bin")), None);
+    assert_eq!(packet::forza(&fixture("forza_sled.bin")[..200]), None);
+}
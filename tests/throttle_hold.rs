@@ -0,0 +1,126 @@
+// A source mapped to throttle_hold_toggle freezes the throttle where it is
+// until braking or toggling again, and lets go without a jump.
+
+use std::process::{Command, Output};
+
+fn run(name: &str, fall_ms: u32, script: &str) -> Output {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-hold-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = format!(
+        "log_backend = \"stderr\"\n\
+         [hotkeys]\npause = \"KEY_P\"\n\
+         [buttons]\nKEY_C = {{ action = \"throttle_hold_toggle\" }}\n\
+         [pedals.brake]\nkeys = [\"KEY_S\"]\nrise_ms = 0\nfall_ms = 0\n\
+         [pedals.throttle]\nkeys = [\"KEY_W\"]\nrise_ms = 0\nfall_ms = {}\n",
+        fall_ms
+    );
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    output
+}
+
+fn events(output: &Output) -> Vec<String> {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect()
+}
+
+#[test]
+fn the_throttle_stays_until_braking() {
+    let output = run(
+        "brake",
+        0,
+        "0 EV_KEY KEY_W 1\n\
+         10 EV_KEY KEY_C 1\n\
+         0 EV_KEY KEY_C 0\n\
+         10 EV_KEY KEY_W 0\n\
+         1000 EV_KEY KEY_S 1\n\
+         10 EV_KEY KEY_S 0\n",
+    );
+    let log = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert_eq!(
+        events(&output),
+        [
+            "0.000 EV_ABS ABS_GAS 1023",
+            // nothing on letting go of W
+            "1.020 EV_ABS ABS_GAS 0",
+            "1.020 EV_ABS ABS_BRAKE 1023",
+            "1.030 EV_ABS ABS_BRAKE 0",
+        ]
+    );
+    assert!(log.contains("Throttle held at 100%"), "{}", log);
+    assert!(log.contains("Throttle hold released, braking"), "{}", log);
+}
+
+#[test]
+fn toggled_off_the_throttle_ramps_down_from_the_held_value() {
+    let output = run(
+        "toggle",
+        0,
+        "0 EV_KEY KEY_W 1\n\
+         10 EV_KEY KEY_C 1\n\
+         10 EV_KEY KEY_W 0\n\
+         100 EV_KEY KEY_C 0\n\
+         0 EV_KEY KEY_C 1\n",
+    );
+    let log = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(log.contains("Throttle hold released, toggled off"), "{}", log);
+    // with a fall time of 0 the ramp drops at once, with one it falls
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_GAS 1023", "0.120 EV_ABS ABS_GAS 0"]);
+
+    let output = run(
+        "ramp",
+        100,
+        "0 EV_KEY KEY_W 1\n\
+         10 EV_KEY KEY_C 1\n\
+         10 EV_KEY KEY_W 0\n\
+         100 EV_KEY KEY_C 1\n\
+         200 EV_KEY KEY_Q 1\n",
+    );
+    let values: Vec<i32> = events(&output)
+        .iter()
+        .filter(|event| event.contains("ABS_GAS"))
+        .map(|event| event.rsplit(' ').next().unwrap().parse().unwrap())
+        .collect();
+    assert_eq!(values[0], 1023);
+    assert!(values[1] > 900, "{:?}", values);
+    assert!(values.windows(2).all(|pair| pair[1] < pair[0]), "{:?}", values);
+    assert_eq!(values.last(), Some(&0));
+}
+
+#[test]
+fn engaging_without_throttle_does_nothing() {
+    let output = run("zero", 0, "0 EV_KEY KEY_C 1\n10 EV_KEY KEY_W 1\n10 EV_KEY KEY_W 0\n");
+    let log = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert_eq!(events(&output), ["0.010 EV_ABS ABS_GAS 1023", "0.020 EV_ABS ABS_GAS 0"]);
+    assert!(log.contains("No throttle to hold"), "{}", log);
+}
+
+#[test]
+fn pausing_clears_the_hold() {
+    let output = run(
+        "pause",
+        0,
+        "0 EV_KEY KEY_W 1\n\
+         10 EV_KEY KEY_C 1\n\
+         10 EV_KEY KEY_W 0\n\
+         10 EV_KEY KEY_P 1\n\
+         10 EV_KEY KEY_P 0\n\
+         10 EV_KEY KEY_P 1\n\
+         10 EV_REL REL_Y 0\n",
+    );
+    let log = String::from_utf8_lossy(&output.stderr).into_owned();
+    assert!(log.contains("Throttle hold released"), "{}", log);
+    assert_eq!(events(&output), ["0.000 EV_ABS ABS_GAS 1023", "0.030 EV_ABS ABS_GAS 0"]);
+}
@@ -0,0 +1,68 @@
+// --trace-input writes the events read and sent, one per line, and stops
+// after --trace-seconds on the script's simulated clock.
+
+use std::process::Command;
+
+fn trace(name: &str, script: &str, args: &[&str]) -> Vec<String> {
+    let dir = std::env::temp_dir().join(format!("mouse2joy-trace-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), "sensitivity = 100\nlog_backend = \"stderr\"\n").unwrap();
+    std::fs::write(dir.join("input.txt"), script).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mouse2joy"))
+        .env("RUST_LOG", "off")
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .arg("--input-script")
+        .arg(dir.join("input.txt"))
+        .arg("--dry-run")
+        .arg("--trace-input")
+        .arg(dir.join("trace.txt"))
+        .args(args)
+        .output()
+        .unwrap();
+    let trace = std::fs::read_to_string(dir.join("trace.txt")).unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    trace.lines().map(|line| line.split_whitespace().collect::<Vec<_>>().join(" ")).collect()
+}
+
+#[test]
+fn input_and_output_are_interleaved() {
+    let lines = trace(
+        "interleaved",
+        "0 EV_REL REL_X 10\n\
+         0 EV_SYN 0 0\n\
+         250 EV_KEY BTN_LEFT 1\n",
+        &[],
+    );
+    assert_eq!(
+        lines,
+        [
+            "0.000000 in steering 0.000000 EV_REL REL_X 10",
+            "0.000000 out virtual - EV_ABS ABS_X 1000",
+            "0.000000 in steering 0.000000 EV_SYN SYN_REPORT 0",
+            "0.250000 in steering 0.000000 EV_KEY BTN_LEFT 1",
+            // centered on exit, at the time of the last output
+            "0.000000 out virtual - EV_ABS ABS_X 0",
+        ]
+    );
+}
+
+#[test]
+fn tracing_stops_after_the_duration() {
+    let lines = trace(
+        "stops",
+        "0 EV_REL REL_X 10\n\
+         1500 EV_REL REL_X 10\n",
+        &["--trace-seconds", "1"],
+    );
+    assert_eq!(
+        lines,
+        [
+            "0.000000 in steering 0.000000 EV_REL REL_X 10",
+            "0.000000 out virtual - EV_ABS ABS_X 1000",
+            "# stopped after 1s",
+        ]
+    );
+}
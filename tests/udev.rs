@@ -0,0 +1,45 @@
+// Renders the udev rule of --install-udev-rule for different groups and
+// virtual device IDs.
+
+#[path = "../src/udev/rule.rs"]
+mod rule;
+
+#[test]
+fn uinput_goes_to_the_group() {
+    let text = rule::render("games", None);
+    let lines: Vec<&str> = text.lines().filter(|line| !line.starts_with('#')).collect();
+    assert_eq!(
+        lines,
+        ["KERNEL==\"uinput\", SUBSYSTEM==\"misc\", OPTIONS+=\"static_node=uinput\", GROUP=\"games\", MODE=\"0660\""]
+    );
+}
+
+#[test]
+fn every_virtual_device_is_tagged_as_joystick() {
+    let text = rule::render("input", Some((0x1234, &[0x5678, 0x567a])));
+    let tagged: Vec<&str> = text.lines().filter(|line| line.contains("ID_INPUT_JOYSTICK")).collect();
+    assert_eq!(
+        tagged,
+        [
+            "SUBSYSTEM==\"input\", ATTRS{id/vendor}==\"1234\", ATTRS{id/product}==\"5678\", ENV{ID_INPUT_JOYSTICK}=\"1\"",
+            "SUBSYSTEM==\"input\", ATTRS{id/vendor}==\"1234\", ATTRS{id/product}==\"567a\", ENV{ID_INPUT_JOYSTICK}=\"1\"",
+        ]
+    );
+    assert!(text.contains("GROUP=\"input\""));
+}
+
+#[test]
+fn ids_are_zero_padded_hex() {
+    let text = rule::render("input", Some((0x0a, &[0x1])));
+    assert!(text.contains("ATTRS{id/vendor}==\"000a\", ATTRS{id/product}==\"0001\""), "{}", text);
+}
+
+#[test]
+fn group_names_that_would_break_the_rule_are_refused() {
+    for group in ["input", "_games", "uinput-users", "plugdev2"] {
+        assert!(rule::valid_group(group), "{}", group);
+    }
+    for group in ["", "x\"y", "two words", "Input", "-input", "a,b"] {
+        assert!(!rule::valid_group(group), "{}", group);
+    }
+}